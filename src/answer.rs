@@ -0,0 +1,663 @@
+// Instant answers: queries navim can answer itself, shown in a card above the
+// results. Arithmetic and unit conversions are worked out locally, so they
+// need no network at all; currency conversions need the day's exchange rates,
+// fetched once a day.
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use tracing::warn;
+use Dimension::*;
+
+use crate::config::get_state_dir;
+use crate::net::{configured_client, fixtures, private_enabled, Fetcher, RequestKind};
+
+#[derive(Clone)]
+pub struct InstantAnswer {
+    pub kind: &'static str, // Card title, e.g. "Calculator"
+    pub question: String,   // The query as understood
+    pub answer: String,
+}
+
+// The answer to a query, when it is a calculation or a unit or currency
+// conversion
+pub(crate) fn instant_answer(query: &str) -> Option<InstantAnswer> {
+    answer(query, exchange_rates)
+}
+
+// The same, with the exchange rates fetched through fetcher and not cached
+pub fn instant_answer_with(fetcher: &dyn Fetcher, query: &str) -> Option<InstantAnswer> {
+    answer(query, || fetch_rates(fetcher))
+}
+
+fn answer(query: &str, rates: impl FnOnce() -> Result<Rates, Box<dyn Error>>) -> Option<InstantAnswer> {
+    let query = query.trim();
+    let query = ["what is ", "what's ", "="]
+        .iter()
+        .find_map(|prefix| query.get(..prefix.len()).filter(|p| p.eq_ignore_ascii_case(prefix)).map(|_| &query[prefix.len()..]))
+        .unwrap_or(query)
+        .trim()
+        .trim_end_matches('?')
+        .trim();
+    if query.is_empty() {
+        return None;
+    }
+    convert_units(query).or_else(|| convert_currency(query, rates)).or_else(|| calculate(query))
+}
+
+fn calculate(query: &str) -> Option<InstantAnswer> {
+    let mut parser = Parser::new(query);
+    let value = parser.parse()?;
+    // A bare number or constant isn't worth a card
+    if parser.operations == 0 {
+        return None;
+    }
+    Some(InstantAnswer { kind: "Calculator", question: query.to_string(), answer: format_number(value) })
+}
+
+// "3 inches in cm", or a quantity on its own such as "2^20 bytes"
+fn convert_units(query: &str) -> Option<InstantAnswer> {
+    let lower = query.to_ascii_lowercase();
+    let split = [" in ", " to ", " as ", " into "].iter().filter_map(|sep| lower.rfind(sep).map(|i| (i, sep.len()))).max();
+    if let Some((i, len)) = split {
+        if let (Some((value, from, _, _)), Some(to)) = (quantity(&query[..i]), find_unit(&query[i + len..])) {
+            if from.dimension != to.dimension {
+                return None;
+            }
+            let converted = convert(value, from, to);
+            return Some(InstantAnswer {
+                kind: "Unit conversion",
+                question: format!("{} {}", format_number(value), from.symbol),
+                answer: format!("{} {}", format_number(converted), to.symbol),
+            });
+        }
+    }
+
+    // On its own, "5g" or "4k" is more likely a search than a quantity
+    let (value, from, name, operations) = quantity(query)?;
+    if name.chars().count() < 2 && operations == 0 {
+        return None;
+    }
+    let answer = if from.dimension == Data {
+        // Sizes read best in the largest unit of each kind that keeps a whole part
+        let bytes = value * from.factor;
+        let pick = |symbols: &[&str]| {
+            let units: Vec<&Unit> = symbols.iter().filter_map(|s| UNITS.iter().find(|u| u.symbol == *s)).collect();
+            let unit = units.iter().rev().find(|u| bytes.abs() >= u.factor).unwrap_or(&units[0]);
+            format!("{} {}", format_number(bytes / unit.factor), unit.symbol)
+        };
+        format!("{} · {}", pick(&["B", "KiB", "MiB", "GiB", "TiB", "PiB"]), pick(&["B", "kB", "MB", "GB", "TB", "PB"]))
+    } else {
+        let to = UNITS.iter().find(|u| u.symbol == from.counterpart)?;
+        format!("{} {}", format_number(convert(value, from, to)), to.symbol)
+    };
+    Some(InstantAnswer { kind: "Unit conversion", question: format!("{} {}", format_number(value), from.symbol), answer })
+}
+
+// An expression followed by a unit, e.g. "1/2 cup", "100km/h" or "40
+// degrees f": its value, the unit and its name as written, and how many
+// operations the expression had
+fn quantity(text: &str) -> Option<(f64, &'static Unit, &'static str, usize)> {
+    let lower = text.trim().to_ascii_lowercase();
+    // The longest name wins, so "10 min" isn't read as "10 m" of "in"
+    let (unit, name) = UNITS
+        .iter()
+        .flat_map(|u| u.names.iter().map(move |name| (u, *name)))
+        .filter(|(_, name)| lower.ends_with(name))
+        .max_by_key(|(_, name)| name.len())?;
+    let expression = lower[..lower.len() - name.len()].trim_end();
+    let expression = expression.strip_suffix("degrees").unwrap_or(expression).trim();
+    if expression.is_empty() {
+        return None;
+    }
+    let mut parser = Parser::new(expression);
+    let value = parser.parse()?;
+    Some((value, unit, name, parser.operations))
+}
+
+fn convert(value: f64, from: &Unit, to: &Unit) -> f64 {
+    (value * from.factor + from.offset - to.offset) / to.factor
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Dimension {
+    Length,
+    Area,
+    Volume,
+    Mass,
+    Time,
+    Speed,
+    Temperature,
+    Data,
+}
+
+struct Unit {
+    names: &'static [&'static str], // Lowercase spellings that name it
+    symbol: &'static str,
+    dimension: Dimension,
+    factor: f64,                    // In the dimension's base unit
+    offset: f64,                    // Only temperatures have one
+    counterpart: &'static str,      // What a lone quantity is converted to
+}
+
+const fn unit(
+    names: &'static [&'static str],
+    symbol: &'static str,
+    dimension: Dimension,
+    factor: f64,
+    counterpart: &'static str,
+) -> Unit {
+    Unit { names, symbol, dimension, factor, offset: 0.0, counterpart }
+}
+
+// Bases: metre, square metre, litre, kilogram, second, metre per second,
+// kelvin and byte. US customary volumes.
+const UNITS: &[Unit] = &[
+    unit(&["mm", "millimeter", "millimeters", "millimetre", "millimetres"], "mm", Length, 0.001, "in"),
+    unit(&["cm", "centimeter", "centimeters", "centimetre", "centimetres"], "cm", Length, 0.01, "in"),
+    unit(&["m", "meter", "meters", "metre", "metres"], "m", Length, 1.0, "ft"),
+    unit(&["km", "kilometer", "kilometers", "kilometre", "kilometres"], "km", Length, 1000.0, "mi"),
+    unit(&["in", "inch", "inches"], "in", Length, 0.0254, "cm"),
+    unit(&["ft", "foot", "feet"], "ft", Length, 0.3048, "m"),
+    unit(&["yd", "yard", "yards"], "yd", Length, 0.9144, "m"),
+    unit(&["mi", "mile", "miles"], "mi", Length, 1609.344, "km"),
+    unit(&["nmi", "nautical mile", "nautical miles"], "nmi", Length, 1852.0, "km"),
+    unit(&["m²", "m2", "sqm", "square meter", "square meters", "square metre", "square metres"], "m²", Area, 1.0, "ft²"),
+    unit(&["km²", "km2", "square kilometer", "square kilometers", "square kilometre", "square kilometres"], "km²", Area, 1e6, "mi²"),
+    unit(&["ft²", "ft2", "sqft", "square foot", "square feet"], "ft²", Area, 0.09290304, "m²"),
+    unit(&["mi²", "mi2", "square mile", "square miles"], "mi²", Area, 2589988.110336, "km²"),
+    unit(&["ha", "hectare", "hectares"], "ha", Area, 10000.0, "acre"),
+    unit(&["acre", "acres"], "acre", Area, 4046.8564224, "ha"),
+    unit(&["ml", "milliliter", "milliliters", "millilitre", "millilitres"], "ml", Volume, 0.001, "tsp"),
+    unit(&["cl", "centiliter", "centiliters", "centilitre", "centilitres"], "cl", Volume, 0.01, "tbsp"),
+    unit(&["l", "liter", "liters", "litre", "litres"], "l", Volume, 1.0, "gal"),
+    unit(&["tsp", "teaspoon", "teaspoons"], "tsp", Volume, 0.00492892159375, "ml"),
+    unit(&["tbsp", "tablespoon", "tablespoons"], "tbsp", Volume, 0.01478676478125, "ml"),
+    unit(&["cup", "cups"], "cup", Volume, 0.2365882365, "ml"),
+    unit(&["pt", "pint", "pints"], "pt", Volume, 0.473176473, "l"),
+    unit(&["qt", "quart", "quarts"], "qt", Volume, 0.946352946, "l"),
+    unit(&["gal", "gallon", "gallons"], "gal", Volume, 3.785411784, "l"),
+    unit(&["mg", "milligram", "milligrams"], "mg", Mass, 1e-6, "g"),
+    unit(&["g", "gram", "grams"], "g", Mass, 0.001, "oz"),
+    unit(&["kg", "kilo", "kilos", "kilogram", "kilograms"], "kg", Mass, 1.0, "lb"),
+    unit(&["t", "tonne", "tonnes"], "t", Mass, 1000.0, "lb"),
+    unit(&["oz", "ounce", "ounces"], "oz", Mass, 0.028349523125, "g"),
+    unit(&["lb", "lbs", "pound", "pounds"], "lb", Mass, 0.45359237, "kg"),
+    unit(&["st", "stone", "stones"], "st", Mass, 6.35029318, "kg"),
+    unit(&["ms", "millisecond", "milliseconds"], "ms", Time, 0.001, "s"),
+    unit(&["s", "sec", "secs", "second", "seconds"], "s", Time, 1.0, "min"),
+    unit(&["min", "mins", "minute", "minutes"], "min", Time, 60.0, "s"),
+    unit(&["h", "hr", "hrs", "hour", "hours"], "h", Time, 3600.0, "min"),
+    unit(&["day", "days"], "day", Time, 86400.0, "h"),
+    unit(&["week", "weeks"], "week", Time, 604800.0, "day"),
+    unit(&["year", "years", "yr", "yrs"], "year", Time, 31557600.0, "day"),
+    unit(&["m/s", "mps"], "m/s", Speed, 1.0, "km/h"),
+    unit(&["km/h", "kmh", "kph"], "km/h", Speed, 1.0 / 3.6, "mph"),
+    unit(&["mph"], "mph", Speed, 0.44704, "km/h"),
+    unit(&["kn", "knot", "knots"], "kn", Speed, 1852.0 / 3600.0, "km/h"),
+    unit(&["ft/s", "fps"], "ft/s", Speed, 0.3048, "m/s"),
+    Unit {
+        names: &["°c", "c", "celsius", "degrees celsius"],
+        symbol: "°C",
+        dimension: Temperature,
+        factor: 1.0,
+        offset: 273.15,
+        counterpart: "°F",
+    },
+    Unit {
+        names: &["°f", "f", "fahrenheit", "degrees fahrenheit"],
+        symbol: "°F",
+        dimension: Temperature,
+        factor: 5.0 / 9.0,
+        offset: 273.15 - 32.0 * 5.0 / 9.0,
+        counterpart: "°C",
+    },
+    unit(&["kelvin", "kelvins"], "K", Temperature, 1.0, "°C"),
+    unit(&["bit", "bits"], "bit", Data, 0.125, "B"),
+    unit(&["b", "byte", "bytes"], "B", Data, 1.0, "B"),
+    unit(&["kb", "kilobyte", "kilobytes"], "kB", Data, 1e3, "B"),
+    unit(&["mb", "megabyte", "megabytes"], "MB", Data, 1e6, "B"),
+    unit(&["gb", "gigabyte", "gigabytes"], "GB", Data, 1e9, "B"),
+    unit(&["tb", "terabyte", "terabytes"], "TB", Data, 1e12, "B"),
+    unit(&["pb", "petabyte", "petabytes"], "PB", Data, 1e15, "B"),
+    unit(&["kib", "kibibyte", "kibibytes"], "KiB", Data, 1024.0, "B"),
+    unit(&["mib", "mebibyte", "mebibytes"], "MiB", Data, 1048576.0, "B"),
+    unit(&["gib", "gibibyte", "gibibytes"], "GiB", Data, 1073741824.0, "B"),
+    unit(&["tib", "tebibyte", "tebibytes"], "TiB", Data, 1099511627776.0, "B"),
+    unit(&["pib", "pebibyte", "pebibytes"], "PiB", Data, 1125899906842624.0, "B"),
+];
+
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    let name = name.trim().to_ascii_lowercase();
+    let name = name.strip_prefix("degrees ").unwrap_or(&name);
+    UNITS.iter().find(|u| u.names.contains(&name))
+}
+
+// Recursive descent over + - * / % ^ !, parentheses, a few functions and the
+// constants pi, tau and e
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    operations: usize, // Operators and functions seen, to tell "2+2" from "2"
+    depth: usize,      // Operands being parsed inside one another
+}
+
+// Every level of parentheses, function call, sign or power is an operand
+// inside another, and each one is a few stack frames deeper
+const MAX_DEPTH: usize = 200;
+
+type Function = fn(f64) -> f64;
+
+const FUNCTIONS: &[(&str, Function)] = &[
+    ("sqrt", f64::sqrt),
+    ("cbrt", f64::cbrt),
+    ("sin", f64::sin),
+    ("cos", f64::cos),
+    ("tan", f64::tan),
+    ("asin", f64::asin),
+    ("acos", f64::acos),
+    ("atan", f64::atan),
+    ("ln", f64::ln),
+    ("log", f64::log10),
+    ("log2", f64::log2),
+    ("exp", f64::exp),
+    ("abs", f64::abs),
+    ("floor", f64::floor),
+    ("ceil", f64::ceil),
+    ("round", f64::round),
+];
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        Parser { chars: text.chars().collect(), pos: 0, operations: 0, depth: 0 }
+    }
+
+    // The value of the whole text, if it is one finite expression
+    fn parse(&mut self) -> Option<f64> {
+        let value = self.expression()?;
+        self.skip_spaces();
+        (self.pos == self.chars.len() && value.is_finite()).then_some(value)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    // The next non-space character, consumed when it is one of `options`
+    fn eat(&mut self, options: &[char]) -> Option<char> {
+        self.skip_spaces();
+        let c = *self.chars.get(self.pos).filter(|c| options.contains(c))?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(op) = self.eat(&['+', '-', '−']) {
+            self.operations += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        loop {
+            // ** is a power, handled further down
+            if self.eat(&['*']).is_some() {
+                if self.chars.get(self.pos) == Some(&'*') {
+                    self.pos -= 1;
+                    return Some(value);
+                }
+                self.operations += 1;
+                value *= self.unary()?;
+                continue;
+            }
+            match self.eat(&['×', '/', '÷', '%']) {
+                Some('×') => value *= self.unary()?,
+                Some('%') => value %= self.unary()?,
+                Some(_) => value /= self.unary()?,
+                None => return Some(value),
+            }
+            self.operations += 1;
+        }
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = match self.eat(&['-', '−', '+']) {
+            Some('+') => self.unary(),
+            Some(_) => self.unary().map(|v| -v),
+            None => self.power(),
+        };
+        self.depth -= 1;
+        value
+    }
+
+    // Right associative, and binds tighter than a leading minus: -2^2 = -4
+    fn power(&mut self) -> Option<f64> {
+        let base = self.factorial()?;
+        self.skip_spaces();
+        let caret = self.chars.get(self.pos) == Some(&'^');
+        let stars = self.chars.get(self.pos..self.pos + 2) == Some(&['*', '*']);
+        if !caret && !stars {
+            return Some(base);
+        }
+        self.pos += if caret { 1 } else { 2 };
+        self.operations += 1;
+        Some(base.powf(self.unary()?))
+    }
+
+    fn factorial(&mut self) -> Option<f64> {
+        let value = self.primary()?;
+        if self.eat(&['!']).is_none() {
+            return Some(value);
+        }
+        self.operations += 1;
+        if value < 0.0 || value.fract() != 0.0 || value > 170.0 {
+            return None;
+        }
+        Some((1..=value as u32).map(f64::from).product())
+    }
+
+    fn primary(&mut self) -> Option<f64> {
+        self.skip_spaces();
+        let c = *self.chars.get(self.pos)?;
+        if c == 'π' {
+            self.pos += 1;
+            return Some(std::f64::consts::PI);
+        }
+        if c == '(' {
+            self.pos += 1;
+            let value = self.expression()?;
+            self.eat(&[')'])?;
+            return Some(value);
+        }
+        if c.is_ascii_digit() || c == '.' {
+            return self.number();
+        }
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect::<String>().to_lowercase();
+        match name.as_str() {
+            "pi" => Some(std::f64::consts::PI),
+            "tau" => Some(std::f64::consts::TAU),
+            "e" => Some(std::f64::consts::E),
+            _ => {
+                let function = FUNCTIONS.iter().find(|(n, _)| *n == name)?.1;
+                self.eat(&['('])?;
+                let value = self.expression()?;
+                self.eat(&[')'])?;
+                self.operations += 1;
+                Some(function(value))
+            }
+        }
+    }
+
+    // Digits with an optional fraction and exponent; commas group thousands
+    fn number(&mut self) -> Option<f64> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.get(self.pos) {
+            let grouping = c == ',' && {
+                let next: String = self.chars.iter().skip(self.pos + 1).take(4).collect();
+                next.len() >= 3 && next.chars().take(3).all(|d| d.is_ascii_digit()) && !next[3..].starts_with(|d: char| d.is_ascii_digit())
+            };
+            if c.is_ascii_digit() || c == '.' {
+                text.push(c);
+            } else if !grouping {
+                break;
+            }
+            self.pos += 1;
+        }
+        // An exponent only when digits follow, so "2e" stays 2 times e
+        if matches!(self.chars.get(self.pos), Some('e' | 'E')) {
+            let sign = matches!(self.chars.get(self.pos + 1), Some('+' | '-'));
+            let digits_at = self.pos + 1 + sign as usize;
+            if self.chars.get(digits_at).is_some_and(|c| c.is_ascii_digit()) {
+                text.extend(&self.chars[self.pos..digits_at]);
+                self.pos = digits_at;
+                while let Some(&c) = self.chars.get(self.pos).filter(|c| c.is_ascii_digit()) {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        text.parse().ok()
+    }
+}
+
+// Up to ten significant digits, thousands grouped, scientific when huge or tiny
+fn format_number(value: f64) -> String {
+    let value = if value == 0.0 { 0.0 } else { value }; // No "-0"
+    let magnitude = value.abs();
+    if magnitude >= 1e15 || (magnitude < 1e-6 && magnitude > 0.0) {
+        let text = format!("{:.9e}", value);
+        let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+        let mantissa = if mantissa.contains('.') { mantissa.trim_end_matches('0').trim_end_matches('.') } else { mantissa };
+        return format!("{}×10^{}", mantissa, exponent);
+    }
+    let decimals = if magnitude < 1.0 { 10 } else { (9 - magnitude.log10().floor() as i32).max(0) as usize };
+    let text = format!("{:.*}", decimals, value);
+    let text = if text.contains('.') { text.trim_end_matches('0').trim_end_matches('.') } else { &text };
+    group_thousands(text)
+}
+
+// Commas between each three digits of a decimal number's whole part
+fn group_thousands(text: &str) -> String {
+    let (sign, text) = text.strip_prefix('-').map_or(("", text), |rest| ("-", rest));
+    let (whole, fraction) = text.split_once('.').map_or((text, None), |(w, f)| (w, Some(f)));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    match fraction {
+        Some(fraction) => format!("{}{}.{}", sign, grouped, fraction),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+// Currencies the European Central Bank publishes reference rates for, which
+// is what the rates endpoint serves
+const CURRENCIES: &[&str] = &[
+    "AUD", "BGN", "BRL", "CAD", "CHF", "CNY", "CZK", "DKK", "EUR", "GBP", "HKD", "HUF", "IDR", "ILS", "INR", "ISK",
+    "JPY", "KRW", "MXN", "MYR", "NOK", "NZD", "PHP", "PLN", "RON", "SEK", "SGD", "THB", "TRY", "USD", "ZAR",
+];
+
+// Names and symbols people type instead of the code
+const CURRENCY_NAMES: &[(&str, &str)] = &[
+    ("$", "USD"),
+    ("dollar", "USD"),
+    ("dollars", "USD"),
+    ("€", "EUR"),
+    ("euro", "EUR"),
+    ("euros", "EUR"),
+    ("£", "GBP"),
+    ("pound", "GBP"),
+    ("pounds", "GBP"),
+    ("¥", "JPY"),
+    ("yen", "JPY"),
+    ("yuan", "CNY"),
+    ("rmb", "CNY"),
+    ("₹", "INR"),
+    ("rupee", "INR"),
+    ("rupees", "INR"),
+    ("franc", "CHF"),
+    ("francs", "CHF"),
+    ("won", "KRW"),
+];
+
+const RATES_URL: &str = "https://api.frankfurter.app/latest";
+
+fn find_currency(name: &str) -> Option<&'static str> {
+    let name = name.trim();
+    let upper = name.to_uppercase();
+    CURRENCIES
+        .iter()
+        .find(|code| **code == upper)
+        .copied()
+        .or_else(|| CURRENCY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, code)| *code))
+}
+
+// "100 eur to usd", "$20 in gbp" or "eur to usd". Only a query that names two
+// known currencies fetches the rates.
+fn convert_currency(query: &str, rates: impl FnOnce() -> Result<Rates, Box<dyn Error>>) -> Option<InstantAnswer> {
+    let lower = query.to_ascii_lowercase();
+    let (i, len) = [" to ", " in ", " into ", " as "].iter().filter_map(|sep| lower.rfind(sep).map(|i| (i, sep.len()))).max()?;
+    let to = find_currency(&query[i + len..])?;
+    let left = query[..i].trim();
+
+    // The currency comes before the amount ("$20", "EUR 100") or after it
+    let (amount, from) = match left.split_once(char::is_whitespace) {
+        Some((first, rest)) if find_currency(first).is_some() => (rest.trim(), find_currency(first)?),
+        _ => {
+            let name_at = left.rfind(|c: char| c.is_ascii_digit() || c == ')').map_or(0, |i| i + 1);
+            match find_currency(&left[name_at..]) {
+                Some(from) => (left[..name_at].trim(), from),
+                None => {
+                    let symbol = left.chars().next()?;
+                    (left[symbol.len_utf8()..].trim(), find_currency(&symbol.to_string())?)
+                }
+            }
+        }
+    };
+    let amount = if amount.is_empty() { 1.0 } else { Parser::new(amount).parse()? };
+    if from == to {
+        return None;
+    }
+
+    let rates = rates().map_err(|e| warn!(error = %e, "could not get exchange rates")).ok()?;
+    let rate = |code: &str| if code == rates.base { Some(1.0) } else { rates.rates.get(code).copied() };
+    let converted = amount / rate(from)? * rate(to)?;
+    Some(InstantAnswer {
+        kind: "Currency",
+        question: format!("{} {} · rates of {}", format_number(amount), from, rates.date),
+        answer: format!("{} {}", group_thousands(&format!("{:.2}", converted)), to),
+    })
+}
+
+// A day's reference rates against one base currency
+#[derive(Serialize, Deserialize)]
+struct Rates {
+    base: String,
+    date: NaiveDate, // The day they were published
+    rates: HashMap<String, f64>,
+    #[serde(default)]
+    fetched: Option<NaiveDate>, // When navim got them, to fetch at most once a day
+}
+
+fn get_rates_path() -> PathBuf {
+    get_state_dir().join("rates.json")
+}
+
+// Today's rates from the cache, or fetched and cached; yesterday's cached
+// rates if fetching fails
+fn exchange_rates() -> Result<Rates, Box<dyn Error>> {
+    let today = Local::now().date_naive();
+    let cached: Option<Rates> = fs::read_to_string(get_rates_path()).ok().and_then(|text| serde_json::from_str(&text).ok());
+    let cached = match cached {
+        Some(rates) if rates.fetched == Some(today) => return Ok(rates),
+        other => other,
+    };
+
+    let fetched = if let Some(fixtures) = fixtures() {
+        fetch_rates(fixtures)
+    } else {
+        configured_client(RequestKind::Search)
+            .and_then(|builder| builder.build())
+            .and_then(|client| client.get(RATES_URL).header("Accept", "application/json").send())
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes())
+            .map_err(Into::into)
+            .and_then(|body| Ok(serde_json::from_slice::<Rates>(&body)?))
+    };
+    let rates = match fetched {
+        Ok(rates) => rates,
+        Err(e) => return cached.ok_or(e),
+    };
+    let rates = Rates { fetched: Some(today), ..rates };
+    if !private_enabled() && fixtures().is_none() {
+        if let Err(e) = fs::write(get_rates_path(), serde_json::to_string(&rates)?) {
+            warn!(error = %e, "could not cache exchange rates");
+        }
+    }
+    Ok(rates)
+}
+
+fn fetch_rates(fetcher: &dyn Fetcher) -> Result<Rates, Box<dyn Error>> {
+    Ok(serde_json::from_slice(&fetcher.fetch(RATES_URL)?.body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_rates() -> Result<Rates, Box<dyn Error>> {
+        Err("no rates in unit tests".into())
+    }
+
+    // The answer line, or None when there is no card
+    fn solve(query: &str) -> Option<String> {
+        answer(query, no_rates).map(|a| a.answer)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(solve("2+3*4").as_deref(), Some("14"));
+        assert_eq!(solve("(2+3)*4").as_deref(), Some("20"));
+        assert_eq!(solve("10-4-3").as_deref(), Some("3"));
+        assert_eq!(solve("2^3^2").as_deref(), Some("512"));
+        assert_eq!(solve("-2^2").as_deref(), Some("-4"));
+        assert_eq!(solve("2**3*2").as_deref(), Some("16"));
+        assert_eq!(solve("what is 3! + 1000*1000?").as_deref(), Some("1,000,006"));
+    }
+
+    #[test]
+    fn deep_nesting_has_no_answer() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(solve(&format!("{}+1", nested(50))).as_deref(), Some("2"));
+        assert_eq!(solve(&format!("{}+1", nested(20_000))), None);
+        assert_eq!(solve(&format!("{}1+1", "-".repeat(20_000))), None);
+        assert_eq!(solve(&format!("2{}", "^2".repeat(20_000))), None);
+    }
+
+    #[test]
+    fn division_by_zero_has_no_answer() {
+        assert_eq!(solve("1/0"), None);
+        assert_eq!(solve("0/0"), None);
+        assert_eq!(solve("5 % 0"), None);
+    }
+
+    #[test]
+    fn malformed_expressions_have_no_answer() {
+        for query in ["2+", "(1+2", "1+2)", "2 3", "sqrt 4", "foo(2)", "=", "3", "pi", "171!"] {
+            assert_eq!(solve(query), None, "{}", query);
+        }
+    }
+
+    #[test]
+    fn unit_conversions() {
+        let convert = |query: &str| answer(query, no_rates).map(|a| format!("{} = {}", a.question, a.answer));
+        assert_eq!(convert("3 inches in cm").as_deref(), Some("3 in = 7.62 cm"));
+        assert_eq!(convert("100 c to f").as_deref(), Some("100 °C = 212 °F"));
+        assert_eq!(convert("5 kg in lb").as_deref(), Some("5 kg = 11.02311311 lb"));
+        assert_eq!(convert("1 mile").as_deref(), Some("1 mi = 1.609344 km"));
+        assert_eq!(convert("2^20 bytes").as_deref(), Some("1,048,576 B = 1 MiB · 1.048576 MB"));
+        assert_eq!(convert("3 kg in m"), None);
+        // One letter on its own is more likely a search
+        assert_eq!(convert("5g"), None);
+    }
+}
@@ -0,0 +1,105 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// User configuration, read from config.toml in the config directory
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Config {
+    // Extra or overridden bangs: name (without '!') -> URL template with {} or a site domain
+    pub(crate) bangs: HashMap<String, String>,
+    // Engines queried for web results; more than one turns on meta-search
+    pub(crate) engines: Vec<String>,
+    // Domains moved to the top of web results, most preferred first
+    pub(crate) boost: Vec<String>,
+    // Extra attempts after a timeout, reset connection or server error
+    pub(crate) retries: Option<u32>,
+    // SOCKS proxy used by --tor instead of the default 127.0.0.1:9050
+    pub(crate) tor_proxy: Option<String>,
+    // Sites allowed to keep cookies (subdomains too). Empty keeps every
+    // request cookie-free.
+    pub(crate) cookies: Vec<String>,
+    // Load pages over https:// only, like --https-only
+    pub(crate) https_only: bool,
+    // Ad and analytics hosts to block on top of the built-in list
+    // (subdomains too)
+    pub(crate) block: Vec<String>,
+    // Least time between starting two requests to the same host
+    pub(crate) host_delay_ms: Option<u64>,
+    // Most requests in flight to the same host at once
+    pub(crate) host_connections: Option<usize>,
+    // Timeouts, user agent and extra headers, with per-feature overrides
+    pub(crate) http: HttpConfig,
+    // Domain (subdomains too) -> CSS selector for its main content, tried
+    // before the built-in list
+    pub(crate) selectors: HashMap<String, String>,
+    // Widest page text gets, in columns; wider windows center it
+    pub(crate) text_width: Option<usize>,
+    // Linear output for screen readers, like --accessible
+    pub(crate) accessible: bool,
+    // Show the highlighted result's page beside the results (p toggles it)
+    pub(crate) preview: bool,
+    // Results O opens in background tabs (default 5, 0 for all of them)
+    pub(crate) open_tabs: Option<usize>,
+}
+
+// Request settings under [http]; [http.search], [http.page] and [http.image]
+// override them for one kind of request
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct HttpConfig {
+    #[serde(flatten)]
+    pub(crate) all: RequestSettings,
+    pub(crate) search: RequestSettings,
+    pub(crate) page: RequestSettings,
+    pub(crate) image: RequestSettings,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct RequestSettings {
+    pub(crate) timeout_secs: Option<u64>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) headers: HashMap<String, String>,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn get_config_path() -> PathBuf {
+    get_config_dir().join("config.toml")
+}
+
+fn load_config() -> Config {
+    let path = get_config_path();
+    match fs::read_to_string(&path) {
+        Ok(s) => toml::from_str(&s).unwrap_or_else(|e| {
+            eprintln!("Ignoring invalid config {}: {}", path.display(), e);
+            Config::default()
+        }),
+        Err(_) => Config::default(),
+    }
+}
+
+pub(crate) fn config() -> &'static Config {
+    CONFIG.get_or_init(load_config)
+}
+
+pub(crate) fn get_config_dir() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("navim");
+    fs::create_dir_all(&config_dir).ok();
+    config_dir
+}
+
+// ~/.local/state/navim, for the log
+pub(crate) fn get_state_dir() -> PathBuf {
+    let state_dir = dirs::state_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("state")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("navim");
+    fs::create_dir_all(&state_dir).ok();
+    state_dir
+}
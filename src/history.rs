@@ -0,0 +1,592 @@
+use chrono::{DateTime, Days, Local, NaiveDate, Timelike};
+use rusqlite::{params, Connection};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use tracing::warn;
+use url::Url;
+
+use crate::config::get_config_dir;
+use crate::render::{escape_html, RenderedPage};
+use crate::search::SearchResult;
+
+// A visited page: one entry per URL, with the query that led to it the last
+// time and how often it was visited
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct HistoryEntry {
+    pub(crate) query: String,
+    pub(crate) title: String,
+    pub(crate) url: String,
+    pub(crate) timestamp: DateTime<Local>, // Last visit
+    #[serde(default)]
+    pub(crate) visits: u32,
+}
+
+// History lives in SQLite so a visit is one small write rather than
+// rewriting a file, and nothing has to be dropped to keep it fast
+const HISTORY_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS history (
+        url TEXT PRIMARY KEY,
+        query TEXT NOT NULL,
+        title TEXT NOT NULL,
+        visits INTEGER NOT NULL,
+        last_visit INTEGER NOT NULL -- Unix seconds
+    );
+    CREATE INDEX IF NOT EXISTS history_last_visit ON history (last_visit);
+    CREATE TABLE IF NOT EXISTS visits (
+        url TEXT NOT NULL,
+        at INTEGER NOT NULL -- Unix seconds
+    );
+    CREATE INDEX IF NOT EXISTS visits_url ON visits (url);
+";
+
+fn get_history_path() -> PathBuf {
+    get_config_dir().join("history.sqlite")
+}
+
+// History from before the SQLite store, imported once and then renamed
+fn get_legacy_history_path() -> PathBuf {
+    get_config_dir().join("history.json")
+}
+
+fn open_history() -> rusqlite::Result<Connection> {
+    let conn = Connection::open(get_history_path())?;
+    // Overwrite deleted entries on disk instead of leaving them in free pages
+    conn.pragma_update(None, "secure_delete", true)?;
+    let has_visit_log: bool =
+        conn.query_row("SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'visits'", [], |row| {
+            row.get(0)
+        })?;
+    conn.execute_batch(HISTORY_SCHEMA)?;
+    // Pages recorded before every visit was logged count once, at their last visit
+    if !has_visit_log {
+        conn.execute("INSERT INTO visits (url, at) SELECT url, last_visit FROM history", [])?;
+    }
+    migrate_json_history(&conn)?;
+    Ok(conn)
+}
+
+// Move the entries of history.json into the database, oldest first so visit
+// counts add up and the newest visit wins. The file is kept as
+// history.json.migrated in case anything went wrong.
+fn migrate_json_history(conn: &Connection) -> rusqlite::Result<()> {
+    let path = get_legacy_history_path();
+    let Ok(json) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    let Ok(entries) = serde_json::from_str::<Vec<HistoryEntry>>(&json) else {
+        warn!(path = %path.display(), "could not read the old history file, leaving it alone");
+        return Ok(());
+    };
+    let tx = conn.unchecked_transaction()?;
+    for entry in entries.iter().rev() {
+        record_visit(&tx, &entry.query, &entry.title, &entry.url, entry.timestamp)?;
+    }
+    tx.commit()?;
+    if let Err(e) = fs::rename(&path, path.with_extension("json.migrated")) {
+        warn!(error = %e, "could not rename the migrated history file");
+    }
+    Ok(())
+}
+
+// Opening a URL directly gives it as the query and title, which shouldn't
+// replace the real ones from an earlier visit
+fn record_visit(conn: &Connection, query: &str, title: &str, url: &str, at: DateTime<Local>) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO history (url, query, title, visits, last_visit) VALUES (?1, ?2, ?3, 1, ?4)
+         ON CONFLICT (url) DO UPDATE SET
+             query = iif(excluded.query = excluded.url, query, excluded.query),
+             title = iif(excluded.title = excluded.url, title, excluded.title),
+             visits = visits + 1,
+             last_visit = max(last_visit, excluded.last_visit)",
+        params![url, query, title, at.timestamp()],
+    )?;
+    conn.execute("INSERT INTO visits (url, at) VALUES (?1, ?2)", params![url, at.timestamp()])?;
+    Ok(())
+}
+
+// Every visited page, most recent first
+pub(crate) fn load_history() -> Vec<HistoryEntry> {
+    let load = || -> rusqlite::Result<Vec<HistoryEntry>> {
+        let conn = open_history()?;
+        let mut stmt =
+            conn.prepare("SELECT query, title, url, visits, last_visit FROM history ORDER BY last_visit DESC")?;
+        let rows = stmt.query_map([], |row| {
+            let last_visit: i64 = row.get(4)?;
+            Ok(HistoryEntry {
+                query: row.get(0)?,
+                title: row.get(1)?,
+                url: row.get(2)?,
+                visits: row.get(3)?,
+                timestamp: DateTime::from_timestamp(last_visit, 0).unwrap_or_default().with_timezone(&Local),
+            })
+        })?;
+        rows.collect()
+    };
+    load().unwrap_or_else(|e| {
+        warn!(error = %e, "could not load history");
+        Vec::new()
+    })
+}
+
+// Something to offer again on the Home screen: a query searched before or a
+// page visited before
+pub(crate) enum Frecent {
+    Query(String),
+    Page { title: String, url: String },
+}
+
+// A visit counts half as much after this many days
+const FRECENCY_HALF_LIFE_DAYS: f64 = 14.0;
+
+// The `limit` queries and pages visited most often and most lately: visit
+// counts decayed by the age of the last visit. A query scores the sum of the
+// pages it led to.
+pub(crate) fn load_frecent(limit: usize) -> Vec<Frecent> {
+    let now = Local::now();
+    let mut queries: Vec<(String, f64)> = Vec::new();
+    let mut query_index: HashMap<String, usize> = HashMap::new();
+    let mut scored: Vec<(Frecent, f64)> = Vec::new();
+    for entry in load_history() {
+        let age_days = (now - entry.timestamp).num_seconds().max(0) as f64 / 86400.0;
+        let score = entry.visits.max(1) as f64 * 0.5f64.powf(age_days / FRECENCY_HALF_LIFE_DAYS);
+        // Opening a URL directly records it as the query, which the page
+        // already stands for
+        if Url::parse(&entry.query).is_err() && !entry.query.trim().is_empty() {
+            match query_index.get(&entry.query) {
+                Some(&i) => queries[i].1 += score,
+                None => {
+                    query_index.insert(entry.query.clone(), queries.len());
+                    queries.push((entry.query.clone(), score));
+                }
+            }
+        }
+        scored.push((Frecent::Page { title: entry.title, url: entry.url }, score));
+    }
+    scored.extend(queries.into_iter().map(|(q, score)| (Frecent::Query(q), score)));
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.into_iter().take(limit).map(|(item, _)| item).collect()
+}
+
+// What `navim stats` shows
+pub(crate) struct HistoryStats {
+    pub(crate) pages: usize,
+    pub(crate) visits: usize,
+    pub(crate) first_visit: Option<DateTime<Local>>,
+    pub(crate) top_domains: Vec<(String, u64)>, // Visits, most first
+    pub(crate) top_queries: Vec<(String, u64)>, // Visits to the pages each led to
+    pub(crate) per_day: Vec<(NaiveDate, u64)>,  // The last STATS_DAYS days, oldest first
+    pub(crate) per_hour: [u64; 24],             // Local time
+}
+
+// How many domains and queries to rank, and days to chart
+const STATS_TOP: usize = 10;
+pub(crate) const STATS_DAYS: usize = 14;
+
+pub(crate) fn load_stats() -> rusqlite::Result<HistoryStats> {
+    let history = load_history();
+    let rank = |totals: HashMap<String, u64>| {
+        let mut totals: Vec<(String, u64)> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        totals.truncate(STATS_TOP);
+        totals
+    };
+    let add = |totals: &mut HashMap<String, u64>, key: String, n: u64| *totals.entry(key).or_default() += n;
+    let mut domains = HashMap::new();
+    let mut queries = HashMap::new();
+    for entry in &history {
+        let visits = entry.visits.max(1) as u64;
+        if let Some(host) = Url::parse(&entry.url).ok().and_then(|u| u.host_str().map(str::to_string)) {
+            add(&mut domains, host.trim_start_matches("www.").to_string(), visits);
+        }
+        if Url::parse(&entry.query).is_err() && !entry.query.trim().is_empty() {
+            add(&mut queries, entry.query.clone(), visits);
+        }
+    }
+
+    let conn = open_history()?;
+    let mut stmt = conn.prepare("SELECT at FROM visits")?;
+    let times = stmt
+        .query_map([], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|at| DateTime::from_timestamp(at, 0))
+        .map(|at| at.with_timezone(&Local))
+        .collect::<Vec<_>>();
+
+    let today = Local::now().date_naive();
+    let mut per_day: Vec<(NaiveDate, u64)> =
+        (0..STATS_DAYS as u64).rev().filter_map(|back| Some((today.checked_sub_days(Days::new(back))?, 0))).collect();
+    let mut per_hour = [0; 24];
+    for at in &times {
+        per_hour[at.hour() as usize] += 1;
+        if let Some((_, n)) = per_day.iter_mut().find(|(day, _)| *day == at.date_naive()) {
+            *n += 1;
+        }
+    }
+
+    Ok(HistoryStats {
+        pages: history.len(),
+        visits: history.iter().map(|entry| entry.visits.max(1) as usize).sum(),
+        first_visit: times.iter().min().copied(),
+        top_domains: rank(domains),
+        top_queries: rank(queries),
+        per_day,
+        per_hour,
+    })
+}
+
+// Point the visits to `old` at the URL it redirected to, merging them with
+// any earlier visits there
+pub(crate) fn update_history_url(old: &str, new: &str) -> rusqlite::Result<()> {
+    if old == new {
+        return Ok(());
+    }
+    let mut conn = open_history()?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO history (url, query, title, visits, last_visit)
+         SELECT ?2, query, title, visits, last_visit FROM history WHERE url = ?1
+         ON CONFLICT (url) DO UPDATE SET
+             visits = visits + excluded.visits,
+             last_visit = max(last_visit, excluded.last_visit)",
+        params![old, new],
+    )?;
+    tx.execute("DELETE FROM history WHERE url = ?1", params![old])?;
+    tx.execute("UPDATE visits SET url = ?2 WHERE url = ?1", params![old, new])?;
+    tx.commit()
+}
+
+pub(crate) fn add_to_history(query: &str, title: &str, url: &str) -> rusqlite::Result<()> {
+    record_visit(&open_history()?, query, title, url, Local::now())
+}
+
+// Forget every visit to a page
+pub(crate) fn remove_from_history(url: &str) -> rusqlite::Result<()> {
+    let conn = open_history()?;
+    conn.execute("DELETE FROM history WHERE url = ?1", params![url])?;
+    conn.execute("DELETE FROM visits WHERE url = ?1", params![url])?;
+    Ok(())
+}
+
+// Forget every visit
+pub(crate) fn clear_history() -> rusqlite::Result<()> {
+    let conn = open_history()?;
+    conn.execute("DELETE FROM history", [])?;
+    conn.execute("DELETE FROM visits", [])?;
+    // Give the freed pages back so the file doesn't hint at what was there
+    conn.execute_batch("VACUUM")
+}
+
+// Article saved to read later, with its rendered text for offline reading
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ReadLaterEntry {
+    pub(crate) title: String,
+    pub(crate) url: String,
+    pub(crate) added: DateTime<Local>,
+    pub(crate) page: RenderedPage,
+}
+
+fn get_read_later_path() -> PathBuf {
+    get_config_dir().join("readlater.json")
+}
+
+pub(crate) fn load_read_later() -> Vec<ReadLaterEntry> {
+    fs::read_to_string(get_read_later_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn save_read_later(entries: &[ReadLaterEntry]) -> io::Result<()> {
+    let json = serde_json::to_string(entries)?;
+    fs::write(get_read_later_path(), json)
+}
+
+// A feed the user follows
+#[derive(Serialize, Deserialize)]
+pub(crate) struct FeedSubscription {
+    pub(crate) url: String,
+    pub(crate) title: String,
+    added: DateTime<Local>,
+}
+
+// Subscriptions and the entries already read, kept in feeds.json
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct FeedStore {
+    pub(crate) subscriptions: Vec<FeedSubscription>,
+    pub(crate) read: Vec<String>, // Entry URLs, oldest first
+}
+
+// Remember this many read entries; older ones have long left their feeds
+const MAX_READ_FEED_ITEMS: usize = 5000;
+
+fn get_feeds_path() -> PathBuf {
+    get_config_dir().join("feeds.json")
+}
+
+pub(crate) fn load_feeds() -> FeedStore {
+    fs::read_to_string(get_feeds_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn save_feeds(store: &FeedStore) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(store)?;
+    fs::write(get_feeds_path(), json)
+}
+
+impl FeedStore {
+    pub(crate) fn subscribe(&mut self, url: &str, title: &str) -> bool {
+        if self.subscriptions.iter().any(|s| s.url == url) {
+            return false;
+        }
+        self.subscriptions.push(FeedSubscription {
+            url: url.to_string(),
+            title: title.to_string(),
+            added: Local::now(),
+        });
+        true
+    }
+
+    pub(crate) fn is_read(&self, url: &str) -> bool {
+        self.read.iter().any(|u| u == url)
+    }
+
+    pub(crate) fn mark_read(&mut self, url: &str) {
+        if !self.is_read(url) {
+            self.read.push(url.to_string());
+        }
+        if self.read.len() > MAX_READ_FEED_ITEMS {
+            let excess = self.read.len() - MAX_READ_FEED_ITEMS;
+            self.read.drain(..excess);
+        }
+    }
+}
+
+// A saved page, tagged with the folders it was filed under
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Bookmark {
+    pub(crate) title: String,
+    pub(crate) url: String,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    pub(crate) added: DateTime<Local>,
+}
+
+// Bookmarks in the order they were added, kept in bookmarks.json
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct BookmarkStore {
+    pub(crate) bookmarks: Vec<Bookmark>,
+}
+
+fn get_bookmarks_path() -> PathBuf {
+    get_config_dir().join("bookmarks.json")
+}
+
+pub(crate) fn load_bookmarks() -> BookmarkStore {
+    fs::read_to_string(get_bookmarks_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn save_bookmarks(store: &BookmarkStore) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(store)?;
+    fs::write(get_bookmarks_path(), json)
+}
+
+impl BookmarkStore {
+    // Add a bookmark, or give one already saved for the URL the new tags.
+    // Returns whether it was new.
+    pub(crate) fn merge(&mut self, bookmark: Bookmark) -> bool {
+        match self.bookmarks.iter_mut().find(|b| b.url == bookmark.url) {
+            Some(existing) => {
+                for tag in bookmark.tags {
+                    if !existing.tags.contains(&tag) {
+                        existing.tags.push(tag);
+                    }
+                }
+                false
+            }
+            None => {
+                self.bookmarks.push(bookmark);
+                true
+            }
+        }
+    }
+}
+
+// Bookmarks from the Netscape bookmark file Firefox, Chrome and most other
+// browsers export. Folders are nested <DL> lists, each following the <H3>
+// that names it, so a link's folders are the headings before the lists it
+// sits in; Firefox's own tags come in a TAGS attribute.
+pub(crate) fn parse_netscape_bookmarks(html: &str) -> Vec<Bookmark> {
+    let document = Html::parse_document(html);
+    let link_selector = Selector::parse("a[href]").unwrap();
+    document
+        .select(&link_selector)
+        .filter_map(|link| {
+            let url = link.value().attr("href")?.trim().to_string();
+            // Bookmarklets and browser-internal pages can't be opened here
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return None;
+            }
+            let mut tags: Vec<String> = link
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .filter(|e| e.value().name() == "dl")
+                .filter_map(|list| {
+                    let heading = list.prev_siblings().filter_map(ElementRef::wrap).next()?;
+                    (heading.value().name() == "h3").then(|| heading.text().collect::<String>().trim().to_string())
+                })
+                .filter(|name| !name.is_empty())
+                .collect();
+            tags.reverse();
+            for tag in link.value().attr("tags").unwrap_or_default().split(',').map(str::trim) {
+                if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                    tags.push(tag.to_string());
+                }
+            }
+            let title = link.text().collect::<String>().trim().to_string();
+            let added = link
+                .value()
+                .attr("add_date")
+                .and_then(|d| d.trim().parse::<i64>().ok())
+                .and_then(|d| DateTime::from_timestamp(d, 0))
+                .map_or_else(Local::now, |d| d.with_timezone(&Local));
+            Some(Bookmark { title: if title.is_empty() { url.clone() } else { title }, url, tags, added })
+        })
+        .collect()
+}
+
+// Bookmarks as a Netscape bookmark file any browser can import. Tags go in
+// the TAGS attribute Firefox reads; the list itself is flat.
+pub(crate) fn bookmarks_to_html(bookmarks: &[Bookmark]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks</H1>\n\
+         <DL><p>\n",
+    );
+    for bookmark in bookmarks {
+        html.push_str(&format!(
+            "    <DT><A HREF=\"{}\" ADD_DATE=\"{}\"",
+            escape_html(&bookmark.url),
+            bookmark.added.timestamp()
+        ));
+        if !bookmark.tags.is_empty() {
+            html.push_str(&format!(" TAGS=\"{}\"", escape_html(&bookmark.tags.join(","))));
+        }
+        html.push_str(&format!(">{}</A>\n", escape_html(&bookmark.title)));
+    }
+    html.push_str("</DL><p>\n");
+    html
+}
+
+// Quote a CSV field that would otherwise break the row, doubling quotes inside
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// History as CSV with a header row, one line per page
+pub(crate) fn history_to_csv(history: &[HistoryEntry]) -> String {
+    let mut csv = String::from("url,title,query,visits,last_visit\n");
+    for entry in history {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            csv_field(&entry.url),
+            csv_field(&entry.title),
+            csv_field(&entry.query),
+            entry.visits,
+            entry.timestamp.to_rfc3339()
+        ));
+    }
+    csv
+}
+
+// A search result as :export and --output write it
+#[derive(Serialize)]
+struct ExportedResult<'a> {
+    query: &'a str,
+    rank: usize,
+    title: &'a str,
+    url: &'a str,
+    display_url: &'a str,
+    description: &'a str,
+    engine: String,
+}
+
+// Search results as CSV (for a .csv file) or a JSON array (.json), ranked
+// from 1 for each query. Results from a single engine don't record it, so
+// `engine` names the one searched.
+pub(crate) fn results_export(file: &str, searches: &[(&str, &[SearchResult])], engine: &str) -> Result<String, String> {
+    let rows: Vec<ExportedResult> = searches
+        .iter()
+        .flat_map(|(query, results)| {
+            results.iter().enumerate().map(move |(i, result)| ExportedResult {
+                query,
+                rank: i + 1,
+                title: &result.title,
+                url: &result.url,
+                display_url: &result.display_url,
+                description: &result.description,
+                engine: if result.engines.is_empty() { engine.to_string() } else { result.engines.join("+") },
+            })
+        })
+        .collect();
+    let extension = file.rsplit_once('.').map(|(_, extension)| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("json") => serde_json::to_string_pretty(&rows).map(|json| json + "\n").map_err(|e| e.to_string()),
+        Some("csv") => {
+            let mut csv = String::from("query,rank,title,url,display_url,description,engine\n");
+            for row in &rows {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{},{}\n",
+                    csv_field(row.query),
+                    row.rank,
+                    csv_field(row.title),
+                    csv_field(row.url),
+                    csv_field(row.display_url),
+                    csv_field(row.description),
+                    csv_field(&row.engine)
+                ));
+            }
+            Ok(csv)
+        }
+        _ => Err(format!("Export to a .csv or .json file, not {}", file)),
+    }
+}
+
+// A page kept on a number key, see App::set_quickmark
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Quickmark {
+    pub(crate) title: String,
+    pub(crate) url: String,
+}
+
+fn get_quickmarks_path() -> PathBuf {
+    get_config_dir().join("quickmarks.json")
+}
+
+// Quickmarks by their key, '1' to '9'
+pub(crate) fn load_quickmarks() -> BTreeMap<char, Quickmark> {
+    fs::read_to_string(get_quickmarks_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn save_quickmarks(quickmarks: &BTreeMap<char, Quickmark>) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(quickmarks)?;
+    fs::write(get_quickmarks_path(), json)
+}
@@ -0,0 +1,416 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::ui::View;
+
+// Every key binding, per view. App::handle_key feeds keys through
+// PendingKeys, which looks them up here, and the help overlay (?) lists the
+// same tables, so the two can't drift apart.
+// Prompts and popups (table of contents, links, filters...) handle their own
+// keys and show them in their titles.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Action {
+    Help,
+    TogglePrivate,
+    // Home
+    Search,
+    NextSuggestion,
+    PreviousSuggestion,
+    CursorLeft,
+    CursorRight,
+    DeleteChar,
+    QuitIfEmpty,
+    HomeEscape,
+    // Lists (results, gallery, read later, feeds)
+    Down,
+    Up,
+    Open,
+    Close,
+    Delete,
+    MoreResults,
+    Filters,
+    ReopenClosed,
+    ToggleMark,
+    FilterResults,
+    RelatedSearches,
+    SearchCorrection,
+    TogglePreview,
+    OpenTopResults,
+    NextTab,
+    PreviousTab,
+    NextVertical,
+    PreviousVertical,
+    // Page motions; these take a count
+    Left,
+    Right,
+    WordForward,
+    WordBackward,
+    ParagraphForward,
+    ParagraphBackward,
+    NextLink,
+    PreviousLink,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    Top,
+    Bottom,
+    CountDigit,
+    // These wait for one more key, the mark's letter
+    SetMark,
+    JumpToMark,
+    // M{1-9} keeps the page on a number key; the number alone opens it from
+    // Home, and '{1-9} from the results, where digits are a count
+    SetQuickmark,
+    JumpToQuickmark,
+    // Page tools
+    FollowLink,
+    Visual,
+    LinksPanel,
+    TableOfContents,
+    Gallery,
+    Redirects,
+    Headers,
+    ReaderMode,
+    ToggleImages,
+    Fold,
+    FoldAll,
+    SaveMarkdown,
+    YankMarkdown,
+    SaveHtml,
+    Subscribe,
+    OpenPdf,
+    CommandLine,
+    // Shared
+    OpenUrl,
+    Yank,
+    ReadLaterAdd,
+    ReadLaterOpen,
+    // Feeds
+    ToggleRead,
+    MarkAllRead,
+    ShowAll,
+    Refresh,
+    // Load errors
+    Retry,
+    OpenCached,
+    OpenWayback,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct Key(KeyCode, KeyModifiers);
+
+const fn key(c: char) -> Key {
+    Key(KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+const fn code(code: KeyCode) -> Key {
+    Key(code, KeyModifiers::NONE)
+}
+
+const fn ctrl(c: char) -> Key {
+    Key(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+pub(crate) struct Binding {
+    pub(crate) keys: &'static [Key],
+    pub(crate) action: Action,
+    pub(crate) help: &'static str,
+}
+
+const fn bind(keys: &'static [Key], action: Action, help: &'static str) -> Binding {
+    Binding { keys, action, help }
+}
+
+const DIGITS: &[Key] = &[
+    key('0'),
+    key('1'),
+    key('2'),
+    key('3'),
+    key('4'),
+    key('5'),
+    key('6'),
+    key('7'),
+    key('8'),
+    key('9'),
+];
+
+const QUICKMARK_DIGITS: &[Key] = &[
+    key('1'),
+    key('2'),
+    key('3'),
+    key('4'),
+    key('5'),
+    key('6'),
+    key('7'),
+    key('8'),
+    key('9'),
+];
+
+const HOME: &[Binding] = &[
+    bind(&[code(KeyCode::Enter)], Action::Search, "Search, or open a URL"),
+    bind(&[code(KeyCode::Down), code(KeyCode::Tab)], Action::NextSuggestion, "Next suggestion"),
+    bind(&[code(KeyCode::Up), code(KeyCode::BackTab)], Action::PreviousSuggestion, "Previous suggestion"),
+    bind(&[code(KeyCode::Left)], Action::CursorLeft, "Move the cursor left"),
+    bind(&[code(KeyCode::Right)], Action::CursorRight, "Move the cursor right"),
+    bind(&[code(KeyCode::Backspace)], Action::DeleteChar, "Delete the character before the cursor"),
+    bind(QUICKMARK_DIGITS, Action::JumpToQuickmark, "Open a quickmark (when the search box is empty)"),
+    bind(&[ctrl('t')], Action::ReopenClosed, "Reopen the last closed page"),
+    bind(&[ctrl('p')], Action::TogglePrivate, "Private mode on or off"),
+    bind(&[key('?')], Action::Help, "This help (when the search box is empty)"),
+    bind(&[key('q')], Action::QuitIfEmpty, "Quit (when the search box is empty)"),
+    bind(&[code(KeyCode::Esc)], Action::HomeEscape, "Close suggestions, or quit"),
+];
+
+const RESULTS: &[Binding] = &[
+    bind(&[key('j'), code(KeyCode::Down)], Action::Down, "Next result"),
+    bind(&[key('k'), code(KeyCode::Up)], Action::Up, "Previous result"),
+    bind(&[code(KeyCode::Enter), key('l'), code(KeyCode::Right)], Action::Open, "Open the result (result N with a count)"),
+    bind(&[key('n')], Action::MoreResults, "Load more results"),
+    bind(DIGITS, Action::CountDigit, "Count for the next key, e.g. 5j or 3Enter"),
+    bind(&[key('\'')], Action::JumpToQuickmark, "Open a quickmark"),
+    bind(&[key('u'), ctrl('t')], Action::ReopenClosed, "Reopen the last closed page"),
+    bind(&[key('/')], Action::FilterResults, "Filter the results as you type (Esc clears)"),
+    bind(&[key('r')], Action::RelatedSearches, "Pick a related search"),
+    bind(&[key('c')], Action::SearchCorrection, "Search for the suggested spelling"),
+    bind(&[key(' ')], Action::ToggleMark, "Mark the result for O, a or y"),
+    bind(&[key('O')], Action::OpenTopResults, "Open the marked or top results in background tabs"),
+    bind(&[key('K')], Action::NextTab, "Next background tab"),
+    bind(&[key('J')], Action::PreviousTab, "Previous background tab"),
+    bind(&[key('f')], Action::Filters, "Search filters"),
+    bind(&[key('p')], Action::TogglePreview, "Page preview beside the results"),
+    bind(&[code(KeyCode::Tab)], Action::NextVertical, "Next tab (web, news, images, videos)"),
+    bind(&[code(KeyCode::BackTab)], Action::PreviousVertical, "Previous tab"),
+    bind(&[key('o')], Action::OpenUrl, "Open a URL"),
+    bind(&[key('y')], Action::Yank, "Copy the result's URL, or every marked one"),
+    bind(&[key('Y')], Action::YankMarkdown, "Copy the results (or the marked ones) as a Markdown list"),
+    bind(&[key('s')], Action::SaveMarkdown, "Save the results (or the marked ones) as a Markdown list"),
+    bind(&[key(':')], Action::CommandLine, "Command line, e.g. :export results.csv"),
+    bind(&[key('a')], Action::ReadLaterAdd, "Save to read later (every marked result)"),
+    bind(&[key('A')], Action::ReadLaterOpen, "Read later list"),
+    bind(&[ctrl('p')], Action::TogglePrivate, "Private mode on or off"),
+    bind(&[key('?')], Action::Help, "This help"),
+    bind(&[key('q'), code(KeyCode::Esc)], Action::Close, "Back to the home screen"),
+];
+
+const PAGE: &[Binding] = &[
+    bind(&[key('h'), code(KeyCode::Left)], Action::Left, "Cursor left"),
+    bind(&[key('l'), code(KeyCode::Right)], Action::Right, "Cursor right"),
+    bind(&[key('j'), code(KeyCode::Down)], Action::Down, "Cursor down"),
+    bind(&[key('k'), code(KeyCode::Up)], Action::Up, "Cursor up"),
+    bind(&[key('w')], Action::WordForward, "Next word"),
+    bind(&[key('b')], Action::WordBackward, "Previous word"),
+    bind(&[key('}')], Action::ParagraphForward, "Next paragraph"),
+    bind(&[key('{')], Action::ParagraphBackward, "Previous paragraph"),
+    bind(&[key('L'), code(KeyCode::Tab)], Action::NextLink, "Next link"),
+    bind(&[key('H'), code(KeyCode::BackTab)], Action::PreviousLink, "Previous link"),
+    bind(&[key(' '), key('d'), ctrl('d'), code(KeyCode::PageDown)], Action::HalfPageDown, "Scroll down half a screen"),
+    bind(&[key('u'), ctrl('u'), code(KeyCode::PageUp)], Action::HalfPageUp, "Scroll up half a screen"),
+    bind(&[ctrl('f')], Action::PageDown, "Scroll down a screen"),
+    bind(&[ctrl('b')], Action::PageUp, "Scroll up a screen"),
+    bind(&[key('g'), code(KeyCode::Home)], Action::Top, "Top of the page"),
+    bind(&[key('G'), code(KeyCode::End)], Action::Bottom, "End of the page, or line N with a count"),
+    bind(DIGITS, Action::CountDigit, "Count for the next motion, e.g. 20j"),
+    bind(&[key('m')], Action::SetMark, "Mark the position"),
+    bind(&[key('\'')], Action::JumpToMark, "Jump to a mark ('' jumps back)"),
+    bind(&[key('M')], Action::SetQuickmark, "Keep the page on a number key"),
+    bind(&[key('K')], Action::NextTab, "Next background tab"),
+    bind(&[key('J')], Action::PreviousTab, "Previous background tab"),
+    bind(&[code(KeyCode::Enter)], Action::FollowLink, "Follow the link under the cursor"),
+    bind(&[key('v'), key('V')], Action::Visual, "Visual selection (y copies it)"),
+    bind(&[key('f')], Action::LinksPanel, "All links"),
+    bind(&[key('t')], Action::TableOfContents, "Table of contents"),
+    bind(&[key('i')], Action::Gallery, "Image gallery"),
+    bind(&[ctrl('g')], Action::Redirects, "How we got here (redirects)"),
+    bind(&[key('=')], Action::Headers, "Response headers"),
+    bind(&[key('R')], Action::ReaderMode, "Reader mode"),
+    bind(&[key('I')], Action::ToggleImages, "Images on or off"),
+    bind(&[key('z')], Action::Fold, "Fold the JSON node or comment under the cursor"),
+    bind(&[key('Z')], Action::FoldAll, "Unfold everything, or fold every top-level comment"),
+    bind(&[key('s')], Action::SaveMarkdown, "Save as Markdown"),
+    bind(&[key('S')], Action::SaveHtml, "Save the HTML"),
+    bind(&[key('F')], Action::Subscribe, "Subscribe to the page's feed"),
+    bind(&[key('P')], Action::OpenPdf, "Read the paper's PDF (arXiv and journal pages)"),
+    bind(&[key(':')], Action::CommandLine, "Command line, e.g. :set nonumber"),
+    bind(&[key('o')], Action::OpenUrl, "Open a URL"),
+    bind(&[key('y')], Action::Yank, "Copy the page URL (or the selection)"),
+    bind(&[key('a')], Action::ReadLaterAdd, "Save to read later"),
+    bind(&[key('A')], Action::ReadLaterOpen, "Read later list"),
+    bind(&[ctrl('p')], Action::TogglePrivate, "Private mode on or off"),
+    bind(&[key('?')], Action::Help, "This help"),
+    bind(&[key('q'), code(KeyCode::Esc)], Action::Close, "Leave visual mode, or go back"),
+];
+
+const GALLERY: &[Binding] = &[
+    bind(&[key('j'), code(KeyCode::Down), key('n')], Action::Down, "Next image"),
+    bind(&[key('k'), code(KeyCode::Up), key('p')], Action::Up, "Previous image"),
+    bind(&[code(KeyCode::Enter), key('l'), code(KeyCode::Right)], Action::Open, "Show the image full width"),
+    bind(&[key('o')], Action::OpenUrl, "Open a URL"),
+    bind(&[key('?')], Action::Help, "This help"),
+    bind(&[key('q'), code(KeyCode::Esc)], Action::Close, "Back to the list, or to the page"),
+];
+
+const READ_LATER: &[Binding] = &[
+    bind(&[key('j'), code(KeyCode::Down)], Action::Down, "Next entry"),
+    bind(&[key('k'), code(KeyCode::Up)], Action::Up, "Previous entry"),
+    bind(&[code(KeyCode::Enter), key('l'), code(KeyCode::Right)], Action::Open, "Open the saved page"),
+    bind(&[key('d'), code(KeyCode::Delete)], Action::Delete, "Delete the entry"),
+    bind(&[key('?')], Action::Help, "This help"),
+    bind(&[key('q'), code(KeyCode::Esc)], Action::Close, "Close the list"),
+];
+
+const FEEDS: &[Binding] = &[
+    bind(&[key('j'), code(KeyCode::Down)], Action::Down, "Next entry"),
+    bind(&[key('k'), code(KeyCode::Up)], Action::Up, "Previous entry"),
+    bind(&[code(KeyCode::Enter), key('l'), code(KeyCode::Right)], Action::Open, "Open the entry"),
+    bind(&[key('m')], Action::ToggleRead, "Mark read or unread"),
+    bind(&[key('M')], Action::MarkAllRead, "Mark everything read"),
+    bind(&[key('u')], Action::ShowAll, "Show read entries too"),
+    bind(&[key('r')], Action::Refresh, "Refresh the feeds"),
+    bind(&[key('?')], Action::Help, "This help"),
+    bind(&[key('q'), code(KeyCode::Esc)], Action::Close, "Close the feed reader"),
+];
+
+const LOAD_ERROR: &[Binding] = &[
+    bind(&[key('r')], Action::Retry, "Try again"),
+    bind(&[key('c')], Action::OpenCached, "Open the cached copy"),
+    bind(&[key('w')], Action::OpenWayback, "Open the Wayback Machine's copy"),
+    bind(&[key('K')], Action::NextTab, "Next background tab"),
+    bind(&[key('J')], Action::PreviousTab, "Previous background tab"),
+    bind(&[key('y')], Action::Yank, "Copy the URL"),
+    bind(&[key('?')], Action::Help, "This help"),
+    bind(&[key('q'), code(KeyCode::Esc)], Action::Close, "Go back"),
+];
+
+pub(crate) fn bindings(view: &View) -> &'static [Binding] {
+    match view {
+        View::Home => HOME,
+        View::SearchResults => RESULTS,
+        View::WebPage => PAGE,
+        View::ImageGallery => GALLERY,
+        View::ReadLater => READ_LATER,
+        View::Feeds => FEEDS,
+        View::LoadError => LOAD_ERROR,
+    }
+}
+
+// Larger counts are cut down to this, which is still past the end of any page
+const MAX_COUNT: usize = 99_999;
+
+// Keys typed ahead of a command, vim-style. Digits bound to CountDigit build
+// up a count (0 only continues one) that goes to the next command, and
+// actions like SetMark take the key after them as their argument.
+#[derive(Default)]
+pub(crate) struct PendingKeys {
+    count: Option<usize>,
+    awaiting: Option<Action>,
+}
+
+pub(crate) enum Step {
+    // Part of a prefix; wait for the next key
+    Pending,
+    // Run the action, with the count typed before it
+    Run(Action, Option<usize>),
+    // Run an action that took the next character as its argument
+    RunWith(Action, char),
+    // Not bound in this view; any prefix is dropped
+    Unbound,
+}
+
+impl PendingKeys {
+    pub(crate) fn feed(&mut self, view: &View, code: KeyCode, modifiers: KeyModifiers) -> Step {
+        if let Some(action) = self.awaiting.take() {
+            self.count = None;
+            return match code {
+                KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => Step::RunWith(action, c),
+                // Esc or anything else cancels
+                _ => Step::Pending,
+            };
+        }
+        match (lookup(view, code, modifiers), code) {
+            // The digit is the quickmark
+            (Some(Action::JumpToQuickmark), KeyCode::Char(c)) if c.is_ascii_digit() => {
+                self.count = None;
+                Step::RunWith(Action::JumpToQuickmark, c)
+            }
+            (
+                Some(action @ (Action::SetMark | Action::JumpToMark | Action::SetQuickmark | Action::JumpToQuickmark)),
+                _,
+            ) => {
+                self.awaiting = Some(action);
+                Step::Pending
+            }
+            (Some(Action::CountDigit), KeyCode::Char('0')) if self.count.is_none() => Step::Unbound,
+            (Some(Action::CountDigit), KeyCode::Char(c)) => {
+                let digit = c.to_digit(10).unwrap_or(0) as usize;
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit).min(MAX_COUNT));
+                Step::Pending
+            }
+            (Some(action), _) => Step::Run(action, self.count.take()),
+            (None, _) => {
+                self.count = None;
+                Step::Unbound
+            }
+        }
+    }
+
+    // The count typed so far, for the status bar
+    pub(crate) fn count(&self) -> Option<usize> {
+        self.count
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.count = None;
+        self.awaiting = None;
+    }
+}
+
+// Shift is part of the character for letters and symbols, so it is ignored
+pub(crate) fn lookup(view: &View, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+    let modifiers = modifiers & KeyModifiers::CONTROL;
+    bindings(view)
+        .iter()
+        .find(|binding| binding.keys.contains(&Key(code, modifiers)))
+        .map(|binding| binding.action)
+}
+
+// How a binding's keys are written in the help, e.g. "j ↓"
+pub(crate) fn keys_label(binding: &Binding) -> String {
+    match binding.action {
+        Action::CountDigit => return "0-9".to_string(),
+        Action::SetMark => return "m{a-z}".to_string(),
+        Action::JumpToMark => return "'{a-z}".to_string(),
+        Action::SetQuickmark => return "M{1-9}".to_string(),
+        Action::JumpToQuickmark if binding.keys == QUICKMARK_DIGITS => return "1-9".to_string(),
+        Action::JumpToQuickmark => return "'{1-9}".to_string(),
+        _ => {}
+    }
+    binding.keys.iter().map(key_name).collect::<Vec<_>>().join(" ")
+}
+
+fn key_name(key: &Key) -> String {
+    let name = match key.0 {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::BackTab => "S-Tab".to_string(),
+        KeyCode::Backspace => "Bksp".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        other => format!("{:?}", other),
+    };
+    if key.1.contains(KeyModifiers::CONTROL) {
+        format!("Ctrl-{}", name)
+    } else {
+        name
+    }
+}
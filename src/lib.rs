@@ -0,0 +1,22 @@
+//! navim: a terminal web browser and search client. The extraction pipeline
+//! (fetching, decoding and rendering a page to styled text) is exposed so other
+//! tools can embed it; the TUI itself is started with `run`.
+
+mod answer;
+mod config;
+mod history;
+mod keymap;
+mod net;
+mod render;
+mod search;
+mod sites;
+mod term;
+mod ui;
+
+pub use answer::{instant_answer_with, InstantAnswer};
+pub use net::{fetch_page, fetch_page_with, fixture_name, CancelToken, Fetched, Fetcher, Fixtures};
+pub use render::{
+    extract_content_with_images, HtmlRenderer, PageHeading, PageImage, PageLink, PageMeta, Redirect, RenderedPage,
+};
+pub use search::{correction_with, search_with, vertical_search_with, Correction, SearchResult, Vertical};
+pub use ui::run;
@@ -0,0 +1,1595 @@
+use base64::Engine;
+use chrono::{DateTime, Local};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::config::{config, get_config_dir, HttpConfig, RequestSettings};
+use crate::render::{
+    decode_body, expand_tabs, extract_content_with_images, feed_to_html, html_meta, image_to_ascii, is_markdown,
+    is_pdf, is_plain_text, markdown_to_html, parse_feed, parse_feed_body, parse_json_body, render_pdf,
+    sanitize_display, LoadStats, PageMeta, ParsedFeed, Redirect, RenderedPage,
+};
+use crate::sites::{site_api_url, site_html};
+
+// Fetch an image and convert to ASCII
+pub(crate) fn fetch_image_as_ascii(image_url: &str, max_width: u32) -> Option<String> {
+    let bytes = fetch_image_bytes(image_url)?;
+    image_to_ascii(&bytes, max_width)
+}
+
+// Fetch raw image bytes, skipping formats we can't render
+pub(crate) fn fetch_image_bytes(image_url: &str) -> Option<Vec<u8>> {
+    if is_blocked(image_url) {
+        return None;
+    }
+    if let Some(fixtures) = fixtures() {
+        return fixtures.fetch(image_url).ok().map(|fetched| fetched.body);
+    }
+    // Images next to a local HTML file
+    if let Some(path) = local_file_path(image_url) {
+        let is_image = matches!(guess_content_type(&path), Some(ct) if ct.starts_with("image/") && !ct.contains("svg") && !ct.contains("gif"));
+        return is_image.then(|| fs::read(path).ok()).flatten();
+    }
+
+    let _permit = wait_for_host(image_url, &CancelToken::default()).ok()?;
+    let client = configured_client(RequestKind::Image).ok()?.build().ok()?;
+
+    let response = client.get(image_url).send().ok()?;
+
+    // Check content type to ensure it's an image
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !content_type.starts_with("image/") {
+        return None;
+    }
+
+    // Skip SVG and GIF (animated)
+    if content_type.contains("svg") || content_type.contains("gif") {
+        return None;
+    }
+
+    let bytes = response.bytes().ok()?;
+
+    // Skip very small images (likely icons/tracking pixels)
+    if bytes.len() < 1000 {
+        return None;
+    }
+
+    Some(bytes.to_vec())
+}
+
+// Paths typed into prompts may start with ~
+pub(crate) fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ if path == "~" => dirs::home_dir().unwrap_or_else(|| PathBuf::from(path)),
+        _ => PathBuf::from(path),
+    }
+}
+
+// The path behind a file:// URL
+pub(crate) fn local_file_path(url: &str) -> Option<PathBuf> {
+    Url::parse(url).ok().filter(|u| u.scheme() == "file")?.to_file_path().ok()
+}
+
+// A file:// URL for something typed as a path: anything starting with /,
+// ./, ../ or ~/, or the name of a file in the current directory
+pub(crate) fn path_to_file_url(input: &str) -> Option<String> {
+    let explicit = ["/", "./", "../", "~/"].iter().any(|p| input.starts_with(p));
+    let path = expand_tilde(input);
+    if !explicit && !path.is_file() {
+        return None;
+    }
+    let absolute = fs::canonicalize(&path).ok()?;
+    Url::from_file_path(absolute).ok().map(|u| u.to_string())
+}
+
+// Content type for a local file, from its extension. Unknown types are left
+// to the same sniffing as untyped HTTP responses.
+fn guess_content_type(path: &std::path::Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    Some(match ext.as_str() {
+        "html" | "htm" | "xhtml" => "text/html",
+        "txt" | "text" | "log" | "rst" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "rss" => "application/rss+xml",
+        "atom" => "application/atom+xml",
+        "xml" => "application/xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    })
+}
+
+// Each subscription's URL and what fetching it gave
+pub(crate) type FeedResults = Vec<(String, Result<ParsedFeed, String>)>;
+
+// Fetch every subscription at once on a background thread
+pub(crate) fn start_feed_refresh(urls: Vec<String>) -> mpsc::Receiver<FeedResults> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let results = thread::scope(|scope| {
+            let handles: Vec<_> = urls
+                .iter()
+                .map(|url| (url.clone(), scope.spawn(move || fetch_feed(url).map_err(|e| e.to_string()))))
+                .collect();
+            handles
+                .into_iter()
+                .map(|(url, handle)| (url, handle.join().unwrap_or_else(|_| Err("fetch thread panicked".to_string()))))
+                .collect()
+        });
+        tx.send(results).ok();
+    });
+    rx
+}
+
+pub(crate) fn fetch_feed(url: &str) -> Result<ParsedFeed, Box<dyn Error>> {
+    let fetched = match fixtures() {
+        Some(fixtures) => fixtures.fetch(url)?,
+        None => fetch_cached(url, &CancelToken::default(), &|_| {})?,
+    };
+    parse_feed(&fetched.body, &fetched.url).ok_or_else(|| "not an RSS or Atom feed".into())
+}
+
+// Where a link goes, from the start of the page: enough to decide whether
+// to follow it
+pub(crate) struct LinkPreview {
+    pub(crate) url: String, // After redirects
+    pub(crate) meta: PageMeta,
+    pub(crate) content_type: Option<String>,
+}
+
+// Only this much of a page is read for its preview; <head> comes first
+const LINK_PREVIEW_BYTES: u64 = 64 * 1024;
+
+// Fetch the first part of a page for its title and description. Servers that
+// honor the Range header send no more than that; the rest of the body is
+// never read either way.
+pub(crate) fn fetch_link_preview(url: &str) -> Result<LinkPreview, Box<dyn Error>> {
+    use reqwest::header::{CONTENT_TYPE, RANGE};
+    use std::io::Read;
+
+    if is_blocked(url) {
+        return Err("blocked host".into());
+    }
+    let (body, content_type, url) = if let Some(fixtures) = fixtures() {
+        let fetched = fixtures.fetch(url)?;
+        (fetched.body, fetched.content_type, fetched.url)
+    } else if let Some(path) = local_file_path(url) {
+        (fs::read(&path)?, guess_content_type(&path).map(str::to_string), url.to_string())
+    } else {
+        let url = https_upgrade(url).unwrap_or_else(|| url.to_string());
+        let _permit = wait_for_host(&url, &CancelToken::default())?;
+        let client = configured_client(RequestKind::Page)?.build()?;
+        let response = client.get(&url).header(RANGE, format!("bytes=0-{}", LINK_PREVIEW_BYTES - 1)).send()?;
+        info!(url, status = response.status().as_u16(), "GET (link preview)");
+        if !response.status().is_success() {
+            return Err(Box::new(HttpStatus {
+                status: response.status(),
+                response: Fetched {
+                    url: response.url().to_string(),
+                    status: Some(response.status().as_u16()),
+                    ..Default::default()
+                },
+            }));
+        }
+        let content_type = response.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let final_url = response.url().to_string();
+        let mut body = Vec::new();
+        response.take(LINK_PREVIEW_BYTES).read_to_end(&mut body)?;
+        (body, content_type, final_url)
+    };
+    let is_html = content_type.as_deref().is_none_or(|ct| ct.contains("html"));
+    let meta = if is_html { html_meta(&decode_body(&body, content_type.as_deref())) } else { PageMeta::default() };
+    Ok(LinkPreview { url, meta, content_type })
+}
+
+// Shared flag telling a background fetch to stop. A request already on the
+// wire runs to its timeout, but nothing further (e.g. images) is fetched.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Validators and metadata stored next to a cached body
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    content_type: Option<String>,
+    pub(crate) fetched: DateTime<Local>,
+}
+
+fn get_cache_dir() -> PathBuf {
+    get_config_dir().join("cache")
+}
+
+// File name for a URL's cache entry (FNV-1a, so it stays the same across builds).
+// Fragments only pick a section of the same document.
+fn cache_key(url: &str) -> String {
+    let without_fragment = url.split('#').next().unwrap_or(url);
+    let hash = without_fragment
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+pub(crate) fn read_cache(url: &str) -> Option<(CacheEntry, Vec<u8>)> {
+    if private_enabled() {
+        return None;
+    }
+    let base = get_cache_dir().join(cache_key(url));
+    let entry: CacheEntry = serde_json::from_str(&fs::read_to_string(base.with_extension("json")).ok()?).ok()?;
+    let body = fs::read(base.with_extension("body")).ok()?;
+    Some((entry, body))
+}
+
+// Caching is best effort; a failed write just means a refetch next time
+fn write_cache(entry: &CacheEntry, body: &[u8]) {
+    if private_enabled() {
+        return;
+    }
+    let dir = get_cache_dir();
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let base = dir.join(cache_key(&entry.url));
+    if fs::write(base.with_extension("body"), body).is_ok() {
+        if let Ok(json) = serde_json::to_string(entry) {
+            fs::write(base.with_extension("json"), json).ok();
+        }
+    }
+}
+
+// Politeness limits for any one host. Page loads, prefetching, image fetching
+// and SERP pagination all queue here, so together they can't hammer a site.
+const DEFAULT_HOST_DELAY_MS: u64 = 100;
+const DEFAULT_HOST_CONNECTIONS: usize = 6;
+
+// Requests in flight to a host, and when the next may start
+struct HostSlot {
+    host: String,
+    active: usize,
+    next_start: Instant,
+}
+
+static HOST_SLOTS: Mutex<Vec<HostSlot>> = Mutex::new(Vec::new());
+static HOST_FREED: Condvar = Condvar::new();
+
+// Held for the length of a request; dropping it lets the next one in
+pub(crate) struct HostPermit(String);
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+        let mut hosts = HOST_SLOTS.lock().unwrap();
+        if let Some(slot) = hosts.iter_mut().find(|slot| slot.host == self.0) {
+            slot.active = slot.active.saturating_sub(1);
+        }
+        HOST_FREED.notify_all();
+    }
+}
+
+// Wait until the URL's host may take another request
+pub(crate) fn wait_for_host(url: &str, cancel: &CancelToken) -> Result<HostPermit, Box<dyn Error>> {
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_lowercase))
+        .unwrap_or_default();
+    let delay = Duration::from_millis(config().host_delay_ms.unwrap_or(DEFAULT_HOST_DELAY_MS));
+    let limit = config().host_connections.unwrap_or(DEFAULT_HOST_CONNECTIONS).max(1);
+
+    let started = Instant::now();
+    let mut hosts = HOST_SLOTS.lock().unwrap();
+    loop {
+        if cancel.is_canceled() {
+            return Err("canceled".into());
+        }
+        let now = Instant::now();
+        hosts.retain(|slot| slot.active > 0 || slot.next_start > now);
+        let wait = match hosts.iter_mut().find(|slot| slot.host == host) {
+            None => {
+                hosts.push(HostSlot {
+                    host: host.clone(),
+                    active: 1,
+                    next_start: now + delay,
+                });
+                log_host_wait(&host, started);
+                return Ok(HostPermit(host));
+            }
+            Some(slot) if slot.active < limit && slot.next_start <= now => {
+                slot.active += 1;
+                slot.next_start = now + delay;
+                log_host_wait(&host, started);
+                return Ok(HostPermit(host));
+            }
+            // Wake up for a freed slot, the end of the delay, or to check for cancel
+            Some(slot) => slot.next_start.saturating_duration_since(now).clamp(Duration::from_millis(1), Duration::from_millis(50)),
+        };
+        hosts = HOST_FREED.wait_timeout(hosts, wait).unwrap().0;
+    }
+}
+
+fn log_host_wait(host: &str, started: Instant) {
+    let waited = started.elapsed();
+    if waited >= Duration::from_millis(1) {
+        debug!(host, waited_ms = waited.as_millis() as u64, "waited for the per-host limit");
+    }
+}
+
+const DEFAULT_RETRIES: u32 = 2;
+// Backoff before the second attempt; doubled for each one after that
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+pub(crate) fn max_attempts() -> u32 {
+    1 + config().retries.unwrap_or(DEFAULT_RETRIES)
+}
+
+// Timeouts and dropped connections are worth another try; DNS failures,
+// refused connections and bad URLs are not
+fn is_transient(e: &reqwest::Error) -> bool {
+    if e.is_timeout() {
+        return true;
+    }
+    let mut source = e.source();
+    while let Some(err) = source {
+        if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            return matches!(
+                io_err.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = err.source();
+    }
+    false
+}
+
+// Send a request, retrying transient failures with exponential backoff.
+// `on_retry` is told the number of each attempt after the first. A server
+// error that outlasts the retries is returned as the response.
+pub(crate) fn send_with_retry(
+    request: reqwest::blocking::RequestBuilder,
+    cancel: &CancelToken,
+    on_retry: &dyn Fn(u32),
+) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+    let attempts = max_attempts();
+    let mut attempt = 1;
+    loop {
+        // Requests without a streamed body can always be cloned
+        let Some(this_try) = request.try_clone().filter(|_| attempt < attempts) else {
+            return request.send().map_err(|e| retry_error(e, attempt));
+        };
+        match this_try.send() {
+            Ok(response) if !response.status().is_server_error() => return Ok(response),
+            Err(e) if !is_transient(&e) => return Err(e.into()),
+            _ => {}
+        }
+
+        let delay = Duration::from_millis(RETRY_BASE_DELAY_MS << (attempt - 1));
+        warn!(attempt, delay_ms = delay.as_millis() as u64, "transient failure, retrying");
+        let deadline = Instant::now() + delay;
+        while Instant::now() < deadline {
+            if cancel.is_canceled() {
+                return Err("canceled".into());
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        attempt += 1;
+        on_retry(attempt);
+    }
+}
+
+fn retry_error(e: reqwest::Error, attempts: u32) -> Box<dyn Error> {
+    if attempts > 1 {
+        format!("{} (after {} attempts)", e, attempts).into()
+    } else {
+        e.into()
+    }
+}
+
+// A page needs a login we don't have (or the one we sent was refused)
+#[derive(Debug)]
+pub(crate) struct AuthRequired {
+    pub(crate) host: String,
+    pub(crate) realm: Option<String>,
+    pub(crate) rejected: bool,
+}
+
+impl std::fmt::Display for AuthRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} requires a login", self.host)
+    }
+}
+
+impl Error for AuthRequired {}
+
+// The server answered with a 4xx or 5xx status instead of the page. What it
+// sent comes along, so its own error page can be shown instead.
+pub(crate) struct HttpStatus {
+    pub(crate) status: reqwest::StatusCode,
+    pub(crate) response: Fetched,
+}
+
+impl std::fmt::Display for HttpStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "HTTP {}", self.status)
+    }
+}
+
+impl std::fmt::Debug for HttpStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "HttpStatus({}, {})", self.status, self.response.url)
+    }
+}
+
+impl Error for HttpStatus {}
+
+// Logins typed this session, by host. Never written to disk.
+static CREDENTIALS: Mutex<Vec<(String, String, String)>> = Mutex::new(Vec::new());
+
+pub(crate) fn remember_credentials(host: &str, username: &str, password: &str) {
+    let mut credentials = CREDENTIALS.lock().unwrap();
+    credentials.retain(|(h, _, _)| h != host);
+    credentials.push((host.to_string(), username.to_string(), password.to_string()));
+}
+
+fn forget_credentials(host: &str) {
+    CREDENTIALS.lock().unwrap().retain(|(h, _, _)| h != host);
+}
+
+fn credentials_for(host: &str) -> Option<(String, String)> {
+    let credentials = CREDENTIALS.lock().unwrap();
+    credentials.iter().find(|(h, _, _)| h == host).map(|(_, u, p)| (u.clone(), p.clone()))
+}
+
+// Split `key=value, key="quoted, value"` challenge parameters
+fn parse_auth_params(params: &str) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    let mut rest = params.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_lowercase();
+        let after = after.trim_start();
+        let (value, remaining) = if let Some(quoted) = after.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+        } else {
+            let end = after.find(',').unwrap_or(after.len());
+            (after[..end].trim(), &after[end..])
+        };
+        map.insert(key, value.to_string());
+        rest = remaining.trim_start().trim_start_matches(',');
+    }
+    map
+}
+
+fn md5_hex(input: &str) -> String {
+    use md5::{Digest, Md5};
+    Md5::digest(input.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Build the Authorization header for a Basic or Digest (MD5) challenge
+fn authorization_header(challenge: &str, url: &Url, username: &str, password: &str) -> Option<String> {
+    let (scheme, params) = challenge.trim().split_once(' ').unwrap_or((challenge.trim(), ""));
+    if scheme.eq_ignore_ascii_case("basic") {
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        return Some(format!("Basic {}", token));
+    }
+    if !scheme.eq_ignore_ascii_case("digest") {
+        return None;
+    }
+
+    let params = parse_auth_params(params);
+    let realm = params.get("realm").map_or("", String::as_str);
+    let nonce = params.get("nonce")?;
+    let algorithm = params.get("algorithm").map_or("MD5", String::as_str);
+    let uri = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let nc = "00000001";
+    // Only needs to be unpredictable enough not to repeat
+    let cnonce = cache_key(&format!("{}{:?}", nonce, Instant::now()));
+
+    let mut ha1 = md5_hex(&format!("{}:{}:{}", username, realm, password));
+    if algorithm.eq_ignore_ascii_case("md5-sess") {
+        ha1 = md5_hex(&format!("{}:{}:{}", ha1, nonce, cnonce));
+    } else if !algorithm.eq_ignore_ascii_case("md5") {
+        return None;
+    }
+    let ha2 = md5_hex(&format!("GET:{}", uri));
+    let qop_auth = params
+        .get("qop")
+        .is_some_and(|qop| qop.split(',').any(|q| q.trim().eq_ignore_ascii_case("auth")));
+
+    let mut header = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}",
+        username, realm, nonce, uri, algorithm
+    );
+    if qop_auth {
+        let response = md5_hex(&format!("{}:{}:{}:{}:auth:{}", ha1, nonce, nc, cnonce, ha2));
+        header.push_str(&format!(", response=\"{}\", qop=auth, nc={}, cnonce=\"{}\"", response, nc, cnonce));
+    } else {
+        let response = md5_hex(&format!("{}:{}:{}", ha1, nonce, ha2));
+        header.push_str(&format!(", response=\"{}\"", response));
+    }
+    if let Some(opaque) = params.get("opaque") {
+        header.push_str(&format!(", opaque=\"{}\"", opaque));
+    }
+    Some(header)
+}
+
+// Answer a 401 with the login remembered for the host, or ask the UI for one.
+// Responses without a challenge we understand are returned as they are.
+fn answer_auth_challenge(
+    request: reqwest::blocking::RequestBuilder,
+    response: reqwest::blocking::Response,
+    cancel: &CancelToken,
+    on_retry: &dyn Fn(u32),
+) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+    let url = response.url().clone();
+    let host = url.host_str().unwrap_or_default().to_string();
+    let challenges: Vec<String> = response
+        .headers()
+        .get_all(reqwest::header::WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|v| v.to_str().ok().map(str::to_string))
+        .collect();
+    // Prefer Digest, which doesn't send the password itself
+    let Some(challenge) = challenges
+        .iter()
+        .find(|c| c.trim_start().to_lowercase().starts_with("digest"))
+        .or_else(|| challenges.iter().find(|c| c.trim_start().to_lowercase().starts_with("basic")))
+    else {
+        return Ok(response);
+    };
+    let realm = parse_auth_params(challenge.trim().split_once(' ').map_or("", |(_, p)| p))
+        .remove("realm");
+
+    let Some((username, password)) = credentials_for(&host) else {
+        return Err(Box::new(AuthRequired { host, realm, rejected: false }));
+    };
+    let Some(authorization) = authorization_header(challenge, &url, &username, &password) else {
+        return Ok(response);
+    };
+    let retried = send_with_retry(request.header(reqwest::header::AUTHORIZATION, authorization), cancel, on_retry)?;
+    if retried.status() == reqwest::StatusCode::UNAUTHORIZED {
+        forget_credentials(&host);
+        return Err(Box::new(AuthRequired { host, realm, rejected: true }));
+    }
+    Ok(retried)
+}
+
+// A fetched body and the redirects followed to get it
+#[derive(Default)]
+pub struct Fetched {
+    pub body: Vec<u8>,
+    pub content_type: Option<String>,
+    pub url: String, // After redirects
+    pub redirects: Vec<Redirect>,
+    pub status: Option<u16>, // None for local files, cache hits and fixtures without one
+    pub cached: bool,
+    pub headers: Vec<(String, String)>,
+}
+
+// Where page and search bodies come from: the network normally, saved files
+// with --offline-fixtures and in the golden tests
+pub trait Fetcher: Sync {
+    fn fetch(&self, url: &str) -> Result<Fetched, Box<dyn Error>>;
+}
+
+// Live requests through the disk cache
+struct Network<'a> {
+    cancel: &'a CancelToken,
+    on_retry: &'a (dyn Fn(u32) + Sync),
+}
+
+impl Fetcher for Network<'_> {
+    fn fetch(&self, url: &str) -> Result<Fetched, Box<dyn Error>> {
+        fetch_cached(url, self.cancel, self.on_retry)
+    }
+}
+
+// Only the disk cache, for when the live copy is gone
+pub(crate) struct CacheOnly;
+
+impl Fetcher for CacheOnly {
+    fn fetch(&self, url: &str) -> Result<Fetched, Box<dyn Error>> {
+        let (entry, body) = read_cache(url).ok_or("no cached copy")?;
+        Ok(Fetched {
+            body,
+            content_type: entry.content_type,
+            url: url.to_string(),
+            cached: true,
+            ..Default::default()
+        })
+    }
+}
+
+// Bodies saved in a directory, one file per URL named by fixture_name with
+// an optional extension that gives the content type. A .redirect file holds
+// the Location of a 302 instead, and a status between the name and the
+// extension (name.404.json) answers with that status. Nothing touches the
+// network, so output only changes when the code does.
+pub struct Fixtures {
+    dir: PathBuf,
+}
+
+impl Fixtures {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Fixtures { dir: dir.into() }
+    }
+
+    fn path(&self, url: &str) -> Option<(PathBuf, Option<u16>)> {
+        let name = fixture_name(url);
+        let exact = self.dir.join(&name);
+        if exact.is_file() {
+            return Some((exact, None));
+        }
+        let mut paths: Vec<(PathBuf, Option<u16>)> = fs::read_dir(&self.dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter_map(|p| {
+                let stem = p.file_stem()?.to_str()?;
+                // "name.404" is the fixture for name, answered with a 404
+                let status = match stem.rsplit_once('.') {
+                    _ if stem == name => None,
+                    Some((rest, status)) if rest == name && status.len() == 3 => Some(status.parse().ok()?),
+                    _ => return None,
+                };
+                Some((p, status))
+            })
+            .collect();
+        paths.sort();
+        paths.into_iter().next()
+    }
+}
+
+impl Fetcher for Fixtures {
+    fn fetch(&self, url: &str) -> Result<Fetched, Box<dyn Error>> {
+        let mut current = url.to_string();
+        let mut redirects = Vec::new();
+        loop {
+            let (path, status) = self.path(&current).ok_or_else(|| {
+                format!("no fixture for {} (expected {})", current, self.dir.join(fixture_name(&current)).display())
+            })?;
+            if path.extension().is_some_and(|ext| ext == "redirect") {
+                if redirects.len() >= MAX_HTTP_REDIRECTS {
+                    return Err(format!("too many redirects (more than {})", MAX_HTTP_REDIRECTS).into());
+                }
+                let next = redirect_target(&current, fs::read_to_string(&path)?.trim())?;
+                redirects.push(Redirect { url: current, reason: "302 Found".to_string() });
+                current = next;
+                continue;
+            }
+            let fetched = Fetched {
+                body: fs::read(&path)?,
+                content_type: guess_content_type(&path).map(str::to_string),
+                url: current,
+                redirects,
+                status,
+                ..Default::default()
+            };
+            return match status.and_then(|s| reqwest::StatusCode::from_u16(s).ok()) {
+                Some(status) if status.is_client_error() || status.is_server_error() => {
+                    Err(Box::new(HttpStatus { status, response: fetched }))
+                }
+                _ => Ok(fetched),
+            };
+        }
+    }
+}
+
+// https://example.com/a?b=c is stored as example.com_a_b_c
+pub fn fixture_name(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let name: String = rest
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-') { c } else { '_' })
+        .collect();
+    name.trim_end_matches('_').to_string()
+}
+
+// Set by --offline-fixtures; every fetch then reads from here
+static FIXTURES: OnceLock<Fixtures> = OnceLock::new();
+
+pub(crate) fn use_fixtures(dir: PathBuf) {
+    FIXTURES.set(Fixtures::new(dir)).ok();
+}
+
+pub(crate) fn fixtures() -> Option<&'static Fixtures> {
+    FIXTURES.get()
+}
+
+// Outcome of requesting one URL
+enum Hop {
+    Done {
+        body: Vec<u8>,
+        content_type: Option<String>,
+        status: Option<u16>,
+        cached: bool,
+        headers: Vec<(String, String)>,
+    },
+    Redirect(String, String), // Status and Location
+}
+
+const MAX_HTTP_REDIRECTS: usize = 10;
+
+// Fetch a URL through the disk cache, following HTTP redirects ourselves so
+// the chain can be shown. A cached copy is revalidated with
+// If-None-Match/If-Modified-Since and served on 304, on a server error, or
+// when the network is unreachable.
+fn fetch_cached(url: &str, cancel: &CancelToken, on_retry: &dyn Fn(u32)) -> Result<Fetched, Box<dyn Error>> {
+    let mut current = url.to_string();
+    let mut redirects = Vec::new();
+    loop {
+        match fetch_hop_secure(&mut current, cancel, on_retry)? {
+            Hop::Done {
+                body,
+                content_type,
+                status,
+                cached,
+                headers,
+            } => {
+                let fetched = Fetched {
+                    body,
+                    content_type,
+                    url: current,
+                    redirects,
+                    status,
+                    cached,
+                    headers,
+                };
+                let failed = status
+                    .and_then(|s| reqwest::StatusCode::from_u16(s).ok())
+                    .filter(|s| !cached && (s.is_client_error() || s.is_server_error()));
+                return match failed {
+                    Some(status) => Err(Box::new(HttpStatus { status, response: fetched })),
+                    None => Ok(fetched),
+                };
+            }
+            Hop::Redirect(reason, location) => {
+                if redirects.len() >= MAX_HTTP_REDIRECTS {
+                    return Err(format!("too many redirects (more than {})", MAX_HTTP_REDIRECTS).into());
+                }
+                let next = redirect_target(&current, &location)?;
+                debug!(from = %current, to = %next, %reason, "HTTP redirect");
+                redirects.push(Redirect { url: current, reason });
+                current = next;
+            }
+        }
+    }
+}
+
+// Where a redirect's Location leads. Only http and https are followed, so a
+// site can't send navim to a local file.
+fn redirect_target(from: &str, location: &str) -> Result<String, Box<dyn Error>> {
+    let next = Url::parse(from)?.join(location)?;
+    if !matches!(next.scheme(), "http" | "https") {
+        return Err(format!("refused a redirect from {} to {}", from, next).into());
+    }
+    Ok(next.to_string())
+}
+
+// In HTTPS-only mode a plain http:// URL is tried over https:// instead, and
+// `url` updated to match. Only a host that can't be reached that way at all is
+// refused; any answer over HTTPS, even an error status, counts.
+fn fetch_hop_secure(url: &mut String, cancel: &CancelToken, on_retry: &dyn Fn(u32)) -> Result<Hop, Box<dyn Error>> {
+    let Some(upgraded) = https_upgrade(url) else {
+        return fetch_hop(url, cancel, on_retry);
+    };
+    match fetch_hop(&upgraded, cancel, on_retry) {
+        Ok(hop) => {
+            *url = upgraded;
+            Ok(hop)
+        }
+        Err(e) if e.is::<AuthRequired>() => Err(e),
+        Err(_) => Err(Box::new(PlaintextRefused {
+            host: Url::parse(url)?.host_str().unwrap_or_default().to_string(),
+        })),
+    }
+}
+
+fn fetch_hop(url: &str, cancel: &CancelToken, on_retry: &dyn Fn(u32)) -> Result<Hop, Box<dyn Error>> {
+    use reqwest::header::{
+        CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION,
+    };
+
+    // Local files are read straight from disk, and never cached
+    if let Some(path) = local_file_path(url) {
+        let body = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        return Ok(Hop::Done {
+            body,
+            content_type: guess_content_type(&path).map(str::to_string),
+            status: None,
+            cached: false,
+            headers: Vec::new(),
+        });
+    }
+
+    let _permit = wait_for_host(url, cancel)?;
+    let cached = read_cache(url);
+    let mut request = page_client()?.get(url);
+    if let Some((entry, _)) = &cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let login_request = request.try_clone();
+    let started = Instant::now();
+    let response = match send_with_retry(request, cancel, on_retry) {
+        Ok(response) if response.status() == reqwest::StatusCode::UNAUTHORIZED => match login_request {
+            Some(login_request) => answer_auth_challenge(login_request, response, cancel, on_retry)?,
+            None => response,
+        },
+        Ok(response) => response,
+        Err(e) => {
+            warn!(url, error = %e, "request failed");
+            return match cached {
+                Some((entry, body)) => Ok(Hop::Done {
+                    body,
+                    content_type: entry.content_type,
+                    status: None,
+                    cached: true,
+                    headers: Vec::new(),
+                }),
+                None => Err(e),
+            }
+        }
+    };
+    let status = response.status();
+    info!(url, status = status.as_u16(), elapsed_ms = started.elapsed().as_millis() as u64, "GET");
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+        .collect();
+    if status == reqwest::StatusCode::NOT_MODIFIED || status.is_server_error() {
+        if let Some((entry, body)) = cached {
+            debug!(url, "serving the cached copy");
+            return Ok(Hop::Done {
+                body,
+                content_type: entry.content_type,
+                status: Some(status.as_u16()),
+                cached: true,
+                headers,
+            });
+        }
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    if status.is_redirection() {
+        if let Some(location) = header(LOCATION) {
+            return Ok(Hop::Redirect(status.to_string(), location));
+        }
+    }
+    let entry = CacheEntry {
+        url: url.to_string(),
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+        content_type: header(CONTENT_TYPE),
+        fetched: Local::now(),
+    };
+    let no_store = header(CACHE_CONTROL).is_some_and(|cc| cc.contains("no-store"));
+    // Some APIs (Stack Exchange's) compress their answers whether asked to or not
+    let gzipped = header(CONTENT_ENCODING).is_some_and(|encoding| encoding.eq_ignore_ascii_case("gzip"));
+
+    let body = response.bytes()?.to_vec();
+    let body = if gzipped { gunzip(&body)? } else { body };
+    debug!(url, bytes = body.len(), content_type = entry.content_type.as_deref(), "read body");
+    if status.is_success() && !no_store {
+        write_cache(&entry, &body);
+    }
+    Ok(Hop::Done {
+        body,
+        content_type: entry.content_type,
+        status: Some(status.as_u16()),
+        cached: false,
+        headers,
+    })
+}
+
+fn gunzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut decoded = Vec::new();
+    flate2::read::MultiGzDecoder::new(body).read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+// The Wayback Machine redirects this to its latest snapshot of the page
+pub(crate) fn wayback_url(url: &str) -> String {
+    format!("https://web.archive.org/web/{}", url)
+}
+
+pub fn fetch_page(
+    url: &str,
+    render_images: bool,
+    cancel: &CancelToken,
+    on_retry: &(dyn Fn(u32) + Sync),
+) -> Result<RenderedPage, Box<dyn Error>> {
+    match fixtures() {
+        Some(fixtures) => fetch_page_with(url, render_images, cancel, fixtures),
+        None => fetch_page_with(url, render_images, cancel, &Network { cancel, on_retry }),
+    }
+}
+
+// Fetch and render a page, getting each URL's body from `fetcher`
+pub fn fetch_page_with(
+    url: &str,
+    render_images: bool,
+    cancel: &CancelToken,
+    fetcher: &dyn Fetcher,
+) -> Result<RenderedPage, Box<dyn Error>> {
+    if is_onion(url) && !tor_enabled() {
+        return Err("onion addresses can only be opened with --tor".into());
+    }
+    let mut current = url.to_string();
+    let mut redirects = Vec::new();
+    let mut hops = 0;
+    let started = Instant::now();
+    let mut fetch_time = Duration::ZERO;
+    let mut bytes = 0;
+    loop {
+        // Pages a site handler reads through the site's API are fetched there
+        let through_api = match site_api_url(&current) {
+            Some(api) => {
+                current = api;
+                true
+            }
+            None => false,
+        };
+        let fetch_started = Instant::now();
+        let fetched = match fetcher.fetch(&current) {
+            Ok(fetched) => fetched,
+            // A site's own 404 or 403 page is shown like any other, with its
+            // status; an API's error body is no use to read
+            Err(e) if !through_api => match e.downcast::<HttpStatus>() {
+                Ok(error) if !error.response.body.trim_ascii().is_empty() => error.response,
+                Ok(error) => return Err(error),
+                Err(e) => return Err(e),
+            },
+            Err(e) => return Err(e),
+        };
+        fetch_time += fetch_started.elapsed();
+        if cancel.is_canceled() {
+            return Err("canceled".into());
+        }
+        bytes += fetched.body.len();
+        let stats = |started: Instant| LoadStats {
+            fetch: fetch_time,
+            render: started.elapsed().saturating_sub(fetch_time),
+            bytes,
+            status: fetched.status,
+            cached: fetched.cached,
+        };
+        let (source, content_type, headers) = (fetched.body, fetched.content_type, fetched.headers);
+        redirects.extend(fetched.redirects);
+        current = fetched.url;
+        // A redirect can land on such a page, as !w searches do
+        if hops < MAX_REFRESH_HOPS && site_api_url(&current).is_some() {
+            hops += 1;
+            continue;
+        }
+        if is_pdf(content_type.as_deref(), &source) {
+            debug!(url = %current, "rendering as PDF");
+            let mut page = render_pdf(&source)?;
+            page.source = source;
+            page.final_url = (current != url).then_some(current);
+            page.redirects = redirects;
+            page.stats = Some(stats(started));
+            page.headers = headers;
+            return Ok(page);
+        }
+        let body = decode_body(&source, content_type.as_deref());
+
+        let mut page = if let Some(site) = site_html(&current, &body, fetcher) {
+            debug!(url = %current, page = %site.url, "rendering through a site handler");
+            current = site.url;
+            let mut page = extract_content_with_images(&site.html, &current, render_images, cancel);
+            if let Some(mut thread) = site.thread {
+                thread.locate(&page.links);
+                page.thread = Some(thread);
+            }
+            page
+        } else if let Some(feed) = parse_feed_body(content_type.as_deref(), &source, &current) {
+            debug!(url = %current, entries = feed.items.len(), "rendering as a feed");
+            extract_content_with_images(&feed_to_html(&feed), &current, render_images, cancel)
+        } else if let Some(mut doc) = parse_json_body(content_type.as_deref(), &body) {
+            debug!(url = %current, "rendering as JSON");
+            let mut page = doc.render();
+            page.json = Some(doc);
+            page
+        } else if is_markdown(content_type.as_deref(), &current) {
+            debug!(url = %current, "rendering as Markdown");
+            let html = markdown_to_html(&body);
+            extract_content_with_images(&html, &current, render_images, cancel)
+        } else if is_plain_text(content_type.as_deref(), &body) {
+            debug!(url = %current, "rendering as plain text");
+            RenderedPage {
+                text: expand_tabs(&body),
+                ..Default::default()
+            }
+        } else {
+            // Redirect stubs have nothing worth showing; go where they point
+            if hops < MAX_REFRESH_HOPS {
+                if let Some((target, reason)) = html_redirect_target(&body, &current) {
+                    debug!(from = %current, to = %target, reason, "HTML redirect");
+                    redirects.push(Redirect { url: current, reason: reason.to_string() });
+                    current = target;
+                    hops += 1;
+                    continue;
+                }
+            }
+            // Extract content with images and links
+            extract_content_with_images(&body, &current, render_images, cancel)
+        };
+        page.text = sanitize_display(&page.text);
+        let stats = stats(started);
+        info!(
+            url = %current,
+            fetch_ms = stats.fetch.as_millis() as u64,
+            render_ms = stats.render.as_millis() as u64,
+            lines = page.text.lines().count(),
+            links = page.links.len(),
+            "rendered"
+        );
+        page.source = source;
+        page.final_url = (current != url).then_some(current);
+        page.redirects = redirects;
+        page.stats = Some(stats);
+        page.headers = headers;
+
+        return Ok(page);
+    }
+}
+
+// How many meta-refresh or interstitial redirects to follow in a row
+const MAX_REFRESH_HOPS: usize = 5;
+// Longer refresh delays are usually a page reloading itself, not a redirect
+const MAX_REFRESH_DELAY_SECS: f32 = 10.0;
+// Interstitials are short; anything longer is a real page with few links
+const MAX_INTERSTITIAL_TEXT: usize = 400;
+
+// Where a redirect stub sends the browser: a <meta http-equiv="refresh">
+// with a URL, or a near-empty "click here to continue" page with one link
+fn html_redirect_target(html: &str, base_url: &str) -> Option<(String, &'static str)> {
+    let document = Html::parse_document(html);
+    let base = Url::parse(base_url).ok()?;
+    // Only a local page may lead to another local file
+    let resolve = |href: &str| {
+        base.join(href.trim())
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https") || (u.scheme() == "file" && base.scheme() == "file"))
+            .map(|u| u.to_string())
+            .filter(|u| u != base_url)
+    };
+
+    let meta = Selector::parse("meta[http-equiv][content]").unwrap();
+    for element in document.select(&meta) {
+        if !element.value().attr("http-equiv").unwrap_or("").eq_ignore_ascii_case("refresh") {
+            continue;
+        }
+        // content="5; url=https://example.com/" (the url= part is optional)
+        let content = element.value().attr("content").unwrap_or("");
+        let (delay, rest) = content.split_once([';', ',']).unwrap_or((content, ""));
+        if delay.trim().parse::<f32>().is_ok_and(|d| d > MAX_REFRESH_DELAY_SECS) {
+            continue;
+        }
+        let rest = rest.trim();
+        let target = match rest.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("url=") => &rest[4..],
+            _ => rest,
+        };
+        let target = target.trim().trim_matches(|c| c == '\'' || c == '"');
+        if let Some(url) = (!target.is_empty()).then(|| resolve(target)).flatten() {
+            return Some((url, "meta refresh"));
+        }
+    }
+
+    let body = Selector::parse("body").unwrap();
+    let text: String = document
+        .select(&body)
+        .next()?
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.chars().count() > MAX_INTERSTITIAL_TEXT {
+        return None;
+    }
+    let anchor = Selector::parse("a[href]").unwrap();
+    let mut links: Vec<String> = document
+        .select(&anchor)
+        .filter_map(|a| a.value().attr("href"))
+        .filter(|href| !href.starts_with('#') && !href.to_lowercase().starts_with("javascript:"))
+        .filter_map(resolve)
+        .collect();
+    links.sort();
+    links.dedup();
+    let [link] = links.as_slice() else {
+        return None;
+    };
+    let lower = text.to_lowercase();
+    ["click here", "continue", "redirect", "proceed", "has moved", "been moved"]
+        .iter()
+        .any(|phrase| lower.contains(phrase))
+        .then(|| (link.clone(), "continue link"))
+}
+
+// Whether a host is the domain or one of its subdomains
+pub(crate) fn host_matches(host: &str, domain: &str) -> bool {
+    let host = host.to_lowercase();
+    let domain = domain.trim().trim_start_matches('.').to_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+// Ad networks and analytics services that embed images (mostly tracking
+// pixels) and click-through links. Subdomains are blocked too.
+const BLOCKED_HOSTS: &[&str] = &[
+    "2mdn.net",
+    "adform.net",
+    "adnxs.com",
+    "ads-twitter.com",
+    "ads.linkedin.com",
+    "adsafeprotected.com",
+    "adservice.google.com",
+    "adsrvr.org",
+    "amazon-adsystem.com",
+    "analytics.twitter.com",
+    "bat.bing.com",
+    "bluekai.com",
+    "casalemedia.com",
+    "chartbeat.com",
+    "chartbeat.net",
+    "clarity.ms",
+    "criteo.com",
+    "criteo.net",
+    "demdex.net",
+    "doubleclick.net",
+    "doubleverify.com",
+    "everesttech.net",
+    "google-analytics.com",
+    "googleadservices.com",
+    "googlesyndication.com",
+    "googletagmanager.com",
+    "googletagservices.com",
+    "hotjar.com",
+    "indexww.com",
+    "krxd.net",
+    "mixpanel.com",
+    "moatads.com",
+    "nr-data.net",
+    "omtrdc.net",
+    "openx.net",
+    "outbrain.com",
+    "pixel.wp.com",
+    "pubmatic.com",
+    "quantserve.com",
+    "rubiconproject.com",
+    "scorecardresearch.com",
+    "stats.wp.com",
+    "taboola.com",
+    "yieldmo.com",
+];
+
+// Whether a URL points at a blocked ad or analytics host
+pub(crate) fn is_blocked(url: &str) -> bool {
+    let Some(host) = Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)) else {
+        return false;
+    };
+    BLOCKED_HOSTS.iter().any(|domain| host_matches(&host, domain))
+        || config().block.iter().any(|domain| host_matches(&host, domain))
+}
+
+// Tor's default SOCKS port. socks5h resolves names through the proxy, which
+// keeps DNS lookups private and is what makes .onion addresses reachable.
+const TOR_PROXY: &str = "socks5h://127.0.0.1:9050";
+
+// Set once at startup by --tor
+pub(crate) static TOR_MODE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn tor_enabled() -> bool {
+    TOR_MODE.load(Ordering::Relaxed)
+}
+
+// Set by --private, Ctrl-p or :set private. Nothing about the session is
+// written to disk: no history, cache or cookies.
+pub(crate) static PRIVATE_MODE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn private_enabled() -> bool {
+    PRIVATE_MODE.load(Ordering::Relaxed)
+}
+
+// Set once at startup by --https-only or the https_only setting
+pub(crate) static HTTPS_ONLY: AtomicBool = AtomicBool::new(false);
+
+// Hosts allowed over plain HTTP for this session despite HTTPS-only mode
+static PLAINTEXT_HOSTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub(crate) fn allow_plaintext(host: &str) {
+    PLAINTEXT_HOSTS.lock().unwrap().push(host.to_string());
+}
+
+// The https:// form of a plain http:// URL, when HTTPS-only mode wants it.
+// Onion services are already encrypted end to end.
+pub(crate) fn https_upgrade(url: &str) -> Option<String> {
+    if !HTTPS_ONLY.load(Ordering::Relaxed) || !url.starts_with("http://") || is_onion(url) {
+        return None;
+    }
+    let mut parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    if PLAINTEXT_HOSTS.lock().unwrap().iter().any(|h| h == host) {
+        return None;
+    }
+    parsed.set_scheme("https").ok()?;
+    Some(parsed.to_string())
+}
+
+// A plain HTTP page that HTTPS-only mode would not load
+#[derive(Debug)]
+pub(crate) struct PlaintextRefused {
+    pub(crate) host: String,
+}
+
+impl std::fmt::Display for PlaintextRefused {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} is not available over HTTPS (HTTPS-only mode)", self.host)
+    }
+}
+
+impl Error for PlaintextRefused {}
+
+// Sent unless the config sets user_agent; sites serve their full pages to it
+const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+// What a client is for, which picks its settings from the [http] config
+#[derive(Clone, Copy)]
+pub(crate) enum RequestKind {
+    Search,
+    Suggest, // Search settings, but a short default timeout while typing
+    Page,
+    Image,
+}
+
+impl RequestKind {
+    fn default_timeout_secs(self) -> u64 {
+        match self {
+            RequestKind::Search | RequestKind::Page => 15,
+            RequestKind::Image => 10,
+            RequestKind::Suggest => 5,
+        }
+    }
+
+    fn settings(self, http: &HttpConfig) -> &RequestSettings {
+        match self {
+            RequestKind::Search | RequestKind::Suggest => &http.search,
+            RequestKind::Page => &http.page,
+            RequestKind::Image => &http.image,
+        }
+    }
+}
+
+// A client builder with the timeout, user agent and headers configured for
+// this kind of request. Configured headers replace the built-in ones.
+pub(crate) fn configured_client(kind: RequestKind) -> reqwest::Result<reqwest::blocking::ClientBuilder> {
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, ACCEPT_LANGUAGE};
+
+    let http = &config().http;
+    let specific = kind.settings(http);
+    let timeout = specific
+        .timeout_secs
+        .or(http.all.timeout_secs)
+        .unwrap_or(kind.default_timeout_secs());
+    let user_agent = specific
+        .user_agent
+        .as_deref()
+        .or(http.all.user_agent.as_deref())
+        .unwrap_or(DEFAULT_USER_AGENT);
+
+    let mut headers = HeaderMap::new();
+    if matches!(kind, RequestKind::Search | RequestKind::Page) {
+        headers.insert(ACCEPT, HeaderValue::from_static("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"));
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.5"));
+    }
+    for (name, value) in http.all.headers.iter().chain(&specific.headers) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+            headers.insert(name, value);
+        }
+    }
+
+    Ok(client_builder()?
+        .user_agent(user_agent)
+        .timeout(Duration::from_secs(timeout))
+        .default_headers(headers))
+}
+
+// Every HTTP client starts here, so that in Tor mode nothing (pages, images,
+// suggestions) bypasses the proxy
+fn client_builder() -> reqwest::Result<reqwest::blocking::ClientBuilder> {
+    let mut builder = reqwest::blocking::Client::builder();
+    if let Some(jar) = cookie_jar() {
+        builder = builder.cookie_provider(jar.clone());
+    }
+    if !tor_enabled() {
+        return Ok(builder);
+    }
+    let proxy = config().tor_proxy.as_deref().unwrap_or(TOR_PROXY);
+    Ok(builder.proxy(reqwest::Proxy::all(proxy)?))
+}
+
+// A cookie set by an allowlisted site
+#[derive(Serialize, Deserialize, Clone)]
+struct StoredCookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool, // No Domain attribute: only sent back to the exact host
+    path: String,
+    expires: Option<DateTime<Local>>, // None for session cookies, which aren't saved
+    secure: bool,
+}
+
+impl StoredCookie {
+    fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|t| t <= Local::now())
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let domain_ok = if self.host_only {
+            host.eq_ignore_ascii_case(&self.domain)
+        } else {
+            host_matches(host, &self.domain)
+        };
+        domain_ok && url.path().starts_with(&self.path) && (!self.secure || url.scheme() == "https") && !self.is_expired()
+    }
+}
+
+// Parse a Set-Cookie header sent by `host`. Attributes we don't act on
+// (HttpOnly, SameSite) are ignored.
+fn parse_set_cookie(header: &str, host: &str) -> Option<StoredCookie> {
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let mut cookie = StoredCookie {
+        name: name.to_string(),
+        value: value.trim().to_string(),
+        domain: host.to_lowercase(),
+        host_only: true,
+        path: "/".to_string(),
+        expires: None,
+        secure: false,
+    };
+    let mut max_age = None;
+    for attr in parts {
+        let (key, val) = attr.split_once('=').unwrap_or((attr, ""));
+        let val = val.trim();
+        match key.trim().to_lowercase().as_str() {
+            "domain" if !val.is_empty() => {
+                // A site can't set cookies for a domain it isn't part of
+                if !host_matches(host, val) {
+                    return None;
+                }
+                cookie.domain = val.trim_start_matches('.').to_lowercase();
+                cookie.host_only = false;
+            }
+            "path" if val.starts_with('/') => cookie.path = val.to_string(),
+            "max-age" => max_age = val.parse::<i64>().ok(),
+            "expires" => {
+                cookie.expires = DateTime::parse_from_rfc2822(val).ok().map(|t| t.with_timezone(&Local));
+            }
+            "secure" => cookie.secure = true,
+            _ => {}
+        }
+    }
+    // Max-Age wins over Expires; capped at 400 days like browsers do
+    if let Some(seconds) = max_age {
+        cookie.expires = Some(Local::now() + chrono::Duration::seconds(seconds.min(400 * 24 * 60 * 60)));
+    }
+    Some(cookie)
+}
+
+fn get_cookies_path() -> PathBuf {
+    get_config_dir().join("cookies.json")
+}
+
+// Cookie store shared by every client, keeping cookies only for the
+// domains listed under `cookies` in the config
+struct CookieJar {
+    allow: Vec<String>,
+    cookies: Mutex<Vec<StoredCookie>>,
+}
+
+impl CookieJar {
+    fn load(allow: Vec<String>) -> Self {
+        let cookies: Vec<StoredCookie> = fs::read_to_string(get_cookies_path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        CookieJar {
+            allow,
+            cookies: Mutex::new(cookies.into_iter().filter(|c| !c.is_expired()).collect()),
+        }
+    }
+
+    fn allowed(&self, host: &str) -> bool {
+        self.allow.iter().any(|d| host_matches(host, d))
+    }
+
+    // Session cookies stay in memory only
+    fn save(cookies: &[StoredCookie]) {
+        let persistent: Vec<&StoredCookie> = cookies.iter().filter(|c| c.expires.is_some()).collect();
+        if let Ok(json) = serde_json::to_string(&persistent) {
+            fs::write(get_cookies_path(), json).ok();
+        }
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    // Private mode neither keeps cookies nor sends the ones from before
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &reqwest::header::HeaderValue>, url: &Url) {
+        let Some(host) = url.host_str().filter(|h| self.allowed(h) && !private_enabled()) else {
+            return;
+        };
+        let mut cookies = self.cookies.lock().unwrap();
+        let mut changed = false;
+        for cookie in cookie_headers.filter_map(|h| h.to_str().ok()).filter_map(|h| parse_set_cookie(h, host)) {
+            cookies.retain(|c| !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path));
+            // Sending an expiry in the past is how a site deletes a cookie
+            if !cookie.is_expired() {
+                cookies.push(cookie);
+            }
+            changed = true;
+        }
+        if changed {
+            CookieJar::save(&cookies);
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<reqwest::header::HeaderValue> {
+        url.host_str().filter(|h| self.allowed(h) && !private_enabled())?;
+        let cookies = self.cookies.lock().unwrap();
+        let pairs: Vec<String> = cookies
+            .iter()
+            .filter(|c| c.matches(url))
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect();
+        if pairs.is_empty() {
+            return None;
+        }
+        reqwest::header::HeaderValue::from_str(&pairs.join("; ")).ok()
+    }
+}
+
+static COOKIE_JAR: OnceLock<Option<Arc<CookieJar>>> = OnceLock::new();
+
+// The jar, unless no site is allowlisted. Tor mode never keeps cookies,
+// since they would tie its requests back to earlier sessions.
+fn cookie_jar() -> Option<&'static Arc<CookieJar>> {
+    COOKIE_JAR
+        .get_or_init(|| {
+            let allow = &config().cookies;
+            (!allow.is_empty() && !tor_enabled()).then(|| Arc::new(CookieJar::load(allow.clone())))
+        })
+        .as_ref()
+}
+
+// `navim clear-cookies`
+pub(crate) fn clear_cookies() -> Result<(), Box<dyn Error>> {
+    match fs::remove_file(get_cookies_path()) {
+        Ok(()) => println!("Cookies cleared."),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => println!("No cookies stored."),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+fn is_onion(url: &str) -> bool {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.ends_with(".onion")))
+        .unwrap_or(false)
+}
+
+static PAGE_CLIENT: OnceLock<reqwest::blocking::Client> = OnceLock::new();
+
+// Like browser_client, but leaves redirects to fetch_cached so it can
+// record them
+fn page_client() -> reqwest::Result<reqwest::blocking::Client> {
+    if let Some(client) = PAGE_CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let client = configured_client(RequestKind::Page)?
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    Ok(PAGE_CLIENT.get_or_init(|| client).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Answers every URL with a 404 and this body
+    struct NotFound(&'static str);
+
+    impl Fetcher for NotFound {
+        fn fetch(&self, url: &str) -> Result<Fetched, Box<dyn Error>> {
+            Err(Box::new(HttpStatus {
+                status: reqwest::StatusCode::NOT_FOUND,
+                response: Fetched {
+                    body: self.0.into(),
+                    content_type: Some("text/html".to_string()),
+                    url: url.to_string(),
+                    status: Some(404),
+                    ..Default::default()
+                },
+            }))
+        }
+    }
+
+    fn load(fetcher: &NotFound) -> Result<RenderedPage, Box<dyn Error>> {
+        fetch_page_with("https://example.com/gone", false, &CancelToken::default(), fetcher)
+    }
+
+    #[test]
+    fn a_sites_own_error_page_is_shown() {
+        let page = load(&NotFound("<h1>Gone</h1><p>Try the home page.</p>")).unwrap();
+        assert!(page.text.contains("Try the home page."));
+        assert_eq!(page.stats.and_then(|stats| stats.error_status()), Some(reqwest::StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn an_error_status_without_a_page_is_an_error() {
+        let error = load(&NotFound("\n")).err().map(|e| e.to_string());
+        assert_eq!(error.as_deref(), Some("HTTP 404 Not Found"));
+    }
+}
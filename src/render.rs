@@ -0,0 +1,2147 @@
+use chrono::{DateTime, Local, NaiveDate};
+use image::GenericImageView;
+use ratatui::style::{Color, Modifier, Style};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::config::config;
+use crate::net::{fetch_image_as_ascii, host_matches, https_upgrade, is_blocked, CancelToken};
+use crate::search::display_host;
+use crate::sites::Thread;
+
+// Set once at startup by --accessible or the accessible setting. Pages are
+// rendered as linear text for screen readers: spoken prefixes instead of
+// rules, boxes and bullets, and images only as their alt text.
+pub(crate) static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+// Cut s to max_width terminal columns and mark the cut with "...". Wide
+// characters (CJK, most emoji) take two columns, and a character is never
+// split from the accents combined with it.
+pub(crate) fn truncate_string(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+    let mut width = 0;
+    let mut truncated = String::new();
+    for grapheme in s.graphemes(true) {
+        width += grapheme.width();
+        if width > max_width {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+    format!("{}...", truncated)
+}
+
+// Greedy word wrap to a display width. Returns each line with the char
+// offset in the input where it starts, so positions can be mapped back.
+pub(crate) fn wrap_with_offsets(text: &str, width: usize) -> Vec<(usize, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let width = width.max(1);
+
+    // Words as [start, end) char ranges
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == ' ' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && chars[i] != ' ' {
+            i += 1;
+        }
+        words.push((start, i));
+    }
+
+    let span_width = |a: usize, b: usize| chars[a..b].iter().map(|c| c.width().unwrap_or(0)).sum::<usize>();
+
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut line: Option<(usize, usize)> = None; // [start, end) of the current line
+    for (mut start, end) in words {
+        if let Some((line_start, _)) = line {
+            if span_width(line_start, end) <= width {
+                line = Some((line_start, end));
+                continue;
+            }
+            let (a, b) = line.take().unwrap();
+            lines.push((a, chars[a..b].iter().collect()));
+        }
+        // Hard-break words wider than the column
+        while span_width(start, end) > width {
+            let mut cut = start;
+            let mut w = 0;
+            while cut < end && w + chars[cut].width().unwrap_or(0) <= width {
+                w += chars[cut].width().unwrap_or(0);
+                cut += 1;
+            }
+            let cut = cut.max(start + 1);
+            lines.push((start, chars[start..cut].iter().collect()));
+            start = cut;
+        }
+        if start < end {
+            line = Some((start, end));
+        }
+    }
+    if let Some((a, b)) = line {
+        lines.push((a, chars[a..b].iter().collect()));
+    }
+    if lines.is_empty() {
+        lines.push((0, String::new()));
+    }
+    lines
+}
+
+// Split available width between columns: narrow columns keep their natural
+// width, the rest share what is left equally.
+fn fit_column_widths(natural: &[usize], available: usize) -> Vec<usize> {
+    let mut widths = natural.to_vec();
+    if natural.iter().sum::<usize>() <= available {
+        return widths;
+    }
+
+    let mut order: Vec<usize> = (0..natural.len()).collect();
+    order.sort_by_key(|&i| natural[i]);
+
+    let mut remaining = available;
+    let mut cols_left = natural.len();
+    for i in order {
+        let share = (remaining / cols_left).max(MIN_TABLE_COLUMN_WIDTH);
+        widths[i] = natural[i].min(share);
+        remaining = remaining.saturating_sub(widths[i]);
+        cols_left -= 1;
+    }
+    widths
+}
+
+pub(crate) fn sanitize_display(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_control() || *c == ' ' || *c == '\n')
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// ASCII art characters from darkest to lightest
+const ASCII_CHARS: &[char] = &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+// Convert image bytes to ASCII art
+pub(crate) fn image_to_ascii(image_bytes: &[u8], max_width: u32) -> Option<String> {
+    let img = image::load_from_memory(image_bytes).ok()?;
+
+    let (width, height) = img.dimensions();
+
+    // Calculate new dimensions while maintaining aspect ratio
+    // Terminal characters are roughly 2x taller than wide, so we adjust
+    let aspect_ratio = height as f32 / width as f32;
+    let new_width = max_width.min(width);
+    let new_height = ((new_width as f32 * aspect_ratio) / 2.0) as u32;
+
+    // Skip very small or very large images
+    if new_width < 10 || !(5..=50).contains(&new_height) {
+        return None;
+    }
+
+    Some(render_ascii(&img, new_width, new_height))
+}
+
+// Convert image bytes to ASCII art that fits inside a width x height box
+pub(crate) fn image_to_ascii_fit(image_bytes: &[u8], max_width: u32, max_height: u32) -> Option<String> {
+    let img = image::load_from_memory(image_bytes).ok()?;
+
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    // Leave room for the border
+    let max_width = max_width.saturating_sub(2).max(1);
+    let max_height = max_height.saturating_sub(2).max(1);
+
+    let aspect_ratio = height as f32 / width as f32;
+    let mut new_width = max_width;
+    let mut new_height = ((new_width as f32 * aspect_ratio) / 2.0).max(1.0) as u32;
+    if new_height > max_height {
+        new_height = max_height;
+        new_width = ((new_height as f32 * 2.0) / aspect_ratio).max(1.0) as u32;
+    }
+
+    Some(render_ascii(&img, new_width.min(max_width), new_height))
+}
+
+// Draw a resized grayscale image as bordered ASCII art
+fn render_ascii(img: &image::DynamicImage, new_width: u32, new_height: u32) -> String {
+    let resized = img.resize_exact(new_width, new_height, image::imageops::FilterType::Lanczos3);
+    let gray = resized.to_luma8();
+
+    let mut ascii_art = String::new();
+    ascii_art.push_str("\n┌");
+    for _ in 0..new_width {
+        ascii_art.push('─');
+    }
+    ascii_art.push_str("┐\n");
+
+    for y in 0..new_height {
+        ascii_art.push('│');
+        for x in 0..new_width {
+            let pixel = gray.get_pixel(x, y);
+            let brightness = pixel[0] as usize;
+            let char_index = (brightness * (ASCII_CHARS.len() - 1)) / 255;
+            ascii_art.push(ASCII_CHARS[char_index]);
+        }
+        ascii_art.push_str("│\n");
+    }
+
+    ascii_art.push('└');
+    for _ in 0..new_width {
+        ascii_art.push('─');
+    }
+    ascii_art.push_str("┘\n");
+
+    ascii_art
+}
+
+// Link found in a rendered page
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PageLink {
+    pub line: usize,      // Line number where link appears
+    pub col_start: usize, // Column where link text starts
+    pub col_end: usize,   // Column where link text ends
+    pub text: String,     // Link text
+    pub url: String,      // Link URL
+}
+
+// Image found in a rendered page
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PageImage {
+    pub url: String, // Resolved image URL
+    pub alt: String, // Alt text (may be empty)
+}
+
+// Heading found in a rendered page, for the table of contents
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PageHeading {
+    pub level: u8,
+    pub text: String,
+    pub line: usize,
+}
+
+// Semantic style of a run of rendered text
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum TextStyle {
+    Heading(u8),
+    Bold,
+    Italic,
+    Code,
+    Quote,
+    JsonKey,
+    JsonString,
+    JsonNumber,
+    JsonKeyword, // true, false and null
+}
+
+impl TextStyle {
+    pub(crate) fn style(self) -> Style {
+        match self {
+            TextStyle::Heading(1) => Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            TextStyle::Heading(2) => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            TextStyle::Heading(3) => Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            TextStyle::Heading(_) => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+            TextStyle::Bold => Style::default().add_modifier(Modifier::BOLD),
+            TextStyle::Italic => Style::default().add_modifier(Modifier::ITALIC),
+            TextStyle::Code => Style::default().fg(Color::LightMagenta),
+            TextStyle::Quote => Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC),
+            TextStyle::JsonKey => Style::default().fg(Color::Cyan),
+            TextStyle::JsonString => Style::default().fg(Color::Green),
+            TextStyle::JsonNumber => Style::default().fg(Color::Yellow),
+            TextStyle::JsonKeyword => Style::default().fg(Color::LightMagenta),
+        }
+    }
+}
+
+// Styled run of text in a rendered page, from (line, col) up to but not including end
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct StyledSpan {
+    pub(crate) start: (usize, usize),
+    pub(crate) end: (usize, usize),
+    pub(crate) style: TextStyle,
+}
+
+impl StyledSpan {
+    pub(crate) fn contains(&self, line: usize, col: usize) -> bool {
+        (line, col) >= self.start && (line, col) < self.end
+    }
+}
+
+// Everything the renderer extracts from a page
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RenderedPage {
+    pub text: String,
+    pub links: Vec<PageLink>,
+    pub images: Vec<PageImage>,
+    pub(crate) spans: Vec<StyledSpan>,
+    pub headings: Vec<PageHeading>,
+    pub(crate) anchors: HashMap<String, usize>, // Element id -> line
+    #[serde(skip)]
+    pub(crate) source: Vec<u8>, // Body exactly as fetched
+    #[serde(skip)]
+    pub(crate) json: Option<JsonDoc>, // Set for JSON responses, which can be folded
+    #[serde(skip)]
+    pub(crate) thread: Option<Thread>, // Set for comment threads, which fold too
+    #[serde(skip)]
+    pub final_url: Option<String>, // Where the page ended up, when redirected
+    #[serde(skip)]
+    pub redirects: Vec<Redirect>, // Every URL passed through on the way there
+    #[serde(default)]
+    pub meta: PageMeta,
+    #[serde(skip)]
+    pub(crate) stats: Option<LoadStats>,
+    #[serde(skip)]
+    pub(crate) headers: Vec<(String, String)>, // HTTP response headers, in the order sent
+}
+
+// How loading a page went, shown in the page header
+#[derive(Clone, Copy)]
+pub(crate) struct LoadStats {
+    pub(crate) fetch: Duration,  // Waiting on the network (or disk), redirects included
+    pub(crate) render: Duration, // Everything else, including fetching inline images
+    pub(crate) bytes: usize,     // Body sizes of every hop
+    pub(crate) status: Option<u16>,
+    pub(crate) cached: bool,
+}
+
+impl LoadStats {
+    pub(crate) fn summary(&self, lines: usize) -> String {
+        let status = match (self.status, self.cached) {
+            (Some(status), true) => format!("{} cached", status),
+            (Some(status), false) => status.to_string(),
+            (None, true) => "cached".to_string(),
+            (None, false) => "local".to_string(),
+        };
+        format!(
+            "{} · {} · {} ms fetch + {} ms render · {} line{}",
+            status,
+            format_bytes(self.bytes),
+            self.fetch.as_millis(),
+            self.render.as_millis(),
+            lines,
+            if lines == 1 { "" } else { "s" }
+        )
+    }
+
+    // A 4xx or 5xx status, when the page is the server's own error page
+    pub(crate) fn error_status(&self) -> Option<reqwest::StatusCode> {
+        let status = reqwest::StatusCode::from_u16(self.status?).ok()?;
+        (status.is_client_error() || status.is_server_error()).then_some(status)
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+// What an HTML page says about itself in <meta> tags (OpenGraph and plain)
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageMeta {
+    pub title: Option<String>,
+    pub site_name: Option<String>,
+    pub author: Option<String>,
+    pub published: Option<NaiveDate>,
+    pub description: Option<String>,
+    pub pdf: Option<String>, // citation_pdf_url, as arXiv and journals set it
+}
+
+impl PageMeta {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.site_name.is_none() && self.author.is_none() && self.published.is_none() && self.description.is_none()
+    }
+}
+
+// A URL we were sent away from, and why (e.g. "301 Moved Permanently")
+#[derive(Clone)]
+pub struct Redirect {
+    pub url: String,
+    pub reason: String,
+}
+
+// Feeds a page advertises with <link rel="alternate" type="application/rss+xml">
+pub(crate) fn discover_feeds(html: &str, base_url: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("link[rel~='alternate'][href]").unwrap();
+    let base = Url::parse(base_url).ok();
+    document
+        .select(&selector)
+        .filter(|link| {
+            let kind = link.value().attr("type").unwrap_or("").to_lowercase();
+            matches!(kind.as_str(), "application/rss+xml" | "application/atom+xml" | "application/feed+json")
+        })
+        .filter_map(|link| {
+            let href = link.value().attr("href")?;
+            base.as_ref().and_then(|b| b.join(href).ok()).map(|u| u.to_string())
+        })
+        .collect()
+}
+
+// Check if an image URL should be rendered
+fn should_render_image(src: &str) -> bool {
+    let src_lower = src.to_lowercase();
+
+    // Trackers are caught by host in fetch_image_bytes; this only skips
+    // decoration
+    !(src_lower.starts_with("data:")
+        || src_lower.contains("icon")
+        || src_lower.contains("avatar")
+        || src_lower.contains("sprite")
+        || src_lower.contains("badge")
+        || src_lower.contains("button")
+        || src_lower.contains("arrow")
+        || src_lower.contains("spacer")
+        || src_lower.ends_with(".svg")
+        || src_lower.ends_with(".gif")
+        || src_lower.contains("/static/")
+        || src_lower.contains("widget")
+        || src_lower.contains("logo")
+        || src_lower.contains("spinner")
+        || src_lower.contains("loading"))
+}
+
+// Resolve a potentially relative URL to absolute
+fn resolve_url(src: &str, base: &Option<Url>) -> Option<String> {
+    if src.starts_with("http") {
+        Some(src.to_string())
+    } else if src.starts_with("//") {
+        Some(format!("https:{}", src))
+    } else if let Some(ref base) = base {
+        base.join(src).map(|u| u.to_string()).ok()
+    } else {
+        None
+    }
+}
+
+// Widest a rendered table may get, in display columns
+const MAX_TABLE_WIDTH: usize = 100;
+const MIN_TABLE_COLUMN_WIDTH: usize = 3;
+// Wider colspans and rowspans are broken markup, not layout worth keeping
+const MAX_TABLE_SPAN: usize = 20;
+
+// A table cell rendered into a single line, with links, styles, headings and
+// anchors mapped to char offsets in that line
+struct TableCell {
+    text: String,
+    links: Vec<(usize, usize, String, String)>, // (start, end, text, url)
+    spans: Vec<(usize, usize, TextStyle)>,
+    headings: Vec<(usize, u8, String)>, // (offset, level, text)
+    anchors: Vec<(usize, String)>,      // (offset, element id)
+    header: bool,
+    col: usize,  // First grid column it covers
+    span: usize, // Columns it covers
+}
+
+// Custom HTML renderer that preserves document structure
+pub struct HtmlRenderer {
+    output: String,
+    base_url: Option<Url>,
+    image_count: usize,
+    max_images: usize,
+    list_depth: usize,
+    in_pre: bool,
+    last_was_block: bool,
+    open_marker: bool, // Just pushed an opening inline marker like ** or [
+    links: Vec<PageLink>,
+    images: Vec<PageImage>,
+    spans: Vec<StyledSpan>,
+    headings: Vec<PageHeading>,
+    anchors: Vec<(String, usize)>, // Element id and the output offset where it starts
+    current_line: usize,
+    current_col: usize,
+    counted: usize, // How much of the output current_line and current_col cover
+    cancel: CancelToken, // Stops image downloads when the load is abandoned
+    accessible: bool,    // Linear screen-reader output, see ACCESSIBLE
+}
+
+impl HtmlRenderer {
+    pub fn new(base_url: &str) -> Self {
+        HtmlRenderer {
+            output: String::new(),
+            base_url: Url::parse(base_url).ok(),
+            image_count: 0,
+            max_images: 3,
+            list_depth: 0,
+            in_pre: false,
+            last_was_block: true,
+            open_marker: false,
+            links: Vec::new(),
+            images: Vec::new(),
+            spans: Vec::new(),
+            headings: Vec::new(),
+            anchors: Vec::new(),
+            current_line: 0,
+            current_col: 0,
+            counted: 0,
+            cancel: CancelToken::default(),
+            accessible: accessible(),
+        }
+    }
+
+    // Renderer for a fragment (e.g. a table cell) of the same page
+    fn nested(&self) -> Self {
+        HtmlRenderer {
+            output: String::new(),
+            base_url: self.base_url.clone(),
+            image_count: 0,
+            max_images: 0,
+            list_depth: 0,
+            in_pre: false,
+            last_was_block: true,
+            open_marker: false,
+            links: Vec::new(),
+            images: Vec::new(),
+            spans: Vec::new(),
+            headings: Vec::new(),
+            anchors: Vec::new(),
+            current_line: 0,
+            current_col: 0,
+            counted: 0,
+            cancel: CancelToken::default(),
+            accessible: self.accessible,
+        }
+    }
+
+    fn current_col(&mut self) -> usize {
+        self.update_line_count();
+        self.current_col
+    }
+
+    // Only the output added since the last call is scanned, so positions
+    // don't cost more the longer the page gets
+    fn update_line_count(&mut self) {
+        let added = &self.output[self.counted..];
+        match added.rfind('\n') {
+            Some(i) => {
+                self.current_line += added.matches('\n').count();
+                self.current_col = added[i + 1..].chars().count();
+            }
+            None => self.current_col += added.chars().count(),
+        }
+        self.counted = self.output.len();
+    }
+
+    fn position(&mut self) -> (usize, usize) {
+        self.update_line_count();
+        (self.current_line, self.current_col)
+    }
+
+    // Push an opening inline marker, separating it from preceding text.
+    // Returns the position of the marker.
+    fn open_inline(&mut self, marker: &str) -> (usize, usize) {
+        if !self.last_was_block && !self.output.is_empty()
+            && !self.output.ends_with(' ') && !self.output.ends_with('\n') {
+            self.output.push(' ');
+        }
+        let start = self.position();
+        self.output.push_str(marker);
+        self.open_marker = true;
+        start
+    }
+
+    // The decorated marker, or the plain one in accessible mode
+    fn glyph<'a>(&self, decorated: &'a str, plain: &'a str) -> &'a str {
+        if self.accessible { plain } else { decorated }
+    }
+
+    // Text before and after a heading: rules by level, or a spoken prefix
+    fn heading_marks(&self, level: u8) -> (String, &'static str) {
+        if self.accessible {
+            return (format!("Heading level {}: ", level), "");
+        }
+        match level {
+            1 => ("═══ ".to_string(), " ═══"),
+            2 => ("━━ ".to_string(), " ━━"),
+            3 => ("── ".to_string(), " ──"),
+            _ => ("▸ ".to_string(), ""),
+        }
+    }
+
+    // Remember a heading's line for the table of contents
+    fn record_heading(&mut self, element: scraper::ElementRef, level: u8) {
+        let text = element.text().collect::<Vec<_>>().join(" ");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            self.update_line_count();
+            self.headings.push(PageHeading { level, text, line: self.current_line });
+        }
+    }
+
+    // Render children and record the output they produce as a styled span
+    fn render_styled(&mut self, element: scraper::ElementRef, style: TextStyle) {
+        let start = self.position();
+        self.render_children(element);
+        let end = self.position();
+        if start != end {
+            self.spans.push(StyledSpan { start, end, style });
+        }
+    }
+
+    pub fn render_element(&mut self, element: scraper::ElementRef) {
+        let tag = element.value().name();
+
+        // Skip unwanted elements
+        if matches!(tag, "script" | "style" | "nav" | "header" | "footer" | "aside" | "noscript" | "iframe" | "form") {
+            return;
+        }
+
+        // Skip elements with hidden classes
+        if let Some(class) = element.value().attr("class") {
+            let class_lower = class.to_lowercase();
+            if class_lower.contains("hidden") || class_lower.contains("sidebar")
+                || class_lower.contains("nav") || class_lower.contains("menu")
+                || class_lower.contains("footer") || class_lower.contains("header")
+                || class_lower.contains("advertisement") || class_lower.contains("ad-") {
+                return;
+            }
+        }
+
+        // Remember where ids (and old-style named anchors) start for #fragment links
+        let id = element.value().id().or_else(|| {
+            if tag == "a" { element.value().attr("name") } else { None }
+        });
+        if let Some(id) = id {
+            self.anchors.push((id.to_string(), self.output.len()));
+        }
+
+        match tag {
+            // Block elements that need newlines
+            "p" => {
+                self.ensure_blank_line();
+                self.render_children(element);
+                self.ensure_newline();
+                self.last_was_block = true;
+            }
+            "div" | "section" | "article" => {
+                self.ensure_newline();
+                self.render_children(element);
+                self.ensure_newline();
+                self.last_was_block = true;
+            }
+            "br" => {
+                self.output.push('\n');
+                self.last_was_block = true;
+            }
+            "hr" => {
+                self.ensure_blank_line();
+                if !self.accessible {
+                    self.output.push_str("────────────────────────────────────────");
+                    self.ensure_blank_line();
+                }
+                self.last_was_block = true;
+            }
+
+            // Headings
+            "h1" => {
+                self.ensure_blank_line();
+                self.record_heading(element, 1);
+                let start = self.position();
+                let (before, after) = self.heading_marks(1);
+                self.output.push_str(&before);
+                self.render_children(element);
+                self.output.push_str(after);
+                let end = self.position();
+                self.spans.push(StyledSpan { start, end, style: TextStyle::Heading(1) });
+                self.ensure_blank_line();
+                self.last_was_block = true;
+            }
+            "h2" => {
+                self.ensure_blank_line();
+                self.record_heading(element, 2);
+                let start = self.position();
+                let (before, after) = self.heading_marks(2);
+                self.output.push_str(&before);
+                self.render_children(element);
+                self.output.push_str(after);
+                let end = self.position();
+                self.spans.push(StyledSpan { start, end, style: TextStyle::Heading(2) });
+                self.ensure_blank_line();
+                self.last_was_block = true;
+            }
+            "h3" => {
+                self.ensure_blank_line();
+                self.record_heading(element, 3);
+                let start = self.position();
+                let (before, after) = self.heading_marks(3);
+                self.output.push_str(&before);
+                self.render_children(element);
+                self.output.push_str(after);
+                let end = self.position();
+                self.spans.push(StyledSpan { start, end, style: TextStyle::Heading(3) });
+                self.ensure_blank_line();
+                self.last_was_block = true;
+            }
+            "h4" | "h5" | "h6" => {
+                self.ensure_blank_line();
+                if tag == "h4" {
+                    self.record_heading(element, 4);
+                }
+                let start = self.position();
+                let level = tag[1..].parse().unwrap_or(4);
+                let (before, _) = self.heading_marks(level);
+                self.output.push_str(&before);
+                self.render_children(element);
+                let end = self.position();
+                self.spans.push(StyledSpan { start, end, style: TextStyle::Heading(4) });
+                self.ensure_newline();
+                self.last_was_block = true;
+            }
+
+            // Lists
+            "ul" | "ol" => {
+                self.ensure_newline();
+                self.list_depth += 1;
+                self.render_children(element);
+                self.list_depth -= 1;
+                self.ensure_newline();
+                self.last_was_block = true;
+            }
+            "li" => {
+                self.ensure_newline();
+                let indent = "  ".repeat(self.list_depth.saturating_sub(1));
+                self.output.push_str(&indent);
+                let bullet = self.glyph("• ", "List item: ");
+                self.output.push_str(bullet);
+                self.render_children(element);
+                self.last_was_block = true;
+            }
+
+            // Code and preformatted
+            "pre" => {
+                self.ensure_blank_line();
+                let top = self.glyph("┌─────────────────────────────────────────┐\n", "Code block:\n");
+                self.output.push_str(top);
+                self.in_pre = true;
+                self.render_styled(element, TextStyle::Code);
+                self.in_pre = false;
+                self.ensure_newline();
+                let bottom = self.glyph("└─────────────────────────────────────────┘", "End of code block");
+                self.output.push_str(bottom);
+                self.ensure_blank_line();
+                self.last_was_block = true;
+            }
+            "code" if !self.in_pre => {
+                let marker = self.glyph("`", "");
+                let start = self.open_inline(marker);
+                self.render_children(element);
+                self.output.push_str(marker);
+                let end = self.position();
+                self.spans.push(StyledSpan { start, end, style: TextStyle::Code });
+            }
+
+            // Inline formatting
+            "strong" | "b" => {
+                let marker = self.glyph("**", "");
+                let start = self.open_inline(marker);
+                self.render_children(element);
+                self.output.push_str(marker);
+                let end = self.position();
+                self.spans.push(StyledSpan { start, end, style: TextStyle::Bold });
+            }
+            "em" | "i" => {
+                let marker = self.glyph("_", "");
+                let start = self.open_inline(marker);
+                self.render_children(element);
+                self.output.push_str(marker);
+                let end = self.position();
+                self.spans.push(StyledSpan { start, end, style: TextStyle::Italic });
+            }
+
+            // Links - track for navigation
+            "a" => {
+                if let Some(href) = element.value().attr("href") {
+                    // Resolve the URL
+                    let resolved = if href.starts_with("http") {
+                        Some(href.to_string())
+                    } else if href.starts_with("//") {
+                        Some(format!("https:{}", href))
+                    } else if href.starts_with('/') || href.starts_with('.') || !href.contains(':') {
+                        resolve_url(href, &self.base_url)
+                    } else {
+                        None
+                    };
+
+                    if let Some(url) = resolved.map(|url| https_upgrade(&url).unwrap_or(url)) {
+                        // Ad click-throughs keep their text but aren't followable
+                        if !url.contains("javascript:") && !is_blocked(&url) {
+                            // Render link text with markers
+                            let (open, close) = if self.accessible { ("Link: ", "") } else { ("[", "]") };
+                            let (line, col_start) = self.open_inline(open);
+                            self.render_children(element);
+                            self.output.push_str(close);
+
+                            let col_end = self.current_col();
+
+                            // Get link text
+                            let text: String = element.text().collect::<Vec<_>>().join(" ");
+                            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+                            // Store the link
+                            if !text.is_empty() && text.len() < 200 {
+                                self.links.push(PageLink {
+                                    line,
+                                    col_start,
+                                    col_end,
+                                    text: truncate_string(&text, 50),
+                                    url,
+                                });
+                            }
+                        } else {
+                            self.render_children(element);
+                        }
+                    } else {
+                        self.render_children(element);
+                    }
+                } else {
+                    self.render_children(element);
+                }
+            }
+
+            // Images - render inline where they appear
+            "img" => {
+                let src = element.value().attr("src")
+                    .or_else(|| element.value().attr("data-src"))
+                    .or_else(|| element.value().attr("data-lazy-src"));
+
+                // Remember every image for the gallery, rendered or not
+                if let Some(src) = src {
+                    if !src.starts_with("data:") {
+                        if let Some(full_url) = resolve_url(src, &self.base_url) {
+                            if !self.images.iter().any(|img| img.url == full_url) {
+                                self.images.push(PageImage {
+                                    url: full_url,
+                                    alt: element.value().attr("alt").unwrap_or("").trim().to_string(),
+                                });
+                            }
+                        }
+                    }
+                }
+
+                // Alt text stands in for the picture, and empty alt marks a decorative one
+                if self.accessible {
+                    let alt = element.value().attr("alt").unwrap_or("").trim();
+                    if src.is_some() && !alt.is_empty() {
+                        self.ensure_newline();
+                        self.output.push_str(&format!("Image: {}", alt));
+                        self.ensure_newline();
+                        self.last_was_block = true;
+                    }
+                } else if self.image_count < self.max_images && !self.cancel.is_canceled() {
+                    if let Some(src) = src {
+                        if should_render_image(src) {
+                            if let Some(full_url) = resolve_url(src, &self.base_url) {
+                                if let Some(ascii_art) = fetch_image_as_ascii(&full_url, 60) {
+                                    self.ensure_blank_line();
+                                    // Add image caption if available
+                                    if let Some(alt) = element.value().attr("alt") {
+                                        if !alt.is_empty() && alt.len() < 100 {
+                                            self.output.push_str(&format!("[Image: {}]\n", alt));
+                                        }
+                                    }
+                                    self.output.push_str(&ascii_art);
+                                    self.ensure_blank_line();
+                                    self.image_count += 1;
+                                    self.last_was_block = true;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Figure (often wraps images with captions)
+            "figure" => {
+                self.ensure_newline();
+                self.render_children(element);
+                self.ensure_newline();
+                self.last_was_block = true;
+            }
+            "figcaption" => {
+                let marker = self.glyph("  ↳ ", "Caption: ");
+                self.output.push_str(marker);
+                self.render_children(element);
+                self.ensure_newline();
+            }
+
+            // Blockquote
+            "blockquote" => {
+                self.ensure_blank_line();
+                let start_len = self.output.len();
+                let start = self.position();
+                let first_link = self.links.len();
+                let first_span = self.spans.len();
+                let first_anchor = self.anchors.len();
+                self.render_children(element);
+                // Add quote markers to each line
+                let content = self.output[start_len..].to_string();
+                self.output.truncate(start_len);
+                (self.current_line, self.current_col) = start;
+                self.counted = start_len;
+                let marker = self.glyph("│ ", "Quote: ");
+                let shift = marker.chars().count();
+                for line in content.lines() {
+                    self.output.push_str(marker);
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+                // Shift anything recorded inside the quote past the marker
+                for link in &mut self.links[first_link..] {
+                    link.col_start += shift;
+                    link.col_end += shift;
+                }
+                for span in &mut self.spans[first_span..] {
+                    span.start.1 += shift;
+                    span.end.1 += shift;
+                }
+                // Anchors are byte offsets, moved by a marker for each line up to theirs
+                for (_, offset) in &mut self.anchors[first_anchor..] {
+                    let inside = offset.saturating_sub(start_len).min(content.len());
+                    let line = content[..inside].matches('\n').count();
+                    *offset = (*offset + marker.len() * (line + 1)).min(self.output.len());
+                }
+                let end = self.position();
+                self.spans.push(StyledSpan { start, end, style: TextStyle::Quote });
+                self.last_was_block = true;
+            }
+
+            // Tables - laid out as aligned boxes
+            "table" => {
+                self.ensure_blank_line();
+                self.render_table(element);
+                self.ensure_blank_line();
+                self.last_was_block = true;
+            }
+            // Rows and cells outside a data table flow as blocks
+            "tr" | "th" | "td" => {
+                self.ensure_newline();
+                self.render_children(element);
+                self.ensure_newline();
+                self.last_was_block = true;
+            }
+
+            // Span and other inline elements
+            "span" | "label" | "time" | "small" | "sup" | "sub" => {
+                self.render_children(element);
+            }
+
+            // Default: just render children
+            _ => {
+                self.render_children(element);
+            }
+        }
+    }
+
+    // Rows belonging to this table (not to tables nested inside it)
+    fn table_rows(table: scraper::ElementRef) -> Vec<scraper::ElementRef> {
+        let mut rows = Vec::new();
+        for child in table.children().filter_map(scraper::ElementRef::wrap) {
+            match child.value().name() {
+                "tr" => rows.push(child),
+                "thead" | "tbody" | "tfoot" => rows.extend(
+                    child
+                        .children()
+                        .filter_map(scraper::ElementRef::wrap)
+                        .filter(|e| e.value().name() == "tr"),
+                ),
+                _ => {}
+            }
+        }
+        rows
+    }
+
+    // Render a cell's contents on one line, keeping link and style offsets
+    fn render_cell(&mut self, cell: scraper::ElementRef) -> TableCell {
+        let mut sub = self.nested();
+        sub.render_children(cell);
+        let page = sub.finish();
+        for image in page.images {
+            if !self.images.iter().any(|img| img.url == image.url) {
+                self.images.push(image);
+            }
+        }
+
+        // Flatten lines, remembering where each one starts
+        let lines: Vec<&str> = page.text.split('\n').collect();
+        let mut line_offsets = Vec::with_capacity(lines.len());
+        let mut offset = 0;
+        for line in &lines {
+            line_offsets.push(offset);
+            offset += line.chars().count() + 1;
+        }
+        let flat = lines.join(" ");
+        let lead = flat.chars().take_while(|c| c.is_whitespace()).count();
+        let text = flat.trim().to_string();
+        let len = text.chars().count();
+        let to_flat = |(line, col): (usize, usize)| {
+            (line_offsets.get(line).copied().unwrap_or(offset) + col).saturating_sub(lead).min(len)
+        };
+
+        let header = cell.value().name() == "th";
+        // The cell's own id as well as those inside it
+        let mut anchors: Vec<(usize, String)> = cell.value().id().map(|id| (0, id.to_string())).into_iter().collect();
+        anchors.extend(page.anchors.into_iter().map(|(id, line)| (to_flat((line, 0)), id)));
+        let mut spans: Vec<(usize, usize, TextStyle)> = page
+            .spans
+            .iter()
+            .map(|s| (to_flat(s.start), to_flat(s.end), s.style))
+            .filter(|(a, b, _)| a < b)
+            .collect();
+        if header && len > 0 {
+            spans.push((0, len, TextStyle::Bold));
+        }
+
+        TableCell {
+            links: page
+                .links
+                .into_iter()
+                .map(|l| (to_flat((l.line, l.col_start)), to_flat((l.line, l.col_end)), l.text, l.url))
+                .collect(),
+            spans,
+            headings: page.headings.into_iter().map(|h| (to_flat((h.line, 0)), h.level, h.text)).collect(),
+            anchors,
+            text,
+            header,
+            col: 0,
+            span: 1,
+        }
+    }
+
+    // Record a cell's headings and anchors on the line being written, for
+    // those whose offset `on_line` accepts
+    fn place_cell_marks(&mut self, cell: &TableCell, line: usize, on_line: impl Fn(usize) -> bool) {
+        for (offset, level, text) in &cell.headings {
+            if on_line(*offset) {
+                self.headings.push(PageHeading { level: *level, text: text.clone(), line });
+            }
+        }
+        for (offset, id) in &cell.anchors {
+            if on_line(*offset) {
+                self.anchors.push((id.clone(), self.output.len()));
+            }
+        }
+    }
+
+    // Lay a table's cells out on a grid: each cell goes in the first column
+    // not still covered by a rowspan from a row above
+    fn table_grid(&mut self, rows: &[scraper::ElementRef]) -> Vec<Vec<TableCell>> {
+        let span_attr = |cell: scraper::ElementRef, name: &str| {
+            cell.value()
+                .attr(name)
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .unwrap_or(1)
+                .clamp(1, MAX_TABLE_SPAN)
+        };
+        let mut covered: Vec<usize> = Vec::new(); // Rows left for each column, this one included
+        let mut grid = Vec::new();
+        for row in rows {
+            let mut cells = Vec::new();
+            let mut col = 0;
+            for element in row
+                .children()
+                .filter_map(scraper::ElementRef::wrap)
+                .filter(|e| matches!(e.value().name(), "td" | "th"))
+            {
+                while covered.get(col).is_some_and(|&left| left > 0) {
+                    col += 1;
+                }
+                let mut cell = self.render_cell(element);
+                cell.col = col;
+                cell.span = span_attr(element, "colspan");
+                if covered.len() < col + cell.span {
+                    covered.resize(col + cell.span, 0);
+                }
+                let down = span_attr(element, "rowspan");
+                for left in &mut covered[col..col + cell.span] {
+                    *left = down;
+                }
+                col += cell.span;
+                cells.push(cell);
+            }
+            for left in &mut covered {
+                *left = left.saturating_sub(1);
+            }
+            grid.push(cells);
+        }
+        grid.retain(|cells| cells.iter().any(|c| !c.text.is_empty()));
+        grid
+    }
+
+    // Accessible tables: one line per row, each cell labeled with its column header
+    fn render_linear_table(&mut self, grid: &[Vec<TableCell>]) {
+        let has_header = grid[0].iter().all(|c| c.header);
+        // A header spanning columns labels each of them
+        let mut headers: Vec<&str> = Vec::new();
+        if has_header {
+            for cell in &grid[0] {
+                headers.resize(cell.col, "");
+                headers.extend(std::iter::repeat_n(cell.text.as_str(), cell.span));
+            }
+        }
+
+        self.ensure_newline();
+        let mut number = 0;
+        for (row_idx, row) in grid.iter().enumerate() {
+            self.update_line_count();
+            let line = self.current_line;
+            let mut text = if has_header && row_idx == 0 {
+                String::from("Columns: ")
+            } else {
+                number += 1;
+                format!("Row {}: ", number)
+            };
+            for (i, cell) in row.iter().enumerate() {
+                if i > 0 {
+                    text.push_str("; ");
+                }
+                if row_idx > 0 {
+                    if let Some(header) = headers.get(cell.col).filter(|h| !h.is_empty()) {
+                        text.push_str(header);
+                        text.push_str(": ");
+                    }
+                }
+                let col = text.chars().count();
+                for (a, b, link_text, url) in &cell.links {
+                    self.links.push(PageLink {
+                        line,
+                        col_start: col + a,
+                        col_end: col + b,
+                        text: link_text.clone(),
+                        url: url.clone(),
+                    });
+                }
+                for (a, b, style) in &cell.spans {
+                    self.spans.push(StyledSpan { start: (line, col + a), end: (line, col + b), style: *style });
+                }
+                text.push_str(&cell.text);
+                self.place_cell_marks(cell, line, |_| true);
+            }
+            self.output.push_str(&text);
+            self.output.push('\n');
+        }
+    }
+
+    fn render_table(&mut self, table: scraper::ElementRef) {
+        let rows = Self::table_rows(table);
+
+        // Layout tables (nested tables or a single column) are not data, let them flow
+        let has_nested_table = table
+            .descendants()
+            .skip(1)
+            .any(|n| n.value().as_element().is_some_and(|e| e.name() == "table"));
+        let cell_count = |row: &scraper::ElementRef| {
+            row.children()
+                .filter_map(scraper::ElementRef::wrap)
+                .filter(|e| matches!(e.value().name(), "td" | "th"))
+                .count()
+        };
+        let most_cells = rows.iter().map(cell_count).max().unwrap_or(0);
+        if has_nested_table || most_cells <= 1 {
+            self.render_children(table);
+            return;
+        }
+
+        // Caption above the box
+        if let Some(caption) = table
+            .children()
+            .filter_map(scraper::ElementRef::wrap)
+            .find(|e| e.value().name() == "caption")
+        {
+            self.render_styled(caption, TextStyle::Bold);
+            self.ensure_newline();
+        }
+
+        let grid = self.table_grid(&rows);
+        if grid.is_empty() {
+            return;
+        }
+        if self.accessible {
+            self.render_linear_table(&grid);
+            return;
+        }
+        let max_cols = grid.iter().flatten().map(|c| c.col + c.span).max().unwrap_or(0);
+
+        // Column widths from display width, squeezed to fit. A spanning cell
+        // widens the last of its columns if they are too narrow together.
+        let mut natural = vec![1usize; max_cols];
+        for cell in grid.iter().flatten().filter(|c| c.span == 1) {
+            natural[cell.col] = natural[cell.col].max(cell.text.width());
+        }
+        for cell in grid.iter().flatten().filter(|c| c.span > 1) {
+            let columns = cell.col..cell.col + cell.span;
+            let have = natural[columns.clone()].iter().sum::<usize>() + 3 * (cell.span - 1);
+            natural[columns.end - 1] += cell.text.width().saturating_sub(have);
+        }
+        // Borders take 3 columns per cell plus one
+        let available = MAX_TABLE_WIDTH.saturating_sub(3 * max_cols + 1);
+        let widths = fit_column_widths(&natural, available);
+        let span_width = |col: usize, span: usize| widths[col..col + span].iter().sum::<usize>() + 3 * (span - 1);
+
+        // Where a row has a │ before each column; none inside a spanning cell
+        let splits = |row: &[TableCell]| {
+            let mut split = vec![true; max_cols];
+            for cell in row {
+                split[cell.col + 1..cell.col + cell.span].fill(false);
+            }
+            split
+        };
+        let border = |left: &str, right: &str, above: Option<&[bool]>, below: Option<&[bool]>| {
+            let mut line = String::from(left);
+            for (i, width) in widths.iter().enumerate() {
+                if i > 0 {
+                    line.push(match (above.is_some_and(|s| s[i]), below.is_some_and(|s| s[i])) {
+                        (true, true) => '┼',
+                        (true, false) => '┴',
+                        (false, true) => '┬',
+                        (false, false) => '─',
+                    });
+                }
+                line.push_str(&"─".repeat(width + 2));
+            }
+            line.push_str(right);
+            line
+        };
+
+        self.ensure_newline();
+        self.output.push_str(&border("┌", "┐", None, Some(&splits(&grid[0]))));
+        self.output.push('\n');
+
+        for (row_idx, row) in grid.iter().enumerate() {
+            // Each cell with the columns it covers; gaps are empty cells
+            let mut slots: Vec<(Option<&TableCell>, usize)> = Vec::new();
+            let mut cells = row.iter().peekable();
+            let mut col = 0;
+            while col < max_cols {
+                match cells.next_if(|cell| cell.col == col) {
+                    Some(cell) => {
+                        slots.push((Some(cell), span_width(col, cell.span)));
+                        col += cell.span;
+                    }
+                    None => {
+                        slots.push((None, widths[col]));
+                        col += 1;
+                    }
+                }
+            }
+            let wrapped: Vec<Vec<(usize, String)>> = slots
+                .iter()
+                .map(|(cell, width)| match cell {
+                    Some(cell) => wrap_with_offsets(&cell.text, *width),
+                    None => vec![(0, String::new())],
+                })
+                .collect();
+            let height = wrapped.iter().map(|w| w.len()).max().unwrap_or(1);
+
+            for line_idx in 0..height {
+                self.update_line_count();
+                let line = self.current_line;
+                let mut row_text = String::from("│");
+                for ((cell, width), cell_lines) in slots.iter().zip(&wrapped) {
+                    row_text.push(' ');
+                    let col = row_text.chars().count();
+                    let (seg_start, seg_text) = cell_lines
+                        .get(line_idx)
+                        .cloned()
+                        .unwrap_or((0, String::new()));
+                    let seg_len = seg_text.chars().count();
+                    let padding = width.saturating_sub(seg_text.width());
+                    row_text.push_str(&seg_text);
+                    row_text.push_str(&" ".repeat(padding));
+                    row_text.push_str(" │");
+
+                    // Map links and styles that touch this segment of the cell
+                    if let Some(cell) = cell {
+                        let seg_end = seg_start + seg_len;
+                        let clip = |a: usize, b: usize| {
+                            let a = a.max(seg_start);
+                            let b = b.min(seg_end);
+                            (a < b).then(|| (col + a - seg_start, col + b - seg_start))
+                        };
+                        for (a, b, text, url) in &cell.links {
+                            // Only the first visible piece of a wrapped link is navigable
+                            if *a >= seg_start || line_idx == 0 {
+                                if let Some((start, end)) = clip(*a, *b) {
+                                    self.links.push(PageLink {
+                                        line,
+                                        col_start: start,
+                                        col_end: end,
+                                        text: text.clone(),
+                                        url: url.clone(),
+                                    });
+                                }
+                            }
+                        }
+                        for (a, b, style) in &cell.spans {
+                            if let Some((start, end)) = clip(*a, *b) {
+                                self.spans.push(StyledSpan { start: (line, start), end: (line, end), style: *style });
+                            }
+                        }
+                        // Headings and anchors go on the line their text wrapped to
+                        let line_of = |offset| cell_lines.iter().rposition(|(start, _)| *start <= offset).unwrap_or(0);
+                        self.place_cell_marks(cell, line, |offset| line_of(offset) == line_idx);
+                    }
+                }
+                self.output.push_str(&row_text);
+                self.output.push('\n');
+            }
+
+            // Rule under a header row
+            let is_header = !row.is_empty() && row.iter().all(|c| c.header);
+            if is_header && row_idx + 1 < grid.len() {
+                self.output.push_str(&border("├", "┤", Some(&splits(row)), Some(&splits(&grid[row_idx + 1]))));
+                self.output.push('\n');
+            }
+        }
+
+        self.output.push_str(&border("└", "┘", Some(&splits(&grid[grid.len() - 1])), None));
+        self.output.push('\n');
+        self.last_was_block = true;
+    }
+
+    fn render_children(&mut self, element: scraper::ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                scraper::Node::Text(text) => {
+                    let content = text.text.to_string();
+                    if self.in_pre {
+                        self.output.push_str(&content);
+                    } else {
+                        // Normalize whitespace
+                        let normalized: String = content
+                            .split_whitespace()
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        if !normalized.is_empty() {
+                            if !self.last_was_block && !self.open_marker && !self.output.is_empty()
+                                && !self.output.ends_with(' ') && !self.output.ends_with('\n') {
+                                self.output.push(' ');
+                            }
+                            self.output.push_str(&normalized);
+                            self.last_was_block = false;
+                            self.open_marker = false;
+                        }
+                    }
+                }
+                scraper::Node::Element(_) => {
+                    if let Some(child_elem) = scraper::ElementRef::wrap(child) {
+                        self.render_element(child_elem);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn ensure_newline(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+    }
+
+    fn ensure_blank_line(&mut self) {
+        // Never start the document with blank lines, they would shift positions on trim
+        if self.output.is_empty() {
+            return;
+        }
+        self.ensure_newline();
+        if !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    pub fn finish(self) -> RenderedPage {
+        // An anchor belongs to the line of the first visible text after it
+        let mut anchors = HashMap::new();
+        for (id, offset) in &self.anchors {
+            let offset = (*offset).min(self.output.len());
+            let text_start = self.output[offset..]
+                .find(|c: char| !c.is_whitespace())
+                .map_or(self.output.len(), |i| offset + i);
+            let line = self.output[..text_start].matches('\n').count();
+            anchors.entry(id.clone()).or_insert(line);
+        }
+
+        RenderedPage {
+            text: self.output,
+            links: self.links,
+            images: self.images,
+            spans: self.spans,
+            headings: self.headings,
+            anchors,
+            source: Vec::new(),
+            json: None,
+            thread: None,
+            final_url: None,
+            redirects: Vec::new(),
+            meta: PageMeta::default(),
+            stats: None,
+            headers: Vec::new(),
+        }
+    }
+}
+
+// Render the main content of a page; with render_images off, images are
+// only listed for the gallery and never downloaded
+pub fn extract_content_with_images(html: &str, base_url: &str, render_images: bool, cancel: &CancelToken) -> RenderedPage {
+    let document = Html::parse_document(html);
+
+    // Find the main content element
+    let content_selectors = vec![
+        "#mw-content-text .mw-parser-output",
+        "#mw-content-text",
+        "#bodyContent",
+        ".question .s-prose",
+        ".answercell .s-prose",
+        "#mainbar",
+        "article .post-content",
+        "article .entry-content",
+        "article .content",
+        "article",
+        "main .content",
+        "main article",
+        "#main-content",
+        ".main-content",
+        "[role='main']",
+        "main",
+        ".post-body",
+        ".article-body",
+        ".story-body",
+        ".markdown-body",
+        ".documentation",
+        ".doc-content",
+        "#readme",
+        "#content",
+        "body",
+    ];
+
+    // A selector configured for the site wins; every element it matches is
+    // rendered, in document order
+    let configured = site_content_selector(base_url)
+        .map(|selector| document.select(&selector).collect::<Vec<_>>())
+        .filter(|elements| !elements.is_empty());
+
+    // Find the best content element
+    let mut content_element = None;
+    for sel_str in &content_selectors {
+        if let Ok(selector) = Selector::parse(sel_str) {
+            if let Some(element) = document.select(&selector).next() {
+                let inner = element.html();
+                if inner.len() > 500 {
+                    debug!(selector = sel_str, bytes = inner.len(), "content selector matched");
+                    content_element = Some(element);
+                    break;
+                }
+                debug!(selector = sel_str, bytes = inner.len(), "content selector too small, skipped");
+            }
+        }
+    }
+
+    // No known container, so let text density pick one instead of showing
+    // the whole body with its navigation and footers
+    let is_body = content_element.is_none_or(|element| element.value().name() == "body");
+    let readable = if is_body && configured.is_none() { readability_content(&document) } else { None };
+    match (&configured, &readable) {
+        (Some(elements), _) => debug!(url = base_url, elements = elements.len(), "using the configured site selector"),
+        (None, Some(elements)) => debug!(url = base_url, elements = elements.len(), "using readability blocks"),
+        (None, None) if content_element.is_none() => debug!(url = base_url, "no content found, rendering the body"),
+        (None, None) => {}
+    }
+
+    // Render the content
+    let mut renderer = HtmlRenderer::new(base_url);
+    renderer.cancel = cancel.clone();
+    if !render_images {
+        renderer.max_images = 0;
+    }
+
+    if let Some(elements) = configured.or(readable) {
+        for element in elements {
+            renderer.render_element(element);
+        }
+    } else if let Some(element) = content_element {
+        renderer.render_element(element);
+    } else {
+        // Fallback to body
+        if let Ok(selector) = Selector::parse("body") {
+            if let Some(body) = document.select(&selector).next() {
+                renderer.render_element(body);
+            }
+        }
+    }
+
+    let mut page = renderer.finish();
+    page.meta = parse_page_meta(&document);
+    if page.text.trim().is_empty() {
+        warn!(url = base_url, html_bytes = html.len(), "page rendered empty");
+    }
+    page
+}
+
+// The <meta> tags of a page without rendering it, e.g. the start of one
+pub(crate) fn html_meta(html: &str) -> PageMeta {
+    parse_page_meta(&Html::parse_document(html))
+}
+
+// Title, site, author, date, description and PDF from OpenGraph, standard
+// and citation <meta> tags, taking the first of each that is set
+fn parse_page_meta(document: &Html) -> PageMeta {
+    let selector = Selector::parse("meta[content]").unwrap();
+    let mut tags: HashMap<String, String> = HashMap::new();
+    for element in document.select(&selector) {
+        let key = element
+            .value()
+            .attr("property")
+            .or_else(|| element.value().attr("name"))
+            .or_else(|| element.value().attr("itemprop"));
+        let value = element.value().attr("content").unwrap_or("").split_whitespace().collect::<Vec<_>>().join(" ");
+        if let Some(key) = key {
+            if !value.is_empty() {
+                tags.entry(key.to_lowercase()).or_insert(value);
+            }
+        }
+    }
+    let first = |keys: &[&str]| keys.iter().find_map(|key| tags.get(*key).cloned());
+
+    let title_selector = Selector::parse("title").unwrap();
+    let title = first(&["og:title", "twitter:title"]).or_else(|| {
+        let element = document.select(&title_selector).next()?;
+        let text = element.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ");
+        (!text.is_empty()).then_some(text)
+    });
+    // article:author is often a profile URL rather than a name
+    let author = first(&["author", "article:author", "parsely-author", "sailthru.author", "dc.creator"])
+        .filter(|author| !author.starts_with("http"));
+    let published = first(&[
+        "article:published_time",
+        "datepublished",
+        "og:published_time",
+        "parsely-pub-date",
+        "dc.date.issued",
+        "dc.date",
+        "date",
+    ])
+    .and_then(|date| parse_meta_date(&date));
+
+    PageMeta {
+        title,
+        site_name: first(&["og:site_name", "application-name"]),
+        author,
+        published,
+        description: first(&["og:description", "description", "twitter:description"]),
+        pdf: first(&["citation_pdf_url"]),
+    }
+}
+
+// ISO 8601 timestamps, or at least a leading YYYY-MM-DD
+fn parse_meta_date(date: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|d| d.date_naive())
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok())
+}
+
+// How long ago a date was, e.g. "3 months ago"
+pub(crate) fn age_of(date: NaiveDate) -> String {
+    let days = (Local::now().date_naive() - date).num_days();
+    let (count, unit) = match days {
+        ..=0 => return "today".to_string(),
+        1 => return "yesterday".to_string(),
+        2..=30 => (days, "day"),
+        31..=364 => (days / 30, "month"),
+        _ => (days / 365, "year"),
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}
+
+// The configured content selector for a URL's site. The most specific domain
+// wins, so "docs.example.com" can differ from "example.com".
+fn site_content_selector(url: &str) -> Option<Selector> {
+    let host = Url::parse(url).ok()?.host_str()?.to_string();
+    let (_, selector) = config()
+        .selectors
+        .iter()
+        .filter(|(domain, _)| host_matches(&host, domain))
+        .max_by_key(|(domain, _)| domain.len())?;
+    Selector::parse(selector).ok()
+}
+
+// Reader mode: just the article Readability picks, without images
+pub(crate) fn extract_reader_content(html: &str, base_url: &str) -> Option<RenderedPage> {
+    let document = Html::parse_document(html);
+    let elements = readability_content(&document)?;
+    let mut renderer = HtmlRenderer::new(base_url);
+    renderer.max_images = 0;
+    for element in elements {
+        renderer.render_element(element);
+    }
+    let mut page = renderer.finish();
+    page.meta = parse_page_meta(&document);
+    Some(page)
+}
+
+// Class and id words that mark article containers and page furniture
+const READABILITY_POSITIVE: &[&str] = &["article", "body", "content", "entry", "main", "page", "post", "story", "text", "blog"];
+const READABILITY_NEGATIVE: &[&str] = &[
+    "comment", "meta", "footer", "footnote", "sidebar", "sponsor", "share", "social", "nav", "menu", "related",
+    "promo", "banner", "masthead", "widget", "combx", "popup", "cookie", "newsletter", "subscribe",
+];
+// A winner scoring below this is probably not an article at all
+const READABILITY_MIN_SCORE: f64 = 20.0;
+
+// Readability (arc90) scoring. Every paragraph awards points for its length
+// and commas to its parent, and half as many to its grandparent; containers
+// start from a bonus or penalty for their tag and class names, and lose the
+// share of their text that is links. The winner is returned with any
+// siblings that score nearly as well (articles split across several divs).
+fn readability_content(document: &Html) -> Option<Vec<ElementRef<'_>>> {
+    let paragraphs = Selector::parse("p, pre, td").unwrap();
+    let mut scores: HashMap<_, f64> = HashMap::new();
+    for paragraph in document.select(&paragraphs) {
+        let text: String = paragraph.text().collect();
+        let len = text.trim().chars().count();
+        if len < 25 {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (len / 100).min(3) as f64;
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            *scores.entry(parent.id()).or_insert_with(|| readability_weight(parent)) += score;
+        }
+        if let Some(grandparent) = ancestors.next() {
+            *scores.entry(grandparent.id()).or_insert_with(|| readability_weight(grandparent)) += score / 2.0;
+        }
+    }
+
+    let element = |id| document.tree.get(id).and_then(ElementRef::wrap);
+    let final_score = |element: ElementRef, score: f64| score * (1.0 - link_density(element));
+    let (top, best) = scores
+        .iter()
+        .filter_map(|(&id, &score)| element(id).map(|e| (e, final_score(e, score))))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if best < READABILITY_MIN_SCORE {
+        return None;
+    }
+
+    let Some(parent) = top.parent().and_then(ElementRef::wrap) else {
+        return Some(vec![top]);
+    };
+    let threshold = (best * 0.2).max(10.0);
+    let siblings = parent
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|sibling| {
+            if sibling.id() == top.id() {
+                return true;
+            }
+            if scores.get(&sibling.id()).is_some_and(|&score| final_score(*sibling, score) >= threshold) {
+                return true;
+            }
+            // Stray paragraphs of real prose next to the article
+            sibling.value().name() == "p"
+                && sibling.text().map(|t| t.chars().count()).sum::<usize>() > 80
+                && link_density(*sibling) < 0.25
+        })
+        .collect();
+    Some(siblings)
+}
+
+// Starting score for a container, from its tag and class/id names
+fn readability_weight(element: ElementRef) -> f64 {
+    let mut weight = match element.value().name() {
+        "div" | "article" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" | "nav" | "aside" | "footer" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" | "header" => -5.0,
+        _ => 0.0,
+    };
+    let names = format!(
+        "{} {}",
+        element.value().attr("class").unwrap_or(""),
+        element.value().id().unwrap_or("")
+    )
+    .to_lowercase();
+    if READABILITY_NEGATIVE.iter().any(|word| names.contains(word)) {
+        weight -= 25.0;
+    }
+    if READABILITY_POSITIVE.iter().any(|word| names.contains(word)) {
+        weight += 25.0;
+    }
+    weight
+}
+
+// Share of an element's text that sits inside links
+fn link_density(element: ElementRef) -> f64 {
+    let total: usize = element.text().map(|t| t.chars().count()).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let anchor = Selector::parse("a").unwrap();
+    let linked: usize = element
+        .select(&anchor)
+        .flat_map(|a| a.text())
+        .map(|t| t.chars().count())
+        .sum();
+    linked as f64 / total as f64
+}
+
+// Decode a body with the charset named in Content-Type, defaulting to UTF-8
+pub(crate) fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(|ct| ct.split(';').find_map(|part| part.trim().strip_prefix("charset=")))
+        .and_then(|charset| encoding_rs::Encoding::for_label(charset.trim_matches('"').as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+// Containers with more children than this start folded (except the root)
+const JSON_AUTO_FOLD: usize = 100;
+
+// A JSON object or array spanning some lines of the pretty-printed text
+#[derive(Clone)]
+pub(crate) struct JsonFold {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) pointer: String, // JSON Pointer, e.g. /items/0
+}
+
+// A parsed JSON response and which of its containers are folded
+#[derive(Clone)]
+pub(crate) struct JsonDoc {
+    value: serde_json::Value,
+    pub(crate) folded: HashSet<String>,
+    pub(crate) folds: Vec<JsonFold>, // From the last render
+}
+
+impl JsonDoc {
+    fn new(value: serde_json::Value) -> Self {
+        let mut folded = HashSet::new();
+        collect_large_nodes(&value, "", &mut folded);
+        folded.remove("");
+        JsonDoc {
+            value,
+            folded,
+            folds: Vec::new(),
+        }
+    }
+
+    // Pretty-print with two-space indents, coloring keys and values
+    pub(crate) fn render(&mut self) -> RenderedPage {
+        let mut writer = JsonWriter {
+            folded: &self.folded,
+            text: String::new(),
+            line: 0,
+            col: 0,
+            spans: Vec::new(),
+            folds: Vec::new(),
+        };
+        writer.value(&self.value, "", 0);
+        self.folds = writer.folds;
+        RenderedPage {
+            text: writer.text,
+            spans: writer.spans,
+            ..Default::default()
+        }
+    }
+}
+
+fn collect_large_nodes(value: &serde_json::Value, pointer: &str, out: &mut HashSet<String>) {
+    let children: Vec<(String, &serde_json::Value)> = match value {
+        serde_json::Value::Object(map) => map.iter().map(|(k, v)| (json_pointer_token(k), v)).collect(),
+        serde_json::Value::Array(items) => items.iter().enumerate().map(|(i, v)| (i.to_string(), v)).collect(),
+        _ => return,
+    };
+    if children.len() > JSON_AUTO_FOLD {
+        out.insert(pointer.to_string());
+    }
+    for (token, child) in children {
+        collect_large_nodes(child, &format!("{}/{}", pointer, token), out);
+    }
+}
+
+fn json_pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+struct JsonWriter<'a> {
+    folded: &'a HashSet<String>,
+    text: String,
+    line: usize,
+    col: usize, // In chars, like StyledSpan positions
+    spans: Vec<StyledSpan>,
+    folds: Vec<JsonFold>,
+}
+
+impl JsonWriter<'_> {
+    fn push(&mut self, s: &str, style: Option<TextStyle>) {
+        let start = (self.line, self.col);
+        self.text.push_str(s);
+        self.col += s.chars().count();
+        if let Some(style) = style {
+            self.spans.push(StyledSpan { start, end: (self.line, self.col), style });
+        }
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.text.push('\n');
+        self.line += 1;
+        self.col = 0;
+        self.push(&" ".repeat(indent), None);
+    }
+
+    fn value(&mut self, value: &serde_json::Value, pointer: &str, indent: usize) {
+        use serde_json::Value;
+        let (open, close, len) = match value {
+            Value::Object(map) if !map.is_empty() => ("{", "}", map.len()),
+            Value::Array(items) if !items.is_empty() => ("[", "]", items.len()),
+            Value::Object(_) => return self.push("{}", None),
+            Value::Array(_) => return self.push("[]", None),
+            Value::String(_) => return self.push(&value.to_string(), Some(TextStyle::JsonString)),
+            Value::Number(_) => return self.push(&value.to_string(), Some(TextStyle::JsonNumber)),
+            Value::Bool(_) | Value::Null => return self.push(&value.to_string(), Some(TextStyle::JsonKeyword)),
+        };
+
+        let start = self.line;
+        if self.folded.contains(pointer) {
+            self.push(&format!("{}…{}", open, close), None);
+            let noun = if matches!(value, Value::Object(_)) { "keys" } else { "items" };
+            self.push(&format!(" {} {}", len, noun), Some(TextStyle::Quote));
+        } else {
+            self.push(open, None);
+            let children: Vec<(Option<&String>, &Value)> = match value {
+                Value::Object(map) => map.iter().map(|(k, v)| (Some(k), v)).collect(),
+                Value::Array(items) => items.iter().map(|v| (None, v)).collect(),
+                _ => Vec::new(),
+            };
+            for (i, (key, child)) in children.into_iter().enumerate() {
+                self.newline(indent + 2);
+                let token = match key {
+                    Some(key) => {
+                        self.push(&serde_json::Value::String(key.clone()).to_string(), Some(TextStyle::JsonKey));
+                        self.push(": ", None);
+                        json_pointer_token(key)
+                    }
+                    None => i.to_string(),
+                };
+                self.value(child, &format!("{}/{}", pointer, token), indent + 2);
+                if i + 1 < len {
+                    self.push(",", None);
+                }
+            }
+            self.newline(indent);
+            self.push(close, None);
+        }
+        self.folds.push(JsonFold {
+            start,
+            end: self.line,
+            pointer: pointer.to_string(),
+        });
+    }
+}
+
+// JSON by content type (application/json, application/*+json), or a body
+// that parses as an object or array when the type doesn't say otherwise
+pub(crate) fn parse_json_body(content_type: Option<&str>, body: &str) -> Option<JsonDoc> {
+    let mime = content_type.map(|ct| ct.split(';').next().unwrap_or("").trim().to_lowercase());
+    let declared = mime.as_deref().is_some_and(|m| m == "application/json" || m.ends_with("+json"));
+    let sniffable = matches!(mime.as_deref(), None | Some("text/plain") | Some("application/octet-stream"))
+        && body.trim_start().starts_with(['{', '[']);
+    if !declared && !sniffable {
+        return None;
+    }
+    serde_json::from_str(body).ok().map(JsonDoc::new)
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// One entry of an RSS, Atom or JSON feed
+pub(crate) struct FeedItem {
+    pub(crate) title: String,
+    pub(crate) url: String,
+    pub(crate) date: Option<DateTime<Local>>,
+    summary_html: String,
+}
+
+pub(crate) struct ParsedFeed {
+    pub(crate) title: String,
+    description: Option<String>,
+    pub(crate) items: Vec<FeedItem>,
+}
+
+// Feeds by content type, or XML whose root is <rss>, <feed> or <rdf:RDF>
+pub(crate) fn parse_feed_body(content_type: Option<&str>, body: &[u8], url: &str) -> Option<ParsedFeed> {
+    let mime = content_type.map(|ct| ct.split(';').next().unwrap_or("").trim().to_lowercase());
+    let declared = matches!(
+        mime.as_deref(),
+        Some("application/rss+xml") | Some("application/atom+xml") | Some("application/feed+json")
+    );
+    let head = String::from_utf8_lossy(&body[..body.len().min(1024)]).to_lowercase();
+    let sniffed = matches!(
+        mime.as_deref(),
+        None | Some("text/xml") | Some("application/xml") | Some("text/plain") | Some("application/octet-stream")
+    ) && ["<rss", "<feed", "<rdf:rdf"].iter().any(|tag| head.contains(tag));
+    if !declared && !sniffed {
+        return None;
+    }
+    parse_feed(body, url)
+}
+
+pub(crate) fn parse_feed(body: &[u8], url: &str) -> Option<ParsedFeed> {
+    let feed = feed_rs::parser::parse(body).ok()?;
+    let base = Url::parse(url).ok();
+    // Summaries are HTML in most feeds; plain ones are escaped so they stay literal
+    let as_html = |text: &feed_rs::model::Text| {
+        if text.content_type.to_string().contains("html") {
+            text.content.clone()
+        } else {
+            format!("<p>{}</p>", escape_html(&text.content))
+        }
+    };
+    let items = feed
+        .entries
+        .iter()
+        .map(|entry| {
+            let link = entry
+                .links
+                .iter()
+                .find(|l| l.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+                .or(entry.links.first())
+                .map(|l| l.href.clone())
+                .unwrap_or_default();
+            FeedItem {
+                title: entry.title.as_ref().map_or_else(|| "(untitled)".to_string(), |t| t.content.trim().to_string()),
+                url: base.as_ref().and_then(|b| b.join(&link).ok()).map_or(link, |u| u.to_string()),
+                date: entry.published.or(entry.updated).map(|d| d.with_timezone(&Local)),
+                summary_html: entry
+                    .summary
+                    .as_ref()
+                    .map(as_html)
+                    .or_else(|| entry.content.as_ref().and_then(|c| c.body.clone()))
+                    .unwrap_or_default(),
+            }
+        })
+        .collect();
+    Some(ParsedFeed {
+        title: feed.title.map_or_else(|| display_host(url), |t| t.content.trim().to_string()),
+        description: feed.description.map(|d| d.content.trim().to_string()).filter(|d| !d.is_empty()),
+        items,
+    })
+}
+
+// Lay a feed out as a page: a heading and link per entry, then its date and
+// summary, so entries open like any other link and show in the contents
+pub(crate) fn feed_to_html(feed: &ParsedFeed) -> String {
+    let mut html = format!("<html><body><article><h1>{}</h1>", escape_html(&feed.title));
+    if let Some(description) = &feed.description {
+        html.push_str(&format!("<p><em>{}</em></p>", escape_html(description)));
+    }
+    for item in &feed.items {
+        html.push_str(&format!(
+            "<h2><a href=\"{}\">{}</a></h2>",
+            escape_html(&item.url),
+            escape_html(&item.title)
+        ));
+        if let Some(date) = item.date {
+            html.push_str(&format!("<p><em>{}</em></p>", date.format("%Y-%m-%d %H:%M")));
+        }
+        html.push_str(&item.summary_html);
+    }
+    html.push_str("</article></body></html>");
+    html
+}
+
+pub(crate) fn is_pdf(content_type: Option<&str>, body: &[u8]) -> bool {
+    content_type.is_some_and(|ct| ct.trim().to_lowercase().starts_with("application/pdf")) || body.starts_with(b"%PDF-")
+}
+
+// Extract a PDF's text page by page. Each page starts with a separator line
+// that is also listed in the table of contents.
+pub(crate) fn render_pdf(bytes: &[u8]) -> Result<RenderedPage, Box<dyn Error>> {
+    // pdf-extract panics on some malformed files rather than returning an error
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| "could not read this PDF")??;
+    if pages.iter().all(|text| text.trim().is_empty()) {
+        return Err("this PDF has no extractable text (it may be scanned images)".into());
+    }
+
+    let mut page = RenderedPage::default();
+    let mut lines: Vec<String> = Vec::new();
+    for (i, text) in pages.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        let label = format!("Page {} of {}", i + 1, pages.len());
+        let separator = if accessible() { format!("Heading level 2: {}", label) } else { format!("── {} ──", label) };
+        page.spans.push(StyledSpan {
+            start: (lines.len(), 0),
+            end: (lines.len(), separator.chars().count()),
+            style: TextStyle::Heading(2),
+        });
+        page.headings.push(PageHeading { level: 2, text: label, line: lines.len() });
+        lines.push(separator);
+        lines.push(String::new());
+
+        // Keep paragraph breaks but not the runs of blank lines PDFs produce
+        let mut blank = false;
+        for line in sanitize_display(&expand_tabs(text)).lines() {
+            let line = line.trim_end();
+            if line.is_empty() && blank {
+                continue;
+            }
+            blank = line.is_empty();
+            lines.push(line.to_string());
+        }
+    }
+    page.text = lines.join("\n");
+    Ok(page)
+}
+
+// text/markdown, or a .md file served as plain text (e.g. raw.githubusercontent.com)
+pub(crate) fn is_markdown(content_type: Option<&str>, url: &str) -> bool {
+    let mime = content_type.map(|ct| ct.split(';').next().unwrap_or("").trim().to_lowercase());
+    if matches!(mime.as_deref(), Some("text/markdown") | Some("text/x-markdown")) {
+        return true;
+    }
+    let path = Url::parse(url).map(|u| u.path().to_lowercase()).unwrap_or_default();
+    matches!(mime.as_deref(), None | Some("text/plain") | Some("application/octet-stream"))
+        && (path.ends_with(".md") || path.ends_with(".markdown"))
+}
+
+// Turn Markdown (with GitHub's tables, task lists and strikethrough) into
+// HTML for the page renderer. The article wrapper makes the renderer take
+// the whole document as the main content.
+pub(crate) fn markdown_to_html(markdown: &str) -> String {
+    let mut out = String::from("<html><body><article>");
+    pulldown_cmark::html::push_html(&mut out, markdown_parser(markdown));
+    out.push_str("</article></body></html>");
+    out
+}
+
+// The Markdown dialect navim reads, for callers that rewrite the events
+pub(crate) fn markdown_parser(markdown: &str) -> pulldown_cmark::Parser<'_> {
+    use pulldown_cmark::{Options, Parser};
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    Parser::new_ext(markdown, options)
+}
+
+// Whether a body should be shown verbatim instead of going through the HTML
+// renderer. Without a telling content type we look at the body itself.
+pub(crate) fn is_plain_text(content_type: Option<&str>, body: &str) -> bool {
+    let mime = content_type.map(|ct| ct.split(';').next().unwrap_or("").trim().to_lowercase());
+    match mime.as_deref() {
+        Some("text/plain") => true,
+        Some("text/html") | Some("application/xhtml+xml") => false,
+        _ => !looks_like_html(body),
+    }
+}
+
+pub(crate) fn looks_like_html(body: &str) -> bool {
+    let start: String = body.trim_start().chars().take(1024).collect::<String>().to_lowercase();
+    ["<!doctype html", "<html", "<head", "<body", "<div", "<p>", "<meta", "<title"]
+        .iter()
+        .any(|tag| start.contains(tag))
+}
+
+// Replace tabs with spaces up to the next 8-column stop, so columns in
+// plain-text tables stay aligned
+pub(crate) fn expand_tabs(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut col = 0;
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let spaces = 8 - col % 8;
+                out.extend(std::iter::repeat_n(' ', spaces));
+                col += spaces;
+            }
+            '\n' => {
+                out.push(c);
+                col = 0;
+            }
+            _ => {
+                out.push(c);
+                col += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(html: &str) -> RenderedPage {
+        extract_content_with_images(html, "https://example.com/", false, &CancelToken::default())
+    }
+
+    #[test]
+    fn anchors_inside_a_blockquote_keep_their_line() {
+        let page = render(r#"<body><blockquote><p>a</p><p id="a">cd</p></blockquote></body>"#);
+        let line = page.anchors["a"];
+        assert_eq!(page.text.lines().nth(line), Some("│ cd"));
+    }
+
+    #[test]
+    fn table_cells_can_span_columns_and_rows() {
+        let page = render(
+            r#"<body><table>
+            <tr><th colspan="2">Language</th><th>Year</th></tr>
+            <tr><td rowspan="2">Rust</td><td>1.0</td><td>2015</td></tr>
+            <tr><td>2021</td><td>2021</td></tr>
+            </table></body>"#,
+        );
+        let lines: Vec<&str> = page.text.trim_end().lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "┌─────────────┬──────┐",
+                "│ Language    │ Year │",
+                "├──────┬──────┼──────┤",
+                "│ Rust │ 1.0  │ 2015 │",
+                "│      │ 2021 │ 2021 │",
+                "└──────┴──────┴──────┘",
+            ]
+        );
+    }
+
+    #[test]
+    fn headings_and_anchors_in_table_cells_reach_the_page() {
+        let page = render(
+            r#"<body><p>Before</p><table>
+            <tr><td><h3 id="setup">Setup</h3></td><td>Install it</td></tr>
+            <tr><td id="usage">Usage</td><td>Run it</td></tr>
+            </table></body>"#,
+        );
+        let line_with = |text| page.text.lines().position(|l| l.contains(text));
+        let headings: Vec<(&str, Option<usize>)> = page.headings.iter().map(|h| (h.text.as_str(), Some(h.line))).collect();
+        assert_eq!(headings, [("Setup", line_with("Setup"))]);
+        assert_eq!(Some(page.anchors["setup"]), line_with("Setup"));
+        assert_eq!(Some(page.anchors["usage"]), line_with("Usage"));
+    }
+}
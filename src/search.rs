@@ -0,0 +1,1080 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+use std::error::Error;
+use std::sync::OnceLock;
+use std::thread;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::config::config;
+use crate::net::{
+    configured_client, fixtures, host_matches, https_upgrade, max_attempts, path_to_file_url, send_with_retry,
+    wait_for_host, CancelToken, Fetched, Fetcher, RequestKind,
+};
+use crate::render::{decode_body, sanitize_display};
+use crate::sites::DICTIONARY_URL;
+
+// Built-in bangs. A target containing {} is a URL template filled with the
+// query; anything else is a domain searched with site:
+const DEFAULT_BANGS: &[(&str, &str)] = &[
+    ("w", "https://en.wikipedia.org/wiki/Special:Search?search={}"),
+    ("wiki", "https://en.wikipedia.org/wiki/Special:Search?search={}"),
+    ("so", "stackoverflow.com"),
+    ("gh", "github.com"),
+    ("mdn", "developer.mozilla.org"),
+    ("rs", "https://docs.rs/releases/search?query={}"),
+    ("crates", "crates.io"),
+    ("arch", "https://wiki.archlinux.org/index.php?search={}"),
+    ("py", "docs.python.org"),
+    ("r", "reddit.com"),
+    ("hn", "news.ycombinator.com"),
+    ("def", DICTIONARY_URL),
+    ("yt", "youtube.com"),
+];
+
+// What a query typed by the user should do
+pub(crate) enum QueryTarget {
+    Search(String), // Run a web search with this query
+    Url(String),    // Open this page directly
+}
+
+fn find_bang(name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    config()
+        .bangs
+        .get(&name)
+        .cloned()
+        .or_else(|| DEFAULT_BANGS.iter().find(|(n, _)| *n == name).map(|(_, t)| t.to_string()))
+}
+
+// Input that is already a web address: an http(s) URL or a bare www. host
+fn as_url(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() || input.contains(char::is_whitespace) {
+        return None;
+    }
+    if let Ok(url) = Url::parse(input) {
+        if matches!(url.scheme(), "http" | "https" | "file") {
+            return Some(url.to_string());
+        }
+    }
+    if let Some(url) = path_to_file_url(input) {
+        return Some(url);
+    }
+    if input.starts_with("www.") {
+        return Some(format!("https://{}", input));
+    }
+    // Onion services rarely have certificates, so bare addresses get http
+    let host = input.split('/').next().unwrap_or(input);
+    host.ends_with(".onion").then(|| format!("http://{}", input))
+}
+
+// Turn typed text into a URL, assuming https when no scheme is given
+pub(crate) fn normalize_url(input: &str) -> String {
+    let input = input.trim();
+    if input.contains("://") {
+        input.to_string()
+    } else {
+        as_url(input).unwrap_or_else(|| format!("https://{}", input))
+    }
+}
+
+// Open URLs directly and expand a leading !bang into a direct URL or a
+// site-restricted search
+pub(crate) fn resolve_query(query: &str) -> QueryTarget {
+    let query = query.trim();
+    if let Some(url) = as_url(query) {
+        return QueryTarget::Url(url);
+    }
+    if let Some(rest) = query.strip_prefix('!') {
+        let (name, terms) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let terms = terms.trim();
+        if let Some(target) = find_bang(name) {
+            if target.contains("{}") {
+                let encoded: String = url::form_urlencoded::byte_serialize(terms.as_bytes()).collect();
+                return QueryTarget::Url(target.replace("{}", &encoded));
+            }
+            return QueryTarget::Search(format!("site:{} {}", target, terms).trim().to_string());
+        }
+    }
+    QueryTarget::Search(query.to_string())
+}
+
+// Time range filter for search results
+#[derive(Clone, Copy, PartialEq, Default)]
+pub(crate) enum TimeRange {
+    #[default]
+    Any,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeRange {
+    pub(crate) const ALL: [TimeRange; 5] = [TimeRange::Any, TimeRange::Day, TimeRange::Week, TimeRange::Month, TimeRange::Year];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            TimeRange::Any => "any time",
+            TimeRange::Day => "past day",
+            TimeRange::Week => "past week",
+            TimeRange::Month => "past month",
+            TimeRange::Year => "past year",
+        }
+    }
+
+    // Brave's tf parameter
+    fn param(self) -> Option<&'static str> {
+        match self {
+            TimeRange::Any => None,
+            TimeRange::Day => Some("pd"),
+            TimeRange::Week => Some("pw"),
+            TimeRange::Month => Some("pm"),
+            TimeRange::Year => Some("py"),
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "any" | "all" => Some(TimeRange::Any),
+            "day" | "d" => Some(TimeRange::Day),
+            "week" | "w" => Some(TimeRange::Week),
+            "month" | "m" => Some(TimeRange::Month),
+            "year" | "y" => Some(TimeRange::Year),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub(crate) enum SafeSearch {
+    Off,
+    #[default]
+    Moderate,
+    Strict,
+}
+
+impl SafeSearch {
+    pub(crate) const ALL: [SafeSearch; 3] = [SafeSearch::Off, SafeSearch::Moderate, SafeSearch::Strict];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            SafeSearch::Off => "off",
+            SafeSearch::Moderate => "moderate",
+            SafeSearch::Strict => "strict",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        SafeSearch::ALL.into_iter().find(|v| v.label() == s.to_lowercase())
+    }
+}
+
+// Regions offered by the filter popup; any two-letter code works from the CLI
+pub(crate) const REGIONS: &[&str] = &["us", "gb", "ca", "au", "de", "fr", "es", "it", "nl", "in", "jp", "br", "mx"];
+
+// Parameters narrowing a search
+#[derive(Clone, PartialEq, Default)]
+pub(crate) struct SearchFilters {
+    pub(crate) time: TimeRange,
+    pub(crate) region: Option<String>, // Country code, None for all regions
+    pub(crate) safesearch: SafeSearch,
+    pub(crate) verbatim: bool, // Search the query as typed, without spelling correction
+}
+
+impl SearchFilters {
+    // Whether any filter from the popup is set
+    pub(crate) fn is_default(&self) -> bool {
+        SearchFilters { verbatim: false, ..self.clone() } == SearchFilters::default()
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "{} · {} · safe search {}",
+            self.time.label(),
+            self.region.as_deref().unwrap_or("all regions"),
+            self.safesearch.label()
+        )
+    }
+}
+
+// Step through a fixed list of values, wrapping around
+pub(crate) fn cycle<T: Copy + PartialEq>(values: &[T], current: T, forward: bool) -> T {
+    let i = values.iter().position(|v| *v == current).unwrap_or(0);
+    let n = values.len();
+    if forward { values[(i + 1) % n] } else { values[(i + n - 1) % n] }
+}
+
+// Kind of results to search for
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Vertical {
+    #[default]
+    Web,
+    News,
+    Images,
+    Videos,
+}
+
+impl Vertical {
+    pub(crate) const ALL: [Vertical; 4] = [Vertical::Web, Vertical::News, Vertical::Images, Vertical::Videos];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Vertical::Web => "Web",
+            Vertical::News => "News",
+            Vertical::Images => "Images",
+            Vertical::Videos => "Videos",
+        }
+    }
+
+    // Path of the vertical's SERP on search.brave.com
+    fn path(self) -> &'static str {
+        match self {
+            Vertical::Web => "search",
+            Vertical::News => "news",
+            Vertical::Images => "images",
+            Vertical::Videos => "videos",
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub display_url: String,
+    pub description: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub source: String, // Publisher (news) or channel (videos)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub age: String, // e.g. "3 hours ago"
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub duration: String, // Videos only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>, // Images and videos
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub engines: Vec<&'static str>, // Engines that returned this result, set by meta-search
+    #[serde(skip)]
+    pub(crate) boosted: bool, // Matched a preferred domain
+}
+
+// How long typing must pause before asking for suggestions
+pub(crate) const SUGGEST_DEBOUNCE_MS: u64 = 150;
+const MAX_SUGGESTIONS: usize = 8;
+
+// Query Brave's autocomplete endpoint (OpenSearch format: [query, [suggestions...]])
+pub(crate) fn fetch_suggestions(query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    if fixtures().is_some() {
+        return Ok(Vec::new());
+    }
+    let url = Url::parse_with_params("https://search.brave.com/api/suggest", &[("q", query)])?;
+
+    let client = configured_client(RequestKind::Suggest)?.build()?;
+
+    let text = client
+        .get(url)
+        .header("Accept", "application/json")
+        .send()?
+        .text()?;
+    let body: serde_json::Value = serde_json::from_str(&text)?;
+
+    let suggestions = body
+        .get(1)
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(sanitize_display)
+                .filter(|s| !s.is_empty())
+                .take(MAX_SUGGESTIONS)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(suggestions)
+}
+
+// One page of a SERP: the results, and what the engine offers around them
+#[derive(Default)]
+pub(crate) struct Serp {
+    pub(crate) results: Vec<SearchResult>,
+    pub(crate) related: Vec<String>, // Related searches, as queries
+    pub(crate) infobox: Option<Infobox>,
+    pub(crate) correction: Option<Correction>,
+}
+
+// The engine's take on the query's spelling
+#[derive(Clone)]
+pub struct Correction {
+    pub query: String,           // What to search for instead
+    pub showing: Option<String>, // Set when the results are already for a corrected query
+}
+
+// The knowledge panel some queries get beside the results: what the thing
+// is, a few facts about it and where to read more
+#[derive(Clone, Default)]
+pub(crate) struct Infobox {
+    pub(crate) title: String,
+    pub(crate) subtitle: String,
+    pub(crate) summary: String,
+    pub(crate) facts: Vec<(String, String)>, // Label, value
+    pub(crate) links: Vec<(String, String)>, // Text, URL
+}
+
+pub(crate) fn search(
+    query: &str,
+    vertical: Vertical,
+    filters: &SearchFilters,
+    engines: &[String],
+) -> Result<Serp, Box<dyn Error>> {
+    search_page(query, vertical, 0, filters, engines)
+}
+
+// Fetch one SERP page; offset is the page index (0 is the first page).
+// Web searches go to the configured engines, the other verticals to Brave.
+pub(crate) fn search_page(
+    query: &str,
+    vertical: Vertical,
+    offset: usize,
+    filters: &SearchFilters,
+    engines: &[String],
+) -> Result<Serp, Box<dyn Error>> {
+    let fetcher = serp_fetcher();
+    if vertical != Vertical::Web {
+        let mut serp = brave_search(fetcher, query, vertical, offset, filters)?;
+        upgrade_results(&mut serp.results);
+        return Ok(serp);
+    }
+
+    let providers: Vec<&'static dyn SearchProvider> = engines.iter().filter_map(|name| find_provider(name)).collect();
+    let mut serp = match providers.as_slice() {
+        [] => Brave.search(fetcher, query, offset, filters)?,
+        [provider] => provider.search(fetcher, query, offset, filters)?,
+        _ => meta_search(fetcher, &providers, query, offset, filters)?,
+    };
+    boost_domains(&mut serp.results, &config().boost);
+    upgrade_results(&mut serp.results);
+    info!(query, engines = providers.len().max(1), offset, results = serp.results.len(), "web search");
+    Ok(serp)
+}
+
+// The engine a search goes to when it asks only one, for recording where
+// results came from
+pub(crate) fn search_engine(vertical: Vertical, engines: &[String]) -> &'static str {
+    let providers: Vec<&'static dyn SearchProvider> = engines.iter().filter_map(|name| find_provider(name)).collect();
+    match providers.as_slice() {
+        [provider] if vertical == Vertical::Web => provider.name(),
+        _ => Brave.name(),
+    }
+}
+
+// HTTPS-only mode shows results with the address they will be loaded from
+fn upgrade_results(results: &mut [SearchResult]) {
+    for result in results {
+        if let Some(url) = https_upgrade(&result.url) {
+            result.url = url;
+        }
+    }
+}
+
+// Move results from preferred domains to the top, in the order the domains
+// are listed. Everything else keeps the engine's ranking.
+fn boost_domains(results: &mut [SearchResult], domains: &[String]) {
+    if domains.is_empty() {
+        return;
+    }
+    let preference = |url: &str| {
+        let host = Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_lowercase))?;
+        domains.iter().position(|d| host_matches(&host, d))
+    };
+    for result in results.iter_mut() {
+        result.boosted = preference(&result.url).is_some();
+    }
+    // sort_by_key is stable, so ties keep their rank
+    results.sort_by_key(|r| preference(&r.url).unwrap_or(usize::MAX));
+}
+
+// A web search engine whose SERP we can parse
+pub(crate) trait SearchProvider: Sync {
+    fn name(&self) -> &'static str;
+    fn search(
+        &self,
+        fetcher: &dyn Fetcher,
+        query: &str,
+        offset: usize,
+        filters: &SearchFilters,
+    ) -> Result<Serp, Box<dyn Error>>;
+}
+
+struct Brave;
+struct DuckDuckGo;
+struct Mojeek;
+
+pub(crate) const PROVIDERS: &[&dyn SearchProvider] = &[&Brave, &DuckDuckGo, &Mojeek];
+
+pub(crate) fn find_provider(name: &str) -> Option<&'static dyn SearchProvider> {
+    PROVIDERS.iter().copied().find(|p| p.name().eq_ignore_ascii_case(name))
+}
+
+static BROWSER_CLIENT: OnceLock<reqwest::blocking::Client> = OnceLock::new();
+
+// Client that looks like a desktop browser, which SERPs expect. Built once
+// and shared so connections are reused across searches.
+fn browser_client() -> reqwest::Result<reqwest::blocking::Client> {
+    if let Some(client) = BROWSER_CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let client = configured_client(RequestKind::Search)?.build()?;
+    Ok(BROWSER_CLIENT.get_or_init(|| client).clone())
+}
+
+// Live SERP requests, made with the browser-like client
+struct SerpNetwork;
+
+impl Fetcher for SerpNetwork {
+    fn fetch(&self, url: &str) -> Result<Fetched, Box<dyn Error>> {
+        let _permit = wait_for_host(url, &CancelToken::default())?;
+        let request = browser_client()?.get(url);
+        let response = send_with_retry(request, &CancelToken::default(), &|_| {})?;
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(format!("search engine returned {} (after {} attempts)", status, max_attempts()).into());
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        Ok(Fetched {
+            url: response.url().to_string(),
+            status: Some(status.as_u16()),
+            content_type,
+            body: response.bytes()?.to_vec(),
+            ..Default::default()
+        })
+    }
+}
+
+// Saved SERPs with --offline-fixtures, the network otherwise
+fn serp_fetcher() -> &'static dyn Fetcher {
+    match fixtures() {
+        Some(fixtures) => fixtures,
+        None => &SerpNetwork,
+    }
+}
+
+fn fetch_serp(fetcher: &dyn Fetcher, url: Url) -> Result<Html, Box<dyn Error>> {
+    debug!(%url, "fetching SERP");
+    let fetched = fetcher.fetch(url.as_str())?;
+    let body = decode_body(&fetched.body, fetched.content_type.as_deref());
+    Ok(Html::parse_document(&body))
+}
+
+// The first page of Brave web results, fetched through `fetcher`
+pub fn search_with(fetcher: &dyn Fetcher, query: &str) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+    Ok(brave_search(fetcher, query, Vertical::Web, 0, &SearchFilters::default())?.results)
+}
+
+// The first page of a Brave vertical's results, fetched through `fetcher`
+pub fn vertical_search_with(
+    fetcher: &dyn Fetcher,
+    query: &str,
+    vertical: Vertical,
+) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+    Ok(brave_search(fetcher, query, vertical, 0, &SearchFilters::default())?.results)
+}
+
+// The same page's spelling correction; verbatim searches the query as typed
+pub fn correction_with(fetcher: &dyn Fetcher, query: &str, verbatim: bool) -> Result<Option<Correction>, Box<dyn Error>> {
+    let filters = SearchFilters { verbatim, ..Default::default() };
+    Ok(brave_search(fetcher, query, Vertical::Web, 0, &filters)?.correction)
+}
+
+// Query every provider at once, then interleave their lists by rank.
+// A URL returned by several engines keeps its best rank and lists them all.
+// The rest of the page comes from the first engine that has it.
+fn meta_search(
+    fetcher: &dyn Fetcher,
+    providers: &[&'static dyn SearchProvider],
+    query: &str,
+    offset: usize,
+    filters: &SearchFilters,
+) -> Result<Serp, Box<dyn Error>> {
+    let lists: Vec<(&'static str, Result<Serp, String>)> = thread::scope(|scope| {
+        let handles: Vec<_> = providers
+            .iter()
+            .map(|provider| {
+                let handle = scope.spawn(move || provider.search(fetcher, query, offset, filters).map_err(|e| e.to_string()));
+                (provider.name(), handle)
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|(name, handle)| (name, handle.join().unwrap_or_else(|_| Err("search thread panicked".to_string()))))
+            .collect()
+    });
+
+    let mut errors = Vec::new();
+    let mut ranked = Vec::new();
+    for (name, list) in lists {
+        match list {
+            Ok(results) => ranked.push((name, results)),
+            Err(e) => {
+                warn!(engine = name, error = %e, "search failed");
+                errors.push(format!("{}: {}", name, e))
+            }
+        }
+    }
+    // Only fail when every engine did
+    if ranked.is_empty() {
+        return Err(errors.join("; ").into());
+    }
+
+    let mut merged: Vec<SearchResult> = Vec::new();
+    let longest = ranked.iter().map(|(_, serp)| serp.results.len()).max().unwrap_or(0);
+    for rank in 0..longest {
+        for (name, serp) in &ranked {
+            let Some(result) = serp.results.get(rank) else {
+                continue;
+            };
+            let key = dedupe_key(&result.url);
+            match merged.iter_mut().find(|r| dedupe_key(&r.url) == key) {
+                Some(existing) => existing.engines.push(name),
+                None => {
+                    let mut result = result.clone();
+                    result.engines = vec![name];
+                    merged.push(result);
+                }
+            }
+        }
+    }
+    let related = ranked.iter().map(|(_, serp)| &serp.related).find(|related| !related.is_empty()).cloned();
+    let infobox = ranked.iter().find_map(|(_, serp)| serp.infobox.clone());
+    let correction = ranked.iter().find_map(|(_, serp)| serp.correction.clone());
+    Ok(Serp { results: merged, related: related.unwrap_or_default(), infobox, correction })
+}
+
+// URLs that differ only in scheme, www. or a trailing slash are the same result
+fn dedupe_key(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.trim_start_matches("www.").trim_end_matches('/').to_lowercase()
+}
+
+impl SearchProvider for Brave {
+    fn name(&self) -> &'static str {
+        "brave"
+    }
+
+    fn search(
+        &self,
+        fetcher: &dyn Fetcher,
+        query: &str,
+        offset: usize,
+        filters: &SearchFilters,
+    ) -> Result<Serp, Box<dyn Error>> {
+        brave_search(fetcher, query, Vertical::Web, offset, filters)
+    }
+}
+
+impl SearchProvider for DuckDuckGo {
+    fn name(&self) -> &'static str {
+        "duckduckgo"
+    }
+
+    // The JavaScript-free HTML endpoint
+    fn search(
+        &self,
+        fetcher: &dyn Fetcher,
+        query: &str,
+        offset: usize,
+        filters: &SearchFilters,
+    ) -> Result<Serp, Box<dyn Error>> {
+        let mut url = Url::parse_with_params("https://html.duckduckgo.com/html/", &[("q", query)])?;
+        {
+            let mut params = url.query_pairs_mut();
+            if offset > 0 {
+                params.append_pair("s", &(offset * 30).to_string());
+                params.append_pair("dc", &(offset * 30 + 1).to_string());
+            }
+            if let Some(tf) = filters.time.param() {
+                // pd/pw/pm/py -> d/w/m/y
+                params.append_pair("df", &tf[1..]);
+            }
+            if let Some(region) = &filters.region {
+                params.append_pair("kl", &format!("{}-en", region));
+            }
+            let kp = match filters.safesearch {
+                SafeSearch::Off => "-2",
+                SafeSearch::Moderate => "-1",
+                SafeSearch::Strict => "1",
+            };
+            params.append_pair("kp", kp);
+        }
+        let document = fetch_serp(fetcher, url)?;
+
+        let result_selector = Selector::parse("div.result").unwrap();
+        let link_selector = Selector::parse("a.result__a").unwrap();
+        let desc_selector = Selector::parse(".result__snippet").unwrap();
+
+        let mut results = Vec::new();
+        for result in document.select(&result_selector) {
+            // Ads carry their own class and point through a tracker
+            if result.value().classes().any(|c| c == "result--ad") {
+                continue;
+            }
+            let Some(link) = result.select(&link_selector).next() else {
+                continue;
+            };
+            let Some(url) = link.value().attr("href").and_then(unwrap_ddg_redirect) else {
+                continue;
+            };
+            let title = sanitize_display(&link.text().collect::<String>()).trim().to_string();
+            if title.is_empty() {
+                continue;
+            }
+            results.push(SearchResult {
+                title,
+                display_url: display_host(&url),
+                description: select_text(result, &desc_selector),
+                url,
+                ..Default::default()
+            });
+        }
+        Ok(Serp { results, ..Default::default() })
+    }
+}
+
+// DuckDuckGo links go through /l/?uddg=<target>
+fn unwrap_ddg_redirect(href: &str) -> Option<String> {
+    let absolute = if href.starts_with("//") { format!("https:{}", href) } else { href.to_string() };
+    let url = Url::parse(&absolute).ok()?;
+    if url.path() == "/l/" {
+        return url.query_pairs().find(|(k, _)| k == "uddg").map(|(_, v)| v.into_owned());
+    }
+    Some(absolute).filter(|u| u.starts_with("http"))
+}
+
+impl SearchProvider for Mojeek {
+    fn name(&self) -> &'static str {
+        "mojeek"
+    }
+
+    // Mojeek has its own index, which helps with niche results. It only
+    // supports the safe search filter.
+    fn search(
+        &self,
+        fetcher: &dyn Fetcher,
+        query: &str,
+        offset: usize,
+        filters: &SearchFilters,
+    ) -> Result<Serp, Box<dyn Error>> {
+        let mut url = Url::parse_with_params("https://www.mojeek.com/search", &[("q", query)])?;
+        {
+            let mut params = url.query_pairs_mut();
+            if offset > 0 {
+                params.append_pair("s", &(offset * 10 + 1).to_string());
+            }
+            if filters.safesearch == SafeSearch::Strict {
+                params.append_pair("safe", "1");
+            }
+        }
+        let document = fetch_serp(fetcher, url)?;
+
+        let result_selector = Selector::parse("ul.results-standard > li").unwrap();
+        let link_selector = Selector::parse("a.title").unwrap();
+        let desc_selector = Selector::parse("p.s").unwrap();
+
+        let mut results = Vec::new();
+        for result in document.select(&result_selector) {
+            let Some(link) = result.select(&link_selector).next() else {
+                continue;
+            };
+            let Some(url) = link.value().attr("href").filter(|h| h.starts_with("http")) else {
+                continue;
+            };
+            let title = sanitize_display(&link.text().collect::<String>()).trim().to_string();
+            if title.is_empty() {
+                continue;
+            }
+            results.push(SearchResult {
+                title,
+                url: url.to_string(),
+                display_url: display_host(url),
+                description: select_text(result, &desc_selector),
+                ..Default::default()
+            });
+        }
+        Ok(Serp { results, ..Default::default() })
+    }
+}
+
+// Fetch one page of one of Brave's verticals
+fn brave_search(
+    fetcher: &dyn Fetcher,
+    query: &str,
+    vertical: Vertical,
+    offset: usize,
+    filters: &SearchFilters,
+) -> Result<Serp, Box<dyn Error>> {
+    let endpoint = format!("https://search.brave.com/{}", vertical.path());
+    let mut url = Url::parse_with_params(&endpoint, &[("q", query)])?;
+    {
+        let mut params = url.query_pairs_mut();
+        if offset > 0 {
+            params.append_pair("offset", &offset.to_string());
+        }
+        if let Some(tf) = filters.time.param() {
+            params.append_pair("tf", tf);
+        }
+        if let Some(region) = &filters.region {
+            params.append_pair("country", region);
+        }
+        if filters.safesearch != SafeSearch::default() {
+            params.append_pair("safesearch", filters.safesearch.label());
+        }
+        if filters.verbatim {
+            params.append_pair("spellcheck", "0");
+        }
+    }
+    let document = fetch_serp(fetcher, url)?;
+
+    let results = match vertical {
+        Vertical::Web => parse_web_results(&document),
+        Vertical::News => parse_news_results(&document),
+        Vertical::Images => parse_image_results(&document),
+        Vertical::Videos => parse_video_results(&document),
+    };
+    let (related, infobox) = if vertical == Vertical::Web {
+        (parse_related_searches(&document, query), parse_infobox(&document))
+    } else {
+        (Vec::new(), None)
+    };
+    let correction = parse_correction(&document, query);
+    debug!(vertical = vertical.path(), results = results.len(), related = related.len(), "parsed Brave SERP");
+    Ok(Serp { results, related, infobox, correction })
+}
+
+// The spelling suggestion above the results. "Did you mean" links to the
+// suggestion; "Showing results for X, search instead for Y" has a link back
+// to the query as typed.
+fn parse_correction(document: &Html, query: &str) -> Option<Correction> {
+    let block_selector = Selector::parse("#altered-query, .altered-query, .did-you-mean, .spelling-suggestion").unwrap();
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let emphasis_selector = Selector::parse("strong, b, em").unwrap();
+    let block = document.select(&block_selector).next()?;
+    let base = Url::parse("https://search.brave.com/").unwrap();
+    let queries: Vec<String> = block
+        .select(&link_selector)
+        .filter_map(|link| base.join(link.value().attr("href")?).ok())
+        .filter_map(|url| url.query_pairs().find(|(k, _)| k == "q").map(|(_, v)| v.into_owned()))
+        .map(|q| sanitize_display(&q).split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|q| !q.is_empty())
+        .collect();
+    let typed = |q: &String| q.eq_ignore_ascii_case(query.trim());
+    if queries.iter().any(typed) {
+        let showing = queries
+            .iter()
+            .find(|q| !typed(q))
+            .cloned()
+            .or_else(|| block.select(&emphasis_selector).map(select_text_of).find(|text| !text.is_empty()));
+        return Some(Correction { query: query.trim().to_string(), showing });
+    }
+    let suggestion = queries.into_iter().next()?;
+    Some(Correction { query: suggestion, showing: None })
+}
+
+// Most facts and links kept from an infobox
+const INFOBOX_FACTS: usize = 8;
+const INFOBOX_LINKS: usize = 4;
+
+// The infobox, when the SERP has one with at least a title
+fn parse_infobox(document: &Html) -> Option<Infobox> {
+    let box_selector = Selector::parse("#infobox, .infobox").unwrap();
+    let title_selector = Selector::parse(".infobox-title, h1, h2, h3").unwrap();
+    let subtitle_selector = Selector::parse(".infobox-subtitle, .subtitle").unwrap();
+    let summary_selector = Selector::parse(".infobox-description, .description, p").unwrap();
+    let row_selector = Selector::parse(".infobox-attr, tr").unwrap();
+    let label_selector = Selector::parse(".attr-name, th, td:first-child").unwrap();
+    let value_selector = Selector::parse(".attr-value, td:last-child").unwrap();
+    let term_selector = Selector::parse("dt").unwrap();
+    let link_selector = Selector::parse("a[href]").unwrap();
+
+    let panel = document.select(&box_selector).next()?;
+    let title = select_text(panel, &title_selector);
+    if title.is_empty() {
+        return None;
+    }
+    let summary = panel
+        .select(&summary_selector)
+        .map(|e| sanitize_display(&e.text().collect::<String>()).split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|text| !text.is_empty())
+        .unwrap_or_default();
+
+    // Facts come as table rows, attribute rows, or a <dl>
+    let mut facts: Vec<(String, String)> = panel
+        .select(&row_selector)
+        .map(|row| (select_text(row, &label_selector), select_text(row, &value_selector)))
+        .collect();
+    for term in panel.select(&term_selector) {
+        let value = term.next_siblings().filter_map(ElementRef::wrap).find(|e| e.value().name() == "dd");
+        if let Some(value) = value {
+            facts.push((select_text_of(term), select_text_of(value)));
+        }
+    }
+    facts.retain(|(label, value)| !label.is_empty() && !value.is_empty() && label != value);
+    facts.truncate(INFOBOX_FACTS);
+
+    let mut links: Vec<(String, String)> = Vec::new();
+    for link in panel.select(&link_selector) {
+        let Some(url) = link.value().attr("href").filter(|h| h.starts_with("http")) else {
+            continue;
+        };
+        let text = sanitize_display(&link.text().collect::<String>()).trim().to_string();
+        let text = if text.is_empty() { display_host(url) } else { text };
+        if links.len() < INFOBOX_LINKS && !links.iter().any(|(_, u)| u == url) {
+            links.push((text, url.to_string()));
+        }
+    }
+
+    Some(Infobox { title, subtitle: select_text(panel, &subtitle_selector), summary, facts, links })
+}
+
+// Trimmed text of an element, whitespace collapsed
+fn select_text_of(element: ElementRef) -> String {
+    sanitize_display(&element.text().collect::<String>()).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Queries in the related searches block, taken from each link's q parameter
+// (or its text), without the query itself
+fn parse_related_searches(document: &Html, query: &str) -> Vec<String> {
+    let block_selector = Selector::parse("#related-queries, .related-queries").unwrap();
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let Some(block) = document.select(&block_selector).next() else {
+        return Vec::new();
+    };
+    let base = Url::parse("https://search.brave.com/").unwrap();
+    let mut related: Vec<String> = Vec::new();
+    for link in block.select(&link_selector) {
+        let from_href = link
+            .value()
+            .attr("href")
+            .and_then(|href| base.join(href).ok())
+            .and_then(|url| url.query_pairs().find(|(k, _)| k == "q").map(|(_, v)| v.into_owned()));
+        let text = from_href.unwrap_or_else(|| link.text().collect::<String>());
+        let text = sanitize_display(&text).split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() && !text.eq_ignore_ascii_case(query) && !related.contains(&text) {
+            related.push(text);
+        }
+    }
+    related
+}
+
+// Trimmed text of the first element matching a selector
+fn select_text(element: ElementRef, selector: &Selector) -> String {
+    element
+        .select(selector)
+        .next()
+        .map(|e| sanitize_display(&e.text().collect::<String>()).trim().to_string())
+        .unwrap_or_default()
+}
+
+// First absolute link inside an element
+fn select_href(element: ElementRef) -> Option<String> {
+    let link_selector = Selector::parse("a[href]").unwrap();
+    element
+        .select(&link_selector)
+        .filter_map(|e| e.value().attr("href"))
+        .find(|h| h.starts_with("http"))
+        .map(str::to_string)
+}
+
+// Host part of a URL for the second line of a result
+pub(crate) fn display_host(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_string()))
+        .unwrap_or_default()
+}
+
+// Organic results from the web SERP
+fn parse_web_results(document: &Html) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+
+    let snippet_selector = Selector::parse("div.snippet").unwrap();
+    let title_link_selector = Selector::parse("a.heading-serpresult, a[href]").unwrap();
+    let title_selector = Selector::parse(".title").unwrap();
+    let url_selector = Selector::parse(".snippet-url").unwrap();
+    let desc_selector = Selector::parse(".snippet-description, .generic-snippet").unwrap();
+
+    for snippet in document.select(&snippet_selector).take(10) {
+        let title = snippet
+            .select(&title_selector)
+            .next()
+            .map(|e| sanitize_display(&e.text().collect::<String>()))
+            .unwrap_or_default();
+
+        let actual_url = snippet
+            .select(&title_link_selector)
+            .find(|e| {
+                e.value()
+                    .attr("href")
+                    .map(|h| h.starts_with("http"))
+                    .unwrap_or(false)
+            })
+            .and_then(|e| e.value().attr("href"))
+            .unwrap_or_default()
+            .to_string();
+
+        let display_url = snippet
+            .select(&url_selector)
+            .next()
+            .map(|e| {
+                e.text()
+                    .collect::<String>()
+                    .replace("›", "/")
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or("")
+                    .to_string()
+            })
+            .unwrap_or_default();
+
+        let description = snippet
+            .select(&desc_selector)
+            .next()
+            .map(|e| sanitize_display(&e.text().collect::<String>()))
+            .unwrap_or_default();
+
+        if !title.is_empty() && !actual_url.is_empty() {
+            results.push(SearchResult {
+                title: title.trim().to_string(),
+                url: actual_url,
+                display_url: display_url.trim().to_string(),
+                description: description.trim().to_string(),
+                ..Default::default()
+            });
+        }
+    }
+
+    results
+}
+
+// Articles from the news SERP, with publisher and age
+fn parse_news_results(document: &Html) -> Vec<SearchResult> {
+    let snippet_selector = Selector::parse("div.snippet, article").unwrap();
+    let title_selector = Selector::parse(".title, h2, h3").unwrap();
+    let desc_selector = Selector::parse(".snippet-description, .description, p").unwrap();
+    let source_selector = Selector::parse(".netloc, .source, cite").unwrap();
+    let age_selector = Selector::parse(".age, .attr, time").unwrap();
+
+    let mut results = Vec::new();
+    for snippet in document.select(&snippet_selector).take(20) {
+        let title = select_text(snippet, &title_selector);
+        let Some(url) = select_href(snippet) else {
+            continue;
+        };
+        if title.is_empty() || results.iter().any(|r: &SearchResult| r.url == url) {
+            continue;
+        }
+
+        let mut source = select_text(snippet, &source_selector);
+        if source.is_empty() {
+            source = display_host(&url);
+        }
+        results.push(SearchResult {
+            title,
+            display_url: display_host(&url),
+            description: select_text(snippet, &desc_selector),
+            source,
+            age: select_text(snippet, &age_selector),
+            url,
+            ..Default::default()
+        });
+    }
+    results
+}
+
+// Image results; the URL is the page the image was found on
+fn parse_image_results(document: &Html) -> Vec<SearchResult> {
+    let item_selector = Selector::parse(".image-result, .image-wrapper, a.image").unwrap();
+    let img_selector = Selector::parse("img").unwrap();
+    let title_selector = Selector::parse(".title, .img-title").unwrap();
+
+    let mut results = Vec::new();
+    for item in document.select(&item_selector).take(30) {
+        let Some(img) = item.select(&img_selector).next() else {
+            continue;
+        };
+        // Lazy-loaded images have a data: placeholder in src until shown
+        let Some(thumbnail) = ["src", "data-src"]
+            .iter()
+            .filter_map(|attr| img.value().attr(attr))
+            .find(|src| src.starts_with("http"))
+            .map(str::to_string)
+        else {
+            continue;
+        };
+
+        // The item itself is often the link to the source page
+        let url = item
+            .value()
+            .attr("href")
+            .filter(|h| h.starts_with("http"))
+            .map(str::to_string)
+            .or_else(|| select_href(item))
+            .unwrap_or_else(|| thumbnail.clone());
+        let mut title = select_text(item, &title_selector);
+        if title.is_empty() {
+            title = sanitize_display(img.value().attr("alt").unwrap_or("Untitled image")).trim().to_string();
+        }
+
+        results.push(SearchResult {
+            title,
+            display_url: display_host(&url),
+            url,
+            thumbnail: Some(thumbnail),
+            ..Default::default()
+        });
+    }
+    results
+}
+
+// Videos with duration and channel
+fn parse_video_results(document: &Html) -> Vec<SearchResult> {
+    let snippet_selector = Selector::parse("div.snippet, .video-snippet").unwrap();
+    let title_selector = Selector::parse(".title, h2, h3").unwrap();
+    let duration_selector = Selector::parse(".duration, .video-duration").unwrap();
+    let channel_selector = Selector::parse(".author, .channel, .creator, .publisher").unwrap();
+    let age_selector = Selector::parse(".age, .attr, time").unwrap();
+    let img_selector = Selector::parse("img[src]").unwrap();
+
+    let mut results = Vec::new();
+    for snippet in document.select(&snippet_selector).take(20) {
+        let title = select_text(snippet, &title_selector);
+        let Some(url) = select_href(snippet) else {
+            continue;
+        };
+        if title.is_empty() || results.iter().any(|r: &SearchResult| r.url == url) {
+            continue;
+        }
+
+        let thumbnail = snippet
+            .select(&img_selector)
+            .filter_map(|e| e.value().attr("src"))
+            .find(|src| src.starts_with("http"))
+            .map(str::to_string);
+        results.push(SearchResult {
+            title,
+            display_url: display_host(&url),
+            source: select_text(snippet, &channel_selector),
+            age: select_text(snippet, &age_selector),
+            duration: select_text(snippet, &duration_selector),
+            thumbnail,
+            url,
+            ..Default::default()
+        });
+    }
+    results
+}
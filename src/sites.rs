@@ -0,0 +1,1427 @@
+// Site handlers: pages that read better built from a site's API than
+// extracted from its HTML. A page URL is swapped for the API's before
+// fetching, and the API response is turned into simple HTML that the usual
+// extractor renders, the way feeds are.
+
+use base64::Engine;
+use chrono::DateTime;
+use pulldown_cmark::{CowStr, Event, Tag};
+use scraper::{Html, Selector};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::error::Error;
+use tracing::warn;
+use url::Url;
+
+use crate::net::{CancelToken, Fetcher, HttpStatus};
+use crate::render::{
+    decode_body, escape_html, extract_content_with_images, markdown_parser, sanitize_display, PageLink, RenderedPage,
+};
+use crate::search::display_host;
+
+// A page built by a site handler
+pub(crate) struct SitePage {
+    pub(crate) html: String,
+    pub(crate) url: String, // The page it shows, which links resolve against
+    pub(crate) thread: Option<Thread>, // For comment threads that fold
+}
+
+// Where to fetch a page from instead, for sites with a handler
+pub(crate) fn site_api_url(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    if let Some(language) = host.strip_suffix(".wikipedia.org") {
+        return wikipedia_api_url(language, &parsed);
+    }
+    if is_stack_exchange(host) {
+        return stack_exchange_api_url(host, &parsed);
+    }
+    if host == "github.com" || host == "www.github.com" {
+        return github_api_url(&parsed);
+    }
+    if host == "news.ycombinator.com" {
+        return hacker_news_api_url(&parsed);
+    }
+    if matches!(host, "reddit.com" | "www.reddit.com" | "old.reddit.com" | "new.reddit.com" | "np.reddit.com") {
+        return reddit_api_url(&parsed);
+    }
+    if host == "lobste.rs" {
+        return lobsters_api_url(&parsed);
+    }
+    if host == "arxiv.org" || host == "www.arxiv.org" {
+        return arxiv_api_url(&parsed);
+    }
+    None
+}
+
+// The page for a response from an API navim knows, or None to render it as
+// usual. Handlers that need more than one response fetch the rest with
+// `fetcher`.
+pub(crate) fn site_html(url: &str, body: &str, fetcher: &dyn Fetcher) -> Option<SitePage> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    if host == "api.dictionaryapi.dev" {
+        return dictionary_html(body).map(|html| SitePage { html, url: url.to_string(), thread: None });
+    }
+    if host.ends_with(".wikipedia.org") {
+        return wikipedia_html(&parsed, body);
+    }
+    if host == "api.stackexchange.com" {
+        return stack_exchange_html(&parsed, body, fetcher);
+    }
+    if host == "api.github.com" {
+        return github_html(&parsed, body, fetcher);
+    }
+    if host == "hn.algolia.com" {
+        return hacker_news_html(&parsed, body);
+    }
+    if host == "www.reddit.com" {
+        return reddit_html(&parsed, body);
+    }
+    if host == "lobste.rs" {
+        return lobsters_html(&parsed, body);
+    }
+    if host == "export.arxiv.org" {
+        return arxiv_html(&parsed, body);
+    }
+    None
+}
+
+// Namespaces whose pages aren't articles; Special:Search is what !w opens
+const WIKIPEDIA_NAMESPACES: &[&str] = &[
+    "Special", "File", "Media", "Category", "Talk", "User", "User_talk", "Wikipedia", "Help", "Template", "Portal",
+    "Module", "Draft",
+];
+
+const WIKIPEDIA_API_PATH: &str = "/api/rest_v1/page/html/";
+
+// Articles come from the REST API's Parsoid HTML, which has the article's
+// sections and figures without the skin around them
+fn wikipedia_api_url(language: &str, url: &Url) -> Option<String> {
+    let language = language.strip_suffix(".m").unwrap_or(language);
+    let title = url.path().strip_prefix("/wiki/")?;
+    let namespace = title.split_once(':').map(|(ns, _)| ns);
+    // Old revisions and actions (?oldid=, ?action=edit) stay on the site
+    if title.is_empty() || url.query().is_some() || namespace.is_some_and(|ns| WIKIPEDIA_NAMESPACES.contains(&ns)) {
+        return None;
+    }
+    Some(format!("https://{}.wikipedia.org{}{}", language, WIKIPEDIA_API_PATH, title.replace('/', "%2F")))
+}
+
+// Sections that are only lists of sources and links
+const WIKIPEDIA_SKIPPED_SECTIONS: &[&str] =
+    &["References", "Notes", "Citations", "Sources", "Footnotes", "External links", "Further reading", "Bibliography"];
+
+// The article without edit links, reference markers, the reference lists
+// and navigation boxes
+fn wikipedia_html(url: &Url, body: &str) -> Option<SitePage> {
+    let title_path = url.path().strip_prefix(WIKIPEDIA_API_PATH)?;
+    let page_url = format!("https://{}/wiki/{}", url.host_str()?, title_path.replace("%2F", "/"));
+
+    let mut document = Html::parse_document(body);
+    let clutter = Selector::parse(
+        ".mw-editsection, sup.reference, sup.mw-ref, .mw-ref, .mw-references-wrap, ol.references, .reflist, .navbox, \
+         .navbox-styles, .metadata, .noprint, .mw-empty-elt, .ambox, .sistersitebox, .side-box, .shortdescription, \
+         style, link, script",
+    )
+    .unwrap();
+    let section_selector = Selector::parse("section").unwrap();
+    let heading_selector = Selector::parse("h2").unwrap();
+    let mut removed: Vec<_> = document.select(&clutter).map(|e| e.id()).collect();
+    for section in document.select(&section_selector) {
+        let heading = section.select(&heading_selector).next().map(|h| h.text().collect::<String>());
+        if heading.is_some_and(|h| WIKIPEDIA_SKIPPED_SECTIONS.contains(&h.trim())) {
+            removed.push(section.id());
+        }
+    }
+    for id in removed {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+    // Images link to their file page, which would leave an empty link
+    // behind; keep the image and drop the link
+    let file_link_selector = Selector::parse("a.mw-file-description").unwrap();
+    let file_links: Vec<_> = document
+        .select(&file_link_selector)
+        .map(|a| (a.id(), a.children().map(|c| c.id()).collect::<Vec<_>>()))
+        .collect();
+    for (link, children) in file_links {
+        if let Some(mut node) = document.tree.get_mut(link) {
+            for child in children {
+                node.insert_id_before(child);
+            }
+            node.detach();
+        }
+    }
+
+    let title_selector = Selector::parse("title").unwrap();
+    let body_selector = Selector::parse("body").unwrap();
+    let title = document
+        .select(&title_selector)
+        .next()
+        .map(|t| t.text().collect::<String>().trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| title_path.replace('_', " "));
+    // Parsoid's links are relative to its <base>, /wiki/, which is not where
+    // an article with a slash in its title lives
+    let content = document.select(&body_selector).next()?.inner_html().replace("href=\"./", "href=\"/wiki/");
+    let html = format!(
+        "<html><head><title>{0}</title></head><body><article><h1>{0}</h1>{1}</article></body></html>",
+        escape_html(&title),
+        content
+    );
+    Some(SitePage { html, url: page_url, thread: None })
+}
+
+// Another response a handler's page needs, parsed
+fn fetch_json<T: DeserializeOwned>(fetcher: &dyn Fetcher, url: &str) -> Result<T, Box<dyn Error>> {
+    let fetched = fetcher.fetch(url)?;
+    let text = decode_body(&fetched.body, fetched.content_type.as_deref());
+    Ok(serde_json::from_str(&text)?)
+}
+
+// Stack Exchange sites other than these are all under stackexchange.com
+const STACK_EXCHANGE_SITES: &[&str] =
+    &["stackoverflow.com", "superuser.com", "serverfault.com", "askubuntu.com", "mathoverflow.net", "stackapps.com"];
+
+fn is_stack_exchange(host: &str) -> bool {
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    STACK_EXCHANGE_SITES.contains(&host) || (host.ends_with(".stackexchange.com") && host != "api.stackexchange.com")
+}
+
+const STACK_EXCHANGE_API: &str = "https://api.stackexchange.com/2.3";
+
+// /questions/<id>/<slug> and /q/<id> pages come from the API, which takes the
+// site's domain as its site parameter
+fn stack_exchange_api_url(host: &str, url: &Url) -> Option<String> {
+    let mut segments = url.path_segments()?;
+    if !matches!(segments.next(), Some("questions" | "q")) {
+        return None;
+    }
+    let id: u64 = segments.next()?.parse().ok()?;
+    let site = host.strip_prefix("www.").unwrap_or(host);
+    Some(format!("{}/questions/{}?site={}&filter=withbody", STACK_EXCHANGE_API, id, site))
+}
+
+#[derive(Deserialize)]
+struct StackExchangeItems<T> {
+    #[serde(default = "Vec::new")]
+    items: Vec<T>,
+    #[serde(default)]
+    error_message: Option<String>, // Sent instead of items when the API refuses, e.g. when throttled
+}
+
+#[derive(Deserialize)]
+struct StackExchangeQuestion {
+    title: String, // HTML-escaped, like every text field
+    body: String,
+    score: i64,
+    link: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    owner: Option<StackExchangeUser>,
+    #[serde(default)]
+    answer_count: u64,
+    #[serde(default)]
+    view_count: u64,
+    creation_date: i64,
+}
+
+#[derive(Deserialize)]
+struct StackExchangeAnswer {
+    body: String,
+    score: i64,
+    #[serde(default)]
+    is_accepted: bool,
+    #[serde(default)]
+    owner: Option<StackExchangeUser>,
+    creation_date: i64,
+}
+
+#[derive(Deserialize)]
+struct StackExchangeUser {
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    reputation: Option<u64>,
+}
+
+// "by name (12345) on 2021-03-04", as HTML since names come escaped
+fn stack_exchange_byline(owner: Option<&StackExchangeUser>, created: i64) -> String {
+    let date = DateTime::from_timestamp(created, 0).map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default();
+    match owner.and_then(|o| o.display_name.as_deref().map(|name| (name, o.reputation))) {
+        Some((name, Some(reputation))) => format!("by {} ({}) on {}", name, reputation, date),
+        Some((name, None)) => format!("by {} on {}", name, date),
+        None => format!("on {}", date),
+    }
+}
+
+fn votes(score: i64) -> String {
+    if score.abs() == 1 { format!("{} vote", score) } else { format!("{} votes", score) }
+}
+
+// The question, then every answer with the highest score first, each under a
+// heading with its votes so the table of contents lists them
+fn stack_exchange_html(url: &Url, body: &str, fetcher: &dyn Fetcher) -> Option<SitePage> {
+    let id = url.path().strip_prefix("/2.3/questions/")?;
+    if id.contains('/') {
+        return None;
+    }
+    let site = url.query_pairs().find(|(k, _)| k == "site")?.1.into_owned();
+    let question = serde_json::from_str::<StackExchangeItems<StackExchangeQuestion>>(body).ok()?.items.into_iter().next()?;
+
+    let answers_url = format!(
+        "{}/questions/{}/answers?site={}&sort=votes&order=desc&pagesize=100&filter=withbody",
+        STACK_EXCHANGE_API, id, site
+    );
+    let answers = fetch_json::<StackExchangeItems<StackExchangeAnswer>>(fetcher, &answers_url)
+        .map_err(|e| e.to_string())
+        .and_then(|answers| answers.error_message.map_or(Ok(answers.items), Err));
+    let mut html = format!("<html><head><title>{0}</title></head><body><article><h1>{0}</h1>", question.title);
+    html.push_str(&format!(
+        "<p><em>{} · {} answers · {} views · asked {}</em></p>",
+        votes(question.score),
+        question.answer_count,
+        question.view_count,
+        stack_exchange_byline(question.owner.as_ref(), question.creation_date)
+    ));
+    if !question.tags.is_empty() {
+        let tags: Vec<String> = question.tags.iter().map(|t| format!("<code>{}</code>", escape_html(t))).collect();
+        html.push_str(&format!("<p>{}</p>", tags.join(" ")));
+    }
+    html.push_str(&question.body);
+
+    match answers {
+        Ok(mut answers) => {
+            answers.sort_by_key(|a| Reverse(a.score));
+            html.push_str(&format!("<h2>{} answers</h2>", answers.len()));
+            for (i, answer) in answers.iter().enumerate() {
+                let accepted = if answer.is_accepted { "✓ Accepted · " } else { "" };
+                html.push_str(&format!(
+                    "{}<h3>{}{}</h3><p><em>Answered {}</em></p>{}",
+                    if i > 0 { "<hr>" } else { "" },
+                    accepted,
+                    votes(answer.score),
+                    stack_exchange_byline(answer.owner.as_ref(), answer.creation_date),
+                    answer.body
+                ));
+            }
+        }
+        Err(e) => {
+            warn!(url = %answers_url, error = %e, "could not fetch the answers");
+            html.push_str(&format!("<p><em>The answers could not be loaded: {}</em></p>", escape_html(&e)));
+        }
+    }
+    html.push_str("</article></body></html>");
+    Some(SitePage { html, url: question.link, thread: None })
+}
+
+const GITHUB_API: &str = "https://api.github.com/repos";
+
+// First path segments on github.com that aren't users or organisations
+const GITHUB_RESERVED: &[&str] = &[
+    "about", "apps", "collections", "enterprise", "explore", "features", "login", "marketplace", "new",
+    "notifications", "organizations", "orgs", "pricing", "search", "settings", "sponsors", "topics", "trending",
+    "users",
+];
+
+// A repository's front page comes from the repos API, its /tree and /blob
+// pages from the contents API. Issues, pull requests and the rest stay on
+// the site.
+fn github_api_url(url: &Url) -> Option<String> {
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let (owner, repo) = match segments.as_slice() {
+        [owner, repo, ..] if !GITHUB_RESERVED.contains(owner) => (*owner, repo.strip_suffix(".git").unwrap_or(repo)),
+        _ => return None,
+    };
+    match &segments[2..] {
+        [] => Some(format!("{}/{}/{}", GITHUB_API, owner, repo)),
+        ["tree" | "blob", reference, path @ ..] => {
+            Some(format!("{}/{}/{}/contents/{}?ref={}", GITHUB_API, owner, repo, path.join("/"), reference))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+struct GitHubRepo {
+    full_name: String,
+    html_url: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    homepage: Option<String>,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    license: Option<GitHubLicense>,
+    #[serde(default)]
+    topics: Vec<String>,
+    stargazers_count: u64,
+    forks_count: u64,
+    #[serde(default)]
+    open_issues_count: u64,
+    default_branch: String,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    pushed_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitHubLicense {
+    name: String,
+    #[serde(default)]
+    spdx_id: Option<String>,
+}
+
+// A file or directory entry from the contents API; files fetched one at a
+// time come with their content
+#[derive(Deserialize)]
+struct GitHubContent {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    html_url: Option<String>,
+    #[serde(default)]
+    download_url: Option<String>,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    encoding: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GitHubContents {
+    Directory(Vec<GitHubContent>),
+    File(GitHubContent),
+}
+
+// Where a repository's pages and raw files are, at one branch or commit
+struct GitHubTree<'a> {
+    owner: &'a str,
+    repo: &'a str,
+    reference: &'a str,
+}
+
+impl GitHubTree<'_> {
+    fn repo_url(&self) -> String {
+        format!("https://github.com/{}/{}", self.owner, self.repo)
+    }
+
+    fn page_url(&self, kind: &str, path: &str) -> String {
+        format!("https://github.com/{}/{}/{}/{}/{}", self.owner, self.repo, kind, self.reference, path)
+    }
+
+    fn raw_url(&self, path: &str) -> String {
+        format!("https://raw.githubusercontent.com/{}/{}/{}/{}", self.owner, self.repo, self.reference, path)
+    }
+}
+
+fn github_html(url: &Url, body: &str, fetcher: &dyn Fetcher) -> Option<SitePage> {
+    let mut parts = url.path().strip_prefix("/repos/")?.splitn(4, '/');
+    let (owner, repo) = (parts.next()?, parts.next()?);
+    let reference = url.query_pairs().find(|(k, _)| k == "ref").map(|(_, v)| v.into_owned());
+    let tree = GitHubTree { owner, repo, reference: reference.as_deref().unwrap_or("HEAD") };
+    match (parts.next(), parts.next()) {
+        (None, _) => github_repo_html(&tree, body, fetcher),
+        (Some("contents"), path) => match serde_json::from_str(body).ok()? {
+            GitHubContents::Directory(entries) => Some(github_directory_html(&tree, path.unwrap_or(""), entries)),
+            GitHubContents::File(file) => Some(github_file_html(&tree, file)),
+        },
+        _ => None,
+    }
+}
+
+// The description, stars, forks and license, the README, then the files at
+// the top of the tree
+fn github_repo_html(tree: &GitHubTree, body: &str, fetcher: &dyn Fetcher) -> Option<SitePage> {
+    let repo: GitHubRepo = serde_json::from_str(body).ok()?;
+    let tree = GitHubTree { reference: &repo.default_branch, ..*tree };
+    let mut html = format!("<html><head><title>{0}</title></head><body><article><h1>{0}</h1>", escape_html(&repo.full_name));
+    if let Some(description) = repo.description.as_deref().filter(|d| !d.is_empty()) {
+        html.push_str(&format!("<p>{}</p>", escape_html(description)));
+    }
+    let mut facts = vec![format!("★ {} stars", repo.stargazers_count), format!("{} forks", repo.forks_count)];
+    if let Some(license) = &repo.license {
+        facts.push(match license.spdx_id.as_deref() {
+            Some(id) if id != "NOASSERTION" => id.to_string(),
+            _ => license.name.clone(),
+        });
+    }
+    facts.extend(repo.language.clone());
+    facts.push(format!("{} open issues", repo.open_issues_count));
+    if repo.archived {
+        facts.push("archived".to_string());
+    }
+    if let Some(pushed) = repo.pushed_at.as_deref().and_then(|p| p.get(..10)) {
+        facts.push(format!("last push {}", pushed));
+    }
+    html.push_str(&format!("<p><em>{}</em></p>", escape_html(&facts.join(" · "))));
+    if let Some(homepage) = repo.homepage.as_deref().filter(|h| h.starts_with("http")) {
+        html.push_str(&format!("<p><a href=\"{0}\">{0}</a></p>", escape_html(homepage)));
+    }
+    if !repo.topics.is_empty() {
+        let topics: Vec<String> = repo.topics.iter().map(|t| format!("<code>{}</code>", escape_html(t))).collect();
+        html.push_str(&format!("<p>{}</p>", topics.join(" ")));
+    }
+
+    let readme_url = format!("{}/{}/{}/readme?ref={}", GITHUB_API, tree.owner, tree.repo, tree.reference);
+    match fetch_json::<GitHubContent>(fetcher, &readme_url) {
+        Ok(readme) => html.push_str(&github_file_body(&tree, &readme)),
+        // The API's answer for a repository without one
+        Err(e) if e.downcast_ref::<HttpStatus>().is_some_and(|e| e.status == reqwest::StatusCode::NOT_FOUND) => {}
+        Err(e) => {
+            warn!(url = %readme_url, error = %e, "could not fetch the README");
+            html.push_str(&format!("<p><em>The README could not be loaded: {}</em></p>", escape_html(&e.to_string())));
+        }
+    }
+    let contents_url = format!("{}/{}/{}/contents/?ref={}", GITHUB_API, tree.owner, tree.repo, tree.reference);
+    match fetch_json::<Vec<GitHubContent>>(fetcher, &contents_url) {
+        Ok(entries) => {
+            html.push_str("<hr><h2>Files</h2>");
+            html.push_str(&github_listing(entries));
+        }
+        Err(e) => warn!(url = %contents_url, error = %e, "could not fetch the file list"),
+    }
+    html.push_str("</article></body></html>");
+    Some(SitePage { html, url: repo.html_url, thread: None })
+}
+
+// Directories first, each entry linking to its GitHub page, which opens
+// through this handler again
+fn github_listing(mut entries: Vec<GitHubContent>) -> String {
+    entries.sort_by_key(|e| (e.kind != "dir", e.name.to_lowercase()));
+    let mut html = String::from("<ul>");
+    for entry in &entries {
+        let name = if entry.kind == "dir" { format!("{}/", entry.name) } else { entry.name.clone() };
+        match &entry.html_url {
+            Some(href) => html.push_str(&format!("<li><a href=\"{}\">{}</a></li>", escape_html(href), escape_html(&name))),
+            None => html.push_str(&format!("<li>{}</li>", escape_html(&name))),
+        }
+    }
+    html.push_str("</ul>");
+    html
+}
+
+// "owner/repo / path" with links back up the tree
+fn github_breadcrumbs(tree: &GitHubTree, path: &str) -> String {
+    let mut html = format!("<p><a href=\"{}\">{}/{}</a>", tree.repo_url(), escape_html(tree.owner), escape_html(tree.repo));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    for i in 0..segments.len().saturating_sub(1) {
+        let href = tree.page_url("tree", &segments[..=i].join("/"));
+        html.push_str(&format!(" / <a href=\"{}\">{}</a>", escape_html(&href), escape_html(segments[i])));
+    }
+    html.push_str(&format!(" @ <code>{}</code></p>", escape_html(tree.reference)));
+    html
+}
+
+fn github_directory_html(tree: &GitHubTree, path: &str, entries: Vec<GitHubContent>) -> SitePage {
+    let path = path.trim_end_matches('/');
+    let title = if path.is_empty() { format!("{}/{}", tree.owner, tree.repo) } else { format!("{}/", path) };
+    let mut html = format!("<html><head><title>{0}</title></head><body><article><h1>{0}</h1>", escape_html(&title));
+    html.push_str(&github_breadcrumbs(tree, &format!("{}/", path)));
+    html.push_str(&github_listing(entries));
+    html.push_str("</article></body></html>");
+    SitePage { html, url: tree.page_url("tree", path).trim_end_matches('/').to_string(), thread: None }
+}
+
+fn github_file_html(tree: &GitHubTree, file: GitHubContent) -> SitePage {
+    let mut html = format!("<html><head><title>{0}</title></head><body><article><h1>{0}</h1>", escape_html(&file.path));
+    html.push_str(&github_breadcrumbs(tree, &file.path));
+    html.push_str(&github_file_body(tree, &file));
+    html.push_str("</article></body></html>");
+    let url = file.html_url.unwrap_or_else(|| tree.page_url("blob", &file.path));
+    SitePage { html, url, thread: None }
+}
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "ico"];
+
+// Markdown rendered, images shown, other text as a code block, and a
+// download link for the rest (binaries, and files over the API's 1 MB limit
+// that come without their content)
+fn github_file_body(tree: &GitHubTree, file: &GitHubContent) -> String {
+    let extension = file.name.rsplit_once('.').map(|(_, e)| e.to_lowercase()).unwrap_or_default();
+    let raw = file.download_url.clone().unwrap_or_else(|| tree.raw_url(&file.path));
+    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        return format!("<p><img src=\"{}\" alt=\"{}\"></p>", escape_html(&raw), escape_html(&file.name));
+    }
+    let text = file
+        .content
+        .as_deref()
+        .filter(|_| file.encoding.as_deref() == Some("base64"))
+        .and_then(|c| base64::engine::general_purpose::STANDARD.decode(c.replace(['\n', '\r'], "")).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    match text {
+        Some(text) if matches!(extension.as_str(), "md" | "markdown") => github_markdown(tree, &file.path, &text),
+        Some(text) => format!("<pre><code>{}</code></pre>", escape_html(&text)),
+        None => format!("<p><em>This file isn't shown here.</em> <a href=\"{}\">Download it</a></p>", escape_html(&raw)),
+    }
+}
+
+// Relative links in a repository's Markdown point into the repository: to
+// pages at github.com, and for images to the raw files
+fn github_markdown(tree: &GitHubTree, path: &str, markdown: &str) -> String {
+    let dir = path.rsplit_once('/').map(|(dir, _)| format!("{}/", dir)).unwrap_or_default();
+    let resolve = |dest: CowStr<'static>, image: bool| -> CowStr<'static> {
+        if dest.is_empty() || dest.starts_with('#') || dest.starts_with("//") || Url::parse(&dest).is_ok() {
+            return dest;
+        }
+        let target = match dest.strip_prefix('/') {
+            Some(root) => root.to_string(),
+            None => format!("{}{}", dir, dest),
+        };
+        // Joining onto the tree's root also resolves any ../ parts
+        let base = if image { tree.raw_url("") } else { tree.page_url("blob", "") };
+        Url::parse(&base).and_then(|b| b.join(&target)).map(|u| CowStr::from(u.to_string())).unwrap_or(dest)
+    };
+    let events = markdown_parser(markdown).map(|event| match event {
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+            Event::Start(Tag::Link { link_type, dest_url: resolve(dest_url.into_static(), false), title, id })
+        }
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            Event::Start(Tag::Image { link_type, dest_url: resolve(dest_url.into_static(), true), title, id })
+        }
+        other => other,
+    });
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events);
+    html
+}
+
+// A comment and its replies, from any site with comment threads
+#[derive(Clone)]
+struct Comment {
+    permalink: String,      // Also how the thread tells its comments apart
+    author: Option<String>, // None once deleted
+    score: Option<i64>,
+    created: i64,
+    html: String,
+    replies: Vec<Comment>,
+    more: Option<u64>, // Replies the site left out, when it did
+}
+
+impl Comment {
+    fn descendants(&self) -> usize {
+        comment_count(&self.replies)
+    }
+}
+
+fn comment_count(comments: &[Comment]) -> usize {
+    comments.iter().map(|c| 1 + c.descendants()).sum()
+}
+
+fn thread_date(created: i64) -> String {
+    DateTime::from_timestamp(created, 0).map(|d| d.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default()
+}
+
+// A story or post and its comments, any of which can be folded away with
+// their replies
+#[derive(Clone)]
+pub(crate) struct Thread {
+    title: String,
+    head: String, // HTML for what the comments are about
+    comments: Vec<Comment>,
+    url: String,
+    permalinks: HashSet<String>, // Every comment's
+    folded: HashSet<String>,
+    folds: Vec<(usize, String)>, // Header line of each comment shown, from the last render
+}
+
+impl Thread {
+    fn new(title: String, head: String, comments: Vec<Comment>, url: String) -> Self {
+        fn collect(comments: &[Comment], permalinks: &mut HashSet<String>) {
+            for comment in comments {
+                permalinks.insert(comment.permalink.clone());
+                collect(&comment.replies, permalinks);
+            }
+        }
+        let mut permalinks = HashSet::new();
+        collect(&comments, &mut permalinks);
+        Thread { title, head, comments, url, permalinks, folded: HashSet::new(), folds: Vec::new() }
+    }
+
+    fn page(self) -> SitePage {
+        SitePage { html: self.html(), url: self.url.clone(), thread: Some(self) }
+    }
+
+    // The head, then the comments as nested quotes
+    fn html(&self) -> String {
+        let mut html = format!(
+            "<html><head><title>{0}</title></head><body><article><h1>{0}</h1>{1}",
+            escape_html(&self.title),
+            self.head
+        );
+        for comment in &self.comments {
+            self.comment_html(comment, &mut html);
+        }
+        html.push_str("</article></body></html>");
+        html
+    }
+
+    fn comment_html(&self, comment: &Comment, html: &mut String) {
+        html.push_str(&format!(
+            "<blockquote><p><strong>{}</strong>",
+            escape_html(comment.author.as_deref().unwrap_or("[deleted]"))
+        ));
+        if let Some(score) = comment.score {
+            html.push_str(&format!(" · {} {}", score, if score.abs() == 1 { "point" } else { "points" }));
+        }
+        html.push_str(&format!(" · <a href=\"{}\">{}</a>", escape_html(&comment.permalink), thread_date(comment.created)));
+        if self.folded.contains(&comment.permalink) {
+            html.push_str(&format!(" · <em>[{} more]</em></p></blockquote>", 1 + comment.descendants()));
+            return;
+        }
+        html.push_str("</p>");
+        html.push_str(&comment.html);
+        for reply in &comment.replies {
+            self.comment_html(reply, html);
+        }
+        match comment.more {
+            Some(0) => html.push_str(&format!("<p><a href=\"{}\">Continue this thread</a></p>", escape_html(&comment.permalink))),
+            Some(more) => html.push_str(&format!("<p><a href=\"{}\">{} more replies</a></p>", escape_html(&comment.permalink), more)),
+            None => {}
+        }
+        html.push_str("</blockquote>");
+    }
+
+    pub(crate) fn render(&mut self) -> RenderedPage {
+        let mut page = extract_content_with_images(&self.html(), &self.url, false, &CancelToken::default());
+        page.text = sanitize_display(&page.text);
+        self.locate(&page.links);
+        page
+    }
+
+    // Find each comment's header by its permalink. A comment can link to
+    // another in the thread, but only after that one's header.
+    pub(crate) fn locate(&mut self, links: &[PageLink]) {
+        self.folds.clear();
+        for link in links {
+            if self.permalinks.contains(&link.url) && !self.folds.iter().any(|(_, seen)| *seen == link.url) {
+                self.folds.push((link.line, link.url.clone()));
+            }
+        }
+    }
+
+    // Fold or unfold the comment whose header is nearest above `line`,
+    // returning that header's line
+    pub(crate) fn toggle(&mut self, line: usize) -> Option<usize> {
+        let (start, permalink) = self.folds.iter().filter(|(start, _)| *start <= line).max_by_key(|(start, _)| *start)?;
+        if !self.folded.remove(permalink) {
+            self.folded.insert(permalink.clone());
+        }
+        Some(*start)
+    }
+
+    // Unfold everything, or when nothing is folded, fold every top-level
+    // comment to leave an outline of the thread
+    pub(crate) fn toggle_all(&mut self) {
+        if self.folded.is_empty() {
+            self.folded = self.comments.iter().map(|c| c.permalink.clone()).collect();
+        } else {
+            self.folded.clear();
+        }
+    }
+}
+
+// The front page, which `navim hn` opens
+pub(crate) const HACKER_NEWS_URL: &str = "https://news.ycombinator.com/";
+
+const HACKER_NEWS_API: &str = "https://hn.algolia.com/api/v1";
+
+// An item and its whole comment tree come from Algolia's API in one request,
+// the front page from its front_page tag. Later pages, /newest and the rest
+// stay on the site.
+fn hacker_news_api_url(url: &Url) -> Option<String> {
+    match (url.path(), url.query()) {
+        ("/item", Some(_)) => {
+            let id: u64 = url.query_pairs().find(|(k, _)| k == "id")?.1.parse().ok()?;
+            Some(format!("{}/items/{}", HACKER_NEWS_API, id))
+        }
+        ("/" | "/news", None) => Some(format!("{}/search?tags=front_page&hitsPerPage=30", HACKER_NEWS_API)),
+        _ => None,
+    }
+}
+
+fn hacker_news_item_url(id: u64) -> String {
+    format!("https://news.ycombinator.com/item?id={}", id)
+}
+
+fn hacker_news_html(url: &Url, body: &str) -> Option<SitePage> {
+    match url.path().strip_prefix("/api/v1/")? {
+        "search" => hacker_news_front_page(body),
+        path => {
+            path.strip_prefix("items/")?.parse::<u64>().ok()?;
+            Some(hacker_news_thread(serde_json::from_str(body).ok()?).page())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HackerNewsHits {
+    hits: Vec<HackerNewsHit>,
+}
+
+#[derive(Deserialize)]
+struct HackerNewsHit {
+    #[serde(rename = "objectID")]
+    id: String,
+    title: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    points: Option<i64>,
+    #[serde(default)]
+    num_comments: Option<u64>,
+    created_at_i: i64,
+}
+
+// The stories in ranking order, each linking to the story and to its comments
+fn hacker_news_front_page(body: &str) -> Option<SitePage> {
+    let stories: HackerNewsHits = serde_json::from_str(body).ok()?;
+    let mut html = String::from("<html><head><title>Hacker News</title></head><body><article><h1>Hacker News</h1><ol>");
+    for story in &stories.hits {
+        let comments = hacker_news_item_url(story.id.parse().unwrap_or_default());
+        let link = story.url.as_deref().filter(|u| !u.is_empty()).unwrap_or(&comments);
+        html.push_str(&format!("<li><a href=\"{}\">{}</a>", escape_html(link), escape_html(&story.title)));
+        if story.url.is_some() {
+            html.push_str(&format!(" ({})", escape_html(&display_host(link))));
+        }
+        html.push_str(&format!(
+            "<br><em>{} points by {} · {} · <a href=\"{}\">{} comments</a></em></li>",
+            story.points.unwrap_or(0),
+            escape_html(story.author.as_deref().unwrap_or("?")),
+            thread_date(story.created_at_i),
+            comments,
+            story.num_comments.unwrap_or(0)
+        ));
+    }
+    html.push_str("</ol></article></body></html>");
+    Some(SitePage { html, url: HACKER_NEWS_URL.to_string(), thread: None })
+}
+
+#[derive(Deserialize)]
+struct HackerNewsItem {
+    id: u64,
+    #[serde(default)]
+    author: Option<String>, // None once deleted
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    text: Option<String>, // HTML
+    #[serde(default)]
+    points: Option<i64>,
+    created_at_i: i64,
+    #[serde(default)]
+    children: Vec<HackerNewsItem>,
+}
+
+impl From<HackerNewsItem> for Comment {
+    fn from(item: HackerNewsItem) -> Self {
+        Comment {
+            permalink: hacker_news_item_url(item.id),
+            author: item.author,
+            score: None, // Comment scores aren't public
+            created: item.created_at_i,
+            html: item.text.unwrap_or_default(),
+            replies: item.children.into_iter().map(Comment::from).collect(),
+            more: None,
+        }
+    }
+}
+
+// The story with its score, author and link above the comments
+fn hacker_news_thread(story: HackerNewsItem) -> Thread {
+    let comments: Vec<Comment> = story.children.into_iter().map(Comment::from).collect();
+    let mut head = String::new();
+    if let Some(url) = story.url.as_deref().filter(|u| !u.is_empty()) {
+        head.push_str(&format!("<p><a href=\"{}\">{}</a></p>", escape_html(url), escape_html(&display_host(url))));
+    }
+    head.push_str(&format!(
+        "<p><em>{} points by {} · {}</em></p>",
+        story.points.unwrap_or(0),
+        escape_html(story.author.as_deref().unwrap_or("?")),
+        thread_date(story.created_at_i)
+    ));
+    head.push_str(story.text.as_deref().unwrap_or(""));
+    head.push_str(&format!("<h2>{} comments</h2>", comment_count(&comments)));
+    let title = story.title.unwrap_or_else(|| "Hacker News".to_string());
+    Thread::new(title, head, comments, hacker_news_item_url(story.id))
+}
+
+const REDDIT: &str = "https://www.reddit.com";
+
+// Posts (and comment permalinks) and subreddit listings come from the same
+// URL with .json on the end; raw_json keeps the HTML in them unescaped, and
+// a post asks for as many comments as Reddit sends at once
+fn reddit_api_url(url: &Url) -> Option<String> {
+    let path = url.path().trim_end_matches('/');
+    if path.ends_with(".json") {
+        return None;
+    }
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let api = |path: &str, query: &str| Some(format!("{}{}.json?raw_json=1{}", REDDIT, path, query));
+    match segments.as_slice() {
+        ["r", _, "comments", _, ..] | ["comments", _, ..] => api(path, "&limit=500"),
+        ["r", _] | ["r", _, "hot" | "new" | "top" | "rising" | "controversial"] => {
+            // The next page of a listing starts after its last post
+            let after = url.query_pairs().find(|(k, _)| k == "after").map(|(_, v)| format!("&after={}", v));
+            api(path, &after.unwrap_or_default())
+        }
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+struct RedditListing {
+    data: RedditListingData,
+}
+
+#[derive(Deserialize)]
+struct RedditListingData {
+    children: Vec<RedditThing>,
+    #[serde(default)]
+    after: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", content = "data")]
+enum RedditThing {
+    #[serde(rename = "t3")]
+    Post(RedditPost),
+    #[serde(rename = "t1")]
+    Comment(RedditComment),
+    #[serde(rename = "more")]
+    More(RedditMore),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct RedditPost {
+    title: String,
+    #[serde(default)]
+    author: Option<String>,
+    subreddit_name_prefixed: String,
+    score: i64,
+    num_comments: u64,
+    url: String,
+    permalink: String,
+    #[serde(default)]
+    is_self: bool,
+    #[serde(default)]
+    selftext_html: Option<String>,
+    #[serde(default)]
+    domain: Option<String>,
+    #[serde(default)]
+    link_flair_text: Option<String>,
+    created_utc: f64,
+}
+
+#[derive(Deserialize)]
+struct RedditComment {
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    body_html: Option<String>,
+    #[serde(default)]
+    score: Option<i64>,
+    #[serde(default)]
+    score_hidden: bool,
+    created_utc: f64,
+    permalink: String,
+    #[serde(default)]
+    replies: serde_json::Value, // A listing, or "" without replies
+}
+
+#[derive(Deserialize)]
+struct RedditMore {
+    #[serde(default)]
+    count: u64, // 0 when the rest is down a "continue this thread" link
+}
+
+fn reddit_html(url: &Url, body: &str) -> Option<SitePage> {
+    url.path().strip_suffix(".json")?;
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    if value.is_array() {
+        let [post, comments]: [RedditListing; 2] = serde_json::from_value(value).ok()?;
+        let RedditThing::Post(post) = post.data.children.into_iter().next()? else {
+            return None;
+        };
+        return Some(reddit_thread(post, comments).page());
+    }
+    reddit_listing(url, serde_json::from_value(value).ok()?)
+}
+
+// Reddit leaves replies out of deep or long threads, saying how many
+fn reddit_comments(listing: RedditListing) -> (Vec<Comment>, Option<u64>) {
+    let mut comments = Vec::new();
+    let mut more = None;
+    for thing in listing.data.children {
+        match thing {
+            RedditThing::Comment(comment) => {
+                let (replies, more) =
+                    serde_json::from_value(comment.replies).map(reddit_comments).unwrap_or_default();
+                comments.push(Comment {
+                    permalink: format!("{}{}", REDDIT, comment.permalink),
+                    author: comment.author.filter(|a| a != "[deleted]"),
+                    score: comment.score.filter(|_| !comment.score_hidden),
+                    created: comment.created_utc as i64,
+                    html: comment.body_html.unwrap_or_default(),
+                    replies,
+                    more,
+                });
+            }
+            RedditThing::More(stub) => more = Some(more.unwrap_or(0) + stub.count),
+            _ => {}
+        }
+    }
+    (comments, more)
+}
+
+fn reddit_byline(post: &RedditPost) -> String {
+    format!(
+        "{} · {} points · {} comments · posted by u/{} · {}",
+        post.subreddit_name_prefixed,
+        post.score,
+        post.num_comments,
+        post.author.as_deref().unwrap_or("[deleted]"),
+        thread_date(post.created_utc as i64)
+    )
+}
+
+// The post with its subreddit, score and link (or text) above the comments
+fn reddit_thread(post: RedditPost, comments: RedditListing) -> Thread {
+    let (comments, more) = reddit_comments(comments);
+    let permalink = format!("{}{}", REDDIT, post.permalink);
+    let mut head = String::new();
+    if !post.is_self {
+        let domain = post.domain.clone().unwrap_or_else(|| display_host(&post.url));
+        head.push_str(&format!("<p><a href=\"{}\">{}</a></p>", escape_html(&post.url), escape_html(&domain)));
+    }
+    head.push_str(&format!("<p><em>{}</em></p>", escape_html(&reddit_byline(&post))));
+    if let Some(flair) = post.link_flair_text.as_deref().filter(|f| !f.is_empty()) {
+        head.push_str(&format!("<p><code>{}</code></p>", escape_html(flair)));
+    }
+    head.push_str(post.selftext_html.as_deref().unwrap_or(""));
+    head.push_str(&format!("<h2>{} comments</h2>", post.num_comments));
+    if let Some(more) = more.filter(|&n| n > 0) {
+        head.push_str(&format!("<p><em>Reddit left out {} more comments</em></p>", more));
+    }
+    Thread::new(post.title, head, comments, permalink)
+}
+
+// Posts in the listing's order, each linking to the post and its comments
+fn reddit_listing(url: &Url, listing: RedditListing) -> Option<SitePage> {
+    let path = url.path().strip_suffix(".json")?;
+    let title = path.trim_start_matches('/').to_string();
+    let mut html = format!("<html><head><title>{0}</title></head><body><article><h1>{0}</h1><ul>", escape_html(&title));
+    for thing in listing.data.children {
+        let RedditThing::Post(post) = thing else { continue };
+        let comments = format!("{}{}", REDDIT, post.permalink);
+        let link = if post.is_self { &comments } else { &post.url };
+        html.push_str(&format!("<li><a href=\"{}\">{}</a>", escape_html(link), escape_html(&post.title)));
+        if !post.is_self {
+            html.push_str(&format!(" ({})", escape_html(post.domain.as_deref().unwrap_or(""))));
+        }
+        html.push_str(&format!(
+            "<br><em>{} points by u/{} · {} · <a href=\"{}\">{} comments</a></em></li>",
+            post.score,
+            escape_html(post.author.as_deref().unwrap_or("[deleted]")),
+            thread_date(post.created_utc as i64),
+            escape_html(&comments),
+            post.num_comments
+        ));
+    }
+    html.push_str("</ul>");
+    let page_url = format!("{}{}", REDDIT, path);
+    if let Some(after) = listing.data.after {
+        html.push_str(&format!("<p><a href=\"{}?after={}\">Next page</a></p>", page_url, escape_html(&after)));
+    }
+    html.push_str("</article></body></html>");
+    // A later page keeps its place, so reloading it doesn't go back to the first
+    let url = match url.query_pairs().find(|(k, _)| k == "after") {
+        Some((_, after)) => format!("{}?after={}", page_url, after),
+        None => page_url,
+    };
+    Some(SitePage { html, url, thread: None })
+}
+
+const LOBSTERS: &str = "https://lobste.rs";
+
+// Stories and the story lists (the front page, /newest, /active and tag
+// pages) have JSON at the same path with .json on the end
+fn lobsters_api_url(url: &Url) -> Option<String> {
+    let path = url.path().trim_end_matches('/');
+    if path.ends_with(".json") {
+        return None;
+    }
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        [] => Some(format!("{}/hottest.json", LOBSTERS)),
+        ["s", id, ..] => Some(format!("{}/s/{}.json", LOBSTERS, id)),
+        ["hottest" | "newest" | "active"] | ["t", _] => Some(format!("{}{}.json", LOBSTERS, path)),
+        _ => None,
+    }
+}
+
+// Older versions of the API give users as objects
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LobstersUser {
+    Name(String),
+    User { username: String },
+}
+
+impl LobstersUser {
+    fn name(&self) -> &str {
+        match self {
+            LobstersUser::Name(name) | LobstersUser::User { username: name } => name,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LobstersStory {
+    title: String,
+    #[serde(default)]
+    url: String, // Empty for text posts
+    score: i64,
+    #[serde(default)]
+    comment_count: u64,
+    #[serde(default)]
+    description: String,
+    comments_url: String,
+    submitter_user: LobstersUser,
+    #[serde(default)]
+    tags: Vec<String>,
+    created_at: String,
+    #[serde(default)]
+    comments: Vec<LobstersComment>,
+}
+
+// Comments come as a flat list in thread order, each naming its parent
+#[derive(Deserialize)]
+struct LobstersComment {
+    short_id: String,
+    #[serde(default)]
+    parent_comment: Option<String>,
+    url: String,
+    commenting_user: LobstersUser,
+    score: i64,
+    #[serde(default)]
+    is_deleted: bool,
+    comment: String,
+    created_at: String,
+}
+
+fn lobsters_time(date: &str) -> i64 {
+    DateTime::parse_from_rfc3339(date).map(|d| d.timestamp()).unwrap_or_default()
+}
+
+fn lobsters_html(url: &Url, body: &str) -> Option<SitePage> {
+    let path = url.path().strip_suffix(".json")?;
+    if path.starts_with("/s/") {
+        return Some(lobsters_thread(serde_json::from_str(body).ok()?).page());
+    }
+    lobsters_list(path, serde_json::from_str(body).ok()?)
+}
+
+fn lobsters_tags(story: &LobstersStory) -> String {
+    story.tags.iter().map(|t| format!("<code>{}</code>", escape_html(t))).collect::<Vec<_>>().join(" ")
+}
+
+// The stories in order, each with its tags and a link to its comments
+fn lobsters_list(path: &str, stories: Vec<LobstersStory>) -> Option<SitePage> {
+    let (title, page_url) = match path {
+        "/hottest" => ("Lobsters".to_string(), format!("{}/", LOBSTERS)),
+        _ => (format!("Lobsters: {}", path.trim_start_matches('/')), format!("{}{}", LOBSTERS, path)),
+    };
+    let mut html = format!("<html><head><title>{0}</title></head><body><article><h1>{0}</h1><ol>", escape_html(&title));
+    for story in &stories {
+        let link = if story.url.is_empty() { &story.comments_url } else { &story.url };
+        html.push_str(&format!("<li><a href=\"{}\">{}</a> {}", escape_html(link), escape_html(&story.title), lobsters_tags(story)));
+        if !story.url.is_empty() {
+            html.push_str(&format!(" ({})", escape_html(&display_host(&story.url))));
+        }
+        html.push_str(&format!(
+            "<br><em>{} points by {} · {} · <a href=\"{}\">{} comments</a></em></li>",
+            story.score,
+            escape_html(story.submitter_user.name()),
+            thread_date(lobsters_time(&story.created_at)),
+            escape_html(&story.comments_url),
+            story.comment_count
+        ));
+    }
+    html.push_str("</ol></article></body></html>");
+    Some(SitePage { html, url: page_url, thread: None })
+}
+
+fn lobsters_comments(comments: &[LobstersComment], parent: Option<&str>) -> Vec<Comment> {
+    comments
+        .iter()
+        .filter(|c| c.parent_comment.as_deref() == parent)
+        .map(|c| Comment {
+            permalink: c.url.clone(),
+            author: Some(c.commenting_user.name().to_string()).filter(|_| !c.is_deleted),
+            score: Some(c.score),
+            created: lobsters_time(&c.created_at),
+            html: c.comment.clone(),
+            replies: lobsters_comments(comments, Some(&c.short_id)),
+            more: None,
+        })
+        .collect()
+}
+
+// The story with its tags, score and link (or text) above the comments
+fn lobsters_thread(story: LobstersStory) -> Thread {
+    let comments = lobsters_comments(&story.comments, None);
+    let mut head = String::new();
+    if !story.url.is_empty() {
+        head.push_str(&format!("<p><a href=\"{}\">{}</a></p>", escape_html(&story.url), escape_html(&display_host(&story.url))));
+    }
+    head.push_str(&format!(
+        "<p><em>{} points by {} · {}</em></p>",
+        story.score,
+        escape_html(story.submitter_user.name()),
+        thread_date(lobsters_time(&story.created_at))
+    ));
+    if !story.tags.is_empty() {
+        head.push_str(&format!("<p>{}</p>", lobsters_tags(&story)));
+    }
+    head.push_str(&story.description);
+    head.push_str(&format!("<h2>{} comments</h2>", comment_count(&comments)));
+    Thread::new(story.title, head, comments, story.comments_url)
+}
+
+const ARXIV_API: &str = "https://export.arxiv.org/api/query";
+
+// Abstract pages come from the arXiv API's Atom entry for the paper. PDFs
+// (/pdf/...) already render as text.
+fn arxiv_api_url(url: &Url) -> Option<String> {
+    let id = url.path().strip_prefix("/abs/")?.trim_end_matches('/');
+    (!id.is_empty()).then(|| format!("{}?id_list={}", ARXIV_API, id))
+}
+
+// The title, authors, dates, categories and abstract. The PDF goes in
+// citation_pdf_url, like on the abstract page itself, for P to open.
+fn arxiv_html(url: &Url, body: &str) -> Option<SitePage> {
+    let id = url.query_pairs().find(|(k, _)| k == "id_list")?.1.into_owned();
+    let page_url = format!("https://arxiv.org/abs/{}", id);
+    let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    // A malformed id gets an entry titled "Error" that says what's wrong, and
+    // one that isn't a paper gets no entry at all
+    let entry = feed_rs::parser::parse(body.as_bytes()).ok()?.entries.into_iter().next();
+    let title = entry.as_ref().and_then(|e| e.title.as_ref()).map(|t| collapse(&t.content));
+    let entry = match entry {
+        Some(entry) if title.as_deref().is_some_and(|t| t != "Error") => entry,
+        entry => {
+            let message = entry
+                .and_then(|e| e.summary)
+                .map(|s| collapse(&s.content))
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "There is no paper with this id.".to_string());
+            let html = format!(
+                "<html><head><title>arXiv:{0}</title></head><body><article><h1>arXiv:{0}</h1><p>{1}</p></article></body></html>",
+                escape_html(&id),
+                escape_html(&message)
+            );
+            return Some(SitePage { html, url: page_url, thread: None });
+        }
+    };
+    let title = title.unwrap_or_default();
+    // arXiv's own elements, which feed-rs leaves out
+    let document = Html::parse_document(body);
+    let extension = |name: &str, attr: Option<&str>| {
+        let selector = Selector::parse(&format!("entry arxiv\\:{}", name)).ok()?;
+        let element = document.select(&selector).next()?;
+        let value = match attr {
+            Some(attr) => element.value().attr(attr)?.to_string(),
+            None => collapse(&element.text().collect::<String>()),
+        };
+        (!value.is_empty()).then_some(value)
+    };
+    let pdf = entry
+        .links
+        .iter()
+        .find(|l| l.title.as_deref() == Some("pdf"))
+        .map(|l| l.href.replacen("http://", "https://", 1))
+        .unwrap_or_else(|| format!("https://arxiv.org/pdf/{}", id));
+
+    let mut html = format!(
+        "<html><head><title>{0}</title><meta name=\"citation_pdf_url\" content=\"{1}\"></head>\
+         <body><article><h1>{0}</h1>",
+        escape_html(&title),
+        escape_html(&pdf)
+    );
+    let authors: Vec<&str> = entry.authors.iter().map(|a| a.name.as_str()).collect();
+    html.push_str(&format!("<p><strong>{}</strong></p>", escape_html(&authors.join(", "))));
+    let mut facts = vec![format!("arXiv:{}", id)];
+    if let Some(published) = entry.published {
+        facts.push(format!("submitted {}", published.format("%Y-%m-%d")));
+    }
+    if let Some(updated) = entry.updated.filter(|u| Some(*u) != entry.published) {
+        facts.push(format!("updated {}", updated.format("%Y-%m-%d")));
+    }
+    facts.extend(extension("comment", None));
+    html.push_str(&format!("<p><em>{}</em></p>", escape_html(&facts.join(" · "))));
+    // The primary category first
+    let primary = extension("primary_category", Some("term"));
+    let mut categories: Vec<&str> = entry.categories.iter().map(|c| c.term.as_str()).collect();
+    categories.sort_by_key(|c| Some(*c) != primary.as_deref());
+    if !categories.is_empty() {
+        let codes: Vec<String> = categories.iter().map(|c| format!("<code>{}</code>", escape_html(c))).collect();
+        html.push_str(&format!("<p>{}</p>", codes.join(" ")));
+    }
+    html.push_str("<h2>Abstract</h2>");
+    if let Some(summary) = &entry.summary {
+        html.push_str(&format!("<p>{}</p>", escape_html(&collapse(&summary.content))));
+    }
+    if let Some(journal) = extension("journal_ref", None) {
+        html.push_str(&format!("<p>Published in {}</p>", escape_html(&journal)));
+    }
+    html.push_str(&format!("<p><a href=\"{}\">PDF</a>", escape_html(&pdf)));
+    if let Some(doi) = extension("doi", None) {
+        html.push_str(&format!(" · <a href=\"https://doi.org/{0}\">doi:{0}</a>", escape_html(&doi)));
+    }
+    html.push_str("</p></article></body></html>");
+    Some(SitePage { html, url: page_url, thread: None })
+}
+
+// Free Dictionary API (dictionaryapi.dev), which !def and `navim define` use
+pub(crate) const DICTIONARY_URL: &str = "https://api.dictionaryapi.dev/api/v2/entries/en/{}";
+
+#[derive(Deserialize)]
+struct DictionaryEntry {
+    word: String,
+    #[serde(default)]
+    phonetic: Option<String>,
+    #[serde(default)]
+    phonetics: Vec<Phonetic>,
+    #[serde(default)]
+    origin: Option<String>,
+    #[serde(default)]
+    meanings: Vec<Meaning>,
+    #[serde(default, rename = "sourceUrls")]
+    source_urls: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Phonetic {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    audio: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Meaning {
+    #[serde(rename = "partOfSpeech")]
+    part_of_speech: String,
+    #[serde(default)]
+    definitions: Vec<Definition>,
+    #[serde(default)]
+    synonyms: Vec<String>,
+    #[serde(default)]
+    antonyms: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Definition {
+    definition: String,
+    #[serde(default)]
+    example: Option<String>,
+}
+
+// What the API answers, with a 404, for a word it doesn't know
+#[derive(Deserialize)]
+struct DictionaryMiss {
+    title: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    resolution: String,
+}
+
+// The word, how it's said, then numbered senses under each part of speech
+fn dictionary_html(body: &str) -> Option<String> {
+    let entries: Vec<DictionaryEntry> = match serde_json::from_str(body) {
+        Ok(entries) => entries,
+        Err(_) => {
+            let miss: DictionaryMiss = serde_json::from_str(body).ok()?;
+            let mut html = format!("<html><head><title>{0}</title></head><body><article><h1>{0}</h1>", escape_html(&miss.title));
+            for text in [&miss.message, &miss.resolution].into_iter().filter(|t| !t.is_empty()) {
+                html.push_str(&format!("<p>{}</p>", escape_html(text)));
+            }
+            html.push_str("</article></body></html>");
+            return Some(html);
+        }
+    };
+    let first = entries.first()?;
+    let mut html = format!("<html><head><title>{}</title></head><body><article>", escape_html(&first.word));
+    let mut sources: Vec<&str> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if i == 0 || entry.word != first.word {
+            html.push_str(&format!("<h1>{}</h1>", escape_html(&entry.word)));
+        } else {
+            html.push_str("<hr>");
+        }
+
+        let spoken = entry.phonetic.iter().chain(entry.phonetics.iter().filter_map(|p| p.text.as_ref()));
+        let mut pronunciations: Vec<&str> = Vec::new();
+        for text in spoken.map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            if !pronunciations.contains(&text) {
+                pronunciations.push(text);
+            }
+        }
+        let audio = entry.phonetics.iter().filter_map(|p| p.audio.as_deref()).find(|a| a.starts_with("http"));
+        if !pronunciations.is_empty() || audio.is_some() {
+            html.push_str(&format!("<p><em>{}</em>", escape_html(&pronunciations.join(" · "))));
+            if let Some(audio) = audio {
+                html.push_str(&format!(" <a href=\"{}\">▶ listen</a>", escape_html(audio)));
+            }
+            html.push_str("</p>");
+        }
+
+        for meaning in &entry.meanings {
+            html.push_str(&format!("<h2>{}</h2>", escape_html(&meaning.part_of_speech)));
+            for (n, sense) in meaning.definitions.iter().enumerate() {
+                html.push_str(&format!("<p>{}. {}", n + 1, escape_html(&sense.definition)));
+                if let Some(example) = sense.example.as_deref().filter(|e| !e.is_empty()) {
+                    html.push_str(&format!("<br><em>“{}”</em>", escape_html(example)));
+                }
+                html.push_str("</p>");
+            }
+            for (label, words) in [("Synonyms", &meaning.synonyms), ("Antonyms", &meaning.antonyms)] {
+                if !words.is_empty() {
+                    html.push_str(&format!("<p><strong>{}:</strong> {}</p>", label, escape_html(&words.join(", "))));
+                }
+            }
+        }
+
+        if let Some(origin) = entry.origin.as_deref().filter(|o| !o.is_empty()) {
+            html.push_str(&format!("<h2>Origin</h2><p>{}</p>", escape_html(origin)));
+        }
+        for source in &entry.source_urls {
+            if !sources.contains(&source.as_str()) {
+                sources.push(source);
+            }
+        }
+    }
+    for source in sources {
+        html.push_str(&format!("<p><small>Source: <a href=\"{0}\">{0}</a></small></p>", escape_html(source)));
+    }
+    html.push_str("</article></body></html>");
+    Some(html)
+}
@@ -0,0 +1,199 @@
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+use std::env;
+use std::sync::OnceLock;
+
+// What the terminal can show, worked out once from the environment. Every
+// frame is drawn as if the terminal could show anything, then degrade()
+// rewrites the finished buffer to fit, so drawing code needs no checks.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ColorSupport {
+    None,      // NO_COLOR or TERM=dumb: attributes only
+    Basic,     // The 16 ANSI colors
+    Indexed,   // The xterm 256-color palette
+    TrueColor, // 24-bit RGB
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Capabilities {
+    colors: ColorSupport,
+    unicode: bool,    // False on non-UTF-8 locales: box glyphs become ASCII
+    hyperlinks: bool, // OSC 8 links, see hyperlinks()
+}
+
+static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+
+fn capabilities() -> Capabilities {
+    *CAPABILITIES.get_or_init(detect)
+}
+
+// Whether links can be wrapped in OSC 8 escapes to make them clickable
+pub(crate) fn hyperlinks() -> bool {
+    capabilities().hyperlinks
+}
+
+fn detect() -> Capabilities {
+    let var = |name: &str| env::var(name).ok();
+    let term = var("TERM").unwrap_or_default();
+    let dumb = term == "dumb";
+
+    // https://no-color.org: present and not empty
+    let colors = if dumb || var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        ColorSupport::None
+    } else if matches!(var("COLORTERM").as_deref(), Some("truecolor" | "24bit")) || term.ends_with("-direct") {
+        ColorSupport::TrueColor
+    } else if term.contains("256color") {
+        ColorSupport::Indexed
+    } else if term.is_empty() && cfg!(windows) {
+        // Windows consoles don't set TERM but have had 24-bit color for years
+        ColorSupport::TrueColor
+    } else {
+        ColorSupport::Basic
+    };
+
+    // The first locale variable that is set decides, as in setlocale(3). With
+    // none set, assume the UTF-8 every current system defaults to.
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter().find_map(|name| var(name).filter(|v| !v.is_empty()));
+    let unicode = !dumb
+        && locale.is_none_or(|l| {
+            let l = l.to_lowercase();
+            l.contains("utf-8") || l.contains("utf8")
+        });
+
+    // Terminals without OSC 8 may print the escape as text, so only the ones
+    // known to handle it get links. FORCE_HYPERLINK=1 or 0 overrides.
+    let hyperlinks = match var("FORCE_HYPERLINK").as_deref() {
+        Some("0") => false,
+        Some(v) if !v.is_empty() => true,
+        _ => {
+            !dumb
+                && (matches!(var("TERM_PROGRAM").as_deref(), Some("iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper"))
+                    || ["kitty", "wezterm", "alacritty", "foot", "ghostty"].iter().any(|t| term.contains(t))
+                    || var("VTE_VERSION").and_then(|v| v.parse::<u32>().ok()).is_some_and(|v| v >= 5000)
+                    || ["KONSOLE_VERSION", "WT_SESSION", "DOMTERM"].iter().any(|name| var(name).is_some()))
+        }
+    };
+
+    Capabilities { colors, unicode, hyperlinks }
+}
+
+// Rewrite a drawn frame for what the terminal can show
+pub(crate) fn degrade(buffer: &mut Buffer) {
+    let caps = capabilities();
+    if caps.colors == ColorSupport::TrueColor && caps.unicode {
+        return;
+    }
+    for cell in buffer.content.iter_mut() {
+        if caps.colors == ColorSupport::None {
+            // Highlights drawn as a background (cursor, selection, badges)
+            // stay visible as reverse video
+            if cell.bg != Color::Reset {
+                cell.modifier.insert(Modifier::REVERSED);
+            }
+            cell.fg = Color::Reset;
+            cell.bg = Color::Reset;
+        } else {
+            cell.fg = fit_color(cell.fg, caps.colors);
+            cell.bg = fit_color(cell.bg, caps.colors);
+        }
+        if !caps.unicode {
+            if let Some(ascii) = cell.symbol().chars().next().and_then(ascii_glyph) {
+                cell.set_char(ascii);
+            }
+        }
+    }
+}
+
+// The nearest color the terminal has
+fn fit_color(color: Color, support: ColorSupport) -> Color {
+    match (color, support) {
+        (Color::Rgb(r, g, b), ColorSupport::Indexed) => Color::Indexed(nearest_indexed(r, g, b)),
+        (Color::Rgb(r, g, b), ColorSupport::Basic) => nearest_basic((r, g, b)),
+        (Color::Indexed(i), ColorSupport::Basic) if i >= 16 => nearest_basic(indexed_rgb(i)),
+        (Color::Indexed(i), ColorSupport::Basic) => BASIC[i as usize].0,
+        _ => color,
+    }
+}
+
+// The 16 ANSI colors in palette order, with xterm's default RGB values
+const BASIC: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).unsigned_abs();
+    d(a.0, b.0).pow(2) + d(a.1, b.1).pow(2) + d(a.2, b.2).pow(2)
+}
+
+fn nearest_basic(rgb: (u8, u8, u8)) -> Color {
+    BASIC.iter().min_by_key(|(_, value)| distance(rgb, *value)).map_or(Color::Reset, |(color, _)| *color)
+}
+
+// Levels of the 6x6x6 color cube that fills indexes 16-231
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => BASIC[i as usize].1,
+        16..=231 => {
+            let i = i - 16;
+            (CUBE[(i / 36) as usize], CUBE[(i / 6 % 6) as usize], CUBE[(i % 6) as usize])
+        }
+        // A 24-step gray ramp from 8 to 238
+        _ => {
+            let level = 8 + (i - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+// The closer of the nearest cube color and the nearest gray
+fn nearest_indexed(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| CUBE.iter().enumerate().min_by_key(|(_, c)| (**c as i32 - v as i32).abs()).map_or(0, |(i, _)| i as u8);
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance((r, g, b), indexed_rgb(gray)) < distance((r, g, b), indexed_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+// ASCII stand-ins for the box drawing and decorative glyphs navim draws
+fn ascii_glyph(c: char) -> Option<char> {
+    let ascii = match c {
+        '═' => '=',
+        '─' | '━' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '╴' | '╶' | '╸' | '╺' | '·' => '-',
+        '│' | '┃' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' | '║' | '╵' | '╷' | '╹' | '╻' => '|',
+        '\u{2500}'..='\u{257F}' => '+',
+        '\u{2580}'..='\u{259F}' => '#', // Block elements, e.g. the scrollbar thumb
+        '•' | '★' => '*',
+        '✓' => 'x',
+        '▸' | '▶' | '›' | '→' | '↳' | '↗' => '>',
+        '⌕' => '?',
+        '◂' | '←' => '<',
+        '↑' => '^',
+        '↓' => 'v',
+        '⟳' => '~',
+        '…' => '.',
+        _ => return None,
+    };
+    Some(ascii)
+}
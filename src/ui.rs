@@ -0,0 +1,7056 @@
+use base64::Engine;
+use chrono::{DateTime, Local};
+use crossterm::{
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode,
+        KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    buffer::{Buffer, Cell},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
+    Terminal,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use url::Url;
+
+use crate::answer::{instant_answer, InstantAnswer};
+use crate::config::{config, get_state_dir};
+use crate::history::{
+    add_to_history, bookmarks_to_html, clear_history, history_to_csv, load_bookmarks, load_feeds, load_frecent,
+    load_history, load_quickmarks, load_read_later, load_stats, parse_netscape_bookmarks, remove_from_history,
+    results_export, save_bookmarks, save_feeds, save_quickmarks, save_read_later, update_history_url, FeedStore, Frecent,
+    HistoryEntry, Quickmark, ReadLaterEntry, STATS_DAYS,
+};
+use crate::keymap::{self, Action, PendingKeys, Step};
+use crate::net::{
+    allow_plaintext, clear_cookies, expand_tilde, fetch_feed, fetch_image_bytes, fetch_link_preview, fetch_page,
+    fetch_page_with, local_file_path, max_attempts, private_enabled, read_cache, remember_credentials,
+    start_feed_refresh, tor_enabled, use_fixtures, wayback_url, AuthRequired, CacheOnly, CancelToken, FeedResults,
+    HTTPS_ONLY, HttpStatus, LinkPreview, PlaintextRefused, PRIVATE_MODE, TOR_MODE,
+};
+use crate::render::{
+    accessible, age_of, discover_feeds, extract_reader_content, image_to_ascii_fit, is_pdf, looks_like_html, parse_feed,
+    sanitize_display, truncate_string, wrap_with_offsets, FeedItem, JsonDoc, LoadStats, PageHeading, PageImage,
+    PageLink, PageMeta, Redirect, RenderedPage, StyledSpan, TextStyle, ACCESSIBLE,
+};
+use crate::search::{
+    cycle, display_host, fetch_suggestions, find_provider, normalize_url, resolve_query, search, search_engine,
+    search_page, Correction, Infobox, PROVIDERS, QueryTarget, REGIONS, SUGGEST_DEBOUNCE_MS, SafeSearch, SearchFilters,
+    SearchResult, Serp, TimeRange, Vertical,
+};
+use crate::sites::{Thread, HACKER_NEWS_URL};
+use crate::term::{degrade, hyperlinks};
+
+// Copy text to the system clipboard with an OSC 52 escape sequence.
+// The terminal does the copying, so this also works over SSH. Locally the
+// clipboard is set directly as well, for terminals that ignore OSC 52.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()?;
+
+    let over_ssh = env::var_os("SSH_CONNECTION").is_some() || env::var_os("SSH_TTY").is_some();
+    if !over_ssh {
+        set_local_clipboard(text);
+    }
+    Ok(())
+}
+
+// On X11 the copied text lives only as long as its owner, so the clipboard
+// handle is kept for the whole session
+static LOCAL_CLIPBOARD: OnceLock<Mutex<Option<arboard::Clipboard>>> = OnceLock::new();
+
+fn set_local_clipboard(text: &str) -> bool {
+    let clipboard = LOCAL_CLIPBOARD.get_or_init(|| Mutex::new(arboard::Clipboard::new().ok()));
+    let mut guard = clipboard.lock().unwrap_or_else(|e| e.into_inner());
+    guard.as_mut().is_some_and(|c| c.set_text(text.to_string()).is_ok())
+}
+
+// File name friendly version of a page title
+fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() { "page".to_string() } else { slug.to_string() }
+}
+
+// Write a file, creating its directory first
+fn write_file(path: &std::path::Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)
+}
+
+// Open a URL in the system's default browser
+fn open_external(url: &str) -> io::Result<()> {
+    // The system browser wouldn't go through the proxy
+    if tor_enabled() {
+        return Err(io::Error::other("disabled in --tor mode"));
+    }
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+#[derive(PartialEq, Clone)]
+pub(crate) enum View {
+    Home,
+    SearchResults,
+    WebPage,
+    ImageGallery,
+    ReadLater,
+    Feeds,
+    LoadError,
+}
+
+impl View {
+    fn name(&self) -> &'static str {
+        match self {
+            View::Home => "home",
+            View::SearchResults => "search results",
+            View::WebPage => "page",
+            View::ImageGallery => "image gallery",
+            View::ReadLater => "read later",
+            View::Feeds => "feeds",
+            View::LoadError => "load error",
+        }
+    }
+}
+
+// What a line typed into the prompt is used for
+#[derive(Clone, Copy, PartialEq)]
+enum PromptKind {
+    OpenUrl,
+    SaveMarkdown,
+    SaveHtml,
+    Username,
+    Password,
+    Subscribe,
+    AllowHttp,
+    Command,
+    ClearHistory,
+}
+
+impl PromptKind {
+    fn label(self) -> &'static str {
+        match self {
+            PromptKind::OpenUrl => "Open URL",
+            PromptKind::SaveMarkdown => "Save as Markdown",
+            PromptKind::SaveHtml => "Save the page as fetched",
+            PromptKind::Username => "Username",
+            PromptKind::Password => "Password",
+            PromptKind::Subscribe => "Subscribe to feed",
+            PromptKind::AllowHttp => "Load over plain HTTP? (y/N)",
+            PromptKind::Command => ":",
+            PromptKind::ClearHistory => "Clear all history? (y/N)",
+        }
+    }
+}
+
+// Single-line input shown at the bottom of any view
+struct Prompt {
+    kind: PromptKind,
+    input: String,
+    cursor: usize,        // In chars
+    note: Option<String>, // Shown after the label, e.g. which site asks for a login
+}
+
+impl Prompt {
+    fn new(kind: PromptKind, initial: &str) -> Self {
+        Prompt {
+            kind,
+            input: initial.to_string(),
+            cursor: initial.chars().count(),
+            note: None,
+        }
+    }
+
+    fn byte_index(&self) -> usize {
+        self.input.char_indices().nth(self.cursor).map_or(self.input.len(), |(i, _)| i)
+    }
+
+    fn insert(&mut self, c: char) {
+        let i = self.byte_index();
+        self.input.insert(i, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let i = self.byte_index();
+            self.input.remove(i);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.input.chars().count() {
+            let i = self.byte_index();
+            self.input.remove(i);
+        }
+    }
+}
+
+// Everything that can happen to the app, fed to App::update by run_app
+enum Msg {
+    Key(KeyCode, KeyModifiers),
+    Mouse(MouseEvent),
+    Paste(String), // Bracketed paste: the text arrives at once instead of as keys
+    PageLoaded(Box<Result<RenderedPage, LoadError>>), // The background fetch finished
+    Tick,                                             // Time passed; check other background work
+}
+
+// Popup drawn over the current view that takes keyboard focus
+#[derive(PartialEq, Clone)]
+enum Overlay {
+    TableOfContents,
+    Links,
+    Filters,
+    Redirects,
+    Headers,
+    Help,
+}
+
+struct App {
+    results: Vec<SearchResult>,
+    list_state: ListState,
+    search_offset: usize, // SERP page index of the last fetched page
+    results_exhausted: bool, // The last page fetched had nothing new
+    vertical: Vertical,
+    results_area: Rect, // Where the results list was last drawn, for mouse clicks
+    highlighted_since: Option<(String, Instant)>, // Highlighted result's URL and since when
+    expanded_result: Option<(usize, usize)>, // Result drawn with an excerpt, and its extra rows
+    marked: HashSet<String>, // URLs of results marked with Space for O, a and y
+    results_filter: String,  // Text typed after / to narrow the results
+    results_filter_editing: bool,
+    unfiltered_results: Option<Vec<SearchResult>>, // Every result, while the filter hides some
+    related: Vec<String>,            // Related searches the engine suggested
+    related_selected: Option<usize>, // Set while picking one with r
+    infobox: Option<Infobox>,        // The engine's knowledge panel for the query
+    correction: Option<Correction>,  // The engine's spelling suggestion, searched with c
+    answer: Option<InstantAnswer>,   // Worked out from the query itself, e.g. a sum
+    engines: Vec<String>, // Empty for the default engine
+    thumbnails: HashMap<String, String>, // Thumbnail URL -> ASCII art
+    thumb_rx: Option<mpsc::Receiver<(String, Option<String>)>>,
+    filters: SearchFilters,
+    filter_draft: SearchFilters, // Edited in the filter popup, applied on Enter
+    filter_row: usize,
+    view: View,
+    query: String,
+    should_quit: bool,
+    // Web page viewing
+    page_content: Vec<String>,
+    page_scroll: usize,
+    page_height: usize, // Rows of page text at the last draw
+    page_title: String,
+    page_url: String,
+    page_fetched: Option<DateTime<Local>>,
+    page_source: Vec<u8>, // Raw HTML of the current page
+    page_json: Option<JsonDoc>,
+    page_thread: Option<Thread>,
+    page_redirects: Vec<Redirect>,
+    page_meta: PageMeta,
+    page_stats: Option<LoadStats>,
+    page_headers: Vec<(String, String)>,
+    headers_state: ListState,
+    help_scroll: usize,
+    reader_mode: bool,
+    load_error: Option<FailedLoad>,
+    plaintext: Option<PlaintextRequest>,
+    return_view: Option<View>, // Where q on a page goes, when not the results
+    closed_pages: Vec<ClosedPage>,
+    reopening: Option<ClosedPage>, // Where to put the view once the reopened page is shown
+    tabs: VecDeque<Tab>,           // Background tabs, next first
+    tabs_opening: Option<(usize, usize)>, // Tabs being opened by O, and how many of them failed
+    read_later: Vec<ReadLaterEntry>,
+    read_later_state: ListState,
+    feeds: FeedStore,
+    quickmarks: BTreeMap<char, Quickmark>, // Pages kept on the number keys, by key
+    feed_items: Vec<(String, FeedItem)>, // Feed title and entry, newest first
+    feeds_state: ListState,
+    feeds_show_all: bool, // Include entries already read
+    feed_refresh: Option<mpsc::Receiver<FeedResults>>,
+    page_spans: Vec<StyledSpan>,
+    page_headings: Vec<PageHeading>,
+    page_anchors: HashMap<String, usize>,
+    // Active popup, if any
+    overlay: Option<Overlay>,
+    prompt: Option<Prompt>,
+    login: Option<LoginRequest>, // Page waiting for the username/password prompts
+    toc_state: ListState,
+    links_state: ListState,
+    links_filter: String,
+    links_filter_editing: bool,
+    // Notifications shown over the bottom-right corner, oldest first
+    toasts: Vec<Toast>,
+    // Links in the last frame, made clickable after it is drawn
+    hyperlinks: Vec<Hyperlink>,
+    // Link navigation
+    page_links: Vec<PageLink>,
+    selected_link: Option<usize>,
+    // Title and description of links held under the cursor, for the session
+    link_preview: bool, // :set linkpreview
+    link_previews: HashMap<String, Result<LinkPreview, String>>,
+    link_preview_due: Option<(String, Instant)>, // Link under the cursor and when to fetch it
+    link_preview_rx: Option<(String, mpsc::Receiver<Result<LinkPreview, String>>)>,
+    // Cursor position in web page (line, column)
+    cursor_line: usize,
+    cursor_col: usize,
+    desired_col: usize,  // Remembered column for vertical movement
+    visual_anchor: Option<usize>, // Line where visual (line selection) mode started
+    number: bool,                 // :set number, absolute line numbers
+    relative_number: bool,        // :set relativenumber; with number, the cursor line shows its own
+    text_width: usize,            // :set textwidth, widest the page text gets (0 for the full window)
+    marks: HashMap<char, usize>,  // Scroll position of each mark on this page
+    jumped_from: Option<usize>,   // Scroll position before the last jump, for ''
+    // Vim-style count prefix (e.g., 20j)
+    pending: PendingKeys,
+    // Image gallery
+    page_images: Vec<PageImage>,
+    gallery_state: ListState,
+    gallery_viewing: bool,                        // Showing the selected image full-width
+    gallery_bytes: Option<(usize, Option<Vec<u8>>)>, // Fetched bytes for an image index
+    gallery_art: Option<(usize, u16, u16, String)>,  // Cached ASCII art (index, width, height)
+    // Home screen
+    search_input: String,
+    cursor_position: usize, // Byte offset into search_input, always between graphemes
+    // Search suggestions for the home screen input
+    suggestions: Vec<String>,
+    suggestion_selected: Option<usize>,
+    suggest_due: Option<Instant>, // When to ask for suggestions (debounced typing)
+    suggest_rx: Option<mpsc::Receiver<(String, Vec<String>)>>,
+    // Recent and frequent picks under the empty search box, reloaded each
+    // time Home is shown; the selection is shared with the suggestions
+    frecent: Option<Vec<Frecent>>,
+    // Background page loading
+    loading: Option<PageLoad>,
+    prefetch: Option<PageLoad>,
+    prefetch_target: Option<(String, Instant)>, // Selected result URL and when to start fetching it
+    prefetched: Vec<(String, RenderedPage)>,    // Oldest first
+    prefetch_failed: Option<(String, String)>,  // The last result that could not be fetched, and why
+    preview: bool,                              // Preview the highlighted result beside the list
+}
+
+// A notification that disappears on its own
+struct Toast {
+    text: String,
+    error: bool,
+    shown: Instant,
+}
+
+impl Toast {
+    // Errors stay up longer, since there may be something to do about them
+    fn expired(&self) -> bool {
+        let secs = if self.error { TOAST_ERROR_SECS } else { TOAST_SECS };
+        self.shown.elapsed() >= Duration::from_secs(secs)
+    }
+}
+
+// A link's place on screen, in cells
+struct Hyperlink {
+    x: u16,
+    y: u16,
+    width: u16,
+    url: String,
+}
+
+const TOAST_SECS: u64 = 3;
+const TOAST_ERROR_SECS: u64 = 6;
+// Older toasts are dropped once this many are showing
+const TOAST_LIMIT: usize = 4;
+
+// How long a result must stay selected before it is prefetched
+const PREFETCH_DELAY_MS: u64 = 300;
+// Prefetched pages kept around for Enter
+const PREFETCH_CACHE_SIZE: usize = 4;
+
+// How long a link must stay selected before its preview is fetched
+const LINK_PREVIEW_DELAY_MS: u64 = 400;
+
+// Entries in the recent & frequent panel on the Home screen
+const FRECENT_LIMIT: usize = 8;
+
+// Pages of results a count before `n` fetches at most, since each one is a
+// search the UI waits for
+const MORE_RESULTS_LIMIT: usize = 5;
+
+// A page to load again once the user has typed a login
+struct LoginRequest {
+    url: String,
+    title: String,
+    host: String,
+    note: String,
+    username: String,
+}
+
+// A page held back by HTTPS-only mode, waiting for the user to allow plain HTTP
+struct PlaintextRequest {
+    url: String,
+    title: String,
+    host: String,
+}
+
+// Why a background load produced no page
+enum LoadError {
+    Failed(LoadFailure),
+    Auth(AuthRequired),
+    Plaintext(PlaintextRefused),
+}
+
+// Broad cause of a failed fetch, shown in the error view
+#[derive(Clone, Copy, PartialEq)]
+enum FailureKind {
+    Http,
+    Dns,
+    Tls,
+    Timeout,
+    Connection,
+    Other,
+}
+
+impl FailureKind {
+    fn label(self) -> &'static str {
+        match self {
+            FailureKind::Http => "HTTP error",
+            FailureKind::Dns => "DNS lookup failed",
+            FailureKind::Tls => "TLS error",
+            FailureKind::Timeout => "Timed out",
+            FailureKind::Connection => "Connection failed",
+            FailureKind::Other => "Error",
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            FailureKind::Http => "The server answered, but not with the page.",
+            FailureKind::Dns => "The host name could not be resolved. Check the address and your network.",
+            FailureKind::Tls => "The secure connection could not be set up; the certificate may be invalid or expired.",
+            FailureKind::Timeout => "The server took too long to answer.",
+            FailureKind::Connection => "The server could not be reached.",
+            FailureKind::Other => "The page could not be loaded.",
+        }
+    }
+}
+
+struct LoadFailure {
+    kind: FailureKind,
+    status: Option<reqwest::StatusCode>,
+    detail: String,
+    answered: Option<String>, // The URL that gave the status, after redirects
+}
+
+impl LoadFailure {
+    fn from_error(e: &(dyn Error + 'static)) -> Self {
+        if let Some(HttpStatus { status, response }) = e.downcast_ref::<HttpStatus>() {
+            return LoadFailure {
+                kind: FailureKind::Http,
+                status: Some(*status),
+                detail: e.to_string(),
+                answered: Some(response.url.clone()),
+            };
+        }
+        // reqwest keeps the useful part ("dns error: ...") a few sources down
+        let mut detail = e.to_string();
+        let mut source = e.source();
+        while let Some(cause) = source {
+            detail.push_str(": ");
+            detail.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        let lower = detail.to_lowercase();
+        let kind = if e.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout()) || lower.contains("timed out") {
+            FailureKind::Timeout
+        } else if ["dns error", "failed to lookup address", "name or service not known"].iter().any(|s| lower.contains(s)) {
+            FailureKind::Dns
+        } else if ["certificate", "tls", "handshake"].iter().any(|s| lower.contains(s)) {
+            FailureKind::Tls
+        } else if lower.contains("connect") {
+            FailureKind::Connection
+        } else {
+            FailureKind::Other
+        };
+        LoadFailure {
+            kind,
+            status: None,
+            detail,
+            answered: None,
+        }
+    }
+
+    fn message(reason: &str) -> Self {
+        LoadFailure {
+            kind: FailureKind::Other,
+            status: None,
+            detail: reason.to_string(),
+            answered: None,
+        }
+    }
+}
+
+// A page that failed to load, kept so it can be retried from the error view
+struct FailedLoad {
+    url: String,
+    title: String,
+    failure: LoadFailure,
+    cache_url: String, // Where the cached copy is kept: the URL after redirects
+    cached: Option<DateTime<Local>>, // When the cached copy was fetched, if there is one
+    previous: View,
+}
+
+// A page that was closed, kept so it can be reopened where it was left
+struct ClosedPage {
+    url: String,
+    title: String,
+    scroll: usize,
+    cursor_line: usize,
+}
+
+// Closed pages remembered for reopening, most recent last
+const CLOSED_PAGES_LIMIT: usize = 10;
+
+// A page kept in the background: opened from the results with O, or put
+// aside by switching to another tab
+struct Tab {
+    place: ClosedPage,
+    page: TabPage,
+}
+
+enum TabPage {
+    Loading(PageLoad),
+    Loaded(Box<RenderedPage>),
+    Failed, // Loaded again, to show why, when switched to
+}
+
+// Results O opens when the config doesn't say
+const OPEN_TABS_DEFAULT: usize = 5;
+
+// Whether pages are rendered with their images. Cleared by --no-images and
+// flipped with I; fetching images is most of a page load on slow links.
+static IMAGES_ENABLED: AtomicBool = AtomicBool::new(true);
+
+fn images_enabled() -> bool {
+    IMAGES_ENABLED.load(Ordering::Relaxed)
+}
+
+// Page fetch running on a background thread
+struct PageLoad {
+    url: String,
+    title: String,
+    started: Instant,
+    cancel: CancelToken,
+    attempt: Arc<AtomicU32>, // Which try the fetch is on, for the loading box
+    rx: mpsc::Receiver<Result<RenderedPage, LoadError>>,
+}
+
+impl PageLoad {
+    fn start(url: &str, title: &str) -> Self {
+        Self::spawn(url, title, false)
+    }
+
+    // Render whatever is in the disk cache, without touching the network
+    fn from_cache(url: &str, title: &str) -> Self {
+        Self::spawn(url, title, true)
+    }
+
+    fn spawn(url: &str, title: &str, cache_only: bool) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = CancelToken::default();
+        let target = url.to_string();
+        let token = cancel.clone();
+        let attempt = Arc::new(AtomicU32::new(1));
+        let progress = attempt.clone();
+        thread::spawn(move || {
+            let on_retry = |n| progress.store(n, Ordering::Relaxed);
+            let result = if cache_only {
+                fetch_page_with(&target, true, &token, &CacheOnly)
+            } else {
+                fetch_page(&target, images_enabled(), &token, &on_retry)
+            };
+            let result = result.map_err(|e| {
+                let e = match e.downcast::<AuthRequired>() {
+                    Ok(auth) => return LoadError::Auth(*auth),
+                    Err(e) => e,
+                };
+                match e.downcast::<PlaintextRefused>() {
+                    Ok(refused) => LoadError::Plaintext(*refused),
+                    Err(e) => LoadError::Failed(LoadFailure::from_error(&*e)),
+                }
+            });
+            // Nobody is listening any more if the load was canceled
+            if !token.is_canceled() {
+                tx.send(result).ok();
+            }
+        });
+        PageLoad {
+            url: url.to_string(),
+            title: title.to_string(),
+            started: Instant::now(),
+            cancel,
+            attempt,
+            rx,
+        }
+    }
+
+    // The fetch's outcome, once there is one
+    fn finished(&self) -> Option<Result<RenderedPage, LoadError>> {
+        match self.rx.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Some(Err(LoadError::Failed(LoadFailure::message("fetch thread stopped"))))
+            }
+        }
+    }
+}
+
+// Dropping a load (canceled, or a prefetch for a result we moved away from)
+// stops its thread from fetching anything more
+impl Drop for PageLoad {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+impl App {
+    fn new(results: Vec<SearchResult>, query: String) -> Self {
+        let mut list_state = ListState::default();
+        if !results.is_empty() {
+            list_state.select(Some(0));
+        }
+        App {
+            results,
+            list_state,
+            search_offset: 0,
+            results_exhausted: false,
+            vertical: Vertical::default(),
+            results_area: Rect::default(),
+            highlighted_since: None,
+            expanded_result: None,
+            marked: HashSet::new(),
+            results_filter: String::new(),
+            results_filter_editing: false,
+            unfiltered_results: None,
+            related: Vec::new(),
+            related_selected: None,
+            infobox: None,
+            correction: None,
+            answer: None,
+            engines: config().engines.clone(),
+            thumbnails: HashMap::new(),
+            thumb_rx: None,
+            filters: SearchFilters::default(),
+            filter_draft: SearchFilters::default(),
+            filter_row: 0,
+            view: View::SearchResults,
+            query,
+            should_quit: false,
+            page_content: Vec::new(),
+            page_scroll: 0,
+            page_height: 20,
+            page_title: String::new(),
+            page_url: String::new(),
+            page_fetched: None,
+            page_source: Vec::new(),
+            page_json: None,
+            page_thread: None,
+            page_redirects: Vec::new(),
+            page_meta: PageMeta::default(),
+            page_stats: None,
+            page_headers: Vec::new(),
+            headers_state: ListState::default(),
+            help_scroll: 0,
+            reader_mode: false,
+            load_error: None,
+            plaintext: None,
+            return_view: None,
+            closed_pages: Vec::new(),
+            reopening: None,
+            tabs: VecDeque::new(),
+            tabs_opening: None,
+            read_later: Vec::new(),
+            feeds: FeedStore::default(),
+            quickmarks: load_quickmarks(),
+            feed_items: Vec::new(),
+            feeds_state: ListState::default(),
+            feeds_show_all: false,
+            feed_refresh: None,
+            read_later_state: ListState::default(),
+            page_spans: Vec::new(),
+            page_headings: Vec::new(),
+            page_anchors: HashMap::new(),
+            overlay: None,
+            prompt: None,
+            login: None,
+            toc_state: ListState::default(),
+            links_state: ListState::default(),
+            links_filter: String::new(),
+            links_filter_editing: false,
+            toasts: Vec::new(),
+            hyperlinks: Vec::new(),
+            page_links: Vec::new(),
+            selected_link: None,
+            link_preview: true,
+            link_previews: HashMap::new(),
+            link_preview_due: None,
+            link_preview_rx: None,
+            cursor_line: 0,
+            visual_anchor: None,
+            // Screen readers would read a number out before every line
+            number: !accessible(),
+            relative_number: !accessible(),
+            text_width: config().text_width.unwrap_or(0),
+            marks: HashMap::new(),
+            jumped_from: None,
+            cursor_col: 0,
+            desired_col: 0,
+            pending: PendingKeys::default(),
+            page_images: Vec::new(),
+            gallery_state: ListState::default(),
+            gallery_viewing: false,
+            gallery_bytes: None,
+            gallery_art: None,
+            search_input: String::new(),
+            cursor_position: 0,
+            suggestions: Vec::new(),
+            suggestion_selected: None,
+            suggest_due: None,
+            suggest_rx: None,
+            frecent: None,
+            loading: None,
+            prefetch: None,
+            prefetch_target: None,
+            prefetched: Vec::new(),
+            prefetch_failed: None,
+            preview: config().preview,
+        }
+    }
+
+    fn new_home() -> Self {
+        App {
+            results: Vec::new(),
+            list_state: ListState::default(),
+            search_offset: 0,
+            results_exhausted: false,
+            vertical: Vertical::default(),
+            results_area: Rect::default(),
+            highlighted_since: None,
+            expanded_result: None,
+            marked: HashSet::new(),
+            results_filter: String::new(),
+            results_filter_editing: false,
+            unfiltered_results: None,
+            related: Vec::new(),
+            related_selected: None,
+            infobox: None,
+            correction: None,
+            answer: None,
+            engines: config().engines.clone(),
+            thumbnails: HashMap::new(),
+            thumb_rx: None,
+            filters: SearchFilters::default(),
+            filter_draft: SearchFilters::default(),
+            filter_row: 0,
+            view: View::Home,
+            query: String::new(),
+            should_quit: false,
+            page_content: Vec::new(),
+            page_scroll: 0,
+            page_height: 20,
+            page_title: String::new(),
+            page_url: String::new(),
+            page_fetched: None,
+            page_source: Vec::new(),
+            page_json: None,
+            page_thread: None,
+            page_redirects: Vec::new(),
+            page_meta: PageMeta::default(),
+            page_stats: None,
+            page_headers: Vec::new(),
+            headers_state: ListState::default(),
+            help_scroll: 0,
+            reader_mode: false,
+            load_error: None,
+            plaintext: None,
+            return_view: None,
+            closed_pages: Vec::new(),
+            reopening: None,
+            tabs: VecDeque::new(),
+            tabs_opening: None,
+            read_later: Vec::new(),
+            feeds: FeedStore::default(),
+            quickmarks: load_quickmarks(),
+            feed_items: Vec::new(),
+            feeds_state: ListState::default(),
+            feeds_show_all: false,
+            feed_refresh: None,
+            read_later_state: ListState::default(),
+            page_spans: Vec::new(),
+            page_headings: Vec::new(),
+            page_anchors: HashMap::new(),
+            overlay: None,
+            prompt: None,
+            login: None,
+            toc_state: ListState::default(),
+            links_state: ListState::default(),
+            links_filter: String::new(),
+            links_filter_editing: false,
+            toasts: Vec::new(),
+            hyperlinks: Vec::new(),
+            page_links: Vec::new(),
+            selected_link: None,
+            link_preview: true,
+            link_previews: HashMap::new(),
+            link_preview_due: None,
+            link_preview_rx: None,
+            cursor_line: 0,
+            visual_anchor: None,
+            // Screen readers would read a number out before every line
+            number: !accessible(),
+            relative_number: !accessible(),
+            text_width: config().text_width.unwrap_or(0),
+            marks: HashMap::new(),
+            jumped_from: None,
+            cursor_col: 0,
+            desired_col: 0,
+            pending: PendingKeys::default(),
+            page_images: Vec::new(),
+            gallery_state: ListState::default(),
+            gallery_viewing: false,
+            gallery_bytes: None,
+            gallery_art: None,
+            search_input: String::new(),
+            cursor_position: 0,
+            suggestions: Vec::new(),
+            suggestion_selected: None,
+            suggest_due: None,
+            suggest_rx: None,
+            frecent: None,
+            loading: None,
+            prefetch: None,
+            prefetch_target: None,
+            prefetched: Vec::new(),
+            prefetch_failed: None,
+            preview: config().preview,
+        }
+    }
+
+    fn notify(&mut self, text: impl Into<String>) {
+        self.push_toast(text.into(), false);
+    }
+
+    fn notify_error(&mut self, text: impl Into<String>) {
+        self.push_toast(text.into(), true);
+    }
+
+    fn push_toast(&mut self, text: String, error: bool) {
+        if error {
+            tracing::warn!("{}", text);
+        }
+        // The same message again just restarts its timer
+        self.toasts.retain(|toast| toast.text != text);
+        self.toasts.push(Toast { text, error, shown: Instant::now() });
+        if self.toasts.len() > TOAST_LIMIT {
+            self.toasts.remove(0);
+        }
+    }
+
+    // Record a visit, unless in private mode; say so if the history can't be written
+    fn add_history(&mut self, query: &str, title: &str, url: &str) {
+        if private_enabled() {
+            return;
+        }
+        if let Err(e) = add_to_history(query, title, url) {
+            self.notify_error(format!("Could not save history: {}", e));
+        }
+    }
+
+    fn save_feed_state(&mut self) {
+        if let Err(e) = save_feeds(&self.feeds) {
+            self.notify_error(format!("Could not save feeds: {}", e));
+        }
+    }
+
+    // Pasted text goes into whichever input has focus in one piece, so none
+    // of it is taken as a command. Inputs are one line, so line breaks and
+    // tabs become spaces.
+    fn paste(&mut self, text: &str) {
+        let text: String = text
+            .trim()
+            .chars()
+            .map(|c| if c.is_whitespace() { ' ' } else { c })
+            .filter(|c| !c.is_control())
+            .collect();
+        if let Some(prompt) = self.prompt.as_mut() {
+            for c in text.chars() {
+                prompt.insert(c);
+            }
+        } else if self.overlay == Some(Overlay::Links) && self.links_filter_editing {
+            self.links_filter.push_str(&text);
+            self.links_state.select(Some(0));
+        } else if self.view == View::SearchResults && self.results_filter_editing {
+            self.results_filter.push_str(&text);
+            self.apply_results_filter();
+        } else if self.view == View::Home && self.overlay.is_none() {
+            self.search_input.insert_str(self.cursor_position, &text);
+            self.cursor_position += text.len();
+            self.input_changed();
+        }
+    }
+
+    fn insert_char(&mut self, c: char) {
+        self.search_input.insert(self.cursor_position, c);
+        self.cursor_position += c.len_utf8();
+        self.input_changed();
+    }
+
+    // Backspace removes a whole grapheme, accents and all
+    fn delete_char(&mut self) {
+        if self.cursor_position > 0 {
+            let start = self.previous_grapheme();
+            self.search_input.replace_range(start..self.cursor_position, "");
+            self.cursor_position = start;
+            self.input_changed();
+        }
+    }
+
+    fn previous_grapheme(&self) -> usize {
+        self.search_input[..self.cursor_position]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_grapheme(&self) -> usize {
+        let rest = &self.search_input[self.cursor_position..];
+        self.cursor_position + rest.graphemes(true).next().map_or(0, str::len)
+    }
+
+    // Replace what's typed and put the cursor at its end, which as a byte
+    // offset is the text's length in bytes, not in characters
+    fn replace_search_input(&mut self, text: String) {
+        self.cursor_position = text.len();
+        self.search_input = text;
+    }
+
+    // Schedule a suggestion lookup once typing pauses
+    fn input_changed(&mut self) {
+        self.suggestion_selected = None;
+        if self.search_input.trim().is_empty() {
+            self.suggestions.clear();
+            self.suggest_due = None;
+        } else {
+            self.suggest_due = Some(Instant::now() + Duration::from_millis(SUGGEST_DEBOUNCE_MS));
+        }
+    }
+
+    // Start due lookups and pick up finished ones; called every tick
+    fn poll_suggestions(&mut self) {
+        if self.suggest_due.is_some_and(|due| Instant::now() >= due) {
+            self.suggest_due = None;
+            let query = self.search_input.clone();
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || {
+                if let Ok(suggestions) = fetch_suggestions(&query) {
+                    tx.send((query, suggestions)).ok();
+                }
+            });
+            // Replacing the receiver drops any answer to an older query
+            self.suggest_rx = Some(rx);
+        }
+
+        if let Some(rx) = &self.suggest_rx {
+            if let Ok((query, suggestions)) = rx.try_recv() {
+                if query == self.search_input {
+                    self.suggestions = suggestions;
+                    self.suggestion_selected = None;
+                }
+                self.suggest_rx = None;
+            }
+        }
+    }
+
+    // Entries in the list under the search box: suggestions while typing,
+    // recent and frequent picks while it is empty
+    fn dropdown_len(&self) -> usize {
+        if self.search_input.is_empty() {
+            self.frecent.as_ref().map_or(0, Vec::len)
+        } else {
+            self.suggestions.len()
+        }
+    }
+
+    // Load the recent & frequent picks on coming back to Home, so visits
+    // since the last time show up
+    fn poll_frecent(&mut self) {
+        if self.view != View::Home {
+            self.frecent = None;
+        } else if self.frecent.is_none() {
+            self.frecent = Some(load_frecent(FRECENT_LIMIT));
+            if self.search_input.is_empty() {
+                self.suggestion_selected = None;
+            }
+        }
+    }
+
+    fn select_next_suggestion(&mut self) {
+        let len = self.dropdown_len();
+        if len == 0 {
+            return;
+        }
+        self.suggestion_selected = match self.suggestion_selected {
+            Some(i) if i + 1 < len => Some(i + 1),
+            Some(_) => None,
+            None => Some(0),
+        };
+    }
+
+    fn select_previous_suggestion(&mut self) {
+        let len = self.dropdown_len();
+        if len == 0 {
+            return;
+        }
+        self.suggestion_selected = match self.suggestion_selected {
+            Some(0) => None,
+            Some(i) => Some(i - 1),
+            None => Some(len - 1),
+        };
+    }
+
+    fn clear_suggestions(&mut self) {
+        self.suggestions.clear();
+        self.suggestion_selected = None;
+        self.suggest_due = None;
+        self.suggest_rx = None;
+    }
+
+    fn move_cursor_left(&mut self) {
+        self.cursor_position = self.previous_grapheme();
+    }
+
+    fn move_cursor_right(&mut self) {
+        self.cursor_position = self.next_grapheme();
+    }
+
+    // Fetch the next SERP page and append results we don't have yet
+    fn load_more_results(&mut self) -> bool {
+        let QueryTarget::Search(query) = resolve_query(&self.query) else {
+            return false;
+        };
+        // The engine has nothing past the end, so don't ask it again
+        if self.results_exhausted {
+            self.notify("No more results");
+            return false;
+        }
+        let next_offset = self.search_offset + 1;
+        match search_page(&query, self.vertical, next_offset, &self.filters, &self.engines).map(|serp| serp.results) {
+            Ok(results) => {
+                let first_new = self.results.len();
+                let mut added = 0;
+                for result in results {
+                    let all = self.unfiltered_results.as_ref().unwrap_or(&self.results);
+                    if all.iter().any(|r| r.url == result.url) {
+                        continue;
+                    }
+                    added += 1;
+                    // While filtering, the full list gets everything and the
+                    // shown one what matches
+                    if let Some(all) = self.unfiltered_results.as_mut() {
+                        all.push(result.clone());
+                        if !results_filter_matches(&self.results_filter, &result) {
+                            continue;
+                        }
+                    }
+                    self.results.push(result);
+                }
+                self.search_offset = next_offset;
+                if self.results.len() > first_new {
+                    self.list_state.select(Some(first_new));
+                }
+                match (added, self.results.len() - first_new) {
+                    (0, _) => {
+                        self.results_exhausted = true;
+                        self.notify("No more results");
+                        false
+                    }
+                    (_, 0) => {
+                        self.notify(format!("Loaded {} more results, none matching the filter", added));
+                        false
+                    }
+                    (added, _) => {
+                        self.notify(format!("Loaded {} more results", added));
+                        true
+                    }
+                }
+            }
+            Err(e) => {
+                self.notify_error(format!("Could not load more results: {}", e));
+                false
+            }
+        }
+    }
+
+    // Narrow the results to those matching the filter text, keeping the full
+    // list to widen them again. The selection stays on its result if shown.
+    fn apply_results_filter(&mut self) {
+        let selected = self.list_state.selected().and_then(|i| self.results.get(i)).map(|r| r.url.clone());
+        let all = self.unfiltered_results.take().unwrap_or_else(|| std::mem::take(&mut self.results));
+        if self.results_filter.is_empty() {
+            self.results = all;
+        } else {
+            self.results = all.iter().filter(|r| results_filter_matches(&self.results_filter, r)).cloned().collect();
+            self.unfiltered_results = Some(all);
+        }
+        let i = selected.and_then(|url| self.results.iter().position(|r| r.url == url)).unwrap_or(0);
+        self.list_state = ListState::default();
+        if !self.results.is_empty() {
+            self.list_state.select(Some(i));
+        }
+    }
+
+    // New results replace the list, filter and all
+    fn forget_results_filter(&mut self) {
+        self.results_filter.clear();
+        self.results_filter_editing = false;
+        self.unfiltered_results = None;
+    }
+
+    // Keys while picking a related search from the row under the results
+    fn handle_related_key(&mut self, code: KeyCode) {
+        let Some(i) = self.related_selected else {
+            return;
+        };
+        match code {
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::BackTab => self.related_selected = Some(i.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => {
+                self.related_selected = Some((i + 1).min(self.related.len().saturating_sub(1)))
+            }
+            KeyCode::Enter => {
+                self.related_selected = None;
+                if let Some(query) = self.related.get(i).cloned() {
+                    self.search_for(query, false);
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q' | 'r' | 'k') | KeyCode::Up => self.related_selected = None,
+            _ => {}
+        }
+    }
+
+    fn handle_results_filter_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Esc => {
+                self.results_filter.clear();
+                self.results_filter_editing = false;
+                self.apply_results_filter();
+            }
+            KeyCode::Enter => self.results_filter_editing = false,
+            KeyCode::Down => self.next(),
+            KeyCode::Up => self.previous(),
+            KeyCode::Backspace => {
+                self.results_filter.pop();
+                self.apply_results_filter();
+            }
+            KeyCode::Char(c) => {
+                self.results_filter.push(c);
+                self.apply_results_filter();
+            }
+            _ => {}
+        }
+    }
+
+    // Run the current query again from the first page (e.g. after changing filters)
+    fn rerun_search(&mut self) {
+        let QueryTarget::Search(query) = resolve_query(&self.query) else {
+            return;
+        };
+        match search(&query, self.vertical, &self.filters, &self.engines) {
+            Ok(serp) => {
+                if serp.results.is_empty() {
+                    self.notify(format!("No {} results", self.vertical.label().to_lowercase()));
+                }
+                self.forget_results_filter();
+                self.results = serp.results;
+                self.related = serp.related;
+                self.related_selected = None;
+                self.infobox = serp.infobox;
+                self.correction = serp.correction;
+                self.search_offset = 0;
+                self.results_exhausted = false;
+                self.marked.clear();
+                self.list_state = ListState::default();
+                if !self.results.is_empty() {
+                    self.list_state.select(Some(0));
+                }
+            }
+            Err(e) => {
+                self.notify_error(format!("Search failed: {}", e));
+            }
+        }
+    }
+
+    // Move to the next or previous vertical and search it
+    fn switch_vertical(&mut self, forward: bool) {
+        self.vertical = cycle(&Vertical::ALL, self.vertical, forward);
+        self.rerun_search();
+    }
+
+    // Fetch the selected image result's thumbnail in the background
+    fn poll_thumbnail(&mut self) {
+        if let Some(rx) = &self.thumb_rx {
+            match rx.try_recv() {
+                Ok((url, art)) => {
+                    self.thumbnails
+                        .insert(url, art.unwrap_or_else(|| "(no preview)".to_string()));
+                    self.thumb_rx = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => self.thumb_rx = None,
+            }
+        }
+
+        if self.vertical != Vertical::Images {
+            return;
+        }
+        let Some(thumb) = self
+            .list_state
+            .selected()
+            .and_then(|i| self.results.get(i))
+            .and_then(|r| r.thumbnail.clone())
+        else {
+            return;
+        };
+        if self.thumbnails.contains_key(&thumb) {
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let art = fetch_image_bytes(&thumb)
+                .and_then(|bytes| image_to_ascii_fit(&bytes, THUMBNAIL_WIDTH as u32, THUMBNAIL_HEIGHT as u32));
+            tx.send((thumb, art)).ok();
+        });
+        self.thumb_rx = Some(rx);
+    }
+
+    fn open_filters(&mut self) {
+        self.filter_draft = self.filters.clone();
+        self.filter_row = 0;
+        self.overlay = Some(Overlay::Filters);
+    }
+
+    // Change the value on the highlighted filter row
+    fn cycle_filter(&mut self, forward: bool) {
+        let draft = &mut self.filter_draft;
+        match self.filter_row {
+            0 => draft.time = cycle(&TimeRange::ALL, draft.time, forward),
+            1 => {
+                // None (all regions) sits before the first code
+                let mut options: Vec<Option<&str>> = vec![None];
+                options.extend(REGIONS.iter().map(|r| Some(*r)));
+                let next = cycle(&options, draft.region.as_deref(), forward);
+                draft.region = next.map(str::to_string);
+            }
+            _ => draft.safesearch = cycle(&SafeSearch::ALL, draft.safesearch, forward),
+        }
+    }
+
+    fn next(&mut self) {
+        if self.results.is_empty() {
+            return;
+        }
+        // Moving past the last result fetches the next page
+        if self.list_state.selected() == Some(self.results.len() - 1) && self.load_more_results() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i >= self.results.len() - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    fn previous(&mut self) {
+        if self.results.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    self.results.len() - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    // Furthest the page scrolls: the last line at the bottom of the screen
+    fn max_scroll(&self) -> usize {
+        self.page_content.len().saturating_sub(self.page_height)
+    }
+
+    fn scroll_down(&mut self, amount: usize) {
+        self.page_scroll = self.page_scroll.saturating_add(amount).min(self.max_scroll());
+    }
+
+    fn scroll_up(&mut self, amount: usize) {
+        self.page_scroll = self.page_scroll.saturating_sub(amount);
+    }
+
+    // Ctrl-d/u and Ctrl-f/b: scroll and put the cursor on the top line
+    fn scroll_page(&mut self, amount: usize, down: bool) {
+        if down {
+            self.scroll_down(amount);
+        } else {
+            self.scroll_up(amount);
+        }
+        self.cursor_line = self.page_scroll;
+        self.cursor_col = 0;
+        self.update_selected_link();
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        // Popups and prompts are keyboard-only
+        if self.overlay.is_some() || self.prompt.is_some() {
+            return;
+        }
+        match (&self.view, mouse.kind) {
+            (View::SearchResults, MouseEventKind::ScrollDown) => self.next(),
+            (View::SearchResults, MouseEventKind::ScrollUp) => self.previous(),
+            (View::SearchResults, MouseEventKind::Down(MouseButton::Left)) => {
+                self.click_result(mouse.column, mouse.row);
+            }
+            (View::WebPage, MouseEventKind::ScrollDown) => self.scroll_page_by_wheel(true),
+            (View::WebPage, MouseEventKind::ScrollUp) => self.scroll_page_by_wheel(false),
+            (View::ImageGallery, MouseEventKind::ScrollDown) => self.gallery_next(),
+            (View::ImageGallery, MouseEventKind::ScrollUp) => self.gallery_previous(),
+            _ => {}
+        }
+    }
+
+    // A click selects a result; clicking the selected one opens it
+    fn click_result(&mut self, column: u16, row: u16) {
+        let area = self.results_area;
+        let inside = column > area.x && column < area.right().saturating_sub(1) && row > area.y && row < area.bottom().saturating_sub(1);
+        if !inside {
+            return;
+        }
+        // Rows down to the clicked result, counting an expanded one's excerpt
+        let mut rows = (row - area.y - 1) as usize;
+        let mut index = self.list_state.offset();
+        loop {
+            let extra = self.expanded_result.filter(|(i, _)| *i == index).map_or(0, |(_, extra)| extra);
+            let height = result_height(self.vertical) + extra;
+            if rows < height {
+                break;
+            }
+            rows -= height;
+            index += 1;
+        }
+        if index >= self.results.len() {
+            return;
+        }
+        if self.list_state.selected() == Some(index) {
+            self.open_selected();
+        } else {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    // Wheel scrolling moves the view a few lines, dragging the cursor along
+    // only when it would leave the screen
+    fn scroll_page_by_wheel(&mut self, down: bool) {
+        if down {
+            self.scroll_down(WHEEL_SCROLL_LINES);
+        } else {
+            self.scroll_up(WHEEL_SCROLL_LINES);
+        }
+        let last_visible = self.page_scroll + self.page_height.saturating_sub(1);
+        if self.cursor_line < self.page_scroll || self.cursor_line > last_visible {
+            self.cursor_line = self.cursor_line.clamp(self.page_scroll, last_visible);
+            self.cursor_line = self.cursor_line.min(self.page_content.len().saturating_sub(1));
+            self.cursor_col = 0;
+            self.update_selected_link();
+        }
+    }
+
+    // Copy a URL to the clipboard and say so
+    fn yank_url(&mut self, url: &str) {
+        if url.is_empty() {
+            return;
+        }
+        match copy_to_clipboard(url) {
+            Ok(()) => self.notify(format!("Copied {}", url)),
+            Err(e) => self.notify_error(format!("Copy failed: {}", e)),
+        }
+    }
+
+    // Mark or unmark the selected result, then move on to the next (without
+    // loading more at the end)
+    fn toggle_mark(&mut self) {
+        let Some(i) = self.list_state.selected() else {
+            return;
+        };
+        let Some(url) = self.results.get(i).map(|r| r.url.clone()) else {
+            return;
+        };
+        if !url.is_empty() && !self.marked.remove(&url) {
+            self.marked.insert(url);
+        }
+        if i + 1 < self.results.len() {
+            self.list_state.select(Some(i + 1));
+        }
+    }
+
+    // Results marked with Space, in list order
+    fn marked_results(&self) -> Vec<&SearchResult> {
+        self.results.iter().filter(|r| self.marked.contains(&r.url)).collect()
+    }
+
+    fn yank_marked(&mut self) {
+        let urls: Vec<&str> = self.marked_results().iter().map(|r| r.url.as_str()).collect();
+        let count = urls.len();
+        match copy_to_clipboard(&urls.join("\n")) {
+            Ok(()) => {
+                self.notify(format!("Copied {} URLs", count));
+                self.marked.clear();
+            }
+            Err(e) => self.notify_error(format!("Copy failed: {}", e)),
+        }
+    }
+
+    // Save every marked result to the read-later list, fetching them all at once
+    fn add_marked_to_read_later(&mut self) {
+        let marked: Vec<(String, String, Option<RenderedPage>)> = self
+            .marked_results()
+            .iter()
+            .map(|r| {
+                let prefetched = self.prefetched.iter().find(|(u, _)| *u == r.url).map(|(_, p)| p.clone());
+                (r.url.clone(), r.title.clone(), prefetched)
+            })
+            .collect();
+        let fetched: Vec<Result<RenderedPage, String>> = thread::scope(|scope| {
+            let fetches: Vec<_> = marked
+                .iter()
+                .map(|(url, _, prefetched)| {
+                    scope.spawn(move || match prefetched {
+                        Some(page) => Ok(page.clone()),
+                        None => fetch_page(url, false, &CancelToken::default(), &|_| {}).map_err(|e| e.to_string()),
+                    })
+                })
+                .collect();
+            fetches.into_iter().map(|fetch| fetch.join().unwrap_or_else(|_| Err("fetch thread stopped".into()))).collect()
+        });
+
+        let total = marked.len();
+        let mut entries = load_read_later();
+        let mut failed = Vec::new();
+        for ((url, title, _), page) in marked.into_iter().zip(fetched) {
+            match page {
+                Ok(page) => {
+                    entries.retain(|e| e.url != url);
+                    entries.insert(0, ReadLaterEntry { title, url, added: Local::now(), page });
+                }
+                Err(e) => failed.push(format!("{}: {}", truncate_string(&title, 30), e)),
+            }
+        }
+        let saved = total - failed.len();
+        if let Err(e) = save_read_later(&entries) {
+            self.notify_error(format!("Could not save for later: {}", e));
+            return;
+        }
+        self.marked.clear();
+        if failed.is_empty() {
+            self.notify(format!("Saved {} for later ({} in list)", saved, entries.len()));
+        } else {
+            self.notify_error(format!("Saved {} for later, {} failed - {}", saved, failed.len(), failed.join("; ")));
+        }
+    }
+
+    fn open_selected(&mut self) {
+        if let Some(i) = self.list_state.selected() {
+            if let Some(result) = self.results.get(i) {
+                if !result.url.is_empty() {
+                    let url = result.url.clone();
+                    let title = result.title.clone();
+                    // Save to history
+                    let query = self.query.clone();
+                    self.add_history(&query, &title, &url);
+                    self.load_page(&url, &title);
+                }
+            }
+        }
+    }
+
+    fn back_to_results(&mut self) {
+        if !self.page_url.is_empty() {
+            self.closed_pages.push(ClosedPage {
+                url: self.page_url.clone(),
+                title: self.page_title.clone(),
+                scroll: self.page_scroll,
+                cursor_line: self.cursor_line,
+            });
+            if self.closed_pages.len() > CLOSED_PAGES_LIMIT {
+                self.closed_pages.remove(0);
+            }
+        }
+        // Pages opened without a search (e.g. through a bang) go back home
+        self.view = match self.return_view.take() {
+            Some(view) => view,
+            None if self.results.is_empty() => View::Home,
+            None => View::SearchResults,
+        };
+        self.page_content.clear();
+        self.page_scroll = 0;
+        self.page_links.clear();
+        self.page_images.clear();
+        self.page_spans.clear();
+        self.page_headings.clear();
+        self.page_anchors.clear();
+        self.page_source.clear();
+        self.page_redirects.clear();
+        self.overlay = None;
+        self.selected_link = None;
+        self.cursor_line = 0;
+        self.cursor_col = 0;
+        self.desired_col = 0;
+        self.visual_anchor = None;
+    }
+
+    fn open_gallery(&mut self) {
+        self.gallery_state = ListState::default();
+        if !self.page_images.is_empty() {
+            self.gallery_state.select(Some(0));
+        }
+        self.gallery_viewing = false;
+        self.view = View::ImageGallery;
+    }
+
+    fn close_gallery(&mut self) {
+        self.gallery_viewing = false;
+        self.gallery_bytes = None;
+        self.gallery_art = None;
+        self.view = View::WebPage;
+    }
+
+    fn gallery_next(&mut self) {
+        if self.page_images.is_empty() {
+            return;
+        }
+        let i = match self.gallery_state.selected() {
+            Some(i) if i + 1 < self.page_images.len() => i + 1,
+            _ => 0,
+        };
+        self.gallery_state.select(Some(i));
+        if self.gallery_viewing {
+            self.load_gallery_image();
+        }
+    }
+
+    fn gallery_previous(&mut self) {
+        if self.page_images.is_empty() {
+            return;
+        }
+        let i = match self.gallery_state.selected() {
+            Some(0) | None => self.page_images.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.gallery_state.select(Some(i));
+        if self.gallery_viewing {
+            self.load_gallery_image();
+        }
+    }
+
+    // Fetch the selected image so it can be drawn full-width
+    fn load_gallery_image(&mut self) {
+        if let Some(i) = self.gallery_state.selected() {
+            if self.gallery_bytes.as_ref().map(|(idx, _)| *idx) != Some(i) {
+                let bytes = self.page_images.get(i).and_then(|img| fetch_image_bytes(&img.url));
+                if bytes.is_none() {
+                    self.notify_error("Image failed to load");
+                }
+                self.gallery_bytes = Some((i, bytes));
+            }
+            self.gallery_viewing = true;
+        }
+    }
+
+    // Move cursor right by one character
+    fn cursor_right(&mut self) {
+        if let Some(line) = self.page_content.get(self.cursor_line) {
+            if self.cursor_col < line.chars().count() {
+                self.cursor_col += 1;
+            } else if self.cursor_line < self.page_content.len() - 1 {
+                // Move to start of next line
+                self.cursor_line += 1;
+                self.cursor_col = 0;
+            }
+        }
+        // Horizontal movement updates desired_col
+        self.desired_col = self.cursor_col;
+        self.update_selected_link();
+        self.ensure_cursor_visible();
+    }
+
+    // Move cursor left by one character
+    fn cursor_left(&mut self) {
+        if self.cursor_col > 0 {
+            self.cursor_col -= 1;
+        } else if self.cursor_line > 0 {
+            // Move to end of previous line
+            self.cursor_line -= 1;
+            if let Some(line) = self.page_content.get(self.cursor_line) {
+                self.cursor_col = line.chars().count();
+            }
+        }
+        // Horizontal movement updates desired_col
+        self.desired_col = self.cursor_col;
+        self.update_selected_link();
+        self.ensure_cursor_visible();
+    }
+
+    // Move cursor down one line, skipping whitespace-only positions
+    // A motion repeated for a count, stopped early once the cursor stays put so
+    // a huge count costs no more than the distance moved
+    fn repeat_motion(&mut self, times: usize, motion: fn(&mut App)) {
+        for _ in 0..times {
+            let before = (self.cursor_line, self.cursor_col);
+            motion(self);
+            if (self.cursor_line, self.cursor_col) == before {
+                break;
+            }
+        }
+    }
+
+    fn link_laps(&self, times: usize) -> usize {
+        times.saturating_sub(1) % self.page_links.len().max(1) + 1
+    }
+
+    fn cursor_down(&mut self) {
+        if self.cursor_line < self.page_content.len().saturating_sub(1) {
+            self.cursor_line += 1;
+            // Try to reach desired_col, but clamp to line length
+            if let Some(line) = self.page_content.get(self.cursor_line) {
+                let line_len = line.chars().count();
+                self.cursor_col = self.desired_col.min(line_len);
+            }
+        }
+        self.update_selected_link();
+        self.ensure_cursor_visible();
+    }
+
+    // Move to the next blank line after a paragraph (vim's })
+    fn paragraph_forward(&mut self) {
+        let last = self.page_content.len().saturating_sub(1);
+        let mut line = self.cursor_line;
+        // Skip blank lines we're already on, then the paragraph itself
+        while line < last && self.page_content[line].trim().is_empty() {
+            line += 1;
+        }
+        while line < last && !self.page_content[line].trim().is_empty() {
+            line += 1;
+        }
+        self.move_to_line(line);
+    }
+
+    // Move to the blank line before a paragraph (vim's {)
+    fn paragraph_backward(&mut self) {
+        let mut line = self.cursor_line;
+        while line > 0 && self.page_content[line].trim().is_empty() {
+            line -= 1;
+        }
+        while line > 0 && !self.page_content[line].trim().is_empty() {
+            line -= 1;
+        }
+        self.move_to_line(line);
+    }
+
+    // Put the cursor at the start of a line, scrolling only as needed
+    fn move_to_line(&mut self, line: usize) {
+        self.cursor_line = line;
+        self.cursor_col = 0;
+        self.desired_col = 0;
+        self.update_selected_link();
+        self.ensure_cursor_visible();
+    }
+
+    fn start_visual(&mut self) {
+        self.visual_anchor = Some(self.cursor_line);
+    }
+
+    // First and last selected line in visual mode
+    fn visual_range(&self) -> Option<(usize, usize)> {
+        self.visual_anchor
+            .map(|anchor| (anchor.min(self.cursor_line), anchor.max(self.cursor_line)))
+    }
+
+    // Copy the selected lines to the clipboard and leave visual mode
+    fn yank_selection(&mut self) {
+        let Some((first, last)) = self.visual_range() else {
+            return;
+        };
+        self.visual_anchor = None;
+        // An empty page, or one that shrank under the selection, has no lines there
+        let Some(lines) = self.page_content.get(first..=last.min(self.page_content.len().saturating_sub(1))) else {
+            self.notify("Nothing to copy");
+            return;
+        };
+        let text = lines.iter().map(|line| line.trim_end()).collect::<Vec<_>>().join("\n");
+        let count = lines.len();
+        match copy_to_clipboard(text.trim_matches('\n')) {
+            Ok(()) => self.notify(format!("Copied {} line{}", count, if count == 1 { "" } else { "s" })),
+            Err(e) => self.notify_error(format!("Copy failed: {}", e)),
+        }
+    }
+
+    // Move cursor up one line, skipping whitespace-only positions
+    fn cursor_up(&mut self) {
+        if self.cursor_line > 0 {
+            self.cursor_line -= 1;
+            // Try to reach desired_col, but clamp to line length
+            if let Some(line) = self.page_content.get(self.cursor_line) {
+                let line_len = line.chars().count();
+                self.cursor_col = self.desired_col.min(line_len);
+            }
+        }
+        self.update_selected_link();
+        self.ensure_cursor_visible();
+    }
+
+    // Move cursor to next word
+    fn cursor_next_word(&mut self) {
+        if let Some(line) = self.page_content.get(self.cursor_line) {
+            let chars: Vec<char> = line.chars().collect();
+            let mut col = self.cursor_col;
+
+            // Skip current word (non-whitespace)
+            while col < chars.len() && !chars[col].is_whitespace() {
+                col += 1;
+            }
+            // Skip whitespace
+            while col < chars.len() && chars[col].is_whitespace() {
+                col += 1;
+            }
+
+            if col < chars.len() {
+                self.cursor_col = col;
+            } else if self.cursor_line < self.page_content.len() - 1 {
+                // Move to next line
+                self.cursor_line += 1;
+                self.cursor_col = 0;
+                // Skip leading whitespace on new line
+                if let Some(next_line) = self.page_content.get(self.cursor_line) {
+                    let next_chars: Vec<char> = next_line.chars().collect();
+                    let mut new_col = 0;
+                    while new_col < next_chars.len() && next_chars[new_col].is_whitespace() {
+                        new_col += 1;
+                    }
+                    self.cursor_col = new_col;
+                }
+            }
+        }
+        // Word movement updates desired_col
+        self.desired_col = self.cursor_col;
+        self.update_selected_link();
+        self.ensure_cursor_visible();
+    }
+
+    // Move cursor to previous word
+    fn cursor_prev_word(&mut self) {
+        if let Some(line) = self.page_content.get(self.cursor_line) {
+            let chars: Vec<char> = line.chars().collect();
+            let mut col = self.cursor_col;
+
+            // Move back one if at word start
+            col = col.saturating_sub(1);
+
+            // Skip whitespace backwards
+            while col > 0 && chars.get(col).is_some_and(|c| c.is_whitespace()) {
+                col -= 1;
+            }
+            // Skip word backwards
+            while col > 0 && chars.get(col - 1).is_some_and(|c| !c.is_whitespace()) {
+                col -= 1;
+            }
+
+            if col > 0 || self.cursor_col > 0 {
+                self.cursor_col = col;
+            } else if self.cursor_line > 0 {
+                // Move to previous line
+                self.cursor_line -= 1;
+                if let Some(prev_line) = self.page_content.get(self.cursor_line) {
+                    self.cursor_col = prev_line.chars().count();
+                }
+            }
+        }
+        // Word movement updates desired_col
+        self.desired_col = self.cursor_col;
+        self.update_selected_link();
+        self.ensure_cursor_visible();
+    }
+
+    // Jump to next link (capital L)
+    fn next_link(&mut self) {
+        if self.page_links.is_empty() {
+            return;
+        }
+
+        // Find next link after current cursor position
+        let next = self.page_links.iter().position(|link| {
+            link.line > self.cursor_line ||
+            (link.line == self.cursor_line && link.col_start > self.cursor_col)
+        });
+
+        if let Some(idx) = next {
+            self.jump_to_link(idx);
+        } else {
+            // Wrap to first link
+            self.jump_to_link(0);
+        }
+    }
+
+    // Jump to previous link (capital H or B)
+    fn prev_link(&mut self) {
+        if self.page_links.is_empty() {
+            return;
+        }
+
+        // Find previous link before current cursor position
+        let prev = self.page_links.iter().rposition(|link| {
+            link.line < self.cursor_line ||
+            (link.line == self.cursor_line && link.col_start < self.cursor_col)
+        });
+
+        if let Some(idx) = prev {
+            self.jump_to_link(idx);
+        } else {
+            // Wrap to last link
+            self.jump_to_link(self.page_links.len() - 1);
+        }
+    }
+
+    fn jump_to_link(&mut self, idx: usize) {
+        if let Some(link) = self.page_links.get(idx) {
+            self.cursor_line = link.line;
+            self.cursor_col = link.col_start;
+            self.desired_col = link.col_start;
+            self.selected_link = Some(idx);
+            self.ensure_cursor_visible();
+        }
+    }
+
+    // Update selected_link based on cursor position
+    fn update_selected_link(&mut self) {
+        self.selected_link = self.page_links.iter().position(|link| {
+            link.line == self.cursor_line &&
+            self.cursor_col >= link.col_start &&
+            self.cursor_col <= link.col_end
+        });
+    }
+
+    // The selected link's URL when it can be previewed: a web page other than
+    // this one
+    fn previewable_link(&self) -> Option<&str> {
+        if !self.link_preview || self.view != View::WebPage || self.overlay.is_some() {
+            return None;
+        }
+        let url = self.page_links.get(self.selected_link?)?.url.as_str();
+        let without_fragment = |u: &str| u.split('#').next().unwrap_or("").to_string();
+        let web = url.starts_with("http://") || url.starts_with("https://");
+        (web && without_fragment(url) != without_fragment(&self.page_url)).then_some(url)
+    }
+
+    // Fetch the preview of the link under the cursor once it has been
+    // selected for a moment; moving on drops a fetch still running
+    fn poll_link_preview(&mut self) {
+        if let Some((url, rx)) = &self.link_preview_rx {
+            match rx.try_recv() {
+                Ok(result) => {
+                    self.link_previews.insert(url.clone(), result);
+                    self.link_preview_rx = None;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => self.link_preview_rx = None,
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+        }
+
+        let Some(url) = self.previewable_link().map(str::to_string) else {
+            self.link_preview_due = None;
+            return;
+        };
+        if self.link_previews.contains_key(&url) || self.link_preview_rx.as_ref().is_some_and(|(u, _)| *u == url) {
+            return;
+        }
+        match &self.link_preview_due {
+            Some((due_url, due)) if *due_url == url => {
+                if Instant::now() >= *due {
+                    self.link_preview_due = None;
+                    let (tx, rx) = mpsc::channel();
+                    let target = url.clone();
+                    thread::spawn(move || {
+                        tx.send(fetch_link_preview(&target).map_err(|e| e.to_string())).ok();
+                    });
+                    self.link_preview_rx = Some((url, rx));
+                }
+            }
+            _ => self.link_preview_due = Some((url, Instant::now() + Duration::from_millis(LINK_PREVIEW_DELAY_MS))),
+        }
+    }
+
+    // Check if cursor is on a link and return its URL
+    fn get_link_at_cursor(&self) -> Option<(String, String)> {
+        if let Some(idx) = self.selected_link {
+            if let Some(link) = self.page_links.get(idx) {
+                return Some((link.url.clone(), link.text.clone()));
+            }
+        }
+        None
+    }
+
+    fn ensure_cursor_visible(&mut self) {
+        // Scroll to keep cursor visible; draw_web_page scrolls further if
+        // wrapped lines push it off the bottom
+        if self.cursor_line < self.page_scroll {
+            self.page_scroll = self.cursor_line;
+        } else if self.cursor_line >= self.page_scroll + self.page_height {
+            self.page_scroll = (self.cursor_line + 1).saturating_sub(self.page_height);
+        }
+    }
+
+    // Open the table of contents with the current section selected
+    fn toggle_toc(&mut self) {
+        if self.overlay == Some(Overlay::TableOfContents) {
+            self.overlay = None;
+            return;
+        }
+        let current = self.page_headings.iter().rposition(|h| h.line <= self.cursor_line);
+        self.toc_state = ListState::default();
+        if !self.page_headings.is_empty() {
+            self.toc_state.select(Some(current.unwrap_or(0)));
+        }
+        self.overlay = Some(Overlay::TableOfContents);
+    }
+
+    // = on a page: list the response headers it was served with
+    fn toggle_headers(&mut self) {
+        if self.overlay == Some(Overlay::Headers) {
+            self.overlay = None;
+            return;
+        }
+        self.headers_state = ListState::default();
+        if !self.page_headers.is_empty() {
+            self.headers_state.select(Some(0));
+        }
+        self.overlay = Some(Overlay::Headers);
+    }
+
+    fn open_links_panel(&mut self) {
+        self.links_filter.clear();
+        self.links_filter_editing = false;
+        self.links_state = ListState::default();
+        if !self.page_links.is_empty() {
+            self.links_state.select(Some(0));
+        }
+        self.overlay = Some(Overlay::Links);
+    }
+
+    // Indices of page links matching the panel's filter text
+    fn filtered_links(&self) -> Vec<usize> {
+        let filter = self.links_filter.to_lowercase();
+        self.page_links
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| {
+                filter.is_empty()
+                    || l.text.to_lowercase().contains(&filter)
+                    || l.url.to_lowercase().contains(&filter)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn selected_panel_link(&self) -> Option<PageLink> {
+        let filtered = self.filtered_links();
+        self.links_state
+            .selected()
+            .and_then(|i| filtered.get(i))
+            .and_then(|&i| self.page_links.get(i))
+            .cloned()
+    }
+
+    fn jump_to_line(&mut self, line: usize) {
+        self.cursor_line = line.min(self.page_content.len().saturating_sub(1));
+        self.cursor_col = 0;
+        self.desired_col = 0;
+        self.page_scroll = self.cursor_line;
+        self.update_selected_link();
+    }
+
+    fn handle_overlay_key(&mut self, code: KeyCode) {
+        match self.overlay {
+            Some(Overlay::Redirects) => match code {
+                KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('g') => {
+                    self.overlay = None;
+                }
+                KeyCode::Char('y') => {
+                    let url = self.page_url.clone();
+                    self.yank_url(&url);
+                }
+                _ => {}
+            },
+            Some(Overlay::Headers) => {
+                let last = self.page_headers.len().saturating_sub(1);
+                match code {
+                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('=') => {
+                        self.overlay = None;
+                    }
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        if let Some(i) = self.headers_state.selected() {
+                            self.headers_state.select(Some((i + 1).min(last)));
+                        }
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        if let Some(i) = self.headers_state.selected() {
+                            self.headers_state.select(Some(i.saturating_sub(1)));
+                        }
+                    }
+                    KeyCode::Char('g') if !self.page_headers.is_empty() => {
+                        self.headers_state.select(Some(0));
+                    }
+                    KeyCode::Char('G') if !self.page_headers.is_empty() => {
+                        self.headers_state.select(Some(last));
+                    }
+                    KeyCode::Char('y') => {
+                        if let Some((name, value)) = self.headers_state.selected().and_then(|i| self.page_headers.get(i)) {
+                            let (name, line) = (name.clone(), format!("{}: {}", name, value));
+                            match copy_to_clipboard(&line) {
+                                Ok(()) => self.notify(format!("Copied {}", name)),
+                                Err(e) => self.notify_error(format!("Copy failed: {}", e)),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some(Overlay::TableOfContents) => match code {
+                KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('t') => {
+                    self.overlay = None;
+                }
+                KeyCode::Char('j') | KeyCode::Down => {
+                    if let Some(i) = self.toc_state.selected() {
+                        self.toc_state.select(Some((i + 1).min(self.page_headings.len().saturating_sub(1))));
+                    }
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    if let Some(i) = self.toc_state.selected() {
+                        self.toc_state.select(Some(i.saturating_sub(1)));
+                    }
+                }
+                KeyCode::Char('g') if !self.page_headings.is_empty() => {
+                    self.toc_state.select(Some(0));
+                }
+                KeyCode::Char('G') if !self.page_headings.is_empty() => {
+                    self.toc_state.select(Some(self.page_headings.len() - 1));
+                }
+                KeyCode::Enter | KeyCode::Char('l') => {
+                    if let Some(line) = self.toc_state.selected().and_then(|i| self.page_headings.get(i)).map(|h| h.line) {
+                        self.jump_to_line(line);
+                    }
+                    self.overlay = None;
+                }
+                _ => {}
+            },
+            Some(Overlay::Links) if self.links_filter_editing => match code {
+                KeyCode::Esc | KeyCode::Enter => {
+                    self.links_filter_editing = false;
+                }
+                KeyCode::Backspace => {
+                    self.links_filter.pop();
+                    self.links_state.select(Some(0));
+                }
+                KeyCode::Char(c) => {
+                    self.links_filter.push(c);
+                    self.links_state.select(Some(0));
+                }
+                _ => {}
+            },
+            Some(Overlay::Links) => {
+                let count = self.filtered_links().len();
+                match code {
+                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('f') => {
+                        self.overlay = None;
+                    }
+                    KeyCode::Char('/') => {
+                        self.links_filter_editing = true;
+                    }
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        if let Some(i) = self.links_state.selected() {
+                            self.links_state.select(Some((i + 1).min(count.saturating_sub(1))));
+                        }
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        if let Some(i) = self.links_state.selected() {
+                            self.links_state.select(Some(i.saturating_sub(1)));
+                        }
+                    }
+                    KeyCode::Enter => {
+                        if let Some(link) = self.selected_panel_link() {
+                            self.overlay = None;
+                            self.follow_link(&link.url, &link.text);
+                        }
+                    }
+                    KeyCode::Char('y') => {
+                        if let Some(link) = self.selected_panel_link() {
+                            self.yank_url(&link.url);
+                        }
+                    }
+                    KeyCode::Char('o') => {
+                        if let Some(link) = self.selected_panel_link() {
+                            match open_external(&link.url) {
+                                Ok(()) => self.notify(format!("Opened {} in browser", link.url)),
+                                Err(e) => self.notify_error(format!("Could not open browser: {}", e)),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some(Overlay::Filters) => match code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    self.overlay = None;
+                }
+                KeyCode::Char('j') | KeyCode::Down | KeyCode::Tab => {
+                    self.filter_row = (self.filter_row + 1) % 3;
+                }
+                KeyCode::Char('k') | KeyCode::Up | KeyCode::BackTab => {
+                    self.filter_row = (self.filter_row + 2) % 3;
+                }
+                KeyCode::Char('l') | KeyCode::Right | KeyCode::Char(' ') => {
+                    self.cycle_filter(true);
+                }
+                KeyCode::Char('h') | KeyCode::Left => {
+                    self.cycle_filter(false);
+                }
+                KeyCode::Char('r') => {
+                    self.filter_draft = SearchFilters::default();
+                }
+                KeyCode::Enter => {
+                    self.overlay = None;
+                    if self.filter_draft != self.filters {
+                        self.filters = self.filter_draft.clone();
+                        self.rerun_search();
+                    }
+                }
+                _ => {}
+            },
+            Some(Overlay::Help) | None => {}
+        }
+    }
+
+    // The : command line; only :set for now
+    fn run_command(&mut self, line: &str) {
+        let line = line.strip_prefix(':').unwrap_or(line);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("set" | "se") => {
+                let options: Vec<&str> = words.collect();
+                if options.is_empty() {
+                    self.notify(self.option_summary());
+                }
+                for option in options {
+                    if let Err(e) = self.set_option(option) {
+                        self.notify_error(e);
+                        return;
+                    }
+                }
+            }
+            Some("history") => match words.next() {
+                Some("clear") => self.open_prompt(PromptKind::ClearHistory, ""),
+                _ => self.notify_error("Usage: :history clear"),
+            },
+            Some("export") => self.export_results(&words.collect::<Vec<_>>().join(" ")),
+            Some(other) => self.notify_error(format!("Not a command: {}", other)),
+            None => {}
+        }
+    }
+
+    // :export results.csv (or .json): the current results, for keeping a
+    // record of the search
+    fn export_results(&mut self, file: &str) {
+        if file.is_empty() {
+            self.notify_error("Usage: :export <file.csv|file.json>");
+            return;
+        }
+        if self.results.is_empty() {
+            self.notify("No results to export");
+            return;
+        }
+        let engine = search_engine(self.vertical, &self.engines);
+        let path = expand_tilde(file);
+        let written = results_export(file, &[(&self.query, &self.results)], engine)
+            .and_then(|text| write_file(&path, text.as_bytes()).map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => self.notify(format!("Exported {} results to {}", self.results.len(), path.display())),
+            Err(e) => self.notify_error(format!("Could not export to {}: {}", path.display(), e)),
+        }
+    }
+
+    // Like vim: name turns an option on, noname off, name! toggles it, and
+    // name=value sets a number
+    fn set_option(&mut self, option: &str) -> Result<(), String> {
+        if let Some((name, value)) = option.split_once('=') {
+            let value: usize = value.parse().map_err(|_| format!("Not a number: {}", option))?;
+            return match name {
+                "textwidth" | "tw" => {
+                    self.text_width = value;
+                    Ok(())
+                }
+                _ => Err(format!("Unknown option: {}", name)),
+            };
+        }
+        let (name, value) = if let Some(name) = option.strip_suffix('!') {
+            (name, None)
+        } else if let Some(name) = option.strip_prefix("no") {
+            (name, Some(false))
+        } else {
+            (option, Some(true))
+        };
+        if name == "private" {
+            self.set_private(value.unwrap_or(!private_enabled()));
+            return Ok(());
+        }
+        let flag = match name {
+            "number" | "nu" => &mut self.number,
+            "relativenumber" | "rnu" => &mut self.relative_number,
+            "linkpreview" | "lp" => &mut self.link_preview,
+            _ => return Err(format!("Unknown option: {}", option)),
+        };
+        *flag = value.unwrap_or(!*flag);
+        Ok(())
+    }
+
+    // What :set with nothing after it shows
+    fn option_summary(&self) -> String {
+        let flag = |on: bool, name: &str| if on { name.to_string() } else { format!("no{}", name) };
+        format!(
+            "{}  {}  textwidth={}  {}  {}",
+            flag(self.number, "number"),
+            flag(self.relative_number, "relativenumber"),
+            self.text_width,
+            flag(self.link_preview, "linkpreview"),
+            flag(private_enabled(), "private")
+        )
+    }
+
+    // Ctrl-p or :set private: stop (or start again) writing history, cache
+    // and cookies to disk
+    fn set_private(&mut self, on: bool) {
+        PRIVATE_MODE.store(on, Ordering::Relaxed);
+        self.notify(if on { "Private mode: nothing from now on is saved" } else { "Private mode off" });
+    }
+
+    fn set_mark(&mut self, c: char) {
+        if !c.is_ascii_lowercase() {
+            self.notify_error(format!("Marks are a-z, not {}", c));
+            return;
+        }
+        self.marks.insert(c, self.page_scroll);
+        self.notify(format!("Mark {} set", c));
+    }
+
+    // '{a-z} goes to a mark; '' goes back to where the last jump started
+    fn jump_to_mark(&mut self, c: char) {
+        let target = match c {
+            '\'' => self.jumped_from,
+            _ => self.marks.get(&c).copied(),
+        };
+        let Some(target) = target else {
+            self.notify_error(format!("Mark {} not set", c));
+            return;
+        };
+        self.jumped_from = Some(self.page_scroll);
+        self.page_scroll = target.min(self.page_content.len().saturating_sub(1));
+        self.cursor_line = self.page_scroll;
+        self.cursor_col = 0;
+        self.desired_col = 0;
+        self.update_selected_link();
+    }
+
+    // M{1-9} keeps the current page on a number key, replacing what was there
+    fn set_quickmark(&mut self, c: char) {
+        if !matches!(c, '1'..='9') {
+            self.notify_error(format!("Quickmarks are 1-9, not {}", c));
+            return;
+        }
+        let quickmark = Quickmark { title: self.page_title.clone(), url: self.page_url.clone() };
+        self.quickmarks.insert(c, quickmark);
+        match save_quickmarks(&self.quickmarks) {
+            Ok(()) => self.notify(format!("Quickmark {}: {}", c, self.page_title)),
+            Err(e) => self.notify_error(format!("Could not save quickmarks: {}", e)),
+        }
+    }
+
+    // 1-9 on Home or '1-'9 in the results opens the page kept on that key
+    fn open_quickmark(&mut self, c: char) {
+        let Some(quickmark) = self.quickmarks.get(&c).cloned() else {
+            self.notify_error(format!("Quickmark {} not set (M{} on a page sets it)", c, c));
+            return;
+        };
+        self.clear_suggestions();
+        self.add_history(&quickmark.url, &quickmark.title, &quickmark.url);
+        self.load_page(&quickmark.url, &quickmark.title);
+    }
+
+    // Follow a link from the current page; links to an anchor on the same page just scroll
+    fn follow_link(&mut self, url: &str, title: &str) {
+        if let (Ok(target), Ok(current)) = (Url::parse(url), Url::parse(&self.page_url)) {
+            if let Some(fragment) = target.fragment() {
+                let mut target_base = target.clone();
+                let mut current_base = current;
+                target_base.set_fragment(None);
+                current_base.set_fragment(None);
+                if target_base == current_base {
+                    self.jump_to_anchor(fragment);
+                    return;
+                }
+            }
+        }
+        let query = self.query.clone();
+        self.add_history(&query, title, url);
+        self.load_page(url, title);
+    }
+
+    // Scroll to the element with the given id, if the page has it
+    fn jump_to_anchor(&mut self, fragment: &str) -> bool {
+        let decoded = url::form_urlencoded::parse(format!("a={}", fragment).as_bytes())
+            .next()
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_else(|| fragment.to_string());
+        let line = self.page_anchors.get(fragment).or_else(|| self.page_anchors.get(&decoded)).copied();
+        if let Some(line) = line {
+            self.jump_to_line(line);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn open_prompt(&mut self, kind: PromptKind, initial: &str) {
+        self.prompt = Some(Prompt::new(kind, initial));
+    }
+
+    fn handle_prompt_key(&mut self, code: KeyCode) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Enter => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.submit_prompt(prompt);
+                }
+            }
+            KeyCode::Char(c) => prompt.insert(c),
+            KeyCode::Backspace => prompt.backspace(),
+            KeyCode::Delete => prompt.delete(),
+            KeyCode::Left => prompt.cursor = prompt.cursor.saturating_sub(1),
+            KeyCode::Right => prompt.cursor = (prompt.cursor + 1).min(prompt.input.chars().count()),
+            KeyCode::Home => prompt.cursor = 0,
+            KeyCode::End => prompt.cursor = prompt.input.chars().count(),
+            _ => {}
+        }
+    }
+
+    fn submit_prompt(&mut self, prompt: Prompt) {
+        let input = prompt.input.trim();
+        // Passwords are taken as typed, and may be empty
+        if prompt.kind == PromptKind::Password {
+            if let Some(login) = self.login.take() {
+                remember_credentials(&login.host, &login.username, &prompt.input);
+                self.load_page(&login.url, &login.title);
+            }
+            return;
+        }
+        if input.is_empty() {
+            return;
+        }
+        match prompt.kind {
+            PromptKind::Username => {
+                if let Some(login) = self.login.as_mut() {
+                    login.username = input.to_string();
+                    let note = login.note.clone();
+                    self.open_prompt(PromptKind::Password, "");
+                    if let Some(prompt) = self.prompt.as_mut() {
+                        prompt.note = Some(note);
+                    }
+                }
+            }
+            PromptKind::Password => {}
+            PromptKind::Subscribe => self.subscribe_to_feed(&normalize_url(input)),
+            PromptKind::Command => self.run_command(input),
+            PromptKind::ClearHistory => {
+                if input.eq_ignore_ascii_case("y") || input.eq_ignore_ascii_case("yes") {
+                    self.frecent = None;
+                    match clear_history() {
+                        Ok(()) => self.notify("History cleared"),
+                        Err(e) => self.notify_error(format!("Could not clear history: {}", e)),
+                    }
+                }
+            }
+            PromptKind::AllowHttp => {
+                if let Some(request) = self.plaintext.take() {
+                    if input.eq_ignore_ascii_case("y") || input.eq_ignore_ascii_case("yes") {
+                        allow_plaintext(&request.host);
+                        self.load_page(&request.url, &request.title);
+                    }
+                }
+            }
+            PromptKind::OpenUrl => {
+                let url = normalize_url(input);
+                self.add_history(&url, &url, &url);
+                self.load_page(&url, &url);
+            }
+            PromptKind::SaveMarkdown | PromptKind::SaveHtml => {
+                let path = expand_tilde(input);
+                let contents = if prompt.kind == PromptKind::SaveHtml {
+                    self.page_source.clone()
+                } else if self.view == View::SearchResults {
+                    self.results_markdown().into_bytes()
+                } else {
+                    self.page_markdown().into_bytes()
+                };
+                match write_file(&path, &contents) {
+                    Ok(()) => {
+                        if self.view == View::SearchResults {
+                            self.marked.clear();
+                        }
+                        self.notify(format!("Saved to {}", path.display()))
+                    }
+                    Err(e) => self.notify_error(format!("Could not save {}: {}", path.display(), e)),
+                }
+            }
+        }
+    }
+
+    // Ask where to save the page, suggesting the downloads folder
+    fn prompt_save(&mut self, kind: PromptKind) {
+        if kind == PromptKind::SaveHtml && self.page_source.is_empty() {
+            self.notify("No HTML to save");
+            return;
+        }
+        let extension = match kind {
+            PromptKind::SaveHtml if is_pdf(None, &self.page_source) => "pdf",
+            PromptKind::SaveHtml => "html",
+            _ => "md",
+        };
+        let dir = dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let name = if self.view == View::SearchResults { &self.query } else { &self.page_title };
+        let path = dir.join(format!("{}.{}", slugify(name), extension));
+        self.open_prompt(kind, &path.to_string_lossy());
+    }
+
+    // The marked results, or else all of them, as a Markdown list:
+    // - [title](url) — description
+    fn results_markdown(&self) -> String {
+        let results = if self.marked.is_empty() { self.results.iter().collect() } else { self.marked_results() };
+        let mut out = String::new();
+        for r in results.iter().filter(|r| !r.url.is_empty()) {
+            let title = if r.title.trim().is_empty() { &r.url } else { &r.title };
+            let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+            let title = title.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]");
+            let url = r.url.replace(' ', "%20").replace('(', "%28").replace(')', "%29");
+            out.push_str(&format!("- [{}]({})", title, url));
+            let description = r.description.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !description.is_empty() {
+                out.push_str(&format!(" — {}", description));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn yank_results_markdown(&mut self) {
+        let markdown = self.results_markdown();
+        let count = markdown.lines().count();
+        if count == 0 {
+            return;
+        }
+        match copy_to_clipboard(&markdown) {
+            Ok(()) => {
+                self.notify(format!("Copied {} results as Markdown", count));
+                self.marked.clear();
+            }
+            Err(e) => self.notify_error(format!("Copy failed: {}", e)),
+        }
+    }
+
+    // The rendered page as Markdown with YAML front matter. The renderer
+    // already writes **bold**, _italic_ and `code`; headings and links are
+    // converted back from their on-screen form here.
+    fn page_markdown(&self) -> String {
+        let fetched = self.page_fetched.unwrap_or_else(Local::now);
+        let mut out = format!(
+            "---\ntitle: {}\nurl: {}\nfetched: {}\n---\n\n",
+            serde_json::to_string(&self.page_title).unwrap_or_default(),
+            serde_json::to_string(&self.page_url).unwrap_or_default(),
+            fetched.to_rfc3339()
+        );
+
+        for (i, line) in self.page_content.iter().enumerate() {
+            if let Some(heading) = self.page_headings.iter().find(|h| h.line == i) {
+                out.push_str(&format!("{} {}\n", "#".repeat(heading.level as usize), heading.text));
+                continue;
+            }
+
+            // [text] -> [text](url), working right to left so columns stay valid
+            let mut chars: Vec<char> = line.chars().collect();
+            let mut links: Vec<&PageLink> = self.page_links.iter().filter(|l| l.line == i).collect();
+            links.sort_by_key(|l| std::cmp::Reverse(l.col_start));
+            for link in links {
+                let well_formed = chars.get(link.col_start) == Some(&'[')
+                    && link.col_end > 0
+                    && chars.get(link.col_end - 1) == Some(&']');
+                if well_formed {
+                    let target: Vec<char> = format!("({})", link.url).chars().collect();
+                    chars.splice(link.col_end..link.col_end, target);
+                }
+            }
+            out.push_str(chars.iter().collect::<String>().trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    // Start loading a page in the background; finish_loading shows it when done.
+    // A prefetched page is shown straight away.
+    fn load_page(&mut self, url: &str, title: &str) {
+        if let Some(i) = self.prefetched.iter().position(|(u, _)| u == url) {
+            let (_, page) = self.prefetched.remove(i);
+            self.show_page(url, title, page);
+            return;
+        }
+        let load = match self.prefetch.take() {
+            Some(mut prefetch) if prefetch.url == url => {
+                prefetch.title = title.to_string();
+                prefetch
+            }
+            _ => PageLoad::start(url, title),
+        };
+        self.loading = Some(load);
+    }
+
+    // Esc while loading: stop the fetch and stay on the current view
+    fn cancel_loading(&mut self) {
+        if let Some(load) = self.loading.take() {
+            self.notify(format!("Canceled loading {}", truncate_string(&load.url, 60)));
+        }
+        // Opened straight from the command line, so there is nothing behind it
+        if self.view == View::SearchResults && self.results.is_empty() {
+            self.view = View::Home;
+        }
+    }
+
+    // A 401 asks for a username, then a password, then loads the page again
+    fn ask_for_login(&mut self, url: &str, title: &str, auth: AuthRequired) {
+        let mut note = match &auth.realm {
+            Some(realm) => format!("{} ({})", auth.host, realm),
+            None => auth.host.clone(),
+        };
+        if auth.rejected {
+            note.push_str(" - login failed, try again");
+        }
+        self.open_prompt(PromptKind::Username, "");
+        if let Some(prompt) = self.prompt.as_mut() {
+            prompt.note = Some(note.clone());
+        }
+        self.login = Some(LoginRequest {
+            url: url.to_string(),
+            title: title.to_string(),
+            host: auth.host,
+            note,
+            username: String::new(),
+        });
+    }
+
+    fn finish_loading(&mut self, result: Result<RenderedPage, LoadError>) {
+        let Some(load) = self.loading.take() else {
+            return;
+        };
+        match result {
+            Ok(page) => self.show_page(&load.url, &load.title, page),
+            Err(LoadError::Auth(auth)) => self.ask_for_login(&load.url, &load.title, auth),
+            Err(LoadError::Plaintext(refused)) => {
+                self.open_prompt(PromptKind::AllowHttp, "");
+                if let Some(prompt) = self.prompt.as_mut() {
+                    prompt.note = Some(format!("{} does not support HTTPS", refused.host));
+                }
+                self.plaintext = Some(PlaintextRequest {
+                    url: load.url.clone(),
+                    title: load.title.clone(),
+                    host: refused.host,
+                });
+            }
+            Err(LoadError::Failed(failure)) => {
+                // A failed retry keeps the view the first failure came from
+                let previous = match self.load_error.take() {
+                    Some(failed) => failed.previous,
+                    None => self.view.clone(),
+                };
+                let cache_url = failure.answered.clone().unwrap_or_else(|| load.url.clone());
+                self.load_error = Some(FailedLoad {
+                    cached: read_cache(&cache_url).map(|(entry, _)| entry.fetched),
+                    cache_url,
+                    url: load.url.clone(),
+                    title: load.title.clone(),
+                    failure,
+                    previous,
+                });
+                self.view = View::LoadError;
+            }
+        }
+    }
+
+    // Error view keys: r retries, c shows the cached copy, w asks the Wayback Machine
+    fn retry_failed_load(&mut self, from: char) {
+        let Some(failed) = &self.load_error else {
+            return;
+        };
+        let (url, title, cache_url) = (failed.url.clone(), failed.title.clone(), failed.cache_url.clone());
+        match from {
+            'c' => match failed.cached {
+                Some(fetched) => {
+                    self.notify(format!("Cached copy from {}", fetched.format("%Y-%m-%d %H:%M")));
+                    self.loading = Some(PageLoad::from_cache(&cache_url, &title));
+                }
+                None => self.notify("No cached copy of this page"),
+            },
+            'w' if local_file_path(&url).is_none() => self.load_page(&wayback_url(&url), &title),
+            'w' => self.notify("Local files are not archived"),
+            _ => self.load_page(&url, &title),
+        }
+    }
+
+    fn close_load_error(&mut self) {
+        if let Some(failed) = self.load_error.take() {
+            self.view = failed.previous;
+        }
+        // Opened straight from the command line, so there is nothing behind it
+        if self.view == View::SearchResults && self.results.is_empty() {
+            self.view = View::Home;
+        }
+    }
+
+    // Note when the highlighted result changes, so it can be expanded once
+    // the selection settles
+    fn track_highlight(&mut self) {
+        let url = self.list_state.selected().and_then(|i| self.results.get(i)).map(|r| r.url.clone());
+        if url.as_ref() != self.highlighted_since.as_ref().map(|(u, _)| u) {
+            self.highlighted_since = url.map(|u| (u, Instant::now()));
+        }
+    }
+
+    // Fetch the highlighted result in the background once the selection
+    // settles, so opening it is instant. Moving on drops the old fetch.
+    fn poll_prefetch(&mut self) {
+        if let Some(prefetch) = &self.prefetch {
+            match prefetch.rx.try_recv() {
+                Ok(Ok(page)) => {
+                    let url = self.prefetch.take().unwrap().url.clone();
+                    self.prefetched.retain(|(u, _)| *u != url);
+                    self.prefetched.push((url, page));
+                    if self.prefetched.len() > PREFETCH_CACHE_SIZE {
+                        self.prefetched.remove(0);
+                    }
+                }
+                Ok(Err(e)) => {
+                    let url = self.prefetch.take().unwrap().url.clone();
+                    let why = match e {
+                        LoadError::Failed(failure) => format!("{}: {}", failure.kind.label(), failure.detail),
+                        LoadError::Auth(_) => "The site asks for a login".to_string(),
+                        LoadError::Plaintext(_) => "Not loaded over plain HTTP in HTTPS-only mode".to_string(),
+                    };
+                    self.prefetch_failed = Some((url, why));
+                }
+                Err(mpsc::TryRecvError::Disconnected) => self.prefetch = None,
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+        }
+
+        if self.view != View::SearchResults || self.loading.is_some() {
+            return;
+        }
+        let Some(url) = self
+            .list_state
+            .selected()
+            .and_then(|i| self.results.get(i))
+            .map(|r| r.url.clone())
+            .filter(|u| !u.is_empty())
+        else {
+            return;
+        };
+        // A failed fetch isn't retried until the selection moves away and back
+        let done = self.prefetched.iter().any(|(u, _)| *u == url)
+            || self.prefetch.as_ref().is_some_and(|p| p.url == url)
+            || self.prefetch_failed.as_ref().is_some_and(|(u, _)| *u == url);
+        if done {
+            return;
+        }
+
+        match &self.prefetch_target {
+            Some((target, due)) if *target == url => {
+                if Instant::now() >= *due {
+                    self.prefetch_target = None;
+                    self.prefetch = Some(PageLoad::start(&url, ""));
+                }
+            }
+            _ => {
+                self.prefetch = None;
+                self.prefetch_failed = None;
+                self.prefetch_target = Some((url, Instant::now() + Duration::from_millis(PREFETCH_DELAY_MS)));
+            }
+        }
+    }
+
+    // Display a rendered page in the page view
+    fn show_page(&mut self, url: &str, title: &str, page: RenderedPage) {
+        // Redirected pages are remembered by where they ended up, which
+        // outlives the old address
+        if let Some(final_url) = page.final_url.as_ref().filter(|_| !private_enabled()) {
+            if let Err(e) = update_history_url(url, final_url) {
+                self.notify_error(format!("Could not save history: {}", e));
+            }
+        }
+        // Pages opened by address are titled with it; the page knows better
+        let title = match &page.meta.title {
+            Some(meta_title) if title.is_empty() || title == url => meta_title.as_str(),
+            _ => title,
+        };
+        let url = page.final_url.as_deref().unwrap_or(url);
+        self.page_title = title.to_string();
+        self.page_url = url.to_string();
+        self.page_fetched = Some(Local::now());
+        self.page_scroll = 0;
+        self.selected_link = None;
+        self.cursor_line = 0;
+        self.cursor_col = 0;
+        self.desired_col = 0;
+        self.overlay = None;
+        self.visual_anchor = None;
+        self.marks.clear();
+        self.jumped_from = None;
+
+        // Normalize all whitespace to regular spaces
+        self.page_content = page.text.lines()
+            .map(|s| s.chars().map(|c| if c.is_whitespace() { ' ' } else { c }).collect())
+            .collect();
+        self.page_links = page.links;
+        self.page_images = page.images;
+        self.page_spans = page.spans;
+        self.page_headings = page.headings;
+        self.page_anchors = page.anchors;
+        self.page_source = page.source;
+        self.page_json = page.json;
+        self.page_thread = page.thread;
+        self.page_redirects = page.redirects;
+        self.page_meta = page.meta;
+        self.page_stats = page.stats;
+        self.page_headers = page.headers;
+        self.reader_mode = false;
+        self.load_error = None;
+        self.view = View::WebPage;
+        // A site's own 404 or 403 page, so it isn't taken for the page itself
+        if let Some(status) = self.page_stats.and_then(|stats| stats.error_status()) {
+            self.notify_error(format!("HTTP {}", status));
+        }
+
+        // Deep links land on their section, reopened pages where they were left
+        if let Some(closed) = self.reopening.take().filter(|closed| closed.url == url) {
+            let last = self.page_content.len().saturating_sub(1);
+            self.page_scroll = closed.scroll.min(last);
+            self.cursor_line = closed.cursor_line.min(last);
+            self.update_selected_link();
+        } else if let Some(fragment) = Url::parse(url).ok().and_then(|u| u.fragment().map(str::to_string)) {
+            self.jump_to_anchor(&fragment);
+        }
+    }
+
+    // Open the marked results, or else the top ones, in background tabs,
+    // fetching them all at once
+    fn open_top_results(&mut self) {
+        let limit = match config().open_tabs.unwrap_or(OPEN_TABS_DEFAULT) {
+            0 => usize::MAX,
+            n => n,
+        };
+        let results: Vec<&SearchResult> = if self.marked.is_empty() {
+            self.results.iter().take(limit).collect()
+        } else {
+            self.marked_results()
+        };
+        if results.is_empty() {
+            self.notify("No results to open");
+            return;
+        }
+        let picked: Vec<(String, String)> = results
+            .into_iter()
+            .filter(|r| !r.url.is_empty() && !self.tabs.iter().any(|tab| tab.place.url == r.url))
+            .map(|r| (r.url.clone(), r.title.clone()))
+            .collect();
+        self.marked.clear();
+        if picked.is_empty() {
+            self.notify("Those results are open already");
+            return;
+        }
+        let query = self.query.clone();
+        for (url, title) in &picked {
+            self.add_history(&query, title, url);
+            let page = match self.prefetched.iter().position(|(u, _)| u == url) {
+                Some(i) => TabPage::Loaded(Box::new(self.prefetched.remove(i).1)),
+                None => TabPage::Loading(PageLoad::start(url, title)),
+            };
+            let place = ClosedPage { url: url.clone(), title: title.clone(), scroll: 0, cursor_line: 0 };
+            self.tabs.push_back(Tab { place, page });
+        }
+        let (opening, failed) = self.tabs_opening.unwrap_or((0, 0));
+        self.tabs_opening = Some((opening + picked.len(), failed));
+        self.poll_tabs();
+    }
+
+    // Keep background tabs that finished loading, and say when O is done
+    fn poll_tabs(&mut self) {
+        let Some((opening, mut failed)) = self.tabs_opening else {
+            return;
+        };
+        let mut loading = 0;
+        for tab in self.tabs.iter_mut() {
+            if let TabPage::Loading(load) = &tab.page {
+                match load.finished() {
+                    Some(Ok(page)) => tab.page = TabPage::Loaded(Box::new(page)),
+                    Some(Err(_)) => {
+                        tab.page = TabPage::Failed;
+                        failed += 1;
+                    }
+                    None => loading += 1,
+                }
+            }
+        }
+        if loading > 0 {
+            self.tabs_opening = Some((opening, failed));
+            return;
+        }
+        self.tabs_opening = None;
+        let mut note = format!("Opened {} tab{}", opening, if opening == 1 { "" } else { "s" });
+        if failed > 0 {
+            note.push_str(&format!(", {} failed", failed));
+        }
+        self.notify(note + " - K/J to switch");
+    }
+
+    // Show the next background tab (or the previous one), putting the page
+    // being read at the other end of the line
+    fn switch_tab(&mut self, forward: bool) {
+        let Some(Tab { place, page }) = (if forward { self.tabs.pop_front() } else { self.tabs.pop_back() }) else {
+            self.notify("No background tabs - O in the results opens the top results in some");
+            return;
+        };
+        let current = match (&self.view, &self.load_error) {
+            (View::WebPage, _) if !self.page_url.is_empty() => Some(Tab {
+                place: ClosedPage {
+                    url: self.page_url.clone(),
+                    title: self.page_title.clone(),
+                    scroll: self.page_scroll,
+                    cursor_line: self.cursor_line,
+                },
+                page: TabPage::Loaded(Box::new(self.current_page())),
+            }),
+            (View::LoadError, Some(failed)) => Some(Tab {
+                place: ClosedPage { url: failed.url.clone(), title: failed.title.clone(), scroll: 0, cursor_line: 0 },
+                page: TabPage::Failed,
+            }),
+            _ => None,
+        };
+        if let Some(current) = current {
+            if forward {
+                self.tabs.push_back(current);
+            } else {
+                self.tabs.push_front(current);
+            }
+        }
+        self.loading = None;
+        match page {
+            TabPage::Loaded(page) => {
+                let (url, title) = (place.url.clone(), place.title.clone());
+                self.reopening = Some(place);
+                self.show_page(&url, &title, *page);
+            }
+            TabPage::Loading(load) => self.loading = Some(load),
+            TabPage::Failed => self.load_page(&place.url, &place.title),
+        }
+    }
+
+    // Reopen the page closed last
+    fn reopen_closed_page(&mut self) {
+        let Some(closed) = self.closed_pages.pop() else {
+            self.notify("No closed pages to reopen");
+            return;
+        };
+        self.clear_suggestions();
+        self.add_history(&closed.url, &closed.title, &closed.url);
+        let (url, title) = (closed.url.clone(), closed.title.clone());
+        self.reopening = Some(closed);
+        self.load_page(&url, &title);
+    }
+
+    // Fold or unfold the comment under the cursor, with its replies. With
+    // `all`, unfold everything, or fold every top-level comment when nothing
+    // is folded.
+    fn toggle_thread_fold(&mut self, all: bool) {
+        let Some(thread) = self.page_thread.as_mut() else {
+            return;
+        };
+        let line = if all {
+            thread.toggle_all();
+            0
+        } else {
+            match thread.toggle(self.cursor_line) {
+                Some(line) => line,
+                None => return,
+            }
+        };
+        let page = thread.render();
+        self.page_content = page.text.lines().map(str::to_string).collect();
+        self.page_links = page.links;
+        self.page_spans = page.spans;
+        self.page_headings = page.headings;
+        self.page_anchors = page.anchors;
+        self.move_to_line(line.min(self.page_content.len().saturating_sub(1)));
+    }
+
+    // Fold or unfold the innermost JSON object or array around the cursor.
+    // With `all`, unfold everything instead.
+    fn toggle_json_fold(&mut self, all: bool) {
+        let Some(doc) = self.page_json.as_mut() else {
+            self.notify("Folding only works on JSON pages and comment threads");
+            return;
+        };
+        let line = if all {
+            doc.folded.clear();
+            0
+        } else {
+            let Some(fold) = doc
+                .folds
+                .iter()
+                .filter(|f| f.start <= self.cursor_line && self.cursor_line <= f.end)
+                .max_by_key(|f| f.start)
+            else {
+                return;
+            };
+            if !doc.folded.remove(&fold.pointer) {
+                doc.folded.insert(fold.pointer.clone());
+            }
+            fold.start
+        };
+        let page = doc.render();
+        self.page_content = page.text.lines().map(str::to_string).collect();
+        self.page_spans = page.spans;
+        self.move_to_line(line.min(self.page_content.len().saturating_sub(1)));
+    }
+
+    // The page being read, as it could be shown again
+    fn current_page(&self) -> RenderedPage {
+        RenderedPage {
+            text: self.page_content.join("\n"),
+            links: self.page_links.clone(),
+            images: self.page_images.clone(),
+            spans: self.page_spans.clone(),
+            headings: self.page_headings.clone(),
+            anchors: self.page_anchors.clone(),
+            source: self.page_source.clone(),
+            json: self.page_json.clone(),
+            thread: self.page_thread.clone(),
+            final_url: None,
+            redirects: self.page_redirects.clone(),
+            meta: self.page_meta.clone(),
+            stats: self.page_stats,
+            headers: self.page_headers.clone(),
+        }
+    }
+
+    // Save the current page, or the selected result, to the read-later list
+    fn add_to_read_later(&mut self) {
+        if self.view == View::SearchResults && !self.marked.is_empty() {
+            self.add_marked_to_read_later();
+            return;
+        }
+        let entry = if self.view == View::WebPage {
+            ReadLaterEntry {
+                title: self.page_title.clone(),
+                url: self.page_url.clone(),
+                added: Local::now(),
+                page: self.current_page(),
+            }
+        } else {
+            let Some(result) = self.list_state.selected().and_then(|i| self.results.get(i)) else {
+                return;
+            };
+            let prefetched = self.prefetched.iter().find(|(u, _)| *u == result.url).map(|(_, p)| p.clone());
+            match prefetched.map_or_else(|| fetch_page(&result.url, false, &CancelToken::default(), &|_| {}), Ok) {
+                Ok(page) => ReadLaterEntry {
+                    title: result.title.clone(),
+                    url: result.url.clone(),
+                    added: Local::now(),
+                    page,
+                },
+                Err(e) => {
+                    self.notify_error(format!("Could not save for later: {}", e));
+                    return;
+                }
+            }
+        };
+
+        let mut entries = load_read_later();
+        entries.retain(|e| e.url != entry.url);
+        let title = entry.title.clone();
+        entries.insert(0, entry);
+        match save_read_later(&entries) {
+            Ok(()) => self.notify(format!("Saved for later: {} ({} in list)", truncate_string(&title, 40), entries.len())),
+            Err(e) => self.notify_error(format!("Could not save for later: {}", e)),
+        }
+    }
+
+    fn open_read_later(&mut self) {
+        self.read_later = load_read_later();
+        self.read_later_state = ListState::default();
+        if !self.read_later.is_empty() {
+            self.read_later_state.select(Some(0));
+        }
+        // q goes back to wherever the list was opened from
+        if self.view != View::ReadLater {
+            self.return_view = Some(self.view.clone());
+        }
+        self.view = View::ReadLater;
+    }
+
+    fn close_read_later(&mut self) {
+        self.view = self.return_view.take().unwrap_or(View::Home);
+        if self.view == View::WebPage && self.page_content.is_empty() {
+            self.view = View::Home;
+        }
+    }
+
+    // Open the selected saved article from disk, no network needed
+    fn open_read_later_entry(&mut self) {
+        let Some(i) = self.read_later_state.selected() else {
+            return;
+        };
+        if i >= self.read_later.len() {
+            return;
+        }
+        let entry = &self.read_later[i];
+        let (title, url, added, page) = (entry.title.clone(), entry.url.clone(), entry.added, entry.page.clone());
+        self.show_page(&url, &title, page);
+        self.page_fetched = Some(added);
+        self.return_view = Some(View::ReadLater);
+    }
+
+    // I: turn page images on or off, and render the current page again
+    fn toggle_images(&mut self) {
+        let enabled = !images_enabled();
+        IMAGES_ENABLED.store(enabled, Ordering::Relaxed);
+        self.notify(if enabled { "Images on" } else { "Images off" });
+        // Prefetched pages were rendered the other way
+        self.prefetched.clear();
+        self.prefetch = None;
+        if self.page_source.is_empty() {
+            return;
+        }
+        let (url, title) = (self.page_url.clone(), self.page_title.clone());
+        self.load_page(&url, &title);
+    }
+
+    // R: show only the article, as picked by Readability; R again reloads the
+    // full page (from the cache, usually)
+    fn toggle_reader_mode(&mut self) {
+        let (url, title) = (self.page_url.clone(), self.page_title.clone());
+        if self.reader_mode {
+            self.load_page(&url, &title);
+            return;
+        }
+        let html = String::from_utf8_lossy(&self.page_source).into_owned();
+        if self.page_json.is_some() || !looks_like_html(&html) {
+            self.notify("Reader mode only works on HTML pages");
+            return;
+        }
+        let Some(mut page) = extract_reader_content(&html, &url) else {
+            self.notify("No article found on this page");
+            return;
+        };
+        page.text = sanitize_display(&page.text);
+        page.source = std::mem::take(&mut self.page_source);
+        page.redirects = std::mem::take(&mut self.page_redirects);
+        page.stats = self.page_stats;
+        page.headers = std::mem::take(&mut self.page_headers);
+        self.show_page(&url, &title, page);
+        self.reader_mode = true;
+        self.notify("Reader mode (R: full page)");
+    }
+
+    // F on a page: subscribe to it if it is a feed, otherwise offer the first
+    // feed it advertises
+    fn prompt_subscribe(&mut self) {
+        let candidate = if parse_feed(&self.page_source, &self.page_url).is_some() {
+            Some(self.page_url.clone())
+        } else {
+            let html = String::from_utf8_lossy(&self.page_source);
+            discover_feeds(&html, &self.page_url).into_iter().next()
+        };
+        match candidate {
+            Some(url) => self.open_prompt(PromptKind::Subscribe, &url),
+            None => self.notify("No feed found on this page"),
+        }
+    }
+
+    // Open the PDF a paper's page points to with citation_pdf_url, as text.
+    // S then saves the PDF itself.
+    fn open_pdf(&mut self) {
+        let Some(pdf) = self.page_meta.pdf.clone() else {
+            self.notify("No PDF linked from this page");
+            return;
+        };
+        let url = Url::parse(&self.page_url).and_then(|base| base.join(&pdf)).map_or(pdf, |u| u.to_string());
+        let title = self.page_title.clone();
+        self.follow_link(&url, &title);
+    }
+
+    fn subscribe_to_feed(&mut self, url: &str) {
+        let mut store = load_feeds();
+        let title = if url == self.page_url && !self.page_title.is_empty() {
+            self.page_title.clone()
+        } else {
+            display_host(url)
+        };
+        if !store.subscribe(url, &title) {
+            self.notify("Already subscribed");
+            return;
+        }
+        match save_feeds(&store) {
+            Ok(()) => self.notify(format!("Subscribed to {} (navim feeds to read)", truncate_string(url, 50))),
+            Err(e) => self.notify_error(format!("Could not save subscription: {}", e)),
+        }
+    }
+
+    fn open_feeds(&mut self) {
+        self.feeds = load_feeds();
+        self.feed_items.clear();
+        self.feeds_state = ListState::default();
+        if self.view != View::Feeds {
+            self.return_view = Some(self.view.clone());
+        }
+        self.view = View::Feeds;
+        self.refresh_feeds();
+    }
+
+    fn close_feeds(&mut self) {
+        self.feed_refresh = None;
+        self.view = self.return_view.take().unwrap_or(View::Home);
+        if self.view == View::WebPage && self.page_content.is_empty() {
+            self.view = View::Home;
+        }
+    }
+
+    fn refresh_feeds(&mut self) {
+        if self.feeds.subscriptions.is_empty() {
+            self.notify("No subscriptions yet. Press F on a page with a feed to subscribe.");
+            return;
+        }
+        let urls = self.feeds.subscriptions.iter().map(|s| s.url.clone()).collect();
+        self.feed_refresh = Some(start_feed_refresh(urls));
+        self.notify(format!("Checking {} feeds...", self.feeds.subscriptions.len()));
+    }
+
+    fn poll_feed_refresh(&mut self) {
+        let Some(rx) = &self.feed_refresh else {
+            return;
+        };
+        let results = match rx.try_recv() {
+            Ok(results) => results,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Vec::new(),
+        };
+        self.feed_refresh = None;
+
+        let mut failed = 0;
+        self.feed_items.clear();
+        for (url, result) in results {
+            let Ok(feed) = result else {
+                failed += 1;
+                continue;
+            };
+            if let Some(sub) = self.feeds.subscriptions.iter_mut().find(|s| s.url == url) {
+                sub.title = feed.title.clone();
+            }
+            for item in feed.items {
+                self.feed_items.push((feed.title.clone(), item));
+            }
+        }
+        // Undated entries go last
+        self.feed_items.sort_by_key(|(_, item)| std::cmp::Reverse(item.date));
+        self.save_feed_state();
+
+        let unread = self.feed_items.iter().filter(|(_, item)| !self.feeds.is_read(&item.url)).count();
+        self.notify(format!("{} unread of {} entries", unread, self.feed_items.len()));
+        if failed > 0 {
+            self.notify_error(format!("{} feeds failed to load", failed));
+        }
+        self.feeds_state.select((!self.visible_feed_items().is_empty()).then_some(0));
+    }
+
+    // Indexes into feed_items of the entries the list shows
+    fn visible_feed_items(&self) -> Vec<usize> {
+        (0..self.feed_items.len())
+            .filter(|&i| self.feeds_show_all || !self.feeds.is_read(&self.feed_items[i].1.url))
+            .collect()
+    }
+
+    fn selected_feed_item(&self) -> Option<usize> {
+        self.feeds_state.selected().and_then(|i| self.visible_feed_items().get(i).copied())
+    }
+
+    fn toggle_feed_item_read(&mut self) {
+        let Some(i) = self.selected_feed_item() else {
+            return;
+        };
+        let url = self.feed_items[i].1.url.clone();
+        if self.feeds.is_read(&url) {
+            self.feeds.read.retain(|u| *u != url);
+        } else {
+            self.feeds.mark_read(&url);
+        }
+        self.save_feed_state();
+        self.clamp_feeds_selection();
+    }
+
+    fn mark_all_feed_items_read(&mut self) {
+        for (_, item) in &self.feed_items {
+            self.feeds.mark_read(&item.url);
+        }
+        self.save_feed_state();
+        self.notify("Marked everything as read");
+        self.clamp_feeds_selection();
+    }
+
+    fn clamp_feeds_selection(&mut self) {
+        let len = self.visible_feed_items().len();
+        self.feeds_state.select(match self.feeds_state.selected() {
+            _ if len == 0 => None,
+            Some(i) => Some(i.min(len - 1)),
+            None => Some(0),
+        });
+    }
+
+    // Open an entry like a search result; q comes back to the list
+    fn open_feed_item(&mut self) {
+        let Some(i) = self.selected_feed_item() else {
+            return;
+        };
+        let (url, title) = (self.feed_items[i].1.url.clone(), self.feed_items[i].1.title.clone());
+        if url.is_empty() {
+            return;
+        }
+        self.feeds.mark_read(&url);
+        self.save_feed_state();
+        self.clamp_feeds_selection();
+        self.add_history(&title, &title, &url);
+        self.return_view = Some(View::Feeds);
+        self.load_page(&url, &title);
+    }
+
+    fn delete_read_later_entry(&mut self) {
+        let Some(i) = self.read_later_state.selected() else {
+            return;
+        };
+        if i >= self.read_later.len() {
+            return;
+        }
+        let entry = self.read_later.remove(i);
+        if let Err(e) = save_read_later(&self.read_later) {
+            self.notify_error(format!("Could not update list: {}", e));
+            return;
+        }
+        self.notify(format!("Removed {}", truncate_string(&entry.title, 50)));
+        if self.read_later.is_empty() {
+            self.read_later_state.select(None);
+        } else {
+            self.read_later_state.select(Some(i.min(self.read_later.len() - 1)));
+        }
+    }
+}
+
+// "Did you mean", or which query the results are really for
+fn correction_line(correction: &Correction) -> Line<'static> {
+    let query = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::ITALIC);
+    let hint = Style::default().fg(Color::DarkGray);
+    let mut spans = match &correction.showing {
+        Some(showing) => vec![
+            Span::raw(" Showing results for "),
+            Span::styled(showing.clone(), query),
+            Span::raw(". Search instead for "),
+        ],
+        None => vec![Span::raw(" Did you mean: ")],
+    };
+    spans.push(Span::styled(correction.query.clone(), query));
+    spans.push(Span::styled(" — press c to search", hint));
+    Line::from(spans)
+}
+
+// Title, summary, facts and links of the infobox, fitted to `width`
+fn infobox_lines(infobox: &Infobox, width: usize) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut title = vec![Span::styled(infobox.title.clone(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD))];
+    if !infobox.subtitle.is_empty() {
+        title.push(Span::styled(format!("  {}", infobox.subtitle), Style::default().fg(Color::DarkGray)));
+    }
+    lines.push(Line::from(title));
+
+    let summary = wrap_with_offsets(&infobox.summary, width.max(1));
+    for (i, (_, line)) in summary.iter().take(3).enumerate() {
+        let line = if i == 2 && summary.len() > 3 { truncate_string(line, width.saturating_sub(3)) + "..." } else { line.clone() };
+        lines.push(Line::from(Span::styled(line, Style::default().fg(Color::Gray))));
+    }
+
+    // Facts are packed several to a line, "Label: value"
+    let mut row: Vec<Span> = Vec::new();
+    let mut row_width = 0;
+    for (label, value) in &infobox.facts {
+        let fact = truncate_string(&format!("{}: {}", label, value), width);
+        let fact_width = fact.width() + if row.is_empty() { 0 } else { 3 };
+        if !row.is_empty() && row_width + fact_width > width {
+            lines.push(Line::from(std::mem::take(&mut row)));
+            row_width = 0;
+        }
+        if !row.is_empty() {
+            row.push(Span::styled(" · ", Style::default().fg(Color::DarkGray)));
+        }
+        row_width += fact.width() + if row.is_empty() { 0 } else { 3 };
+        let label_len = (label.chars().count() + 1).min(fact.chars().count());
+        let (label_part, value_part): (String, String) =
+            (fact.chars().take(label_len).collect(), fact.chars().skip(label_len).collect());
+        row.push(Span::styled(label_part, Style::default().fg(Color::Yellow)));
+        row.push(Span::styled(value_part, Style::default().fg(Color::White)));
+    }
+    if !row.is_empty() {
+        lines.push(Line::from(row));
+    }
+
+    if !infobox.links.is_empty() {
+        let links: Vec<Span> = infobox
+            .links
+            .iter()
+            .flat_map(|(text, _)| {
+                [Span::styled(format!("↗ {}", text), Style::default().fg(Color::Cyan)), Span::raw("  ")]
+            })
+            .collect();
+        lines.push(Line::from(links));
+    }
+    lines
+}
+
+// The related searches row: r picks one, the selected one shown inverted.
+// Entries scroll left to keep the selected one in view.
+fn draw_related_searches(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    let width = area.width.saturating_sub(2) as usize;
+    let label = |query: &String| format!(" {} ", query);
+    let mut first = 0;
+    if let Some(selected) = app.related_selected {
+        while first < selected && app.related[first..=selected].iter().map(|q| label(q).width() + 1).sum::<usize>() > width {
+            first += 1;
+        }
+    }
+    let mut spans = Vec::new();
+    for (i, query) in app.related.iter().enumerate().skip(first) {
+        let style = if app.related_selected == Some(i) {
+            Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        spans.push(Span::styled(label(query), style));
+        spans.push(Span::raw(" "));
+    }
+    let title = if app.related_selected.is_some() { " Related searches  h/l: pick  Enter: search  Esc: back " } else { " Related searches (r) " };
+    let border = if app.related_selected.is_some() { Color::Cyan } else { Color::DarkGray };
+    let row = Paragraph::new(Line::from(spans))
+        .block(Block::default().borders(borders()).border_style(Style::default().fg(border)).title(title));
+    f.render_widget(row, area);
+}
+
+// Whether a result's title, URL or description contains the filter text,
+// ignoring case
+fn results_filter_matches(filter: &str, result: &SearchResult) -> bool {
+    let filter = filter.to_lowercase();
+    [&result.title, &result.url, &result.description].iter().any(|field| field.to_lowercase().contains(&filter))
+}
+
+// The words of a query worth highlighting in results: no operators
+// (site:, -exclusions), bangs or one-letter words
+fn query_terms(query: &str) -> Vec<Vec<char>> {
+    query
+        .split_whitespace()
+        .filter(|word| !word.starts_with(['-', '!']) && !word.contains(':'))
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| word.chars().count() > 1)
+        .map(|word| word.chars().map(fold_case).collect())
+        .collect()
+}
+
+// Lowercase one char to one char, so positions match the original text
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// Text as spans in `style`, with every occurrence of a query term stood out
+fn highlight_terms(text: String, terms: &[Vec<char>], style: Style) -> Vec<Span<'static>> {
+    let chars: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = chars.iter().copied().map(fold_case).collect();
+    let mut hit = vec![false; chars.len()];
+    for term in terms {
+        for start in 0..folded.len().saturating_sub(term.len() - 1) {
+            if folded[start..].starts_with(term) {
+                hit[start..start + term.len()].iter_mut().for_each(|h| *h = true);
+            }
+        }
+    }
+    if !hit.contains(&true) {
+        return vec![Span::styled(text, style)];
+    }
+
+    let highlight = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for i in 1..=chars.len() {
+        if i == chars.len() || hit[i] != hit[start] {
+            let run: String = chars[start..i].iter().collect();
+            spans.push(Span::styled(run, if hit[start] { highlight } else { style }));
+            start = i;
+        }
+    }
+    spans
+}
+
+fn draw_search_results(f: &mut ratatui::Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    // Header
+    let header = Line::from(vec![
+        Span::styled(
+            " NAVIM ",
+            Style::default()
+                .bg(Color::Cyan)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("  Search: "),
+        Span::styled(&app.query, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::styled(
+            if app.filters.is_default() { String::new() } else { format!("  [{}]", app.filters.summary()) },
+            Style::default().fg(Color::Magenta),
+        ),
+        Span::styled(
+            if app.results_filter_editing || !app.results_filter.is_empty() {
+                format!("  /{}", app.results_filter)
+            } else {
+                String::new()
+            },
+            Style::default().fg(Color::White),
+        ),
+    ]);
+    // The filter is typed at the end of the header
+    if app.results_filter_editing && app.prompt.is_none() {
+        f.set_cursor_position((chunks[0].x + 1 + header.width() as u16, chunks[0].y + 1));
+    }
+    let header = Paragraph::new(header).block(Block::default().borders(borders()).title(vertical_tabs(app.vertical)));
+    f.render_widget(header, chunks[0]);
+
+    // Related searches get a row under everything
+    let (body, related_area) = if app.related.is_empty() {
+        (chunks[1], None)
+    } else {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(chunks[1]);
+        (split[0], Some(split[1]))
+    };
+
+    // Image results get a thumbnail preview beside the list, others the
+    // highlighted page when the preview is on
+    let (list_area, preview_area, page_preview_area) = if app.vertical == Vertical::Images {
+        let split = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(THUMBNAIL_WIDTH + 2)])
+            .split(body);
+        (split[0], Some(split[1]), None)
+    } else if app.preview {
+        let split = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .split(body);
+        (split[0], None, Some(split[1]))
+    } else {
+        (body, None, None)
+    };
+    if let Some(area) = related_area {
+        draw_related_searches(f, app, area);
+    }
+    // A spelling suggestion gets a line of its own above everything else
+    let list_area = match &app.correction {
+        Some(correction) if list_area.height > 4 => {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(list_area);
+            f.render_widget(Paragraph::new(correction_line(correction)), split[0]);
+            split[1]
+        }
+        _ => list_area,
+    };
+    // An instant answer comes first among the cards
+    let list_area = match &app.answer {
+        Some(answer) if list_area.height > 8 => {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(4), Constraint::Min(0)])
+                .split(list_area);
+            let lines = vec![
+                Line::styled(answer.question.clone(), Style::default().fg(Color::DarkGray)),
+                Line::styled(format!("= {}", answer.answer), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+            ];
+            let card = Paragraph::new(lines).block(
+                Block::default()
+                    .borders(borders())
+                    .border_style(Style::default().fg(Color::Green))
+                    .title(format!(" {} ", answer.kind)),
+            );
+            f.render_widget(card, split[0]);
+            split[1]
+        }
+        _ => list_area,
+    };
+    // The infobox goes above the results, taking at most half the height
+    let list_area = match &app.infobox {
+        Some(infobox) => {
+            let lines = infobox_lines(infobox, list_area.width.saturating_sub(2) as usize);
+            let height = (lines.len() as u16 + 2).min(list_area.height / 2);
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(height), Constraint::Min(0)])
+                .split(list_area);
+            let panel = Paragraph::new(lines).block(
+                Block::default().borders(borders()).border_style(Style::default().fg(Color::Yellow)).title(" About "),
+            );
+            f.render_widget(panel, split[0]);
+            split[1]
+        }
+        None => list_area,
+    };
+
+    // Results list, laid out per vertical
+    let title_style = Style::default().fg(Color::Green).add_modifier(Modifier::BOLD);
+    let meta_style = Style::default().fg(Color::Yellow);
+    let expanded = expanded_excerpt(app, list_area.width.saturating_sub(2 + 3 + 2) as usize);
+    let terms = query_terms(&app.query);
+    let items: Vec<ListItem> = app
+        .results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let mut title = Vec::new();
+            if app.marked.contains(&r.url) {
+                title.push(Span::styled("✓ ", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)));
+            }
+            if r.boosted {
+                title.push(Span::styled("★ ", Style::default().fg(Color::Yellow)));
+            }
+            title.extend(highlight_terms(truncate_string(&r.title, 70 - 2 * title.len()), &terms, title_style));
+            let title = Line::from(title);
+            let mut url_spans = vec![Span::styled(
+                truncate_string(&r.display_url, 60),
+                Style::default().fg(Color::Cyan),
+            )];
+            if !r.engines.is_empty() {
+                url_spans.push(Span::styled(
+                    format!("  [{}]", r.engines.join(", ")),
+                    Style::default().fg(Color::Magenta),
+                ));
+            }
+            let url = Line::from(url_spans);
+            let description =
+                Line::from(highlight_terms(truncate_string(&r.description, 80), &terms, Style::default().fg(Color::White)));
+            let lines = match app.vertical {
+                Vertical::Web => vec![title, url, description, Line::from("")],
+                Vertical::News => {
+                    let meta = [r.source.as_str(), r.age.as_str()]
+                        .iter()
+                        .filter(|s| !s.is_empty())
+                        .copied()
+                        .collect::<Vec<_>>()
+                        .join(" · ");
+                    vec![
+                        title,
+                        Line::from(Span::styled(truncate_string(&meta, 60), meta_style)),
+                        description,
+                        Line::from(""),
+                    ]
+                }
+                Vertical::Images => vec![title, url, Line::from("")],
+                Vertical::Videos => {
+                    let duration = if r.duration.is_empty() { "▶".to_string() } else { format!("▶ {}", r.duration) };
+                    let meta = [duration.as_str(), r.source.as_str(), r.age.as_str()]
+                        .iter()
+                        .filter(|s| !s.is_empty())
+                        .copied()
+                        .collect::<Vec<_>>()
+                        .join(" · ");
+                    vec![
+                        title,
+                        Line::from(Span::styled(truncate_string(&meta, 60), meta_style)),
+                        url,
+                        Line::from(""),
+                    ]
+                }
+            };
+            let mut lines = lines;
+            if let Some((_, excerpt)) = expanded.as_ref().filter(|(index, _)| *index == i) {
+                let blank = lines.pop();
+                lines.extend(excerpt.iter().map(|l| {
+                    Line::from(vec![Span::raw("  "), Span::styled(l.clone(), Style::default().fg(Color::Gray))])
+                }));
+                lines.extend(blank);
+            }
+            ListItem::new(lines)
+        })
+        .collect();
+    app.expanded_result = expanded.as_ref().map(|(i, excerpt)| (*i, excerpt.len()));
+
+    let list = List::new(items)
+        .block(Block::default().borders(borders()).title(format!(
+            "Results ({}, page {}{})",
+            match &app.unfiltered_results {
+                Some(all) => format!("{} of {}", app.results.len(), all.len()),
+                None => app.results.len().to_string(),
+            },
+            app.search_offset + 1,
+            match app.marked_results().len() {
+                0 => String::new(),
+                n => format!(", {} marked", n),
+            }
+        )))
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+
+    f.render_stateful_widget(list, list_area, &mut app.list_state);
+    app.results_area = list_area;
+    let visible_results = (list_area.height.saturating_sub(2) as usize / result_height(app.vertical)).max(1);
+    draw_scrollbar(f, list_area, app.results.len(), visible_results, app.list_state.offset());
+
+    if let Some(preview_area) = preview_area {
+        let selected = app.list_state.selected().and_then(|i| app.results.get(i));
+        let art = selected
+            .and_then(|r| r.thumbnail.as_ref())
+            .map(|thumb| app.thumbnails.get(thumb).map(String::as_str).unwrap_or("Loading preview..."))
+            .unwrap_or("");
+        let preview = Paragraph::new(art)
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(borders()).title("Preview"));
+        f.render_widget(preview, preview_area);
+    }
+    if let Some(area) = page_preview_area {
+        draw_page_preview(f, app, area);
+    }
+
+    draw_status_bar(f, app, chunks[2], "j/k: Navigate  Enter: Open  n: More  Tab: Verticals  f: Filters  ?: Keys");
+
+    if app.overlay.is_some() {
+        draw_overlay(f, app, chunks[1]);
+    }
+}
+
+// The highlighted result's page as fetched in the background, from the top
+fn draw_page_preview(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    let url = app.list_state.selected().and_then(|i| app.results.get(i)).map_or("", |r| r.url.as_str());
+    let page = app.prefetched.iter().find(|(u, _)| u == url).map(|(_, page)| page);
+    let (title, text) = match (page, &app.prefetch_failed) {
+        (Some(page), _) => (page.meta.title.clone().unwrap_or_else(|| "Preview".to_string()), page.text.as_str()),
+        (None, Some((failed, e))) if failed == url => ("Preview".to_string(), e.as_str()),
+        (None, _) if url.is_empty() => ("Preview".to_string(), ""),
+        (None, _) => ("Preview".to_string(), "Loading preview..."),
+    };
+    let lines: Vec<Line> = text
+        .lines()
+        .skip_while(|l| l.trim().is_empty())
+        .take(area.height as usize)
+        .map(|l| Line::from(sanitize_display(l)))
+        .collect();
+    let preview = Paragraph::new(lines)
+        .style(Style::default().fg(if page.is_some() { Color::White } else { Color::DarkGray }))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(borders()).title(truncate_string(&title, area.width.saturating_sub(4) as usize)));
+    f.render_widget(preview, area);
+}
+
+// How long a result stays highlighted before its excerpt is shown, and how
+// many rows the excerpt takes at most
+const EXPAND_DELAY_MS: u64 = 1000;
+const EXPAND_ROWS: usize = 6;
+
+// The highlighted result and the start of its page, wrapped to `width`, once
+// it has stayed highlighted for EXPAND_DELAY_MS and been prefetched. Not with
+// the preview pane, which shows more.
+fn expanded_excerpt(app: &App, width: usize) -> Option<(usize, Vec<String>)> {
+    if app.preview || app.vertical == Vertical::Images {
+        return None;
+    }
+    let index = app.list_state.selected()?;
+    let url = &app.results.get(index)?.url;
+    let (since_url, since) = app.highlighted_since.as_ref()?;
+    if since_url != url || since.elapsed() < Duration::from_millis(EXPAND_DELAY_MS) {
+        return None;
+    }
+    let (_, page) = app.prefetched.iter().find(|(u, _)| u == url)?;
+    let excerpt: Vec<String> = page_paragraphs(page)
+        .iter()
+        .flat_map(|p| wrap_with_offsets(p, width).into_iter().map(|(_, line)| line))
+        .take(EXPAND_ROWS)
+        .collect();
+    (!excerpt.is_empty()).then_some((index, excerpt))
+}
+
+// The page's paragraphs of running text, each joined into one line.
+// Headings, lists, code and tables are left out, and so are blocks too short
+// to be prose, like menus.
+fn page_paragraphs(page: &RenderedPage) -> Vec<String> {
+    let heading_lines: Vec<usize> = page.headings.iter().map(|h| h.line).collect();
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut prose = true;
+    for (i, line) in page.text.lines().chain([""]).enumerate() {
+        let line = line.trim();
+        if line.is_empty() || heading_lines.contains(&i) {
+            let paragraph = current.join(" ");
+            if prose && paragraph.split_whitespace().count() >= 8 {
+                paragraphs.push(sanitize_display(&paragraph));
+            }
+            current.clear();
+            prose = true;
+        } else {
+            prose &= !line.starts_with(['•', '▸', '┌', '│', '├', '└', '|', '+']);
+            current.push(line);
+        }
+    }
+    paragraphs
+}
+
+const WHEEL_SCROLL_LINES: usize = 3;
+
+// Rows taken by one result in each vertical's list layout (see draw_search_results)
+fn result_height(vertical: Vertical) -> usize {
+    match vertical {
+        Vertical::Images => 3,
+        _ => 4,
+    }
+}
+
+// Size of the ASCII thumbnail pane in the image vertical
+const THUMBNAIL_WIDTH: u16 = 40;
+const THUMBNAIL_HEIGHT: u16 = 20;
+
+// Block title listing the verticals with the active one bracketed
+fn vertical_tabs(active: Vertical) -> String {
+    Vertical::ALL
+        .iter()
+        .map(|v| if *v == active { format!("[{}]", v.label()) } else { format!(" {} ", v.label()) })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn draw_web_page(f: &mut ratatui::Frame, app: &mut App) {
+    // Site, author and date go under the title, then the description
+    let meta_lines = page_meta_lines(&app.page_meta, f.area().width.saturating_sub(4) as usize);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3 + meta_lines.len() as u16),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    // Header with page info and link count
+    let link_info = if !app.page_links.is_empty() {
+        format!(" [{} links]", app.page_links.len())
+    } else {
+        String::new()
+    };
+
+    let mut header_lines = vec![Line::from(vec![
+        Span::raw(" "),
+        Span::styled(
+            truncate_string(&app.page_title, 50),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            link_info,
+            Style::default().fg(Color::Cyan),
+        ),
+    ])];
+    header_lines.extend(meta_lines);
+    let mut header_block = Block::default().borders(borders()).title(truncate_string(&app.page_url, 60));
+    if let Some(stats) = &app.page_stats {
+        header_block = header_block.title(
+            Line::from(Span::styled(
+                format!(" {} ", stats.summary(app.page_content.len())),
+                Style::default().fg(Color::DarkGray),
+            ))
+            .right_aligned(),
+        );
+    }
+    // The title sits where the text inside the borders starts
+    let title_x = header_block.inner(chunks[0]).x;
+    let header = Paragraph::new(header_lines).block(header_block);
+    f.render_widget(header, chunks[0]);
+    let title_width = truncate_string(&app.page_url, 60).width() as u16;
+    app.hyperlinks.push(Hyperlink { x: title_x, y: chunks[0].y, width: title_width, url: app.page_url.clone() });
+
+    // Calculate line number width (for relative numbers, max is total lines)
+    let total_lines = app.page_content.len();
+    let line_num_width = total_lines.to_string().len().max(3);
+    let gutter_width = if app.number || app.relative_number { line_num_width + 3 } else { 0 };
+
+    // Page content area dimensions
+    let visible_height = chunks[1].height.saturating_sub(2) as usize;
+    let content_width = chunks[1].width.saturating_sub(2 + gutter_width as u16 + 1) as usize; // borders + line nums + separator
+    // A narrower text width wraps there and centers the text, gutter and all
+    let (content_width, margin) = match app.text_width {
+        width if width > 0 && width < content_width => (width, (content_width - width) / 2),
+        _ => (content_width, 0),
+    };
+
+    // The gutter of one row, following vim's number and relativenumber. Rows
+    // a long line wraps onto have no absolute number of their own.
+    let gutter = |line_num: usize, distance: usize, current: bool, continuation: bool| -> String {
+        let number = match (app.number, app.relative_number) {
+            (false, false) => None,
+            (true, false) if continuation => None,
+            (true, false) => Some(line_num + 1),
+            (true, true) if current => Some(line_num + 1),
+            (_, true) => Some(distance),
+        };
+        match number {
+            Some(n) => format!("{}{:>width$} │ ", " ".repeat(margin), n, width = line_num_width),
+            None => " ".repeat(margin + gutter_width),
+        }
+    };
+
+    // Wrapped lines take several rows; scroll on until the cursor's line fits
+    app.page_height = visible_height.max(1);
+    let rows = |line: &String| wrapped_rows(line, content_width);
+    while app.page_scroll < app.cursor_line
+        && app.cursor_line < total_lines
+        && app.page_content[app.page_scroll..=app.cursor_line].iter().map(rows).sum::<usize>() > visible_height
+    {
+        app.page_scroll += 1;
+    }
+
+    let selection = app.visual_range();
+    let selection_bg = Color::Rgb(68, 50, 90);
+
+    // Get the selected link info for highlighting
+    let selected_link_info = app.selected_link.and_then(|idx| {
+        app.page_links.get(idx).map(|l| (l.line, l.col_start, l.col_end))
+    });
+
+    // Styles
+    let bg_style = Style::default().fg(Color::White);
+    let line_num_style = Style::default().fg(Color::DarkGray);
+    let current_line_num_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+
+    // Build display lines with wrapping
+    let mut display_lines: Vec<Line> = Vec::new();
+    let mut current_display_row = 0;
+    // Link runs as (row, column, width, url), placed on screen once drawn
+    let mut link_cells: Vec<(usize, usize, usize, &str)> = Vec::new();
+    // Where the selected link starts on screen, for its preview
+    let mut selected_at: Option<(usize, usize)> = None;
+
+    // Safety: ensure content_width is at least 1 to prevent infinite loops
+    let content_width = content_width.max(1);
+
+    // Skip lines based on scroll position
+    let start_line = app.page_scroll;
+
+    // Render lines starting from start_line
+    for (line_num, line_text) in app.page_content.iter().enumerate().skip(start_line) {
+        if current_display_row >= visible_height {
+            break;
+        }
+
+        let chars: Vec<char> = line_text.chars().collect();
+        let cursor_on_line = app.cursor_line == line_num;
+        let selected_line = selection.is_some_and(|(first, last)| line_num >= first && line_num <= last);
+
+        // Get all links on this line
+        let links_on_line: Vec<&PageLink> = app.page_links.iter()
+            .filter(|l| l.line == line_num)
+            .collect();
+        let mut sorted_links = links_on_line.clone();
+        sorted_links.sort_by_key(|l| l.col_start);
+
+        // Styled runs touching this line
+        let spans_on_line: Vec<&StyledSpan> = app.page_spans.iter()
+            .filter(|s| s.start.0 <= line_num && s.end.0 >= line_num)
+            .collect();
+
+        // Handle empty lines
+        if chars.is_empty() {
+            let mut spans: Vec<Span> = Vec::new();
+            let num_style = if cursor_on_line { current_line_num_style } else { line_num_style };
+            let rel_distance = line_num.abs_diff(app.cursor_line);
+            spans.push(Span::styled(gutter(line_num, rel_distance, cursor_on_line, false), num_style));
+            if cursor_on_line && app.cursor_col == 0 {
+                spans.push(Span::styled(" ", Style::default().bg(Color::Blue).fg(Color::White)));
+            } else if selected_line {
+                spans.push(Span::styled(" ", Style::default().bg(selection_bg)));
+            }
+            display_lines.push(Line::from(spans));
+            current_display_row += 1;
+            continue;
+        }
+
+        // Wrap long lines - each wrap gets its own line number
+        let mut char_pos = 0;
+        let mut wrap_line_num = line_num;
+
+        while char_pos < chars.len() && current_display_row < visible_height {
+            let mut spans: Vec<Span> = Vec::new();
+
+            // Calculate end position for this row
+            let row_end = row_end(&chars, char_pos, content_width);
+
+            // Calculate relative line number for this display row
+            let is_cursor_wrap =
+                cursor_on_line && app.cursor_col >= char_pos && (app.cursor_col < row_end || row_end == chars.len());
+            let wrap_rel_distance = wrap_line_num.abs_diff(app.cursor_line);
+            let wrap_line_display = gutter(line_num, wrap_rel_distance, is_cursor_wrap && cursor_on_line, char_pos > 0);
+
+            let num_style = if is_cursor_wrap && cursor_on_line { current_line_num_style } else { line_num_style };
+            let mut col = wrap_line_display.width();
+            spans.push(Span::styled(wrap_line_display, num_style));
+
+            // Render characters for this row
+            for (i, c) in chars.iter().enumerate().take(row_end).skip(char_pos) {
+                let ch = c.to_string();
+
+                // Check if this char is part of a link
+                let link_info = sorted_links.iter().find(|l| i >= l.col_start && i < l.col_end);
+                let char_width = c.width().unwrap_or(0);
+                if let Some(link) = link_info {
+                    match link_cells.last_mut() {
+                        Some((row, start, width, url))
+                            if *row == current_display_row && *start + *width == col && *url == link.url =>
+                        {
+                            *width += char_width
+                        }
+                        _ => link_cells.push((current_display_row, col, char_width, &link.url)),
+                    }
+                }
+                col += char_width;
+
+                // Combine all styles covering this char (e.g. bold inside a heading)
+                let text_style = spans_on_line.iter()
+                    .filter(|s| s.contains(line_num, i))
+                    .fold(bg_style, |style, s| style.patch(s.style.style()));
+                let text_style = if selected_line { text_style.bg(selection_bg) } else { text_style };
+
+                if cursor_on_line && i == app.cursor_col {
+                    // Cursor position - blue box
+                    spans.push(Span::styled(ch, Style::default().bg(Color::Blue).fg(Color::White)));
+                } else if let Some(link) = link_info {
+                    // Check if this is the selected link
+                    let is_selected = selected_link_info.is_some_and(|(sel_line, sel_start, sel_end)| {
+                        line_num == sel_line && link.col_start == sel_start && link.col_end == sel_end
+                    });
+
+                    if is_selected {
+                        selected_at.get_or_insert((current_display_row, col - char_width));
+                        spans.push(Span::styled(
+                            ch,
+                            Style::default().bg(Color::Yellow).fg(Color::Black).add_modifier(Modifier::BOLD),
+                        ));
+                    } else {
+                        spans.push(Span::styled(
+                            ch,
+                            text_style.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED),
+                        ));
+                    }
+                } else {
+                    spans.push(Span::styled(ch, text_style));
+                }
+            }
+
+            // Cursor at end of line (on last wrap segment)
+            if cursor_on_line && app.cursor_col >= row_end && row_end == chars.len() {
+                spans.push(Span::styled(" ", Style::default().bg(Color::Blue).fg(Color::White)));
+            }
+
+            display_lines.push(Line::from(spans));
+            current_display_row += 1;
+
+            char_pos = row_end;
+            wrap_line_num += 1;
+        }
+    }
+
+    let scroll_info = format!(
+        " Line {}/{} Col {} ",
+        app.cursor_line + 1,
+        app.page_content.len().max(1),
+        app.cursor_col
+    );
+
+    let page_block = Block::default()
+        .borders(borders())
+        .border_style(Style::default().fg(Color::Green))
+        .title(scroll_info);
+    let inner = page_block.inner(chunks[1]);
+    for (row, col, width, url) in link_cells {
+        if col < inner.width as usize && width > 0 {
+            app.hyperlinks.push(Hyperlink {
+                x: inner.x + col as u16,
+                y: inner.y + row as u16,
+                width: (width as u16).min(inner.width - col as u16),
+                url: url.to_string(),
+            });
+        }
+    }
+    let page = Paragraph::new(display_lines).block(page_block);
+    f.render_widget(page, chunks[1]);
+    draw_scrollbar(f, chunks[1], app.page_content.len(), app.page_height, app.page_scroll);
+    if let Some((row, col)) = selected_at {
+        draw_link_preview(f, app, inner, row as u16, col as u16);
+    }
+
+    // Status bar hint: the selection, the link under the cursor, or keys
+    let hint = if let Some((first, last)) = selection {
+        format!("{} lines  j/k, {{/}}, g/G: extend  y: copy  Esc: cancel", last - first + 1)
+    } else if let Some(link) = app.selected_link.and_then(|idx| app.page_links.get(idx)) {
+        format!("→ {}  Enter: follow", link.url)
+    } else {
+        "h/j/k/l: Move  L/H: Links  f: All links  t: Contents  q: Back  ?: Keys".to_string()
+    };
+    draw_status_bar(f, app, chunks[2], &hint);
+
+    if app.overlay.is_some() {
+        draw_overlay(f, app, chunks[1]);
+    }
+}
+
+// A small box under (or over) the selected link with where it goes
+fn draw_link_preview(f: &mut ratatui::Frame, app: &mut App, inner: Rect, row: u16, col: u16) {
+    let Some(url) = app.previewable_link() else {
+        return;
+    };
+    let loading = app.link_preview_rx.as_ref().is_some_and(|(u, _)| u == url);
+    let text_width = 64.min(inner.width).saturating_sub(2) as usize;
+    let mut lines: Vec<Line> = Vec::new();
+    match app.link_previews.get(url) {
+        Some(Ok(preview)) => {
+            // Without a title (a PDF, an image) the file name is the best name
+            let file_name = || {
+                let url = Url::parse(&preview.url).ok()?;
+                url.path_segments()?.rfind(|segment| !segment.is_empty()).map(str::to_string)
+            };
+            let title = preview.meta.title.clone().or_else(file_name).unwrap_or_else(|| display_host(&preview.url));
+            lines.push(Line::from(Span::styled(
+                truncate_string(&sanitize_display(&title), text_width.saturating_sub(3)),
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            )));
+            if let Some(description) = &preview.meta.description {
+                let wrapped = wrap_with_offsets(&sanitize_display(description), text_width);
+                for (i, (_, line)) in wrapped.iter().take(3).enumerate() {
+                    let line = if i == 2 && wrapped.len() > 3 { truncate_string(line, text_width.saturating_sub(3)) + "..." } else { line.clone() };
+                    lines.push(Line::from(Span::styled(line, Style::default().fg(Color::Gray))));
+                }
+            }
+            let mut source = display_host(&preview.url);
+            if let Some(kind) = preview.content_type.as_deref().filter(|ct| !ct.contains("html")) {
+                source = format!("{} · {}", source, kind.split(';').next().unwrap_or(kind).trim());
+            }
+            lines.push(Line::from(Span::styled(source, Style::default().fg(Color::Cyan))));
+        }
+        Some(Err(e)) => lines.push(Line::from(Span::styled(
+            truncate_string(&format!("No preview: {}", e), text_width.saturating_sub(3)),
+            Style::default().fg(Color::DarkGray),
+        ))),
+        None if loading => {
+            lines.push(Line::from(Span::styled("Loading preview...", Style::default().fg(Color::DarkGray))))
+        }
+        None => return,
+    }
+
+    let width = (lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2).min(inner.width);
+    let height = lines.len() as u16 + 2;
+    let below = inner.y + row + 1;
+    let y = if below + height <= inner.bottom() { below } else { (inner.y + row).saturating_sub(height).max(inner.y) };
+    let x = (inner.x + col).min(inner.right().saturating_sub(width)).max(inner.x);
+    let area = Rect { x, y, width, height: height.min(inner.height) };
+    // Terminal hyperlinks under the box would wrap its text
+    app.hyperlinks.retain(|link| link.y < area.y || link.y >= area.bottom());
+    let popup = Paragraph::new(lines).block(
+        Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::DarkGray)),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+// The one-line bar under every view: mode, the page or query, a key hint,
+// work still running in the background, and where in the page or list we are
+fn draw_status_bar(f: &mut ratatui::Frame, app: &App, area: Rect, hint: &str) {
+    let (mode, mode_color) = match app.view {
+        View::WebPage if app.visual_anchor.is_some() => ("VISUAL", Color::Magenta),
+        View::WebPage => ("READING", Color::Green),
+        View::Home => ("HOME", Color::Cyan),
+        View::SearchResults => ("RESULTS", Color::Cyan),
+        View::ImageGallery => ("IMAGES", Color::Magenta),
+        View::ReadLater => ("READ LATER", Color::Yellow),
+        View::Feeds => ("FEEDS", Color::Yellow),
+        View::LoadError => ("ERROR", Color::Red),
+    };
+    let location = match app.view {
+        View::SearchResults => app.query.clone(),
+        View::WebPage | View::ImageGallery => app.page_url.clone(),
+        View::LoadError => app.load_error.as_ref().map(|failed| failed.url.clone()).unwrap_or_default(),
+        View::Home | View::ReadLater | View::Feeds => String::new(),
+    };
+
+    let mut tasks = Vec::new();
+    if app.loading.is_some() {
+        tasks.push("loading");
+    }
+    if app.prefetch.is_some() {
+        tasks.push("prefetching");
+    }
+    let opening = app.tabs_opening.map(|(opening, _)| {
+        let loading = app.tabs.iter().filter(|tab| matches!(tab.page, TabPage::Loading(_))).count();
+        format!("opening tabs {}/{}", opening.saturating_sub(loading), opening)
+    });
+    if let Some(opening) = &opening {
+        tasks.push(opening);
+    }
+    if app.feed_refresh.is_some() {
+        tasks.push("checking feeds");
+    }
+    if app.thumb_rx.is_some() {
+        tasks.push("thumbnails");
+    }
+    if app.suggest_rx.is_some() {
+        tasks.push("suggestions");
+    }
+    let mut right = String::from(" ");
+    if !tasks.is_empty() {
+        right.push_str(&format!("⟳ {}  ", tasks.join(", ")));
+    }
+    if let Some(count) = app.pending.count() {
+        right.push_str(&format!("{}  ", count));
+    }
+    if !app.tabs.is_empty() {
+        right.push_str(&format!("+{} tab{}  ", app.tabs.len(), if app.tabs.len() == 1 { "" } else { "s" }));
+    }
+    if let Some(position) = status_position(app) {
+        right.push_str(&position);
+        right.push(' ');
+    }
+
+    let mut spans = vec![
+        Span::styled(format!(" {} ", mode), Style::default().bg(mode_color).fg(Color::Black).add_modifier(Modifier::BOLD)),
+        Span::raw(" "),
+    ];
+    if private_enabled() {
+        spans.push(Span::styled(" PRIVATE ", Style::default().bg(Color::Magenta).fg(Color::White).add_modifier(Modifier::BOLD)));
+        spans.push(Span::raw(" "));
+    }
+    if !location.is_empty() {
+        spans.push(Span::styled(truncate_string(&location, 50), Style::default().fg(Color::Cyan)));
+        spans.push(Span::raw("  "));
+    }
+    spans.push(Span::styled(hint.to_string(), Style::default().fg(Color::Gray)));
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(right.width() as u16)])
+        .split(area);
+    let bar = Style::default().bg(Color::Black);
+    f.render_widget(Paragraph::new(Line::from(spans)).style(bar), chunks[0]);
+    f.render_widget(Paragraph::new(right).style(bar.fg(Color::Gray)), chunks[1]);
+}
+
+// Vim-style scroll position on a page, or "selected/total" in a list
+fn status_position(app: &App) -> Option<String> {
+    let list_position = |selected: Option<usize>, len: usize| match selected {
+        Some(i) if len > 0 => Some(format!("{}/{}", i + 1, len)),
+        _ => None,
+    };
+    match app.view {
+        View::WebPage => {
+            let max_scroll = app.max_scroll();
+            Some(if max_scroll == 0 {
+                "All".to_string()
+            } else if app.page_scroll == 0 {
+                "Top".to_string()
+            } else if app.page_scroll >= max_scroll {
+                "Bot".to_string()
+            } else {
+                format!("{}%", app.page_scroll * 100 / max_scroll)
+            })
+        }
+        View::SearchResults => list_position(app.list_state.selected(), app.results.len()),
+        View::ImageGallery => list_position(app.gallery_state.selected(), app.page_images.len()),
+        View::ReadLater => list_position(app.read_later_state.selected(), app.read_later.len()),
+        View::Feeds => list_position(app.feeds_state.selected(), app.visible_feed_items().len()),
+        View::Home | View::LoadError => None,
+    }
+}
+
+// Where a row of page text starting at chars[start] ends: as many chars as
+// fit in width columns, but at least one. Zero-width combining marks stay on
+// the row of the character they belong to.
+fn row_end(chars: &[char], start: usize, width: usize) -> usize {
+    let mut used = 0;
+    let mut end = start;
+    while end < chars.len() {
+        used += chars[end].width().unwrap_or(0);
+        if used > width && end > start {
+            break;
+        }
+        end += 1;
+    }
+    end
+}
+
+// Rows a line of page text takes once wrapped
+fn wrapped_rows(line: &str, width: usize) -> usize {
+    let chars: Vec<char> = line.chars().collect();
+    let mut rows = 0;
+    let mut start = 0;
+    while start < chars.len() {
+        start = row_end(&chars, start, width);
+        rows += 1;
+    }
+    rows.max(1)
+}
+
+// Box borders, left off in accessible mode so screen readers don't read them
+fn borders() -> Borders {
+    if accessible() {
+        Borders::NONE
+    } else {
+        Borders::ALL
+    }
+}
+
+// Scrollbar over the right border of a bordered area, when not everything fits
+fn draw_scrollbar(f: &mut ratatui::Frame, area: Rect, total: usize, visible: usize, position: usize) {
+    if total <= visible || accessible() {
+        return;
+    }
+    // Positions run from 0 to the last scroll offset, so the thumb reaches
+    // the bottom at the end
+    let mut state = ScrollbarState::new(total - visible + 1).position(position).viewport_content_length(visible);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .track_symbol(Some("│"))
+        .thumb_style(Style::default().fg(Color::Green));
+    f.render_stateful_widget(scrollbar, area.inner(Margin::new(0, 1)), &mut state);
+}
+
+// Rectangle of the given percentage size centered in area
+fn centered_rect(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+// Draw the active popup over the page content area
+fn draw_overlay(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
+    match app.overlay {
+        Some(Overlay::Redirects) => {
+            let popup = centered_rect(area, 80, 60);
+            let url_width = (popup.width as usize).saturating_sub(6);
+            let mut lines = vec![Line::from("")];
+            if app.page_redirects.is_empty() {
+                lines.push(Line::from(Span::styled("  Not redirected", Style::default().fg(Color::DarkGray))));
+                lines.push(Line::from(""));
+            }
+            for redirect in &app.page_redirects {
+                lines.push(Line::from(Span::styled(
+                    format!("  {}", truncate_string(&redirect.url, url_width)),
+                    Style::default().fg(Color::Gray),
+                )));
+                lines.push(Line::from(Span::styled(
+                    format!("    ↓ {}", redirect.reason),
+                    Style::default().fg(Color::Yellow),
+                )));
+            }
+            lines.push(Line::from(Span::styled(
+                format!("  {}", truncate_string(&app.page_url, url_width)),
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            )));
+
+            let widget = Paragraph::new(lines).block(
+                Block::default()
+                    .borders(borders())
+                    .border_style(Style::default().fg(Color::Cyan))
+                    .title(format!(
+                        " Redirects: {}  [y: copy final URL  q: close] ",
+                        app.page_redirects.len()
+                    )),
+            );
+            f.render_widget(Clear, popup);
+            f.render_widget(widget, popup);
+        }
+        Some(Overlay::Headers) => {
+            let popup = centered_rect(area, 90, 80);
+            let name_width = app.page_headers.iter().map(|(name, _)| name.len()).max().unwrap_or(0).min(30);
+            let value_width = (popup.width as usize).saturating_sub(name_width + 8).max(10);
+
+            // Long values (CSP, Set-Cookie) wrap under themselves
+            let items: Vec<ListItem> = if app.page_headers.is_empty() {
+                vec![ListItem::new(Span::styled(
+                    "No response headers (local file or offline cached copy)",
+                    Style::default().fg(Color::DarkGray),
+                ))]
+            } else {
+                app.page_headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let chars: Vec<char> = value.chars().collect();
+                        let mut lines = Vec::new();
+                        for (i, chunk) in chars.chunks(value_width).enumerate() {
+                            let label = if i == 0 { format!("{:<width$}", name, width = name_width) } else { " ".repeat(name_width) };
+                            lines.push(Line::from(vec![
+                                Span::styled(label, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                                Span::raw("  "),
+                                Span::raw(chunk.iter().collect::<String>()),
+                            ]));
+                        }
+                        if lines.is_empty() {
+                            lines.push(Line::from(Span::styled(name.clone(), Style::default().fg(Color::Cyan))));
+                        }
+                        ListItem::new(lines)
+                    })
+                    .collect()
+            };
+
+            let status = match app.page_stats.and_then(|stats| stats.status) {
+                Some(status) => format!("HTTP {}", status),
+                None => "Headers".to_string(),
+            };
+            let list = List::new(items)
+                .block(Block::default()
+                    .borders(borders())
+                    .border_style(Style::default().fg(Color::Cyan))
+                    .title(format!(" {}: {} headers  [y: copy  =/q: close] ", status, app.page_headers.len())))
+                .highlight_style(Style::default().bg(Color::DarkGray))
+                .highlight_symbol("> ");
+
+            f.render_widget(Clear, popup);
+            f.render_stateful_widget(list, popup, &mut app.headers_state);
+        }
+        Some(Overlay::TableOfContents) => {
+            // Sidebar on the right side of the page
+            let width = (area.width / 2).max(30).min(area.width);
+            let popup = Rect {
+                x: area.x + area.width - width,
+                y: area.y,
+                width,
+                height: area.height,
+            };
+
+            let items: Vec<ListItem> = if app.page_headings.is_empty() {
+                vec![ListItem::new(Span::styled("No headings on this page", Style::default().fg(Color::DarkGray)))]
+            } else {
+                app.page_headings
+                    .iter()
+                    .map(|h| {
+                        let indent = "  ".repeat(h.level.saturating_sub(1) as usize);
+                        ListItem::new(Line::from(vec![
+                            Span::raw(indent),
+                            Span::styled(
+                                truncate_string(&h.text, (width as usize).saturating_sub(8 + 2 * h.level as usize)),
+                                TextStyle::Heading(h.level).style(),
+                            ),
+                        ]))
+                    })
+                    .collect()
+            };
+
+            let list = List::new(items)
+                .block(Block::default()
+                    .borders(borders())
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .title(" Contents  [Enter: jump  t/q: close] "))
+                .highlight_style(
+                    Style::default()
+                        .bg(Color::DarkGray)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .highlight_symbol("> ");
+
+            f.render_widget(Clear, popup);
+            f.render_stateful_widget(list, popup, &mut app.toc_state);
+        }
+        Some(Overlay::Links) => {
+            let popup = centered_rect(area, 90, 90);
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Min(0)])
+                .split(popup);
+
+            let filter_style = if app.links_filter_editing {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            let filter_text = if app.links_filter.is_empty() && !app.links_filter_editing {
+                Span::styled("Press / to filter", Style::default().fg(Color::DarkGray))
+            } else {
+                Span::styled(app.links_filter.clone(), Style::default().fg(Color::White))
+            };
+            let filter = Paragraph::new(Line::from(filter_text))
+                .block(Block::default().borders(borders()).border_style(filter_style).title(" Filter "));
+
+            let filtered = app.filtered_links();
+            let url_width = (popup.width as usize).saturating_sub(8);
+            let items: Vec<ListItem> = filtered
+                .iter()
+                .filter_map(|&i| app.page_links.get(i))
+                .map(|l| {
+                    ListItem::new(vec![
+                        Line::from(Span::styled(l.text.clone(), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))),
+                        Line::from(Span::styled(truncate_string(&l.url, url_width), Style::default().fg(Color::Cyan))),
+                    ])
+                })
+                .collect();
+
+            let list = List::new(items)
+                .block(Block::default()
+                    .borders(borders())
+                    .border_style(Style::default().fg(Color::Cyan))
+                    .title(format!(
+                        " {}/{} links  [Enter: open  y: copy URL  o: open in browser  q: close] ",
+                        filtered.len(),
+                        app.page_links.len()
+                    )))
+                .highlight_style(
+                    Style::default()
+                        .bg(Color::DarkGray)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .highlight_symbol("> ");
+
+            f.render_widget(Clear, popup);
+            f.render_widget(filter, chunks[0]);
+            f.render_stateful_widget(list, chunks[1], &mut app.links_state);
+            if app.links_filter_editing {
+                let x = chunks[0].x + 1 + app.links_filter.width() as u16;
+                f.set_cursor_position((x.min(chunks[0].right().saturating_sub(2)), chunks[0].y + 1));
+            }
+        }
+        Some(Overlay::Filters) => {
+            let popup = Rect {
+                x: area.x + area.width.saturating_sub(50) / 2,
+                y: area.y + area.height.saturating_sub(7) / 2,
+                width: 50.min(area.width),
+                height: 7.min(area.height),
+            };
+            let draft = &app.filter_draft;
+            let rows = [
+                ("Time", draft.time.label().to_string()),
+                ("Region", draft.region.clone().unwrap_or_else(|| "all".to_string())),
+                ("Safe search", draft.safesearch.label().to_string()),
+            ];
+            let mut lines = vec![Line::from("")];
+            for (i, (name, value)) in rows.iter().enumerate() {
+                let selected = i == app.filter_row;
+                let marker = if selected { "> " } else { "  " };
+                let value_style = if selected {
+                    Style::default().bg(Color::DarkGray).fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{}{:<12}", marker, name), Style::default().fg(Color::Gray)),
+                    Span::styled(format!("◂ {} ▸", value), value_style),
+                ]));
+            }
+
+            let popup_widget = Paragraph::new(lines).block(
+                Block::default()
+                    .borders(borders())
+                    .border_style(Style::default().fg(Color::Magenta))
+                    .title(" Filters  [h/l: change  r: reset  Enter: apply] "),
+            );
+            f.render_widget(Clear, popup);
+            f.render_widget(popup_widget, popup);
+        }
+        Some(Overlay::Help) | None => {}
+    }
+}
+
+// Stack the toasts up from just above the status bar, newest at the bottom
+fn draw_toasts(f: &mut ratatui::Frame, app: &App) {
+    let area = f.area();
+    let mut bottom = area.bottom().saturating_sub(1);
+    for toast in app.toasts.iter().rev() {
+        let width = (toast.text.width() as u16 + 4).min(60).min(area.width);
+        if bottom < area.y + 3 {
+            break;
+        }
+        let rect = Rect {
+            x: area.right().saturating_sub(width + 1),
+            y: bottom - 3,
+            width,
+            height: 3,
+        };
+        let color = if toast.error { Color::Red } else { Color::Green };
+        let widget = Paragraph::new(truncate_string(&toast.text, width.saturating_sub(4) as usize))
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(borders()).border_style(Style::default().fg(color)));
+        f.render_widget(Clear, rect);
+        f.render_widget(widget, rect);
+        bottom -= 3;
+    }
+}
+
+// Keys for the current view, listed from the keymap
+fn draw_help(f: &mut ratatui::Frame, app: &App) {
+    let area = f.area();
+    let bindings = keymap::bindings(&app.view);
+    let labels: Vec<String> = bindings.iter().map(keymap::keys_label).collect();
+    let key_width = labels.iter().map(|label| label.width()).max().unwrap_or(0);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(64) / 2,
+        y: area.y + area.height.saturating_sub(bindings.len() as u16 + 2) / 2,
+        width: 64.min(area.width),
+        height: (bindings.len() as u16 + 2).min(area.height),
+    };
+    let lines: Vec<Line> = bindings
+        .iter()
+        .zip(&labels)
+        .skip(app.help_scroll)
+        .map(|(binding, label)| {
+            let pad = key_width.saturating_sub(label.width());
+            Line::from(vec![
+                Span::styled(format!(" {}{}  ", label, " ".repeat(pad)), Style::default().fg(Color::Yellow)),
+                Span::styled(binding.help, Style::default().fg(Color::White)),
+            ])
+        })
+        .collect();
+
+    let popup_widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(borders())
+            .border_style(Style::default().fg(Color::Magenta))
+            .title(format!(" Keys: {}  [j/k: scroll  q: close] ", app.view.name())),
+    );
+    f.render_widget(Clear, popup);
+    f.render_widget(popup_widget, popup);
+}
+
+fn draw_read_later(f: &mut ratatui::Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    let header = Paragraph::new(Line::from(vec![
+        Span::styled(
+            " READ LATER ",
+            Style::default()
+                .bg(Color::Blue)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!("  {} saved articles, readable offline", app.read_later.len())),
+    ]))
+    .block(Block::default().borders(borders()).title("Reading List"));
+    f.render_widget(header, chunks[0]);
+
+    let items: Vec<ListItem> = app
+        .read_later
+        .iter()
+        .map(|entry| {
+            ListItem::new(vec![
+                Line::from(Span::styled(
+                    truncate_string(&entry.title, 70),
+                    Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                )),
+                Line::from(Span::styled(truncate_string(&entry.url, 70), Style::default().fg(Color::Cyan))),
+                Line::from(Span::styled(
+                    format!("Saved {}", entry.added.format("%Y-%m-%d %H:%M")),
+                    Style::default().fg(Color::DarkGray),
+                )),
+                Line::from(""),
+            ])
+        })
+        .collect();
+
+    if items.is_empty() {
+        let empty = Paragraph::new("\n  Nothing saved yet. Press a on a result or page to save it for later.")
+            .style(Style::default().fg(Color::Gray))
+            .block(Block::default().borders(borders()));
+        f.render_widget(empty, chunks[1]);
+    } else {
+        let list = List::new(items)
+            .block(Block::default().borders(borders()))
+            .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut app.read_later_state);
+    }
+
+    draw_status_bar(f, app, chunks[2], "j/k: Navigate  Enter: Read  d: Remove  q: Back  ?: Keys");
+}
+
+fn draw_feeds(f: &mut ratatui::Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    let visible = app.visible_feed_items();
+    let status = if app.feed_refresh.is_some() {
+        "checking for new entries...".to_string()
+    } else if app.feeds_show_all {
+        format!("{} entries", visible.len())
+    } else {
+        format!("{} unread", visible.len())
+    };
+    let header = Paragraph::new(Line::from(vec![
+        Span::styled(
+            " FEEDS ",
+            Style::default()
+                .bg(Color::Blue)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!("  {} subscriptions, {}", app.feeds.subscriptions.len(), status)),
+    ]))
+    .block(Block::default().borders(borders()).title("Feed Reader"));
+    f.render_widget(header, chunks[0]);
+
+    let items: Vec<ListItem> = visible
+        .iter()
+        .map(|&i| {
+            let (feed, item) = &app.feed_items[i];
+            let title_style = if app.feeds.is_read(&item.url) {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+            };
+            let date = item.date.map(|d| d.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+            ListItem::new(vec![
+                Line::from(Span::styled(truncate_string(&item.title, 70), title_style)),
+                Line::from(vec![
+                    Span::styled(truncate_string(feed, 40), Style::default().fg(Color::Cyan)),
+                    Span::styled(format!("  {}", date), Style::default().fg(Color::DarkGray)),
+                ]),
+                Line::from(""),
+            ])
+        })
+        .collect();
+
+    if items.is_empty() {
+        let text = if app.feeds.subscriptions.is_empty() {
+            "\n  No subscriptions yet. Press F on a page that has a feed to subscribe."
+        } else if app.feed_refresh.is_some() {
+            "\n  Fetching feeds..."
+        } else {
+            "\n  All caught up. Press u to show entries already read."
+        };
+        let empty = Paragraph::new(text)
+            .style(Style::default().fg(Color::Gray))
+            .block(Block::default().borders(borders()));
+        f.render_widget(empty, chunks[1]);
+    } else {
+        let list = List::new(items)
+            .block(Block::default().borders(borders()))
+            .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut app.feeds_state);
+    }
+
+    draw_status_bar(f, app, chunks[2], "j/k: Navigate  Enter: Read  m: Read/unread  u: Show read  r: Refresh  ?: Keys");
+}
+
+fn draw_load_error(f: &mut ratatui::Frame, app: &App) {
+    let Some(failed) = &app.load_error else {
+        return;
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    let heading = match failed.failure.status {
+        Some(status) => format!("  {}", status),
+        None => format!("  {}", failed.failure.kind.label()),
+    };
+    let header = Paragraph::new(Line::from(vec![
+        Span::styled(
+            " ERROR ",
+            Style::default()
+                .bg(Color::Red)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(heading, Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+    ]))
+    .block(Block::default().borders(borders()).title("Failed to load page"));
+    f.render_widget(header, chunks[0]);
+
+    let label = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(vec![Span::styled("  Page   ", label), Span::raw(failed.title.clone())]),
+        Line::from(vec![Span::styled("  URL    ", label), Span::styled(failed.url.clone(), Style::default().fg(Color::Cyan))]),
+        Line::from(vec![Span::styled("  Kind   ", label), Span::raw(failed.failure.kind.label())]),
+        Line::from(""),
+        Line::from(format!("  {}", failed.failure.kind.hint())),
+        Line::from(""),
+        Line::from(Span::styled(format!("  {}", failed.failure.detail), Style::default().fg(Color::Gray))),
+        Line::from(""),
+    ];
+    lines.push(Line::from(match failed.cached {
+        Some(fetched) => format!("  A cached copy from {} is available (c).", fetched.format("%Y-%m-%d %H:%M")),
+        None => "  There is no cached copy of this page.".to_string(),
+    }));
+    let body = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(borders()));
+    f.render_widget(body, chunks[1]);
+
+    draw_status_bar(f, app, chunks[2], "r: Retry  c: Cached copy  w: Wayback Machine  y: Copy URL  q: Back");
+}
+
+// Header lines for a page's metadata: "site · by author · date (age)", then
+// the description
+fn page_meta_lines(meta: &PageMeta, width: usize) -> Vec<Line<'static>> {
+    if meta.is_empty() {
+        return Vec::new();
+    }
+    let mut parts = Vec::new();
+    if let Some(site) = &meta.site_name {
+        parts.push(Span::styled(site.clone(), Style::default().fg(Color::Cyan)));
+    }
+    if let Some(author) = &meta.author {
+        parts.push(Span::styled(format!("by {}", author), Style::default().fg(Color::White)));
+    }
+    if let Some(date) = meta.published {
+        parts.push(Span::styled(
+            format!("{} ({})", date.format("%Y-%m-%d"), age_of(date)),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    let mut lines = Vec::new();
+    if !parts.is_empty() {
+        let mut spans = vec![Span::raw(" ")];
+        for (i, part) in parts.into_iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(" · ", Style::default().fg(Color::DarkGray)));
+            }
+            spans.push(part);
+        }
+        lines.push(Line::from(spans));
+    }
+    if let Some(description) = &meta.description {
+        lines.push(Line::from(Span::styled(
+            format!(" {}", truncate_string(description, width.saturating_sub(1))),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    lines
+}
+
+// Small box shown while a page is fetched in the background
+fn draw_loading(f: &mut ratatui::Frame, load: &PageLoad) {
+    let area = f.area();
+    let width = 60.min(area.width);
+    let height = 6.min(area.height);
+    let rect = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            truncate_string(&load.url, width.saturating_sub(4) as usize),
+            Style::default().fg(Color::Cyan),
+        )),
+        Line::from(Span::styled(
+            format!("{:.1}s", load.started.elapsed().as_secs_f32()),
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+    let attempt = load.attempt.load(Ordering::Relaxed);
+    if attempt > 1 {
+        lines.push(Line::from(Span::styled(
+            format!("Connection trouble, retrying (attempt {} of {})", attempt, max_attempts()),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(borders())
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(" Loading... ")
+            .title_bottom(" Esc: cancel "),
+    );
+    f.render_widget(Clear, rect);
+    f.render_widget(widget, rect);
+}
+
+// Input line over the bottom of the screen
+fn draw_prompt(f: &mut ratatui::Frame, prompt: &Prompt) {
+    let area = f.area();
+    let height = 3.min(area.height);
+    let rect = Rect {
+        x: area.x,
+        y: area.bottom() - height,
+        width: area.width,
+        height,
+    };
+
+    let input = if prompt.kind == PromptKind::Password {
+        "*".repeat(prompt.input.chars().count())
+    } else {
+        prompt.input.clone()
+    };
+    let title = match &prompt.note {
+        Some(note) => format!(" {}: {}  [Enter: go  Esc: cancel] ", prompt.kind.label(), note),
+        None => format!(" {}  [Enter: go  Esc: cancel] ", prompt.kind.label()),
+    };
+
+    // Keep the cursor in view by scrolling long input to the left
+    let inner_width = rect.width.saturating_sub(2) as usize;
+    // No room inside the borders to show the input or its cursor
+    if inner_width == 0 {
+        return;
+    }
+    let skip = (prompt.cursor + 1).saturating_sub(inner_width);
+    let visible: String = input.chars().skip(skip).collect();
+    let widget = Paragraph::new(visible)
+        .style(Style::default().fg(Color::Yellow))
+        .block(
+            Block::default()
+                .borders(borders())
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(title),
+        );
+    f.render_widget(Clear, rect);
+    f.render_widget(widget, rect);
+
+    let cursor_width = input.chars().skip(skip).take(prompt.cursor - skip).collect::<String>().width();
+    f.set_cursor_position((rect.x + 1 + cursor_width as u16, rect.y + 1));
+}
+
+fn draw_image_gallery(f: &mut ratatui::Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    let position = app
+        .gallery_state
+        .selected()
+        .map(|i| format!("  {}/{}", i + 1, app.page_images.len()))
+        .unwrap_or_default();
+
+    let header = Paragraph::new(Line::from(vec![
+        Span::styled(
+            " IMAGES ",
+            Style::default()
+                .bg(Color::Magenta)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("  "),
+        Span::styled(
+            truncate_string(&app.page_title, 50),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(position, Style::default().fg(Color::Cyan)),
+    ]))
+    .block(Block::default().borders(borders()).title(truncate_string(&app.page_url, 60)));
+    f.render_widget(header, chunks[0]);
+
+    if app.page_images.is_empty() {
+        let empty = Paragraph::new(" No images found on this page.")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(Block::default().borders(borders()).title("Images"));
+        f.render_widget(empty, chunks[1]);
+    } else if app.gallery_viewing {
+        let selected = app.gallery_state.selected().unwrap_or(0);
+        let image = &app.page_images[selected];
+        let area = chunks[1];
+
+        // Alt text above the image, art below
+        let inner_width = area.width.saturating_sub(2);
+        let art_height = area.height.saturating_sub(4);
+
+        let cached = matches!(&app.gallery_art, Some((i, w, h, _)) if *i == selected && *w == inner_width && *h == art_height);
+        if !cached {
+            let art = match &app.gallery_bytes {
+                Some((i, Some(bytes))) if *i == selected => {
+                    image_to_ascii_fit(bytes, inner_width as u32, art_height as u32)
+                        .unwrap_or_else(|| "Could not decode this image.".to_string())
+                }
+                _ => "Could not load this image (unsupported format or network error).".to_string(),
+            };
+            app.gallery_art = Some((selected, inner_width, art_height, art));
+        }
+
+        let alt = if image.alt.is_empty() { "(no alt text)".to_string() } else { image.alt.clone() };
+        let mut lines = vec![
+            Line::from(Span::styled(alt, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+            Line::from(Span::styled(truncate_string(&image.url, inner_width as usize), Style::default().fg(Color::Cyan))),
+        ];
+        if let Some((_, _, _, art)) = &app.gallery_art {
+            lines.extend(art.lines().map(|l| Line::from(Span::styled(l.to_string(), Style::default().fg(Color::White)))));
+        }
+
+        let view = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(borders())
+                .border_style(Style::default().fg(Color::Magenta))
+                .title(format!(" Image {}/{} ", selected + 1, app.page_images.len())));
+        f.render_widget(view, area);
+    } else {
+        let items: Vec<ListItem> = app
+            .page_images
+            .iter()
+            .map(|img| {
+                let alt = if img.alt.is_empty() { "(no alt text)" } else { &img.alt };
+                ListItem::new(vec![
+                    Line::from(Span::styled(
+                        truncate_string(alt, 70),
+                        Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                    )),
+                    Line::from(Span::styled(
+                        truncate_string(&img.url, 80),
+                        Style::default().fg(Color::Cyan),
+                    )),
+                ])
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().borders(borders()).title(format!(" {} images ", app.page_images.len())))
+            .highlight_style(
+                Style::default()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut app.gallery_state);
+    }
+
+    let hint = if app.gallery_viewing {
+        "j/k or n/p: Next/previous image  q: Back to list"
+    } else {
+        "j/k: Navigate  Enter: View image  q: Back to page"
+    };
+    draw_status_bar(f, app, chunks[2], hint);
+}
+
+fn draw_home(f: &mut ratatui::Frame, app: &mut App) {
+    use ratatui::layout::Alignment;
+
+    let screen = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+    let area = screen[0];
+    draw_status_bar(f, app, screen[1], "Enter: Search  Tab: Suggestions  ?: Keys  Esc: Quit");
+
+    // Calculate vertical centering
+    let logo_height = 8;
+    let search_box_height = 3;
+    let tips_height = 5;
+    let total_content_height = logo_height + search_box_height + tips_height + 4; // +4 for spacing
+    let vertical_padding = area.height.saturating_sub(total_content_height) / 2;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(vertical_padding),
+            Constraint::Length(logo_height),
+            Constraint::Length(2),
+            Constraint::Length(search_box_height),
+            Constraint::Length(2),
+            Constraint::Length(tips_height),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    // ASCII Art Logo - centered
+    let ascii_logo = vec![
+        Line::from(Span::styled(
+            "                    _            ",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            "  _ __   __ ___   _(_)_ __ ___   ",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            " | '_ \\ / _` \\ \\ / / | '_ ` _ \\  ",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            " | | | | (_| |\\ V /| | | | | | | ",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            " |_| |_|\\__,_| \\_/ |_|_| |_| |_| ",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Terminal Web Browser",
+            Style::default().fg(Color::Gray),
+        )),
+    ];
+
+    let logo = Paragraph::new(ascii_logo).alignment(Alignment::Center);
+    f.render_widget(logo, chunks[1]);
+
+    // Search box - centered with fixed width
+    let search_width = 60.min(area.width.saturating_sub(4));
+    let search_padding = (area.width.saturating_sub(search_width)) / 2;
+
+    let search_area = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(search_padding),
+            Constraint::Length(search_width),
+            Constraint::Min(0),
+        ])
+        .split(chunks[3]);
+
+    // Long input scrolls left to keep the cursor in the box
+    let inner_width = search_area[1].width.saturating_sub(2) as usize;
+    let before_cursor = &app.search_input[..app.cursor_position];
+    let mut skip = 0;
+    while skip < before_cursor.len() && before_cursor[skip..].width() >= inner_width {
+        skip += before_cursor[skip..].graphemes(true).next().map_or(1, str::len);
+    }
+
+    // Build search input with cursor
+    let input_text = if app.search_input.is_empty() {
+        Span::styled("Search the web...", Style::default().fg(Color::DarkGray))
+    } else {
+        Span::styled(&app.search_input[skip..], Style::default().fg(Color::White))
+    };
+
+    let search_box = Paragraph::new(Line::from(input_text))
+        .block(
+            Block::default()
+                .borders(borders())
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(Span::styled(
+                    " Search ",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )),
+        );
+    f.render_widget(search_box, search_area[1]);
+
+    // Set cursor position
+    let cursor_x = search_area[1].x + 1 + before_cursor[skip..].width() as u16;
+    let cursor_y = search_area[1].y + 1;
+    f.set_cursor_position((cursor_x, cursor_y));
+
+    // Tips/help text - centered
+    let tips = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Yellow)),
+            Span::styled(" to search  ", Style::default().fg(Color::Gray)),
+            Span::styled("↑/↓", Style::default().fg(Color::Yellow)),
+            Span::styled(" suggestions  ", Style::default().fg(Color::Gray)),
+            Span::styled("Esc/q", Style::default().fg(Color::Yellow)),
+            Span::styled(" to quit", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Privacy-focused browsing powered by Brave Search",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+
+    let tips_widget = Paragraph::new(tips).alignment(Alignment::Center);
+    f.render_widget(tips_widget, chunks[5]);
+
+    // Suggestions dropdown just under the search box, or the recent &
+    // frequent panel while it is empty
+    let (items, title): (Vec<ListItem>, &str) = if app.search_input.is_empty() {
+        let items = app.frecent.iter().flatten().map(frecent_item).collect();
+        (items, " Recent & frequent ")
+    } else {
+        let items = app
+            .suggestions
+            .iter()
+            .map(|s| ListItem::new(Span::styled(s.clone(), Style::default().fg(Color::White))))
+            .collect();
+        (items, "")
+    };
+    if !items.is_empty() {
+        let dropdown = Rect {
+            x: search_area[1].x,
+            y: search_area[1].bottom(),
+            width: search_area[1].width,
+            height: (items.len() as u16 + 2).min(area.bottom().saturating_sub(search_area[1].bottom())),
+        };
+        let mut state = ListState::default();
+        state.select(app.suggestion_selected);
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(borders())
+                    .border_style(Style::default().fg(Color::DarkGray))
+                    .title(Span::styled(title, Style::default().fg(Color::DarkGray))),
+            )
+            .highlight_style(Style::default().bg(Color::DarkGray).fg(Color::Yellow).add_modifier(Modifier::BOLD));
+        f.render_widget(Clear, dropdown);
+        f.render_stateful_widget(list, dropdown, &mut state);
+    }
+}
+
+// A past query, or a page with its host
+fn frecent_item(item: &Frecent) -> ListItem<'static> {
+    let (mark, text, host) = match item {
+        Frecent::Query(query) => (if accessible() { "Search: " } else { "⌕ " }, query.clone(), String::new()),
+        Frecent::Page { title, url } => (if accessible() { "Page: " } else { "↗ " }, title.clone(), display_host(url)),
+    };
+    let mut spans = vec![
+        Span::styled(mark, Style::default().fg(Color::Cyan)),
+        Span::styled(text, Style::default().fg(Color::White)),
+    ];
+    if !host.is_empty() {
+        spans.push(Span::styled(format!(" · {}", host), Style::default().fg(Color::DarkGray)));
+    }
+    ListItem::new(Line::from(spans))
+}
+
+impl App {
+    // The only place state changes in response to the outside world. Errors
+    // end the session.
+    fn update(&mut self, msg: Msg) -> Result<(), Box<dyn Error>> {
+        match msg {
+            Msg::Tick => {
+                if self.view == View::Home {
+                    self.poll_suggestions();
+                }
+                self.poll_frecent();
+                if self.view == View::SearchResults {
+                    self.poll_thumbnail();
+                    self.track_highlight();
+                }
+                self.poll_prefetch();
+                self.poll_tabs();
+                self.poll_link_preview();
+                self.poll_feed_refresh();
+                self.toasts.retain(|toast| !toast.expired());
+            }
+            Msg::PageLoaded(result) => self.finish_loading(*result),
+            // The view underneath is frozen while a page loads; Esc cancels
+            Msg::Key(KeyCode::Esc, _) if self.loading.is_some() => self.cancel_loading(),
+            Msg::Key(..) | Msg::Mouse(_) | Msg::Paste(_) if self.loading.is_some() => {}
+            Msg::Mouse(mouse) => self.handle_mouse(mouse),
+            Msg::Paste(text) => self.paste(&text),
+            Msg::Key(code, modifiers) => {
+                let result = self.handle_key(code, modifiers);
+                self.note_crash_context();
+                return result;
+            }
+        }
+        self.note_crash_context();
+        Ok(())
+    }
+
+    fn note_crash_context(&self) {
+        let Ok(mut context) = CRASH_CONTEXT.lock() else {
+            return;
+        };
+        context.view = self.view.name();
+        if context.url != self.page_url {
+            context.url.clone_from(&self.page_url);
+        }
+        if context.query != self.query {
+            context.query.clone_from(&self.query);
+        }
+    }
+
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Result<(), Box<dyn Error>> {
+        // An open prompt takes every key
+        if self.prompt.is_some() {
+            self.handle_prompt_key(code);
+            return Ok(());
+        }
+        if self.overlay == Some(Overlay::Help) {
+            self.handle_help_key(code);
+            return Ok(());
+        }
+        if self.view == View::SearchResults && self.results_filter_editing && self.overlay.is_none() {
+            self.handle_results_filter_key(code);
+            return Ok(());
+        }
+        if self.view == View::SearchResults && self.related_selected.is_some() && self.overlay.is_none() {
+            self.handle_related_key(code);
+            return Ok(());
+        }
+        // Popups take keys before the view underneath, except Ctrl-g, which
+        // opens or closes its own
+        let has_popups = matches!(self.view, View::SearchResults | View::WebPage);
+        if has_popups && self.overlay.is_some() && keymap::lookup(&self.view, code, modifiers) != Some(Action::Redirects) {
+            self.pending.clear();
+            self.handle_overlay_key(code);
+            return Ok(());
+        }
+        match self.pending.feed(&self.view, code, modifiers) {
+            Step::Pending => Ok(()),
+            Step::Run(action, count) => self.run_action(action, count),
+            Step::RunWith(Action::SetMark, c) => {
+                self.set_mark(c);
+                Ok(())
+            }
+            Step::RunWith(Action::SetQuickmark, c) => {
+                self.set_quickmark(c);
+                Ok(())
+            }
+            // On Home the digit is typed unless the box is empty and it has a page
+            Step::RunWith(Action::JumpToQuickmark, c)
+                if self.view == View::Home && (!self.search_input.is_empty() || !self.quickmarks.contains_key(&c)) =>
+            {
+                self.insert_char(c);
+                Ok(())
+            }
+            Step::RunWith(Action::JumpToQuickmark, c) => {
+                self.open_quickmark(c);
+                Ok(())
+            }
+            Step::RunWith(_, c) => {
+                self.jump_to_mark(c);
+                Ok(())
+            }
+            // Typing on the home screen
+            Step::Unbound => {
+                if let (View::Home, KeyCode::Char(c)) = (&self.view, code) {
+                    self.insert_char(c);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Motions repeat count times; other actions ignore it
+    fn run_action(&mut self, action: Action, count: Option<usize>) -> Result<(), Box<dyn Error>> {
+        let times = count.unwrap_or(1);
+        match action {
+            Action::Help if self.view == View::Home && !self.search_input.is_empty() => self.insert_char('?'),
+            Action::Help => self.open_help(),
+
+            // Home screen
+            Action::Search => self.submit_search(),
+            Action::NextSuggestion => self.select_next_suggestion(),
+            Action::PreviousSuggestion => self.select_previous_suggestion(),
+            Action::CursorLeft => self.move_cursor_left(),
+            Action::CursorRight => self.move_cursor_right(),
+            Action::DeleteChar => self.delete_char(),
+            Action::QuitIfEmpty if self.search_input.is_empty() => self.should_quit = true,
+            Action::QuitIfEmpty => self.insert_char('q'),
+            // Esc first closes the suggestions dropdown
+            Action::HomeEscape if !self.suggestions.is_empty() => self.clear_suggestions(),
+            Action::HomeEscape if self.suggestion_selected.is_some() => self.suggestion_selected = None,
+            Action::HomeEscape => self.should_quit = true,
+
+            // Lists
+            Action::Down => match self.view {
+                // A count stops at the last result rather than wrapping or loading more
+                View::SearchResults => {
+                    let below = self.results.len().saturating_sub(self.list_state.selected().unwrap_or(0) + 1);
+                    (0..times.min(below.max(1))).for_each(|_| self.next())
+                }
+                View::WebPage => self.repeat_motion(times, App::cursor_down),
+                View::ImageGallery => self.gallery_next(),
+                View::ReadLater => {
+                    let last = self.read_later.len().saturating_sub(1);
+                    let i = self.read_later_state.selected().map_or(0, |i| (i + 1).min(last));
+                    self.read_later_state.select(Some(i));
+                }
+                View::Feeds => {
+                    let last = self.visible_feed_items().len().saturating_sub(1);
+                    let i = self.feeds_state.selected().map_or(0, |i| (i + 1).min(last));
+                    self.feeds_state.select(Some(i));
+                }
+                _ => {}
+            },
+            Action::Up => match self.view {
+                View::SearchResults => {
+                    let above = self.list_state.selected().unwrap_or(0);
+                    (0..times.min(above.max(1))).for_each(|_| self.previous())
+                }
+                View::WebPage => self.repeat_motion(times, App::cursor_up),
+                View::ImageGallery => self.gallery_previous(),
+                View::ReadLater => {
+                    let i = self.read_later_state.selected().map_or(0, |i| i.saturating_sub(1));
+                    self.read_later_state.select(Some(i));
+                }
+                View::Feeds => {
+                    let i = self.feeds_state.selected().map_or(0, |i| i.saturating_sub(1));
+                    self.feeds_state.select(Some(i));
+                }
+                _ => {}
+            },
+            Action::Open => match self.view {
+                // 3Enter opens the third result
+                View::SearchResults => {
+                    if let Some(n) = count.filter(|&n| n >= 1 && n <= self.results.len()) {
+                        self.list_state.select(Some(n - 1));
+                    }
+                    self.open_selected();
+                }
+                View::ImageGallery => self.load_gallery_image(),
+                View::ReadLater => self.open_read_later_entry(),
+                View::Feeds => self.open_feed_item(),
+                _ => {}
+            },
+            Action::Close => match self.view {
+                // Go back to home instead of quitting
+                // A filter is cleared before leaving
+                View::SearchResults if self.unfiltered_results.is_some() => {
+                    self.results_filter.clear();
+                    self.apply_results_filter();
+                }
+                View::SearchResults => {
+                    self.view = View::Home;
+                    self.results.clear();
+                    self.related.clear();
+                    self.infobox = None;
+                    self.correction = None;
+                    self.answer = None;
+                    self.filters.verbatim = false;
+                    self.marked.clear();
+                }
+                View::WebPage if self.visual_anchor.is_some() => self.visual_anchor = None,
+                View::WebPage => self.back_to_results(),
+                View::ImageGallery if self.gallery_viewing => self.gallery_viewing = false,
+                View::ImageGallery => self.close_gallery(),
+                View::ReadLater => self.close_read_later(),
+                View::Feeds => self.close_feeds(),
+                View::LoadError => self.close_load_error(),
+                View::Home => {}
+            },
+            Action::Delete => self.delete_read_later_entry(),
+            Action::MoreResults => {
+                for _ in 0..times.min(MORE_RESULTS_LIMIT) {
+                    if !self.load_more_results() {
+                        break;
+                    }
+                }
+            }
+            Action::Filters => self.open_filters(),
+            Action::ReopenClosed => self.reopen_closed_page(),
+            Action::ToggleMark => self.toggle_mark(),
+            Action::FilterResults => self.results_filter_editing = true,
+            Action::RelatedSearches if self.related.is_empty() => self.notify("No related searches for this query"),
+            Action::RelatedSearches => self.related_selected = Some(0),
+            Action::SearchCorrection => match self.correction.clone() {
+                Some(correction) => self.search_for(correction.query, correction.showing.is_some()),
+                None => self.notify("No spelling suggestion for this query"),
+            },
+            Action::OpenTopResults => self.open_top_results(),
+            Action::NextTab => self.switch_tab(true),
+            Action::PreviousTab => self.switch_tab(false),
+            Action::TogglePreview => {
+                self.preview = !self.preview;
+                self.notify(if self.preview { "Preview on" } else { "Preview off" });
+            }
+            Action::NextVertical => self.switch_vertical(true),
+            Action::PreviousVertical => self.switch_vertical(false),
+
+            // Page motions
+            Action::Left => self.repeat_motion(times, App::cursor_left),
+            Action::Right => self.repeat_motion(times, App::cursor_right),
+            Action::WordForward => self.repeat_motion(times, App::cursor_next_word),
+            Action::WordBackward => self.repeat_motion(times, App::cursor_prev_word),
+            Action::ParagraphForward => self.repeat_motion(times, App::paragraph_forward),
+            Action::ParagraphBackward => self.repeat_motion(times, App::paragraph_backward),
+            // Link jumps wrap around, so only the remainder of a lap matters
+            Action::NextLink => self.repeat_motion(self.link_laps(times), App::next_link),
+            Action::PreviousLink => self.repeat_motion(self.link_laps(times), App::prev_link),
+            Action::HalfPageDown => self.scroll_page(times.saturating_mul((self.page_height / 2).max(1)), true),
+            Action::HalfPageUp => self.scroll_page(times.saturating_mul((self.page_height / 2).max(1)), false),
+            Action::PageDown => self.scroll_page(times.saturating_mul(self.page_height), true),
+            Action::PageUp => self.scroll_page(times.saturating_mul(self.page_height), false),
+            Action::Top => {
+                self.jumped_from = Some(self.page_scroll);
+                self.page_scroll = 0;
+                self.cursor_line = 0;
+                self.cursor_col = 0;
+                self.update_selected_link();
+            }
+            // With a count, go to that line; otherwise go to the end
+            Action::Bottom => {
+                self.jumped_from = Some(self.page_scroll);
+                if let Some(line_num) = count {
+                    let target = line_num.saturating_sub(1).min(self.page_content.len().saturating_sub(1));
+                    self.cursor_line = target;
+                    self.cursor_col = 0;
+                    self.ensure_cursor_visible();
+                } else {
+                    self.page_scroll = self.max_scroll();
+                    self.cursor_line = self.page_content.len().saturating_sub(1);
+                    self.cursor_col = 0;
+                }
+                self.update_selected_link();
+            }
+            // PendingKeys keeps digits as the count and marks come back as RunWith
+            Action::CountDigit
+            | Action::SetMark
+            | Action::JumpToMark
+            | Action::SetQuickmark
+            | Action::JumpToQuickmark => {}
+
+            // Page tools
+            Action::FollowLink => {
+                if let Some((url, title)) = self.get_link_at_cursor() {
+                    self.follow_link(&url, &title);
+                }
+            }
+            // Visual mode: motions extend the selection, y copies it
+            Action::Visual if self.visual_anchor.is_some() => self.visual_anchor = None,
+            Action::Visual => self.start_visual(),
+            Action::LinksPanel => self.open_links_panel(),
+            Action::TableOfContents => self.toggle_toc(),
+            Action::Gallery => self.open_gallery(),
+            // Ctrl-g shows how we got to the page, like vim's file info
+            Action::Redirects => {
+                self.overlay = match self.overlay {
+                    Some(Overlay::Redirects) => None,
+                    _ => Some(Overlay::Redirects),
+                };
+            }
+            Action::Headers => self.toggle_headers(),
+            Action::TogglePrivate => self.set_private(!private_enabled()),
+            Action::ReaderMode => self.toggle_reader_mode(),
+            Action::ToggleImages => self.toggle_images(),
+            Action::Fold if self.page_thread.is_some() => self.toggle_thread_fold(false),
+            Action::FoldAll if self.page_thread.is_some() => self.toggle_thread_fold(true),
+            Action::Fold => self.toggle_json_fold(false),
+            Action::FoldAll => self.toggle_json_fold(true),
+            Action::SaveMarkdown => self.prompt_save(PromptKind::SaveMarkdown),
+            Action::YankMarkdown => self.yank_results_markdown(),
+            Action::SaveHtml => self.prompt_save(PromptKind::SaveHtml),
+            Action::Subscribe => self.prompt_subscribe(),
+            Action::OpenPdf => self.open_pdf(),
+            Action::CommandLine => self.open_prompt(PromptKind::Command, ""),
+
+            // Shared
+            Action::OpenUrl => self.open_prompt(PromptKind::OpenUrl, ""),
+            Action::Yank => match self.view {
+                View::SearchResults if !self.marked.is_empty() => self.yank_marked(),
+                View::SearchResults => {
+                    if let Some(result) = self.list_state.selected().and_then(|i| self.results.get(i)) {
+                        let url = result.url.clone();
+                        self.yank_url(&url);
+                    }
+                }
+                View::WebPage if self.visual_anchor.is_some() => self.yank_selection(),
+                View::WebPage => {
+                    let url = self.page_url.clone();
+                    self.yank_url(&url);
+                }
+                View::LoadError => {
+                    if let Some(url) = self.load_error.as_ref().map(|failed| failed.url.clone()) {
+                        self.yank_url(&url);
+                    }
+                }
+                _ => {}
+            },
+            Action::ReadLaterAdd => self.add_to_read_later(),
+            Action::ReadLaterOpen => self.open_read_later(),
+
+            // Feeds
+            Action::ToggleRead => self.toggle_feed_item_read(),
+            Action::MarkAllRead => self.mark_all_feed_items_read(),
+            Action::ShowAll => {
+                self.feeds_show_all = !self.feeds_show_all;
+                self.feeds_state.select(None);
+                self.clamp_feeds_selection();
+            }
+            Action::Refresh => self.refresh_feeds(),
+
+            // Load errors
+            Action::Retry => self.retry_failed_load('r'),
+            Action::OpenCached => self.retry_failed_load('c'),
+            Action::OpenWayback => self.retry_failed_load('w'),
+        }
+        Ok(())
+    }
+
+    // Search for a query as if it had been typed on the home screen;
+    // verbatim turns off the engine's spelling correction
+    fn search_for(&mut self, query: String, verbatim: bool) {
+        self.filters.verbatim = verbatim;
+        self.replace_search_input(query);
+        self.suggestion_selected = None;
+        self.submit_search();
+    }
+
+    // Enter on the home screen: open a URL or bang target, or search
+    fn submit_search(&mut self) {
+        if self.search_input.is_empty() && self.suggestion_selected.is_none() {
+            return;
+        }
+        // A highlighted suggestion replaces the typed text; a recent page is
+        // opened by its URL
+        let selected = if self.search_input.is_empty() {
+            self.suggestion_selected.and_then(|i| self.frecent.as_ref()?.get(i)).map(|item| match item {
+                Frecent::Query(query) => query.clone(),
+                Frecent::Page { url, .. } => url.clone(),
+            })
+        } else {
+            self.suggestion_selected.and_then(|i| self.suggestions.get(i)).cloned()
+        };
+        if let Some(suggestion) = selected {
+            self.replace_search_input(suggestion);
+        }
+        self.clear_suggestions();
+
+        let query = self.search_input.clone();
+        let search_query = match resolve_query(&query) {
+            QueryTarget::Url(url) => {
+                self.query = query.clone();
+                self.results.clear();
+                self.add_history(&query, &query, &url);
+                self.load_page(&url, &query);
+                return;
+            }
+            QueryTarget::Search(q) => q,
+        };
+
+        // Perform search; on failure stay on home so the query can be edited,
+        // unless there is an answer to show without the engine
+        let answer = instant_answer(&search_query);
+        let serp = match search(&search_query, self.vertical, &self.filters, &self.engines) {
+            Ok(serp) => serp,
+            Err(e) => {
+                self.notify_error(format!("Search failed: {}", e));
+                if answer.is_none() {
+                    return;
+                }
+                Serp::default()
+            }
+        };
+        if serp.results.is_empty() && answer.is_none() {
+            match serp.correction {
+                Some(correction) => self.notify(format!("No results for {}. Did you mean: {}?", query, correction.query)),
+                None => self.notify(format!("No results for {}", query)),
+            }
+            return;
+        }
+        self.forget_results_filter();
+        self.results = serp.results;
+        self.related = serp.related;
+        self.related_selected = None;
+        self.infobox = serp.infobox;
+        self.correction = serp.correction;
+        self.answer = answer;
+        self.search_offset = 0;
+        self.results_exhausted = false;
+        self.marked.clear();
+        self.query = query;
+        self.list_state = ListState::default();
+        if !self.results.is_empty() {
+            self.list_state.select(Some(0));
+        }
+        self.view = View::SearchResults;
+    }
+
+    fn open_help(&mut self) {
+        self.overlay = Some(Overlay::Help);
+        self.help_scroll = 0;
+    }
+
+    fn handle_help_key(&mut self, code: KeyCode) {
+        let last = keymap::bindings(&self.view).len().saturating_sub(1);
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('?') => self.overlay = None,
+            KeyCode::Char('j') | KeyCode::Down => self.help_scroll = (self.help_scroll + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => self.help_scroll = self.help_scroll.saturating_sub(1),
+            _ => {}
+        }
+    }
+}
+
+fn draw(f: &mut ratatui::Frame, app: &mut App) {
+    app.hyperlinks.clear();
+    match app.view {
+        View::Home => draw_home(f, app),
+        View::SearchResults => draw_search_results(f, app),
+        View::WebPage => draw_web_page(f, app),
+        View::ImageGallery => draw_image_gallery(f, app),
+        View::ReadLater => draw_read_later(f, app),
+        View::Feeds => draw_feeds(f, app),
+        View::LoadError => draw_load_error(f, app),
+    }
+    // Popups cover the links underneath
+    if app.overlay.is_some() || app.loading.is_some() {
+        app.hyperlinks.clear();
+    }
+    if app.overlay == Some(Overlay::Help) {
+        draw_help(f, app);
+    }
+    draw_toasts(f, app);
+    if let Some(prompt) = &app.prompt {
+        draw_prompt(f, prompt);
+    }
+    if let Some(load) = &app.loading {
+        draw_loading(f, load);
+    }
+}
+
+// The frame's cells under each link, with OSC 8 escapes around every run so
+// the terminal makes it clickable. Ratatui would count escapes in a cell as
+// visible width, so these are drawn again on top of the finished frame.
+fn hyperlink_cells(buffer: &Buffer, links: &[Hyperlink]) -> Vec<(u16, u16, Cell)> {
+    let mut cells = Vec::new();
+    for link in links {
+        let url: String = link.url.chars().filter(|c| !c.is_control()).collect();
+        let first = cells.len();
+        let mut x = link.x;
+        while x < link.x + link.width {
+            let Some(cell) = buffer.cell((x, link.y)) else { break };
+            cells.push((x, link.y, cell.clone()));
+            // The rest of a wide character is covered by it
+            x += cell.symbol().width().max(1) as u16;
+        }
+        if cells.len() > first {
+            let (_, _, start) = &mut cells[first];
+            start.set_symbol(&format!("\x1b]8;;{}\x1b\\{}", url, start.symbol()));
+            let (_, _, end) = cells.last_mut().unwrap();
+            end.set_symbol(&format!("{}\x1b]8;;\x1b\\", end.symbol()));
+        }
+    }
+    cells
+}
+
+// Does the IO: draws, reads the terminal and the fetch thread, and hands what
+// happened to App::update as messages
+fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<Result<(), Box<dyn Error>>> {
+    let mut msgs = Vec::new();
+    let mut drawn_area = Rect::default();
+    let mut drawn_links = Vec::new();
+    loop {
+        if let Some(result) = app.loading.as_ref().and_then(PageLoad::finished) {
+            msgs.push(Msg::PageLoaded(Box::new(result)));
+        }
+        msgs.push(Msg::Tick);
+        for msg in msgs.drain(..) {
+            if let Err(e) = app.update(msg) {
+                return Ok(Err(e));
+            }
+        }
+        if app.should_quit {
+            return Ok(Ok(()));
+        }
+
+        let frame = terminal.draw(|f| {
+            draw(f, &mut app);
+            degrade(f.buffer_mut());
+        })?;
+        // Redrawn only when they change, or a resize cleared the screen
+        let cells = if hyperlinks() { hyperlink_cells(frame.buffer, &app.hyperlinks) } else { Vec::new() };
+        if (frame.area, &cells) != (drawn_area, &drawn_links) {
+            drawn_area = frame.area;
+            drawn_links = cells;
+            let backend = terminal.backend_mut();
+            backend.draw(drawn_links.iter().map(|(x, y, cell)| (*x, *y, cell)))?;
+            Backend::flush(backend)?;
+        }
+
+        if event::poll(Duration::from_millis(100))? {
+            match event::read()? {
+                Event::Key(KeyEvent { code, modifiers, .. }) => msgs.push(Msg::Key(code, modifiers)),
+                Event::Mouse(mouse) => msgs.push(Msg::Mouse(mouse)),
+                Event::Paste(text) => msgs.push(Msg::Paste(text)),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn show_about() -> Result<(), Box<dyn Error>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(8),
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ])
+                .split(f.area());
+
+            // ASCII Art Header
+            let ascii_art = vec![
+                Line::from(Span::styled("", Style::default())),
+                Line::from(Span::styled(
+                    "                    _            ",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )),
+                Line::from(Span::styled(
+                    "  _ __   __ ___   _(_)_ __ ___   ",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )),
+                Line::from(Span::styled(
+                    " | '_ \\ / _` \\ \\ / / | '_ ` _ \\  ",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )),
+                Line::from(Span::styled(
+                    " | | | | (_| |\\ V /| | | | | | | ",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )),
+                Line::from(Span::styled(
+                    " |_| |_|\\__,_| \\_/ |_|_| |_| |_| ",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )),
+                Line::from(Span::styled("", Style::default())),
+            ];
+            let header = Paragraph::new(ascii_art)
+                .block(Block::default().borders(borders()).title("Navim"));
+            f.render_widget(header, chunks[0]);
+
+            // About content
+            let about_content = vec![
+                Line::from(""),
+                Line::from(Span::styled(
+                    "  Terminal Web Browser",
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                )),
+                Line::from(Span::styled(
+                    "  Version 2.0.0",
+                    Style::default().fg(Color::Gray),
+                )),
+                Line::from(""),
+                Line::from(Span::styled(
+                    "  A vim-style terminal browser for searching and reading the web.",
+                    Style::default().fg(Color::White),
+                )),
+                Line::from(""),
+                Line::from(Span::styled("  FEATURES", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))),
+                Line::from(Span::styled("  - Intuitive keyboard navigation (arrows or hjkl)", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  - In-terminal web page rendering", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  - ASCII art image rendering", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  - Privacy-focused with Brave Search backend", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  - No tracking, no cookies, no JavaScript", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  - Optional Tor routing (--tor), including .onion sites", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  - Lightweight and fast (built in Rust)", Style::default().fg(Color::White))),
+                Line::from(""),
+                Line::from(Span::styled("  WHY NAVIM?", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))),
+                Line::from(Span::styled("  Modern browsers are bloated, track everything you do, and pull", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  you out of your terminal workflow. Navim lets you find and read", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  information without leaving the command line.", Style::default().fg(Color::White))),
+                Line::from(""),
+                Line::from(Span::styled("  KEYBINDINGS", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))),
+                Line::from(Span::styled("  ↑/↓ or j/k   Navigate / Scroll", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  Enter or →   Open selected result", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  ← or Esc     Go back", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  Space / b    Page down / up", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  g / G        Jump to top / bottom", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  q            Quit", Style::default().fg(Color::White))),
+                Line::from(""),
+                Line::from(Span::styled("  TECHNICAL", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))),
+                Line::from(Span::styled("  Built with: Rust, ratatui, reqwest, scraper, html2text", Style::default().fg(Color::White))),
+                Line::from(Span::styled("  Source: github.com/politikl/navim", Style::default().fg(Color::Cyan))),
+                Line::from(""),
+                Line::from(Span::styled("  LICENSE", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))),
+                Line::from(Span::styled("  MIT License - Free and open source", Style::default().fg(Color::White))),
+                Line::from(""),
+            ];
+            let about = Paragraph::new(about_content)
+                .block(Block::default().borders(borders()).title("About"))
+                .wrap(Wrap { trim: false });
+            f.render_widget(about, chunks[1]);
+
+            // Footer
+            let footer = Paragraph::new(" Press [q] or [Esc] to exit ")
+                .style(Style::default().fg(Color::Gray))
+                .block(Block::default().borders(borders()));
+            f.render_widget(footer, chunks[2]);
+            degrade(f.buffer_mut());
+        })?;
+
+        if event::poll(Duration::from_millis(100))? {
+            if let Event::Key(KeyEvent {
+                code: KeyCode::Char('q') | KeyCode::Esc,
+                ..
+            }) = event::read()?
+            {
+                break;
+            }
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+// Entries fuzzy-matching the filter across query, title and URL, best
+// match first; the whole history, newest first, when there is no filter
+fn filter_history<'a>(history: &'a [HistoryEntry], filter: &str) -> Vec<&'a HistoryEntry> {
+    if filter.is_empty() {
+        return history.iter().collect();
+    }
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut scored: Vec<(i64, &HistoryEntry)> = history
+        .iter()
+        .filter_map(|entry| {
+            [&entry.query, &entry.title, &entry.url]
+                .iter()
+                .filter_map(|field| matcher.fuzzy_match(field, filter))
+                .max()
+                .map(|score| (score, entry))
+        })
+        .collect();
+    // Stable, so equal scores keep the newest first
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn show_history() -> Result<(), Box<dyn Error>> {
+    let mut history = load_history();
+
+    if history.is_empty() {
+        println!("No history yet. Browse some pages to build your history.");
+        return Ok(());
+    }
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(0));
+    let mut scroll_offset = 0usize;
+    // "/" filter, narrowed as it is typed
+    let mut filter = String::new();
+    let mut filtering = false;
+    // Why the last delete failed, shown in the footer
+    let mut error: Option<String> = None;
+
+    loop {
+        let shown = filter_history(&history, &filter);
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ])
+                .split(f.area());
+
+            // Header
+            let mut header_spans = vec![Span::styled(
+                " HISTORY ",
+                Style::default()
+                    .bg(Color::Magenta)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )];
+            if filter.is_empty() && !filtering {
+                header_spans.push(Span::raw(format!("  {} entries", history.len())));
+            } else {
+                header_spans.push(Span::raw(format!("  {} of {} entries  ", shown.len(), history.len())));
+                header_spans.push(Span::styled(format!("/{}", filter), Style::default().fg(Color::Yellow)));
+                if filtering {
+                    header_spans.push(Span::styled(" ", Style::default().bg(Color::Blue)));
+                }
+            }
+            let header = Paragraph::new(Line::from(header_spans))
+                .block(Block::default().borders(borders()).title("Search History"));
+            f.render_widget(header, chunks[0]);
+
+            // History list
+            let visible_height = chunks[1].height.saturating_sub(2) as usize;
+            let items: Vec<ListItem> = shown
+                .iter()
+                .skip(scroll_offset)
+                .take(visible_height / 4 + 1)
+                .map(|entry| {
+                    let lines = vec![
+                        Line::from(vec![
+                            Span::styled(
+                                truncate_string(&entry.title, 60),
+                                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                            ),
+                        ]),
+                        Line::from(vec![
+                            Span::styled("  Query: ", Style::default().fg(Color::Gray)),
+                            Span::styled(
+                                truncate_string(&entry.query, 50),
+                                Style::default().fg(Color::Yellow),
+                            ),
+                        ]),
+                        Line::from(vec![
+                            Span::styled("  URL: ", Style::default().fg(Color::Gray)),
+                            Span::styled(
+                                truncate_string(&entry.url, 55),
+                                Style::default().fg(Color::Cyan),
+                            ),
+                        ]),
+                        Line::from(vec![
+                            Span::styled("  ", Style::default()),
+                            Span::styled(
+                                match entry.visits {
+                                    1 => entry.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+                                    n => format!("{} · {} visits", entry.timestamp.format("%Y-%m-%d %H:%M"), n),
+                                },
+                                Style::default().fg(Color::DarkGray),
+                            ),
+                        ]),
+                    ];
+                    ListItem::new(lines)
+                })
+                .collect();
+
+            let list = List::new(items)
+                .block(Block::default().borders(borders()).title(format!(
+                    " Showing {}-{} of {} ",
+                    (scroll_offset + 1).min(shown.len()),
+                    (scroll_offset + visible_height / 4 + 1).min(shown.len()),
+                    shown.len()
+                )))
+                .highlight_style(
+                    Style::default()
+                        .bg(Color::DarkGray)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .highlight_symbol(">> ");
+
+            f.render_stateful_widget(list, chunks[1], &mut list_state);
+
+            // Footer
+            let keys = if let Some(e) = &error {
+                format!(" Could not delete: {} ", e)
+            } else if filtering {
+                " Type to filter  [Enter] Done  [Esc] Clear ".to_string()
+            } else if !filter.is_empty() {
+                " [j/k] Navigate  [d] Delete  [/] Edit filter  [Esc] Clear filter  [q] Exit ".to_string()
+            } else {
+                " [j/k] Navigate  [d] Delete  [/] Filter  [q/Esc] Exit ".to_string()
+            };
+            let footer = Paragraph::new(keys)
+                .style(Style::default().fg(if error.is_some() { Color::Red } else { Color::Gray }))
+                .block(Block::default().borders(borders()).title("Keys"));
+            f.render_widget(footer, chunks[2]);
+            degrade(f.buffer_mut());
+        })?;
+
+        if event::poll(Duration::from_millis(100))? {
+            if let Event::Key(KeyEvent { code, .. }) = event::read()? {
+                error = None;
+                if filtering {
+                    match code {
+                        KeyCode::Enter => filtering = false,
+                        KeyCode::Esc => {
+                            filter.clear();
+                            filtering = false;
+                        }
+                        KeyCode::Backspace => {
+                            filter.pop();
+                        }
+                        KeyCode::Char(c) => filter.push(c),
+                        _ => {}
+                    }
+                    scroll_offset = 0;
+                    continue;
+                }
+                match code {
+                    KeyCode::Char('/') => filtering = true,
+                    // Delete the entry at the top of the list, the selected one
+                    KeyCode::Char('d') => {
+                        if let Some(entry) = shown.get(scroll_offset) {
+                            let url = entry.url.clone();
+                            match remove_from_history(&url) {
+                                Ok(()) => {
+                                    history.retain(|e| e.url != url);
+                                    let remaining = filter_history(&history, &filter).len();
+                                    scroll_offset = scroll_offset.min(remaining.saturating_sub(1));
+                                }
+                                Err(e) => error = Some(e.to_string()),
+                            }
+                        }
+                    }
+                    KeyCode::Esc if !filter.is_empty() => {
+                        filter.clear();
+                        scroll_offset = 0;
+                    }
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Char('j') | KeyCode::Down
+                        if scroll_offset < shown.len().saturating_sub(1) =>
+                    {
+                        scroll_offset += 1;
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        scroll_offset = scroll_offset.saturating_sub(1);
+                    }
+                    KeyCode::Char('J') => {
+                        scroll_offset = (scroll_offset + 5).min(shown.len().saturating_sub(1));
+                    }
+                    KeyCode::Char('K') => {
+                        scroll_offset = scroll_offset.saturating_sub(5);
+                    }
+                    KeyCode::Char('g') => {
+                        scroll_offset = 0;
+                    }
+                    KeyCode::Char('G') => {
+                        scroll_offset = shown.len().saturating_sub(1);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+// Domains or queries with how many visits each, as bars across
+fn ranked_chart<'a>(title: &'a str, totals: &'a [(String, u64)], color: Color) -> BarChart<'a> {
+    let bars: Vec<Bar> = totals
+        .iter()
+        .map(|(name, n)| {
+            Bar::default()
+                .label(Line::from(truncate_string(name, 24)))
+                .value(*n)
+                .style(Style::default().fg(color))
+                .value_style(Style::default().fg(Color::Black).bg(color))
+        })
+        .collect();
+    BarChart::default()
+        .block(Block::default().borders(borders()).title(title))
+        .direction(Direction::Horizontal)
+        .bar_width(1)
+        .bar_gap(0)
+        .data(BarGroup::default().bars(&bars))
+}
+
+// Visits over time as columns, as wide as the area allows
+fn timeline_chart<'a>(title: String, columns: Vec<(String, u64)>, area: Rect, color: Color) -> BarChart<'a> {
+    let count = columns.len().max(1) as u16;
+    let bar_width = ((area.width.saturating_sub(2) + 1) / count).saturating_sub(1).max(1);
+    let bars: Vec<Bar> = columns
+        .into_iter()
+        .map(|(label, n)| {
+            Bar::default()
+                .label(Line::from(label))
+                .value(n)
+                .style(Style::default().fg(color))
+                .value_style(Style::default().fg(Color::Black).bg(color))
+        })
+        .collect();
+    BarChart::default()
+        .block(Block::default().borders(borders()).title(title))
+        .bar_width(bar_width)
+        .bar_gap(1)
+        .data(BarGroup::default().bars(&bars))
+}
+
+// `navim stats`: top domains and queries and when the browsing happens
+fn show_stats() -> Result<(), Box<dyn Error>> {
+    let stats = load_stats()?;
+
+    if stats.pages == 0 {
+        println!("No history yet. Browse some pages to build your history.");
+        return Ok(());
+    }
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(3),
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(30),
+                    Constraint::Percentage(30),
+                    Constraint::Length(3),
+                ])
+                .split(f.area());
+
+            // Header
+            let mut summary = format!("  {} pages · {} visits", stats.pages, stats.visits);
+            if let Some(first) = stats.first_visit {
+                summary.push_str(&format!(" since {}", first.format("%Y-%m-%d")));
+            }
+            let header = Paragraph::new(Line::from(vec![
+                Span::styled(
+                    " STATS ",
+                    Style::default().bg(Color::Magenta).fg(Color::White).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(summary),
+            ]))
+            .block(Block::default().borders(borders()).title("History Statistics"));
+            f.render_widget(header, chunks[0]);
+
+            let top = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(chunks[1]);
+            f.render_widget(ranked_chart("Top domains", &stats.top_domains, Color::Cyan), top[0]);
+            f.render_widget(ranked_chart("Top queries", &stats.top_queries, Color::Yellow), top[1]);
+
+            let days = stats.per_day.iter().map(|(day, n)| (day.format("%d").to_string(), *n)).collect();
+            let title = format!("Visits per day, last {} days", STATS_DAYS);
+            f.render_widget(timeline_chart(title, days, chunks[2], Color::Green), chunks[2]);
+
+            let hours = stats.per_hour.iter().enumerate().map(|(hour, n)| (format!("{:02}", hour), *n)).collect();
+            f.render_widget(timeline_chart("Busiest hours".to_string(), hours, chunks[3], Color::Blue), chunks[3]);
+
+            // Footer
+            let footer = Paragraph::new(" [q/Esc] Exit ")
+                .style(Style::default().fg(Color::Gray))
+                .block(Block::default().borders(borders()).title("Keys"));
+            f.render_widget(footer, chunks[4]);
+            degrade(f.buffer_mut());
+        })?;
+
+        if event::poll(Duration::from_millis(100))? {
+            if let Event::Key(KeyEvent {
+                code: KeyCode::Char('q') | KeyCode::Esc,
+                ..
+            }) = event::read()?
+            {
+                break;
+            }
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+// Options given on the command line
+struct CliOptions {
+    query: String,
+    filters: SearchFilters,
+    vertical: Vertical,
+    engines: Option<Vec<String>>,      // Overrides the configured engines
+    tor: bool,                         // Route every request through Tor
+    https_only: bool,                  // Upgrade http:// pages and refuse plaintext ones
+    no_images: bool,                   // Render pages without their images
+    accessible: bool,                  // Linear output without borders or glyphs, for screen readers
+    offline_fixtures: Option<PathBuf>, // Read every page and SERP from this directory
+    verbose: bool,                     // Log to navim.log in the state directory
+    dump: bool,                        // Print the page as text instead of starting the UI
+    batch: bool,                       // Read queries from stdin and print their results
+    json: bool,                        // Batch output as JSON lines
+    output: Option<String>,            // Write the results to this .csv or .json file instead
+    clear_history: bool,               // Delete the history, once confirmed, and exit
+    private: bool,                     // Write no history, cache or cookies to disk
+}
+
+// Pull flags out of the arguments; everything else forms the query
+fn parse_args(args: &[String]) -> Result<CliOptions, Box<dyn Error>> {
+    let mut words = Vec::new();
+    let mut filters = SearchFilters::default();
+    let mut vertical = Vertical::default();
+    let mut engines = None;
+    let mut tor = false;
+    let mut https_only = false;
+    let mut no_images = false;
+    let mut accessible = false;
+    let mut offline_fixtures = None;
+    let mut verbose = false;
+    let mut dump = false;
+    let mut batch = false;
+    let mut json = false;
+    let mut output = None;
+    let mut clear_history = false;
+    let mut private = false;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--time" => {
+                let value = iter.next().ok_or("--time needs a value (day, week, month, year)")?;
+                filters.time = TimeRange::parse(value)
+                    .ok_or_else(|| format!("Unknown time range '{}' (use day, week, month or year)", value))?;
+            }
+            "--region" => {
+                let value = iter.next().ok_or("--region needs a country code (e.g. us, de)")?;
+                filters.region = match value.to_lowercase().as_str() {
+                    "all" => None,
+                    code => Some(code.to_string()),
+                };
+            }
+            "--safe" => {
+                let value = iter.next().ok_or("--safe needs a value (off, moderate, strict)")?;
+                filters.safesearch = SafeSearch::parse(value)
+                    .ok_or_else(|| format!("Unknown safe search level '{}' (use off, moderate or strict)", value))?;
+            }
+            "--engines" => {
+                let value = iter.next().ok_or("--engines needs a comma-separated list (e.g. brave,duckduckgo)")?;
+                let names: Vec<String> = value.split(',').map(|s| s.trim().to_lowercase()).collect();
+                if let Some(unknown) = names.iter().find(|n| find_provider(n).is_none()) {
+                    let known: Vec<&str> = PROVIDERS.iter().map(|p| p.name()).collect();
+                    return Err(format!("Unknown engine '{}' (available: {})", unknown, known.join(", ")).into());
+                }
+                engines = Some(names);
+            }
+            // Meta-search across every engine
+            "--meta" => engines = Some(PROVIDERS.iter().map(|p| p.name().to_string()).collect()),
+            "--tor" => tor = true,
+            "--https-only" => https_only = true,
+            "--no-images" => no_images = true,
+            "--accessible" => accessible = true,
+            "--offline-fixtures" => {
+                let value = iter.next().ok_or("--offline-fixtures needs a directory")?;
+                offline_fixtures = Some(expand_tilde(value));
+            }
+            "-v" | "--verbose" => verbose = true,
+            "--dump" => dump = true,
+            "--batch" => batch = true,
+            "--json" => json = true,
+            "--output" => output = Some(iter.next().ok_or("--output needs a .csv or .json file")?.clone()),
+            "--clear-history" => clear_history = true,
+            "--private" => private = true,
+            "--news" => vertical = Vertical::News,
+            "--images" => vertical = Vertical::Images,
+            "--videos" => vertical = Vertical::Videos,
+            _ => words.push(arg.clone()),
+        }
+    }
+
+    Ok(CliOptions {
+        query: words.join(" "),
+        filters,
+        vertical,
+        engines,
+        tor,
+        https_only,
+        no_images,
+        accessible,
+        offline_fixtures,
+        verbose,
+        dump,
+        batch,
+        json,
+        output,
+        clear_history,
+        private,
+    })
+}
+
+// One query's outcome in --batch --json output
+#[derive(Serialize)]
+struct BatchOutput<'a> {
+    query: &'a str,
+    results: &'a [SearchResult],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// --batch: run one query per line of stdin, printing each query's results
+// as text or a JSON line. Blank lines and lines starting with # are skipped.
+fn run_batch(options: &CliOptions) -> Result<(), Box<dyn Error>> {
+    let engines = options.engines.clone().unwrap_or_else(|| config().engines.clone());
+    let mut stdout = io::stdout().lock();
+    let mut exported: Vec<(String, Vec<SearchResult>)> = Vec::new();
+
+    for line in io::stdin().lines() {
+        let line = line?;
+        let query = line.trim();
+        if query.is_empty() || query.starts_with('#') {
+            continue;
+        }
+
+        let outcome = match resolve_query(query) {
+            // Bangs with a URL template have nothing to search
+            QueryTarget::Url(url) => Ok(vec![SearchResult {
+                title: query.to_string(),
+                display_url: display_host(&url),
+                url,
+                ..Default::default()
+            }]),
+            QueryTarget::Search(search_query) => {
+                search(&search_query, options.vertical, &options.filters, &engines).map(|serp| serp.results)
+            }
+        };
+        let (results, error) = match outcome {
+            Ok(results) => (results, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        // With --output everything goes to the file at the end
+        if options.output.is_some() {
+            if let Some(error) = error {
+                eprintln!("{}: {}", query, error);
+            }
+            exported.push((query.to_string(), results));
+            continue;
+        }
+
+        let written = if options.json {
+            let output = BatchOutput {
+                query,
+                results: &results,
+                error,
+            };
+            writeln!(stdout, "{}", serde_json::to_string(&output)?)
+        } else {
+            let mut text = format!("## {}\n", query);
+            if let Some(error) = error {
+                text.push_str(&format!("error: {}\n", error));
+            }
+            for (i, result) in results.iter().enumerate() {
+                text.push_str(&format!("{}. {}\n   {}\n", i + 1, result.title, result.url));
+                if !result.description.is_empty() {
+                    text.push_str(&format!("   {}\n", result.description));
+                }
+            }
+            writeln!(stdout, "{}", text)
+        };
+        match written {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            other => other?,
+        }
+        stdout.flush()?;
+    }
+
+    if let Some(file) = &options.output {
+        let searches: Vec<(&str, &[SearchResult])> = exported.iter().map(|(q, r)| (q.as_str(), r.as_slice())).collect();
+        let text = results_export(file, &searches, search_engine(options.vertical, &engines))?;
+        let count = exported.iter().map(|(_, results)| results.len()).sum();
+        return write_export(&text, file, count, "results");
+    }
+    Ok(())
+}
+
+// --output: search once and write the results to a file instead of showing them
+fn run_output(options: &CliOptions, file: &str) -> Result<(), Box<dyn Error>> {
+    let QueryTarget::Search(search_query) = resolve_query(&options.query) else {
+        return Err("--output needs a search query".into());
+    };
+    if search_query.is_empty() {
+        return Err("--output needs a search query".into());
+    }
+    let engines = options.engines.clone().unwrap_or_else(|| config().engines.clone());
+    let results = search(&search_query, options.vertical, &options.filters, &engines)?.results;
+    let text = results_export(file, &[(&options.query, &results)], search_engine(options.vertical, &engines))?;
+    write_export(&text, file, results.len(), "results")
+}
+
+// Page text followed by a numbered list of its links, like lynx -dump.
+// With hyperlinks, link text is wrapped in OSC 8 escapes to be clickable.
+fn dump_text(page: &RenderedPage, hyperlinks: bool) -> String {
+    let mut out = String::new();
+    for (line_num, line) in page.text.lines().enumerate() {
+        let line = line.trim_end();
+        if !hyperlinks {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+        let mut links: Vec<&PageLink> = page.links.iter().filter(|l| l.line == line_num).collect();
+        links.sort_by_key(|l| l.col_start);
+        let mut open = None;
+        for (i, c) in line.chars().enumerate() {
+            if open.is_some_and(|end| i >= end) {
+                out.push_str("\x1b]8;;\x1b\\");
+                open = None;
+            }
+            if open.is_none() {
+                if let Some(link) = links.iter().find(|l| l.col_start == i && l.col_end > i) {
+                    let url: String = link.url.chars().filter(|c| !c.is_control()).collect();
+                    out.push_str(&format!("\x1b]8;;{}\x1b\\", url));
+                    open = Some(link.col_end);
+                }
+            }
+            out.push(c);
+        }
+        if open.is_some() {
+            out.push_str("\x1b]8;;\x1b\\");
+        }
+        out.push('\n');
+    }
+
+    let mut urls: Vec<&str> = Vec::new();
+    for link in &page.links {
+        if !urls.contains(&link.url.as_str()) {
+            urls.push(&link.url);
+        }
+    }
+    if !urls.is_empty() {
+        out.push_str("\nReferences\n\n");
+        for (i, url) in urls.iter().enumerate() {
+            out.push_str(&format!("{:>4}. {}\n", i + 1, url));
+        }
+    }
+    out
+}
+
+// --clear-history: delete every history entry once the user says yes
+fn run_clear_history() -> Result<(), Box<dyn Error>> {
+    let count = load_history().len();
+    if count == 0 {
+        println!("History is already empty.");
+        return Ok(());
+    }
+    print!("Delete all {} history entries? [y/N] ", count);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        clear_history()?;
+        println!("History cleared.");
+    } else {
+        println!("Nothing deleted.");
+    }
+    Ok(())
+}
+
+// The rest of `navim <name> <subcommand> ...` when the subcommand is one of
+// `known`; anything else is a search that happens to start with the name
+fn subcommand<'a>(query: &'a str, name: &str, known: &[&str]) -> Option<&'a str> {
+    let command = query.strip_prefix(name)?.strip_prefix(' ')?;
+    known.contains(&command.split_whitespace().next()?).then_some(command)
+}
+
+// `navim feeds add <url>`, `navim feeds remove <url>` and `navim feeds list`
+fn manage_feeds(command: &str) -> Result<(), Box<dyn Error>> {
+    let mut store = load_feeds();
+    let (action, arg) = command.trim().split_once(' ').unwrap_or((command.trim(), ""));
+    match action {
+        "add" if !arg.is_empty() => {
+            let url = normalize_url(arg);
+            let feed = fetch_feed(&url)?;
+            if store.subscribe(&url, &feed.title) {
+                save_feeds(&store)?;
+                println!("Subscribed to {} ({} entries)", feed.title, feed.items.len());
+            } else {
+                println!("Already subscribed to {}", url);
+            }
+        }
+        "remove" if !arg.is_empty() => {
+            let url = normalize_url(arg);
+            let before = store.subscriptions.len();
+            store.subscriptions.retain(|s| s.url != url && s.url != arg.trim());
+            if store.subscriptions.len() == before {
+                return Err(format!("Not subscribed to {}", url).into());
+            }
+            save_feeds(&store)?;
+            println!("Unsubscribed from {}", url);
+        }
+        "list" => {
+            if store.subscriptions.is_empty() {
+                println!("No subscriptions yet.");
+            }
+            for sub in &store.subscriptions {
+                println!("{}\n  {}", sub.title, sub.url);
+            }
+        }
+        _ => return Err("Usage: navim feeds [add <url> | remove <url> | list]".into()),
+    }
+    Ok(())
+}
+
+// `navim bookmarks import <file.html>` and `navim bookmarks list`
+fn manage_bookmarks(command: &str) -> Result<(), Box<dyn Error>> {
+    let mut store = load_bookmarks();
+    let (action, arg) = command.trim().split_once(' ').unwrap_or((command.trim(), ""));
+    match action {
+        "import" if !arg.is_empty() => {
+            let path = expand_tilde(arg.trim());
+            let html = fs::read_to_string(&path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+            let bookmarks = parse_netscape_bookmarks(&html);
+            if bookmarks.is_empty() {
+                return Err(format!("No bookmarks found in {}", path.display()).into());
+            }
+            let total = bookmarks.len();
+            let added = bookmarks.into_iter().filter(|b| store.merge(b.clone())).count();
+            save_bookmarks(&store)?;
+            println!("Imported {} bookmarks ({} already saved)", added, total - added);
+        }
+        "export" => write_export(&bookmarks_to_html(&store.bookmarks), arg.trim(), store.bookmarks.len(), "bookmarks")?,
+        "list" => {
+            if store.bookmarks.is_empty() {
+                println!("No bookmarks yet.");
+            }
+            for bookmark in &store.bookmarks {
+                if bookmark.tags.is_empty() {
+                    println!("{}\n  {}", bookmark.title, bookmark.url);
+                } else {
+                    println!("{}\n  {}\n  [{}]", bookmark.title, bookmark.url, bookmark.tags.join(", "));
+                }
+            }
+        }
+        _ => return Err("Usage: navim bookmarks [import <file.html> | export [file.html] | list]".into()),
+    }
+    Ok(())
+}
+
+// `navim history export json|csv [file]`
+fn manage_history(command: &str) -> Result<(), Box<dyn Error>> {
+    let usage = "Usage: navim history export <json|csv> [file]";
+    let mut words = command.split_whitespace();
+    if words.next() != Some("export") {
+        return Err(usage.into());
+    }
+    let format = words.next().ok_or(usage)?;
+    let path = words.collect::<Vec<_>>().join(" ");
+    let history = load_history();
+    let text = match format {
+        "json" => serde_json::to_string_pretty(&history)? + "\n",
+        "csv" => history_to_csv(&history),
+        _ => return Err(usage.into()),
+    };
+    write_export(&text, &path, history.len(), "history entries")
+}
+
+// Print an export, or write it to a file and say how much went there
+fn write_export(text: &str, path: &str, count: usize, what: &str) -> Result<(), Box<dyn Error>> {
+    if path.is_empty() {
+        print!("{}", text);
+        return Ok(());
+    }
+    let path = expand_tilde(path);
+    fs::write(&path, text).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+    println!("Exported {} {} to {}", count, what, path.display());
+    Ok(())
+}
+
+// --dump: print the URL, or the top result for a query, without the UI
+fn run_dump(options: &CliOptions) -> Result<(), Box<dyn Error>> {
+    if options.query.is_empty() {
+        return Err("--dump needs a URL or a search query".into());
+    }
+    let url = match resolve_query(&options.query) {
+        QueryTarget::Url(url) => url,
+        QueryTarget::Search(search_query) => {
+            let engines = options.engines.clone().unwrap_or_else(|| config().engines.clone());
+            let results = search(&search_query, Vertical::Web, &options.filters, &engines)?.results;
+            results
+                .into_iter()
+                .map(|r| r.url)
+                .find(|u| !u.is_empty())
+                .ok_or("No results found.")?
+        }
+    };
+
+    let mut page = fetch_page(&url, false, &CancelToken::default(), &|_| {})?;
+    // The server's error page is still printed, but the dump fails
+    let failed = page.stats.and_then(|stats| stats.error_status());
+    // Nothing to unfold in a dump, so print JSON in full
+    if let Some(mut doc) = page.json.take() {
+        doc.folded.clear();
+        page = doc.render();
+    }
+    let mut stdout = io::stdout().lock();
+    // A closed pipe (e.g. | head) is not an error
+    let links = hyperlinks() && stdout.is_terminal();
+    match stdout.write_all(dump_text(&page, links).as_bytes()) {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => match failed {
+            Some(status) => Err(format!("HTTP {}", status).into()),
+            None => Ok(()),
+        },
+    }
+}
+
+// -v logs to a file, since the terminal belongs to the UI. Only navim's own
+// events are kept; the HTTP stack's are too noisy to be useful. Nothing is
+// logged while private mode is on, since errors can carry URLs and queries.
+fn init_logging() -> Result<(), Box<dyn Error>> {
+    use tracing_subscriber::filter::{dynamic_filter_fn, Targets};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    let path = get_state_dir().join("navim.log");
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(Mutex::new(file)).with_ansi(false))
+        .with(Targets::new().with_target("navim", tracing::Level::DEBUG))
+        .with(dynamic_filter_fn(|_, _| !private_enabled()))
+        .init();
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "navim started");
+    Ok(())
+}
+
+// What the session was doing, for the crash report
+struct CrashContext {
+    view: &'static str,
+    url: String,
+    query: String,
+}
+
+static CRASH_CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    view: "",
+    url: String::new(),
+    query: String::new(),
+});
+
+// A panic mid-session would otherwise leave the terminal in raw mode on the
+// alternate screen with no cursor. Put it back before the message is
+// printed, and save a report with what was open. Background fetch threads
+// that panic only fail their load, so the UI is left alone for those.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!(%info, "panic");
+        if thread::current().name() != Some("main") {
+            default_hook(info);
+            return;
+        }
+        if crossterm::terminal::is_raw_mode_enabled().unwrap_or(false) {
+            disable_raw_mode().ok();
+            execute!(
+                io::stdout(),
+                LeaveAlternateScreen,
+                DisableMouseCapture,
+                DisableBracketedPaste,
+                crossterm::cursor::Show
+            )
+            .ok();
+        }
+        default_hook(info);
+        let report = crash_report(info);
+        let path = get_state_dir().join(format!("crash-{}.txt", Local::now().format("%Y%m%d-%H%M%S")));
+        match fs::write(&path, report) {
+            Ok(()) => eprintln!("\nnavim crashed. A report was saved to {}", path.display()),
+            Err(e) => eprintln!("\nnavim crashed, and the report could not be saved: {}", e),
+        }
+    }));
+}
+
+fn crash_report(info: &std::panic::PanicHookInfo) -> String {
+    // The lock may be poisoned if the panic happened while it was held
+    let context = CRASH_CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    // Private mode keeps what was open out of the report
+    let (url, query) = if private_enabled() {
+        ("(private mode)", "(private mode)")
+    } else {
+        (context.url.as_str(), context.query.as_str())
+    };
+    format!(
+        "navim {} crashed at {}\n\n{}\n\nView: {}\nURL: {}\nQuery: {}\n\nBacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        info,
+        context.view,
+        url,
+        query,
+        std::backtrace::Backtrace::force_capture(),
+    )
+}
+
+pub fn run() -> Result<(), Box<dyn Error>> {
+    install_panic_hook();
+    let args: Vec<String> = env::args().collect();
+    let options = parse_args(&args[1..])?;
+    if options.verbose {
+        init_logging()?;
+    }
+    TOR_MODE.store(options.tor, Ordering::Relaxed);
+    PRIVATE_MODE.store(options.private, Ordering::Relaxed);
+    HTTPS_ONLY.store(options.https_only || config().https_only, Ordering::Relaxed);
+    ACCESSIBLE.store(options.accessible || config().accessible, Ordering::Relaxed);
+    IMAGES_ENABLED.store(!options.no_images && !accessible(), Ordering::Relaxed);
+    if let Some(dir) = &options.offline_fixtures {
+        use_fixtures(dir.clone());
+    }
+
+    if options.clear_history {
+        return run_clear_history();
+    }
+    if options.dump {
+        return run_dump(&options);
+    }
+    if options.batch {
+        return run_batch(&options);
+    }
+    if let Some(file) = &options.output {
+        return run_output(&options, file);
+    }
+
+    // No query - show home screen
+    if options.query.is_empty() {
+        let mut app = App::new_home();
+        app.filters = options.filters;
+        app.vertical = options.vertical;
+        if let Some(engines) = options.engines {
+            app.engines = engines;
+        }
+        return run_tui(app);
+    }
+
+    let query = options.query;
+
+    // Check for about command
+    if query.to_lowercase() == "about" {
+        return show_about();
+    }
+
+    // Check for history command
+    if query == "-h" {
+        return show_history();
+    }
+
+    if query == "stats" {
+        return show_stats();
+    }
+
+    if query == "clear-cookies" {
+        return clear_cookies();
+    }
+
+    // Feed reader: `navim feeds`, `navim feeds add|remove <url>`, `navim feeds list`
+    if query == "feeds" {
+        let mut app = App::new_home();
+        app.open_feeds();
+        return run_tui(app);
+    }
+    if let Some(command) = subcommand(&query, "feeds", &["add", "remove", "list"]) {
+        return manage_feeds(command);
+    }
+
+    // `navim history export json|csv [file]`
+    if let Some(command) = subcommand(&query, "history", &["export"]) {
+        return manage_history(command);
+    }
+
+    // Bookmarks: `navim bookmarks import|export <file.html>`, `navim bookmarks list`
+    if query == "bookmarks" {
+        return manage_bookmarks("list");
+    }
+    if let Some(command) = subcommand(&query, "bookmarks", &["import", "export", "list"]) {
+        return manage_bookmarks(command);
+    }
+
+    // `navim define <word>` is the !def bang, and `navim hn` opens the Hacker
+    // News front page
+    let query = match query.strip_prefix("define ") {
+        Some(word) => format!("!def {}", word.trim()),
+        None if query == "hn" => HACKER_NEWS_URL.to_string(),
+        None => query,
+    };
+
+    // Check for read-later command
+    if query == "readlater" {
+        let mut app = App::new_home();
+        app.open_read_later();
+        return run_tui(app);
+    }
+
+    let app = match resolve_query(&query) {
+        QueryTarget::Url(url) => {
+            println!("Opening: {}...", url);
+            let mut app = App::new(Vec::new(), query.clone());
+            app.filters = options.filters;
+            app.vertical = options.vertical;
+            if let Some(engines) = options.engines {
+                app.engines = engines;
+            }
+            app.add_history(&query, &query, &url);
+            app.load_page(&url, &query);
+            app
+        }
+        QueryTarget::Search(search_query) => {
+            println!("Searching for: {}...", query);
+
+            let engines = options.engines.unwrap_or_else(|| config().engines.clone());
+            // An answer worked out locally doesn't need the engine to be reachable
+            let answer = instant_answer(&search_query);
+            let serp = match search(&search_query, options.vertical, &options.filters, &engines) {
+                Ok(serp) => serp,
+                Err(e) if answer.is_some() => {
+                    eprintln!("Search failed: {}", e);
+                    Serp::default()
+                }
+                Err(e) => return Err(e),
+            };
+
+            if serp.results.is_empty() && answer.is_none() {
+                println!("No results found.");
+                return Ok(());
+            }
+            let mut app = App::new(serp.results, query);
+            app.related = serp.related;
+            app.infobox = serp.infobox;
+            app.correction = serp.correction;
+            app.answer = answer;
+            app.filters = options.filters;
+            app.vertical = options.vertical;
+            app.engines = engines;
+            app
+        }
+    };
+
+    run_tui(app)
+}
+
+// Run the interactive interface until the user quits
+fn run_tui(app: App) -> Result<(), Box<dyn Error>> {
+    // Setup terminal
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    // Run app
+    let res = run_app(&mut terminal, app);
+
+    // Restore terminal
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture, DisableBracketedPaste)?;
+    terminal.show_cursor()?;
+
+    match res {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(n: usize) -> Vec<SearchResult> {
+        (0..n)
+            .map(|i| SearchResult {
+                title: format!("Result {}", i),
+                url: format!("https://example.com/{}", i),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    // A load whose thread never answers; the test sends Msg::PageLoaded itself
+    fn loading(url: &str) -> PageLoad {
+        let (_, rx) = mpsc::channel();
+        PageLoad {
+            url: url.to_string(),
+            title: String::new(),
+            started: Instant::now(),
+            cancel: CancelToken::default(),
+            attempt: Arc::default(),
+            rx,
+        }
+    }
+
+    fn key(app: &mut App, code: KeyCode) {
+        app.update(Msg::Key(code, KeyModifiers::NONE)).unwrap();
+    }
+
+    #[test]
+    fn esc_from_the_results_goes_home() {
+        let mut app = App::new(results(3), "rust".to_string());
+        key(&mut app, KeyCode::Esc);
+        assert!(app.view == View::Home);
+        assert!(app.results.is_empty());
+    }
+
+    #[test]
+    fn a_count_stops_at_the_last_result() {
+        let mut app = App::new(results(3), "rust".to_string());
+        key(&mut app, KeyCode::Char('9'));
+        key(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.list_state.selected(), Some(2));
+    }
+
+    #[test]
+    fn the_end_of_the_results_is_not_fetched_again() {
+        let mut app = App::new(results(3), "rust".to_string());
+        app.results_exhausted = true;
+        app.list_state.select(Some(2));
+        key(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.list_state.selected(), Some(0), "wraps to the first result");
+        assert_eq!(app.search_offset, 0);
+        assert!(app.toasts.iter().any(|t| t.text == "No more results" && !t.error));
+    }
+
+    #[test]
+    fn yanking_on_an_empty_page_copies_nothing() {
+        let mut app = App::new(results(3), "rust".to_string());
+        app.view = View::WebPage;
+        app.visual_anchor = Some(0);
+        app.yank_selection();
+        assert!(app.visual_anchor.is_none());
+        assert!(app.toasts.iter().any(|t| t.text == "Nothing to copy"));
+    }
+
+    #[test]
+    fn typing_after_an_accepted_suggestion_appends_to_it() {
+        let mut app = App::new(Vec::new(), String::new());
+        app.replace_search_input("café crème".to_string());
+        app.insert_char('s');
+        assert_eq!(app.search_input, "café crèmes");
+        assert_eq!(app.cursor_position, app.search_input.len());
+    }
+
+    #[test]
+    fn a_loaded_page_is_shown() {
+        let mut app = App::new(results(3), "rust".to_string());
+        app.loading = Some(loading("https://example.com/0"));
+        let page = RenderedPage {
+            text: "first line\nsecond line".to_string(),
+            ..Default::default()
+        };
+        app.update(Msg::PageLoaded(Box::new(Ok(page)))).unwrap();
+        assert!(app.view == View::WebPage);
+        assert!(app.loading.is_none());
+        assert_eq!(app.page_url, "https://example.com/0");
+        assert_eq!(app.page_content, ["first line", "second line"]);
+    }
+
+    #[test]
+    fn keys_wait_while_a_page_loads_and_esc_cancels_it() {
+        let mut app = App::new(results(3), "rust".to_string());
+        app.loading = Some(loading("https://example.com/0"));
+        key(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.list_state.selected(), Some(0));
+        key(&mut app, KeyCode::Esc);
+        assert!(app.loading.is_none());
+        assert!(app.view == View::SearchResults);
+    }
+}
@@ -0,0 +1,351 @@
+// Golden-output tests: render saved pages, SERPs and answers from
+// tests/fixtures and compare with the expected output in tests/golden. After
+// an intended change to rendering, regenerate the expected files with
+//
+//     UPDATE_GOLDEN=1 cargo test --test golden
+
+use navim::{
+    correction_with, fetch_page_with, instant_answer_with, search_with, vertical_search_with, CancelToken, Correction,
+    Fixtures, RenderedPage, Vertical,
+};
+use std::fs;
+use std::path::PathBuf;
+
+fn fixtures() -> Fixtures {
+    Fixtures::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"))
+}
+
+fn check(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    assert!(
+        actual == expected,
+        "{} differs from the rendered output:\n{}\n(run with UPDATE_GOLDEN=1 to accept it)",
+        name,
+        actual
+    );
+}
+
+// The page text followed by where each link is and where it goes
+fn describe(page: &RenderedPage) -> String {
+    let mut out = page.text.clone();
+    out.push_str("\n--- links ---\n");
+    for link in &page.links {
+        out.push_str(&format!("{}:{}-{} {} -> {}\n", link.line, link.col_start, link.col_end, link.text, link.url));
+    }
+    out.push_str("--- headings ---\n");
+    for heading in &page.headings {
+        out.push_str(&format!("{} h{} {}\n", heading.line, heading.level, heading.text));
+    }
+    if let Some(url) = &page.final_url {
+        out.push_str(&format!("--- final url ---\n{}\n", url));
+    }
+    out
+}
+
+fn render(url: &str) -> RenderedPage {
+    fetch_page_with(url, false, &CancelToken::default(), &fixtures()).unwrap()
+}
+
+#[test]
+fn article() {
+    let page = render("https://example.com/article");
+    assert_eq!(page.meta.site_name.as_deref(), Some("Example Blog"));
+    assert_eq!(page.meta.author.as_deref(), Some("Sam Rivera"));
+    check("article.txt", &describe(&page));
+}
+
+#[test]
+fn meta_refresh_is_followed() {
+    let page = render("https://example.com/moved");
+    assert_eq!(page.final_url.as_deref(), Some("https://example.com/article"));
+    check("moved.txt", &describe(&page));
+}
+
+#[test]
+fn meta_refresh_to_a_local_file_is_ignored() {
+    let page = render("https://example.com/refresh-to-file");
+    assert_eq!(page.final_url, None);
+    check("refresh_to_file.txt", &describe(&page));
+}
+
+#[test]
+fn http_redirect_is_followed() {
+    let page = render("https://example.com/old-article");
+    assert_eq!(page.final_url.as_deref(), Some("https://example.com/article"));
+    assert_eq!(page.redirects.len(), 1);
+    assert_eq!(page.redirects[0].url, "https://example.com/old-article");
+}
+
+#[test]
+fn redirect_to_a_local_file_is_refused() {
+    match fetch_page_with("https://example.com/local-file", false, &CancelToken::default(), &fixtures()) {
+        Ok(page) => panic!("followed a redirect to {:?}", page.final_url),
+        Err(e) => assert!(e.to_string().contains("refused a redirect"), "{}", e),
+    }
+}
+
+#[test]
+fn dictionary_entry() {
+    let page = render("https://api.dictionaryapi.dev/api/v2/entries/en/borrow");
+    assert_eq!(page.meta.title.as_deref(), Some("borrow"));
+    check("dictionary.txt", &describe(&page));
+}
+
+#[test]
+fn dictionary_unknown_word() {
+    let page = render("https://api.dictionaryapi.dev/api/v2/entries/en/borrowck");
+    assert_eq!(page.meta.title.as_deref(), Some("No Definitions Found"));
+    check("dictionary_unknown_word.txt", &describe(&page));
+}
+
+#[test]
+fn wikipedia_article() {
+    let page = render("https://en.wikipedia.org/wiki/Rust_(programming_language)");
+    assert_eq!(page.final_url, None, "the API URL stays hidden behind the article's");
+    assert!(!page.text.contains("[edit]") && !page.text.contains("References"), "{}", page.text);
+    check("wikipedia.txt", &describe(&page));
+}
+
+#[test]
+fn wikipedia_title_with_a_slash() {
+    let page = render("https://en.wikipedia.org/wiki/AC/DC");
+    assert_eq!(page.final_url, None);
+    assert!(
+        page.links.iter().any(|l| l.url == "https://en.wikipedia.org/wiki/Back_in_Black"),
+        "links resolve against /wiki/, not the article: {:?}",
+        page.links.iter().map(|l| &l.url).collect::<Vec<_>>()
+    );
+    check("wikipedia_slash.txt", &describe(&page));
+}
+
+#[test]
+fn stack_overflow_question() {
+    let page = render("https://stackoverflow.com/questions/47618823/cannot-borrow-as-mutable");
+    assert_eq!(
+        page.final_url.as_deref(),
+        Some("https://stackoverflow.com/questions/47618823/cannot-borrow-as-mutable-because-it-is-also-borrowed-as-immutable")
+    );
+    check("stack_overflow.txt", &describe(&page));
+}
+
+#[test]
+fn stack_exchange_answers_that_fail_to_load() {
+    // The answers request is refused, as when the API throttles
+    let page = render("https://superuser.com/questions/123456/caps-lock-stuck-on");
+    assert!(page.text.contains("The answers could not be loaded"), "{}", page.text);
+    check("stack_exchange_answers_refused.txt", &describe(&page));
+}
+
+#[test]
+fn github_repository() {
+    let page = render("https://github.com/dtolnay/anyhow");
+    assert_eq!(page.final_url, None, "the API URL stays hidden behind the repository's");
+    assert!(page.text.contains("★ 5342 stars · 156 forks · Apache-2.0"), "{}", page.text);
+    check("github_repository.txt", &describe(&page));
+}
+
+#[test]
+fn github_repository_without_a_readme() {
+    let page = render("https://github.com/dtolnay/scratch");
+    assert!(!page.text.contains("README could not be loaded"), "{}", page.text);
+    check("github_repository_without_readme.txt", &describe(&page));
+}
+
+#[test]
+fn github_private_repository() {
+    // The API answers 404 for a repository the reader cannot see
+    let result = fetch_page_with("https://github.com/dtolnay/secret", false, &CancelToken::default(), &fixtures());
+    assert_eq!(result.err().map(|e| e.to_string()).as_deref(), Some("HTTP 404 Not Found"));
+}
+
+#[test]
+fn hacker_news_thread() {
+    let page = render("https://news.ycombinator.com/item?id=41234567");
+    assert_eq!(page.final_url, None);
+    assert!(page.text.contains("│ │ │ **pcwalton**"), "replies nest under their parents: {}", page.text);
+    check("hacker_news.txt", &describe(&page));
+}
+
+#[test]
+fn hacker_news_front_page() {
+    let page = render("https://news.ycombinator.com/");
+    assert_eq!(page.final_url, None);
+    assert!(page.links.iter().any(|l| l.url == "https://news.ycombinator.com/item?id=41230001"), "Ask HN links to its thread");
+    check("hacker_news_front_page.txt", &describe(&page));
+}
+
+#[test]
+fn hacker_news_story_without_comments() {
+    let page = render("https://news.ycombinator.com/item?id=41240001");
+    assert!(page.text.contains("0 comments"), "{}", page.text);
+    check("hacker_news_no_comments.txt", &describe(&page));
+}
+
+#[test]
+fn hacker_news_missing_item() {
+    let result =
+        fetch_page_with("https://news.ycombinator.com/item?id=41299999", false, &CancelToken::default(), &fixtures());
+    assert_eq!(result.err().map(|e| e.to_string()).as_deref(), Some("HTTP 404 Not Found"));
+}
+
+#[test]
+fn reddit_post() {
+    let page = render("https://old.reddit.com/r/rust/comments/1f2abcd/what_finally_made_lifetimes_click/");
+    assert_eq!(
+        page.final_url.as_deref(),
+        Some("https://www.reddit.com/r/rust/comments/1f2abcd/what_finally_made_lifetimes_click/")
+    );
+    check("reddit_post.txt", &describe(&page));
+}
+
+#[test]
+fn reddit_listing() {
+    let page = render("https://www.reddit.com/r/rust/");
+    let next = page.links.iter().find(|l| l.text == "Next page").expect("a Next page link");
+    assert_eq!(next.url, "https://www.reddit.com/r/rust?after=t3_1f9xyz0");
+    check("reddit_listing.txt", &describe(&page));
+}
+
+#[test]
+fn reddit_listing_last_page() {
+    let page = render("https://www.reddit.com/r/rust?after=t3_1f9xyz0");
+    assert!(!page.links.iter().any(|l| l.text == "Next page"), "the last page has no Next page link");
+    assert!(page.text.contains("by u/[deleted]"), "{}", page.text);
+    check("reddit_listing_last_page.txt", &describe(&page));
+}
+
+#[test]
+fn reddit_private_subreddit() {
+    let result = fetch_page_with("https://www.reddit.com/r/secretclub/", false, &CancelToken::default(), &fixtures());
+    assert_eq!(result.err().map(|e| e.to_string()).as_deref(), Some("HTTP 403 Forbidden"));
+}
+
+#[test]
+fn lobsters_story() {
+    let page = render("https://lobste.rs/s/x7kq2m/why_rust_s_borrow_checker_rejects_this");
+    assert_eq!(page.final_url, None);
+    assert!(page.text.contains("│ │ │ **[deleted]**"), "replies nest under their parents: {}", page.text);
+    check("lobsters_story.txt", &describe(&page));
+}
+
+#[test]
+fn lobsters_front_page() {
+    let page = render("https://lobste.rs/");
+    assert_eq!(page.final_url, None);
+    assert!(page.text.contains("`rust` `plt`"), "stories show their tags: {}", page.text);
+    check("lobsters_front_page.txt", &describe(&page));
+}
+
+#[test]
+fn lobsters_tag_page() {
+    let page = render("https://lobste.rs/t/rust");
+    assert!(
+        page.links.iter().any(|l| l.url == "https://lobste.rs/s/q3wz8a/what_are_you_doing_this_week"),
+        "a text post links to its comments"
+    );
+    check("lobsters_tag_page.txt", &describe(&page));
+}
+
+#[test]
+fn lobsters_missing_story() {
+    let result = fetch_page_with("https://lobste.rs/s/zz9zz9", false, &CancelToken::default(), &fixtures());
+    assert_eq!(result.err().map(|e| e.to_string()).as_deref(), Some("HTTP 404 Not Found"));
+}
+
+#[test]
+fn arxiv_abstract() {
+    let page = render("https://arxiv.org/abs/1706.03762");
+    assert_eq!(page.final_url, None);
+    assert_eq!(page.meta.pdf.as_deref(), Some("https://arxiv.org/pdf/1706.03762v7"));
+    check("arxiv.txt", &describe(&page));
+}
+
+#[test]
+fn arxiv_unknown_ids() {
+    let malformed = render("https://arxiv.org/abs/notanid");
+    assert_eq!(malformed.final_url, None);
+    assert!(malformed.text.contains("incorrect id format for notanid"), "{}", malformed.text);
+    let missing = render("https://arxiv.org/abs/2409.99999");
+    assert_eq!(missing.final_url, None);
+    check("arxiv_unknown_ids.txt", &format!("{}\n{}", describe(&malformed), describe(&missing)));
+}
+
+#[test]
+fn brave_serp() {
+    let results = search_with(&fixtures(), "rust borrow checker").unwrap();
+    assert_eq!(results.len(), 3, "the result without an absolute link is skipped");
+    check("brave_serp.json", &(serde_json::to_string_pretty(&results).unwrap() + "\n"));
+}
+
+#[test]
+fn brave_news() {
+    let results = vertical_search_with(&fixtures(), "rust 2024 edition", Vertical::News).unwrap();
+    assert_eq!(results.len(), 2, "the repeated story and the relative link are skipped");
+    assert_eq!(results[1].source, "lwn.net", "a story without a publisher shows its host");
+    check("brave_news.json", &(serde_json::to_string_pretty(&results).unwrap() + "\n"));
+}
+
+#[test]
+fn brave_images() {
+    let results = vertical_search_with(&fixtures(), "ferris crab", Vertical::Images).unwrap();
+    assert_eq!(results.len(), 3, "the image still loading is skipped");
+    assert_eq!(results[1].thumbnail.as_deref(), Some("https://imgs.search.brave.com/ferris-thumb-2.png"));
+    assert_eq!(results[2].url, "https://imgs.search.brave.com/ferris-thumb-3.png", "an image without a page links to itself");
+    check("brave_images.json", &(serde_json::to_string_pretty(&results).unwrap() + "\n"));
+}
+
+#[test]
+fn brave_videos() {
+    let results = vertical_search_with(&fixtures(), "rust borrow checker talk", Vertical::Videos).unwrap();
+    assert_eq!(results.len(), 2, "the video without a title is skipped");
+    assert_eq!(results[0].duration, "46:20");
+    check("brave_videos.json", &(serde_json::to_string_pretty(&results).unwrap() + "\n"));
+}
+
+#[test]
+fn spelling_corrections() {
+    let mut out = String::new();
+    // The last is the "search instead" link: the same query with spellcheck=0
+    for (query, verbatim) in [("rust borow checker", false), ("rust lifetiems", false), ("rust lifetiems", true)] {
+        let correction = correction_with(&fixtures(), query, verbatim).unwrap();
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{}{}\n", query, if verbatim { " (verbatim)" } else { "" }));
+        match correction {
+            Some(Correction { query, showing: None }) => out.push_str(&format!("Did you mean: {}\n", query)),
+            Some(Correction { query, showing: Some(showing) }) => {
+                out.push_str(&format!("Showing results for: {}\nSearch instead for: {}\n", showing, query))
+            }
+            None => out.push_str("No correction\n"),
+        }
+    }
+    check("corrections.txt", &out);
+}
+
+#[test]
+fn currency_answer() {
+    let mut out = String::new();
+    for query in ["100 eur to usd", "$20 in gbp", "1500 yen in euros", "chf to cad"] {
+        let answer = instant_answer_with(&fixtures(), query).unwrap_or_else(|| panic!("no answer for {}", query));
+        assert_eq!(answer.kind, "Currency");
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{}\n{}\n{}\n", query, answer.question, answer.answer));
+    }
+    check("currency.txt", &out);
+}
+
+#[test]
+fn missing_fixture_is_an_error() {
+    match fetch_page_with("https://example.com/nope", false, &CancelToken::default(), &fixtures()) {
+        Ok(_) => panic!("rendered a page with no fixture"),
+        Err(e) => assert!(e.to_string().contains("example.com_nope"), "{}", e),
+    }
+}